@@ -0,0 +1,41 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+/*! Converts a WebGPU trace between the RON and binary formats.
+ *
+ * The converted trace is written next to the original one,
+ * so that both can share the binary data files.
+!*/
+
+use wgc::device::{trace, TraceFormat};
+
+use std::path::{Path, PathBuf};
+
+fn main() {
+    env_logger::init();
+
+    let mut args = std::env::args().skip(1);
+    let dir = match args.next() {
+        Some(arg) if Path::new(&arg).is_dir() => PathBuf::from(arg),
+        _ => panic!("Provide the dir path as the first parameter"),
+    };
+    let format = match args.next().as_ref().map(String::as_str) {
+        Some("ron") => TraceFormat::Ron,
+        Some("binary") => TraceFormat::Binary,
+        _ => panic!("Provide the target format as the second parameter: 'ron' or 'binary'"),
+    };
+
+    let source = TraceFormat::detect(&dir).expect("No trace found");
+    if source == format {
+        log::info!("Trace '{:?}' is already in {:?}", dir, format);
+        return;
+    }
+
+    log::info!("Converting trace '{:?}' into {:?}", dir, format);
+    let reader = trace::Reader::open(&dir).unwrap();
+    let mut writer = trace::Writer::new(&dir, format).unwrap();
+    for action in reader {
+        writer.write(&action.unwrap()).unwrap();
+    }
+}
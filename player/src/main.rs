@@ -13,12 +13,13 @@
 use wgc::device::trace;
 
 use std::{
+    collections::HashSet,
     ffi::CString,
     fmt::Debug,
     fs,
     marker::PhantomData,
     path::{Path, PathBuf},
-    ptr,
+    ptr, slice,
 };
 
 macro_rules! gfx_select {
@@ -37,6 +38,8 @@ macro_rules! gfx_select {
     };
 }
 
+mod png;
+
 struct Label(Option<CString>);
 impl Label {
     fn new(text: &str) -> Self {
@@ -110,6 +113,7 @@ trait GlobalExt {
         action: trace::Action,
         dir: &PathBuf,
         comb_manager: &mut wgc::hub::IdentityManager,
+        mapped_buffers: &mut HashSet<wgc::id::BufferId>,
     );
 }
 
@@ -127,32 +131,69 @@ impl GlobalExt for wgc::hub::Global<IdentityPassThroughFactory> {
                     dst,
                     dst_offset,
                     size,
-                } => self.command_encoder_copy_buffer_to_buffer::<B>(
-                    encoder, src, src_offset, dst, dst_offset, size,
-                ),
-                trace::Command::CopyBufferToTexture { src, dst, size } => {
-                    self.command_encoder_copy_buffer_to_texture::<B>(encoder, &src, &dst, &size)
-                }
-                trace::Command::CopyTextureToBuffer { src, dst, size } => {
-                    self.command_encoder_copy_texture_to_buffer::<B>(encoder, &src, &dst, &size)
-                }
-                trace::Command::CopyTextureToTexture { src, dst, size } => {
-                    self.command_encoder_copy_texture_to_texture::<B>(encoder, &src, &dst, &size)
-                }
+                } => self
+                    .command_encoder_copy_buffer_to_buffer::<B>(
+                        encoder, src, src_offset, dst, dst_offset, size,
+                    )
+                    .unwrap(),
+                trace::Command::CopyBufferToTexture { src, dst, size } => self
+                    .command_encoder_copy_buffer_to_texture::<B>(encoder, &src, &dst, &size)
+                    .unwrap(),
+                trace::Command::CopyTextureToBuffer { src, dst, size } => self
+                    .command_encoder_copy_texture_to_buffer::<B>(encoder, &src, &dst, &size)
+                    .unwrap(),
+                trace::Command::CopyTextureToTexture { src, dst, size } => self
+                    .command_encoder_copy_texture_to_texture::<B>(encoder, &src, &dst, &size)
+                    .unwrap(),
+                trace::Command::ClearBuffer { dst, offset, size } => self
+                    .command_encoder_clear_buffer::<B>(encoder, dst, offset, size)
+                    .unwrap(),
+                trace::Command::ClearTexture {
+                    dst,
+                    subresource_range,
+                } => self
+                    .command_encoder_clear_texture::<B>(encoder, dst, &subresource_range)
+                    .unwrap(),
+                trace::Command::WriteTimestamp {
+                    query_set_id,
+                    query_index,
+                } => self
+                    .command_encoder_write_timestamp::<B>(encoder, query_set_id, query_index)
+                    .unwrap(),
+                trace::Command::ResolveQuerySet {
+                    query_set_id,
+                    start_query,
+                    query_count,
+                    destination,
+                    destination_offset,
+                } => self
+                    .command_encoder_resolve_query_set::<B>(
+                        encoder,
+                        query_set_id,
+                        start_query,
+                        query_count,
+                        destination,
+                        destination_offset,
+                    )
+                    .unwrap(),
                 trace::Command::RunComputePass {
                     commands,
                     dynamic_offsets,
+                    push_constant_data,
                 } => unsafe {
                     let mut pass = wgc::command::RawPass::new_compute(encoder);
-                    pass.fill_compute_commands(&commands, &dynamic_offsets);
+                    pass.fill_compute_commands(&commands, &dynamic_offsets, &push_constant_data);
                     let (data, _) = pass.finish_compute();
-                    self.command_encoder_run_compute_pass::<B>(encoder, &data);
+                    self.command_encoder_run_compute_pass::<B>(encoder, &data)
+                        .unwrap();
                 },
                 trace::Command::RunRenderPass {
                     target_colors,
                     target_depth_stencil,
+                    occlusion_query_set,
                     commands,
                     dynamic_offsets,
+                    push_constant_data,
                 } => unsafe {
                     let mut pass = wgc::command::RawPass::new_render(
                         encoder,
@@ -160,15 +201,18 @@ impl GlobalExt for wgc::hub::Global<IdentityPassThroughFactory> {
                             color_attachments: target_colors.as_ptr(),
                             color_attachments_length: target_colors.len(),
                             depth_stencil_attachment: target_depth_stencil.as_ref(),
+                            occlusion_query_set,
                         },
                     );
-                    pass.fill_render_commands(&commands, &dynamic_offsets);
+                    pass.fill_render_commands(&commands, &dynamic_offsets, &push_constant_data);
                     let (data, _) = pass.finish_render();
-                    self.command_encoder_run_render_pass::<B>(encoder, &data);
+                    self.command_encoder_run_render_pass::<B>(encoder, &data)
+                        .unwrap();
                 },
             }
         }
         self.command_encoder_finish::<B>(encoder, &wgt::CommandBufferDescriptor { todo: 0 })
+            .unwrap()
     }
 
     fn process<B: wgc::hub::GfxBackend>(
@@ -177,25 +221,41 @@ impl GlobalExt for wgc::hub::Global<IdentityPassThroughFactory> {
         action: trace::Action,
         dir: &PathBuf,
         comb_manager: &mut wgc::hub::IdentityManager,
+        mapped_buffers: &mut HashSet<wgc::id::BufferId>,
     ) {
         use wgc::device::trace::Action as A;
         match action {
             A::Init { .. } => panic!("Unexpected Action::Init: has to be the first action only"),
             A::CreateSwapChain { .. } | A::PresentSwapChain(_) => {
-                panic!("Unexpected SwapChain action: winit feature is not enabled")
+                panic!("Unexpected SwapChain action: has to be handled by the player")
             }
             A::CreateBuffer { id, desc } => {
                 let label = Label::new(&desc.label);
                 self.device_maintain_ids::<B>(device);
-                self.device_create_buffer::<B>(device, &desc.map_label(|_| label.as_ptr()), id);
+                let (_, error) =
+                    self.device_create_buffer::<B>(device, &desc.map_label(|_| label.as_ptr()), id);
+                if let Some(e) = error {
+                    panic!("{:?}", e);
+                }
+                if desc.mapped_at_creation {
+                    mapped_buffers.insert(id);
+                }
             }
             A::DestroyBuffer(id) => {
+                mapped_buffers.remove(&id);
                 self.buffer_destroy::<B>(id);
             }
             A::CreateTexture { id, desc } => {
                 let label = Label::new(&desc.label);
                 self.device_maintain_ids::<B>(device);
-                self.device_create_texture::<B>(device, &desc.map_label(|_| label.as_ptr()), id);
+                let (_, error) = self.device_create_texture::<B>(
+                    device,
+                    &desc.map_label(|_| label.as_ptr()),
+                    id,
+                );
+                if let Some(e) = error {
+                    panic!("{:?}", e);
+                }
             }
             A::DestroyTexture(id) => {
                 self.texture_destroy::<B>(id);
@@ -207,11 +267,14 @@ impl GlobalExt for wgc::hub::Global<IdentityPassThroughFactory> {
             } => {
                 let label = desc.as_ref().map_or(Label(None), |d| Label::new(&d.label));
                 self.device_maintain_ids::<B>(device);
-                self.texture_create_view::<B>(
+                let (_, error) = self.texture_create_view::<B>(
                     parent_id,
                     desc.map(|d| d.map_label(|_| label.as_ptr())).as_ref(),
                     id,
                 );
+                if let Some(e) = error {
+                    panic!("{:?}", e);
+                }
             }
             A::DestroyTextureView(id) => {
                 self.texture_view_destroy::<B>(id);
@@ -219,11 +282,33 @@ impl GlobalExt for wgc::hub::Global<IdentityPassThroughFactory> {
             A::CreateSampler { id, desc } => {
                 let label = Label::new(&desc.label);
                 self.device_maintain_ids::<B>(device);
-                self.device_create_sampler::<B>(device, &desc.map_label(|_| label.as_ptr()), id);
+                let (_, error) = self.device_create_sampler::<B>(
+                    device,
+                    &desc.map_label(|_| label.as_ptr()),
+                    id,
+                );
+                if let Some(e) = error {
+                    panic!("{:?}", e);
+                }
             }
             A::DestroySampler(id) => {
                 self.sampler_destroy::<B>(id);
             }
+            A::CreateQuerySet { id, desc } => {
+                let label = Label::new(&desc.label);
+                self.device_maintain_ids::<B>(device);
+                let (_, error) = self.device_create_query_set::<B>(
+                    device,
+                    &desc.map_label(|_| label.as_ptr()),
+                    id,
+                );
+                if let Some(e) = error {
+                    panic!("{:?}", e);
+                }
+            }
+            A::DestroyQuerySet(id) => {
+                self.query_set_destroy::<B>(id);
+            }
             A::GetSwapChainTexture { id, parent_id } => {
                 if let Some(id) = id {
                     self.swap_chain_get_next_texture::<B>(parent_id, id)
@@ -232,15 +317,17 @@ impl GlobalExt for wgc::hub::Global<IdentityPassThroughFactory> {
                 }
             }
             A::CreateBindGroupLayout { id, label, entries } => {
-                self.device_create_bind_group_layout::<B>(
+                let (_, error) = self.device_create_bind_group_layout::<B>(
                     device,
-                    &wgt::BindGroupLayoutDescriptor {
+                    &wgc::binding_model::BindGroupLayoutDescriptor {
                         label: Some(&label),
                         bindings: &entries,
                     },
                     id,
-                )
-                .unwrap();
+                );
+                if let Some(e) = error {
+                    panic!("{:?}", e);
+                }
             }
             A::DestroyBindGroupLayout(id) => {
                 self.bind_group_layout_destroy::<B>(id);
@@ -248,17 +335,22 @@ impl GlobalExt for wgc::hub::Global<IdentityPassThroughFactory> {
             A::CreatePipelineLayout {
                 id,
                 bind_group_layouts,
+                push_constant_ranges,
             } => {
                 self.device_maintain_ids::<B>(device);
-                self.device_create_pipeline_layout::<B>(
+                let (_, error) = self.device_create_pipeline_layout::<B>(
                     device,
                     &wgc::binding_model::PipelineLayoutDescriptor {
                         bind_group_layouts: bind_group_layouts.as_ptr(),
                         bind_group_layouts_length: bind_group_layouts.len(),
+                        push_constant_ranges: push_constant_ranges.as_ptr(),
+                        push_constant_ranges_length: push_constant_ranges.len(),
                     },
                     id,
-                )
-                .unwrap();
+                );
+                if let Some(e) = error {
+                    panic!("{:?}", e);
+                }
             }
             A::DestroyPipelineLayout(id) => {
                 self.pipeline_layout_destroy::<B>(id);
@@ -293,7 +385,7 @@ impl GlobalExt for wgc::hub::Global<IdentityPassThroughFactory> {
                     })
                     .collect::<Vec<_>>();
                 self.device_maintain_ids::<B>(device);
-                self.device_create_bind_group::<B>(
+                let (_, error) = self.device_create_bind_group::<B>(
                     device,
                     &wgc::binding_model::BindGroupDescriptor {
                         label: Some(&label),
@@ -301,8 +393,10 @@ impl GlobalExt for wgc::hub::Global<IdentityPassThroughFactory> {
                         bindings: &entry_vec,
                     },
                     id,
-                )
-                .unwrap();
+                );
+                if let Some(e) = error {
+                    panic!("{:?}", e);
+                }
             }
             A::DestroyBindGroup(id) => {
                 self.bind_group_destroy::<B>(id);
@@ -313,27 +407,44 @@ impl GlobalExt for wgc::hub::Global<IdentityPassThroughFactory> {
                     .chunks(4)
                     .map(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]]))
                     .collect::<Vec<_>>();
-                self.device_create_shader_module::<B>(
+                let (_, error) = self.device_create_shader_module::<B>(
                     device,
                     wgc::pipeline::ShaderModuleSource::SpirV(&spv),
                     id,
                 );
+                if let Some(e) = error {
+                    panic!("{:?}", e);
+                }
             }
             A::DestroyShaderModule(id) => {
                 self.shader_module_destroy::<B>(id);
             }
+            A::CreatePipelineCache { id, data } => {
+                let data = data.map(|data| fs::read(dir.join(data)).unwrap());
+                let (_, error) =
+                    self.device_create_pipeline_cache::<B>(device, data.as_deref(), id);
+                if let Some(e) = error {
+                    panic!("{:?}", e);
+                }
+            }
+            A::DestroyPipelineCache(id) => {
+                self.pipeline_cache_destroy::<B>(id);
+            }
             A::CreateComputePipeline { id, desc } => {
                 let cs_stage = OwnedProgrammableStage::from(desc.compute_stage);
                 self.device_maintain_ids::<B>(device);
-                self.device_create_compute_pipeline::<B>(
+                let (_, error) = self.device_create_compute_pipeline::<B>(
                     device,
                     &wgc::pipeline::ComputePipelineDescriptor {
                         layout: desc.layout,
                         compute_stage: cs_stage.desc,
+                        cache: desc.cache,
                     },
                     id,
-                )
-                .unwrap();
+                );
+                if let Some(e) = error {
+                    panic!("{:?}", e);
+                }
             }
             A::DestroyComputePipeline(id) => {
                 self.compute_pipeline_destroy::<B>(id);
@@ -353,13 +464,14 @@ impl GlobalExt for wgc::hub::Global<IdentityPassThroughFactory> {
                     })
                     .collect::<Vec<_>>();
                 self.device_maintain_ids::<B>(device);
-                self.device_create_render_pipeline::<B>(
+                let (_, error) = self.device_create_render_pipeline::<B>(
                     device,
                     &wgc::pipeline::RenderPipelineDescriptor {
                         layout: desc.layout,
                         vertex_stage: vs_stage.desc,
                         fragment_stage: fs_stage.as_ref().map_or(ptr::null(), |s| &s.desc),
                         primitive_topology: desc.primitive_topology,
+                        primitive_restart: desc.primitive_restart,
                         rasterization_state: desc
                             .rasterization_state
                             .as_ref()
@@ -378,10 +490,13 @@ impl GlobalExt for wgc::hub::Global<IdentityPassThroughFactory> {
                         sample_count: desc.sample_count,
                         sample_mask: desc.sample_mask,
                         alpha_to_coverage_enabled: desc.alpha_to_coverage_enabled,
+                        cache: desc.cache,
                     },
                     id,
-                )
-                .unwrap();
+                );
+                if let Some(e) = error {
+                    panic!("{:?}", e);
+                }
             }
             A::DestroyRenderPipeline(id) => {
                 self.render_pipeline_destroy::<B>(id);
@@ -391,6 +506,7 @@ impl GlobalExt for wgc::hub::Global<IdentityPassThroughFactory> {
                 desc,
                 commands,
                 dynamic_offsets,
+                push_constant_data,
             } => {
                 let label = Label::new(&desc.label);
                 let mut bundle_encoder = wgc::command::RenderBundleEncoder::new(
@@ -402,14 +518,17 @@ impl GlobalExt for wgc::hub::Global<IdentityPassThroughFactory> {
                     },
                     device,
                 );
-                bundle_encoder.fill_commands(&commands, &dynamic_offsets);
-                self.render_bundle_encoder_finish::<B>(
+                bundle_encoder.fill_commands(&commands, &dynamic_offsets, &push_constant_data);
+                let (_, error) = self.render_bundle_encoder_finish::<B>(
                     bundle_encoder,
                     &wgt::RenderBundleDescriptor {
                         label: label.as_ptr(),
                     },
                     id,
                 );
+                if let Some(e) = error {
+                    panic!("{:?}", e);
+                }
             }
             A::DestroyRenderBundle(id) => {
                 self.render_bundle_destroy::<B>(id);
@@ -423,10 +542,23 @@ impl GlobalExt for wgc::hub::Global<IdentityPassThroughFactory> {
                 let bin = std::fs::read(dir.join(data)).unwrap();
                 let size = (range.end - range.start) as usize;
                 if queued {
-                    self.queue_write_buffer::<B>(device, id, range.start, &bin);
+                    self.queue_write_buffer::<B>(device, id, range.start, &bin)
+                        .unwrap();
+                } else if mapped_buffers.remove(&id) {
+                    // the buffer is still mapped since its creation
+                    let ptr = self
+                        .buffer_get_mapped_range::<B>(
+                            id,
+                            range.start,
+                            wgt::BufferSize(size as wgt::BufferAddress),
+                        )
+                        .unwrap();
+                    unsafe { ptr::copy_nonoverlapping(bin.as_ptr(), ptr, size) };
+                    self.buffer_unmap::<B>(id).unwrap();
                 } else {
                     self.device_wait_for_buffer::<B>(device, id);
-                    self.device_set_buffer_sub_data::<B>(device, id, range.start, &bin[..size]);
+                    self.device_set_buffer_sub_data::<B>(device, id, range.start, &bin[..size])
+                        .unwrap();
                 }
             }
             A::WriteTexture {
@@ -436,7 +568,8 @@ impl GlobalExt for wgc::hub::Global<IdentityPassThroughFactory> {
                 size,
             } => {
                 let bin = std::fs::read(dir.join(data)).unwrap();
-                self.queue_write_texture::<B>(device, &to, &bin, &layout, &size);
+                self.queue_write_texture::<B>(device, &to, &bin, &layout, &size)
+                    .unwrap();
             }
             A::Submit(_index, commands) => {
                 let encoder = self.device_create_command_encoder::<B>(
@@ -445,160 +578,799 @@ impl GlobalExt for wgc::hub::Global<IdentityPassThroughFactory> {
                     comb_manager.alloc(device.backend()),
                 );
                 let comb = self.encode_commands::<B>(encoder, commands);
-                self.queue_submit::<B>(device, &[comb]);
+                self.queue_submit::<B>(device, &[comb]).unwrap();
             }
         }
     }
 }
 
-fn main() {
-    #[cfg(feature = "winit")]
-    use winit::{event_loop::EventLoop, window::WindowBuilder};
+const USAGE: &str = "Usage: player [OPTIONS] <trace-dir>
 
-    env_logger::init();
+Options:
+    --backend <NAME>    replay on vulkan, metal, dx12 or dx11 instead of the recorded backend
+    --start-frame <N>   fast-forward to frame N before displaying, stepping and timing
+    --stop-frame <N>    stop after presenting frame N
+    --repeat <N>        replay the trace N times, reporting the time of each run
+    --step              wait for a key press (Space, or Enter when headless) after each frame
+    --headless          run without a window, even if built with the winit feature
+    --dump <DIR>        write every presented frame into DIR, only when headless
+    --dump-format <FMT> format of the dumped frames: png (default) or raw";
 
-    #[cfg(feature = "renderdoc")]
-    let mut rd = renderdoc::RenderDoc::<renderdoc::V110>::new()
-        .expect("Failed to connect to RenderDoc: are you running without it?");
+/// File format of the dumped frames.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum DumpFormat {
+    /// 8-bit RGBA image, for the swapchain formats that can be represented this way.
+    Png,
+    /// Texel data as it is in the texture, with tightly packed rows.
+    Raw,
+}
 
-    //TODO: setting for the backend bits
-    //TODO: setting for the target frame, or controls
+/// Settings of the player, parsed from the command line.
+#[derive(Debug)]
+struct Options {
+    dir: PathBuf,
+    backend: Option<wgt::Backend>,
+    /// The first frame to display, frames are counted from 1.
+    start_frame: usize,
+    /// The last frame to replay, if any.
+    stop_frame: Option<usize>,
+    repeat: usize,
+    step: bool,
+    headless: bool,
+    /// The directory to dump the presented frames into, if any.
+    dump: Option<PathBuf>,
+    dump_format: DumpFormat,
+}
 
-    let dir = match std::env::args().nth(1) {
-        Some(arg) if Path::new(&arg).is_dir() => PathBuf::from(arg),
-        _ => panic!("Provide the dir path as the parameter"),
+impl Options {
+    fn from_args() -> Result<Self, String> {
+        fn number(value: Option<String>, name: &str) -> Result<usize, String> {
+            value
+                .and_then(|v| v.parse().ok())
+                .ok_or_else(|| format!("{} expects a number", name))
+        }
+
+        let mut options = Options {
+            dir: PathBuf::new(),
+            backend: None,
+            start_frame: 1,
+            stop_frame: None,
+            repeat: 1,
+            step: false,
+            headless: false,
+            dump: None,
+            dump_format: DumpFormat::Png,
+        };
+        let mut dir = None;
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--backend" => {
+                    options.backend = Some(match args.next().as_ref().map(String::as_str) {
+                        Some("vulkan") => wgt::Backend::Vulkan,
+                        Some("metal") => wgt::Backend::Metal,
+                        Some("dx12") => wgt::Backend::Dx12,
+                        Some("dx11") => wgt::Backend::Dx11,
+                        other => return Err(format!("Unknown backend {:?}", other)),
+                    })
+                }
+                "--start-frame" => options.start_frame = number(args.next(), &arg)?,
+                "--stop-frame" => options.stop_frame = Some(number(args.next(), &arg)?),
+                "--repeat" => options.repeat = number(args.next(), &arg)?,
+                "--step" => options.step = true,
+                "--headless" => options.headless = true,
+                "--dump" => {
+                    options.dump = Some(PathBuf::from(
+                        args.next().ok_or("--dump expects a directory")?,
+                    ))
+                }
+                "--dump-format" => {
+                    options.dump_format = match args.next().as_ref().map(String::as_str) {
+                        Some("png") => DumpFormat::Png,
+                        Some("raw") => DumpFormat::Raw,
+                        other => return Err(format!("Unknown dump format {:?}", other)),
+                    }
+                }
+                _ if arg.starts_with("--") => return Err(format!("Unknown option {}", arg)),
+                _ if dir.is_none() => dir = Some(PathBuf::from(arg)),
+                _ => return Err(format!("Unexpected argument {}", arg)),
+            }
+        }
+
+        options.dir = match dir {
+            Some(dir) if dir.is_dir() => dir,
+            Some(dir) => return Err(format!("{:?} is not a directory", dir)),
+            None => return Err("Provide the dir path as the parameter".to_string()),
+        };
+        if options.start_frame == 0 {
+            return Err("Frames are counted from 1".to_string());
+        }
+        if options
+            .stop_frame
+            .map_or(false, |stop| stop < options.start_frame)
+        {
+            return Err("The stop frame is before the start frame".to_string());
+        }
+        if options.repeat == 0 {
+            return Err("The trace has to be replayed at least once".to_string());
+        }
+        if options.dump.is_some() && !options.headless && cfg!(feature = "winit") {
+            return Err("Frames can only be dumped when headless".to_string());
+        }
+        Ok(options)
+    }
+}
+
+/// Size of a texel in bytes, for the formats a swap chain can have.
+fn texel_size(format: wgt::TextureFormat) -> u32 {
+    use wgt::TextureFormat as Tf;
+    match format {
+        Tf::Rgba8Unorm
+        | Tf::Rgba8UnormSrgb
+        | Tf::Bgra8Unorm
+        | Tf::Bgra8UnormSrgb
+        | Tf::Rgb10a2Unorm => 4,
+        Tf::Rgba16Float => 8,
+        other => panic!("Unsupported swap chain format {:?}", other),
+    }
+}
+
+/// First index of the textures and buffers created by the player itself.
+///
+/// Applications reuse the indices of the dropped objects, so the trace
+/// stays far below this. The storage is indexed directly, which is why
+/// the range can't be at the very top either.
+const RESERVED_INDEX_BASE: u32 = 1 << 14;
+
+/// Allocate an ID that the trace doesn't use.
+fn reserve_id<I: wgc::id::TypedId>(
+    manager: &mut wgc::hub::IdentityManager,
+    backend: wgt::Backend,
+) -> I {
+    let (index, epoch, _) = manager.alloc::<I>(backend).unzip();
+    I::zip(RESERVED_INDEX_BASE + index, epoch, backend)
+}
+
+/// Make sure the trace stays out of the indices reserved for the player.
+fn check_unreserved(action: &trace::Action) {
+    use wgc::id::TypedId as _;
+    let index = match *action {
+        trace::Action::CreateTexture { id, .. } => id.unzip().0,
+        trace::Action::CreateBuffer { id, .. } => id.unzip().0,
+        _ => return,
     };
+    assert!(
+        index < RESERVED_INDEX_BASE,
+        "Trace index {} collides with the offscreen resources",
+        index
+    );
+}
 
-    log::info!("Loading trace '{:?}'", dir);
-    let file = fs::File::open(dir.join(trace::FILE_NAME)).unwrap();
-    let mut actions: Vec<trace::Action> = ron::de::from_reader(file).unwrap();
-    actions.reverse(); // allows us to pop from the top
-    log::info!("Found {} actions", actions.len());
+/// Write the frame into `dir`, with `data` laid out as copied from the texture.
+fn dump_frame(
+    dir: &Path,
+    format: DumpFormat,
+    frame: usize,
+    desc: &wgt::SwapChainDescriptor,
+    data: &[u8],
+    bytes_per_row: u32,
+) {
+    let row_size = (desc.width * texel_size(desc.format)) as usize;
+    let mut pixels = Vec::with_capacity(row_size * desc.height as usize);
+    for row in data.chunks(bytes_per_row as usize) {
+        pixels.extend_from_slice(&row[..row_size]);
+    }
 
-    #[cfg(feature = "winit")]
-    let event_loop = {
-        log::info!("Creating a window");
-        EventLoop::new()
+    let format = match (format, desc.format) {
+        (DumpFormat::Png, wgt::TextureFormat::Bgra8Unorm)
+        | (DumpFormat::Png, wgt::TextureFormat::Bgra8UnormSrgb) => {
+            for texel in pixels.chunks_mut(4) {
+                texel.swap(0, 2);
+            }
+            DumpFormat::Png
+        }
+        (DumpFormat::Png, wgt::TextureFormat::Rgba8Unorm)
+        | (DumpFormat::Png, wgt::TextureFormat::Rgba8UnormSrgb) => DumpFormat::Png,
+        (DumpFormat::Png, other) => {
+            log::warn!(
+                "Format {:?} can't be written as PNG, dumping raw data",
+                other
+            );
+            DumpFormat::Raw
+        }
+        (DumpFormat::Raw, _) => DumpFormat::Raw,
     };
-    #[cfg(feature = "winit")]
-    let window = WindowBuilder::new()
-        .with_title("wgpu player")
-        .with_resizable(false)
-        .build(&event_loop)
-        .unwrap();
 
-    let global =
-        wgc::hub::Global::new("player", IdentityPassThroughFactory, wgt::BackendBit::all());
-    let mut command_buffer_id_manager = wgc::hub::IdentityManager::default();
+    let path = dir.join(match format {
+        DumpFormat::Png => format!("frame{:05}.png", frame),
+        DumpFormat::Raw => format!("frame{:05}.raw", frame),
+    });
+    log::info!("Dumping frame {} into {:?}", frame, path);
+    let mut file = std::io::BufWriter::new(fs::File::create(path).unwrap());
+    match format {
+        DumpFormat::Png => png::write_rgba8(&mut file, desc.width, desc.height, &pixels),
+        DumpFormat::Raw => std::io::Write::write_all(&mut file, &pixels),
+    }
+    .unwrap();
+}
 
-    #[cfg(feature = "winit")]
-    let surface =
-        global.instance_create_surface(&window, wgc::id::TypedId::zip(0, 1, wgt::Backend::Empty));
-
-    let device = match actions.pop() {
-        Some(trace::Action::Init { desc, backend }) => {
-            log::info!("Initializing the device for backend: {:?}", backend);
-            let adapter = global
-                .pick_adapter(
-                    &wgc::instance::RequestAdapterOptions {
-                        power_preference: wgt::PowerPreference::Default,
-                        #[cfg(feature = "winit")]
-                        compatible_surface: Some(surface),
-                        #[cfg(not(feature = "winit"))]
-                        compatible_surface: None,
+/// Offscreen texture standing in for the swap chain when there is no surface.
+struct Offscreen {
+    id: wgc::id::SwapChainId,
+    desc: wgt::SwapChainDescriptor,
+    texture: wgc::id::TextureId,
+    /// Buffer the presented frames are read back into.
+    buffer: wgc::id::BufferId,
+    bytes_per_row: u32,
+    /// View of the texture for the current frame, if acquired.
+    view: Option<wgc::id::TextureViewId>,
+}
+
+impl Offscreen {
+    fn new(
+        global: &wgc::hub::Global<IdentityPassThroughFactory>,
+        device: wgc::id::DeviceId,
+        id: wgc::id::SwapChainId,
+        desc: wgt::SwapChainDescriptor,
+        (texture_id, buffer_id): (wgc::id::TextureId, wgc::id::BufferId),
+    ) -> Self {
+        let bytes_per_row = {
+            let align = wgt::COPY_BYTES_PER_ROW_ALIGNMENT;
+            (desc.width * texel_size(desc.format) + align - 1) / align * align
+        };
+
+        gfx_select!(device => global.device_maintain_ids(device));
+        let (texture, error) = gfx_select!(device => global.device_create_texture(
+            device,
+            &wgt::TextureDescriptor {
+                label: ptr::null(),
+                size: wgt::Extent3d {
+                    width: desc.width,
+                    height: desc.height,
+                    depth: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgt::TextureDimension::D2,
+                format: desc.format,
+                usage: desc.usage | wgt::TextureUsage::COPY_SRC,
+            },
+            texture_id
+        ));
+        if let Some(e) = error {
+            panic!("{:?}", e);
+        }
+        let (buffer, error) = gfx_select!(device => global.device_create_buffer(
+            device,
+            &wgt::BufferDescriptor {
+                label: ptr::null(),
+                size: bytes_per_row as wgt::BufferAddress * desc.height as wgt::BufferAddress,
+                usage: wgt::BufferUsage::MAP_READ | wgt::BufferUsage::COPY_DST,
+                mapped_at_creation: false,
+            },
+            buffer_id
+        ));
+        if let Some(e) = error {
+            panic!("{:?}", e);
+        }
+
+        Offscreen {
+            id,
+            desc,
+            texture,
+            buffer,
+            bytes_per_row,
+            view: None,
+        }
+    }
+
+    fn acquire(
+        &mut self,
+        global: &wgc::hub::Global<IdentityPassThroughFactory>,
+        device: wgc::id::DeviceId,
+        id: wgc::id::SwapChainId,
+        view_id: wgc::id::TextureViewId,
+    ) {
+        assert_eq!(id, self.id);
+        gfx_select!(device => global.device_maintain_ids(device));
+        let (view, error) =
+            gfx_select!(device => global.texture_create_view(self.texture, None, view_id));
+        if let Some(e) = error {
+            panic!("{:?}", e);
+        }
+        self.view = Some(view);
+    }
+
+    /// Finish the current frame, dumping it if `dump` is set.
+    fn present(
+        &mut self,
+        global: &wgc::hub::Global<IdentityPassThroughFactory>,
+        device: wgc::id::DeviceId,
+        id: wgc::id::SwapChainId,
+        comb_manager: &mut wgc::hub::IdentityManager,
+        dump: Option<(&Path, DumpFormat)>,
+        frame: usize,
+    ) {
+        assert_eq!(id, self.id);
+        let view = self
+            .view
+            .take()
+            .expect("Presenting a frame that wasn't acquired");
+
+        if let Some((dir, format)) = dump {
+            let size =
+                self.bytes_per_row as wgt::BufferAddress * self.desc.height as wgt::BufferAddress;
+            let encoder = gfx_select!(device => global.device_create_command_encoder(
+                device,
+                &wgt::CommandEncoderDescriptor { label: ptr::null() },
+                comb_manager.alloc(device.backend())
+            ));
+            gfx_select!(device => global.command_encoder_copy_texture_to_buffer(
+                encoder,
+                &wgc::command::TextureCopyView {
+                    texture: self.texture,
+                    mip_level: 0,
+                    origin: wgt::Origin3d::ZERO,
+                },
+                &wgc::command::BufferCopyView {
+                    buffer: self.buffer,
+                    layout: wgt::TextureDataLayout {
+                        offset: 0,
+                        bytes_per_row: self.bytes_per_row,
+                        rows_per_image: 0,
                     },
-                    unsafe { wgt::UnsafeExtensions::allow() },
-                    wgc::instance::AdapterInputs::IdSet(
-                        &[wgc::id::TypedId::zip(0, 0, backend)],
-                        |id| id.backend(),
-                    ),
-                )
-                .expect("Unable to find an adapter for selected backend");
-
-            let info = gfx_select!(adapter => global.adapter_get_info(adapter));
-            log::info!("Picked '{}'", info.name);
-            gfx_select!(adapter => global.adapter_request_device(
-                adapter,
-                &desc,
-                None,
-                wgc::id::TypedId::zip(1, 0, wgt::Backend::Empty)
+                },
+                &wgt::Extent3d {
+                    width: self.desc.width,
+                    height: self.desc.height,
+                    depth: 1,
+                }
+            ))
+            .unwrap();
+            let comb = gfx_select!(device => global.command_encoder_finish(
+                encoder,
+                &wgt::CommandBufferDescriptor { todo: 0 }
+            ))
+            .unwrap();
+            gfx_select!(device => global.queue_submit(device, &[comb])).unwrap();
+
+            unsafe extern "C" fn map_callback(
+                status: wgc::resource::BufferMapAsyncStatus,
+                _user_data: *mut u8,
+            ) {
+                match status {
+                    wgc::resource::BufferMapAsyncStatus::Success => (),
+                    other => log::error!("Unable to map the frame: {:?}", other),
+                }
+            }
+            gfx_select!(device => global.buffer_map_async(
+                self.buffer,
+                0..size,
+                wgc::resource::BufferMapOperation {
+                    host: wgc::device::HostMap::Read,
+                    callback: map_callback,
+                    user_data: ptr::null_mut(),
+                }
             ))
+            .unwrap();
+            gfx_select!(device => global.device_poll(device, true)).unwrap();
+            let data = gfx_select!(device => global.buffer_get_mapped_range(
+                self.buffer,
+                0,
+                wgt::BufferSize(size)
+            ))
+            .unwrap();
+            dump_frame(
+                dir,
+                format,
+                frame,
+                &self.desc,
+                unsafe { slice::from_raw_parts(data, size as usize) },
+                self.bytes_per_row,
+            );
+            gfx_select!(device => global.buffer_unmap(self.buffer)).unwrap();
+        }
+
+        gfx_select!(device => global.texture_view_destroy(view));
+        // wait for the frame, so that the view ID can be reused by the next one
+        gfx_select!(device => global.device_poll(device, true)).unwrap();
+    }
+
+    fn destroy(
+        self,
+        global: &wgc::hub::Global<IdentityPassThroughFactory>,
+        device: wgc::id::DeviceId,
+    ) {
+        if let Some(view) = self.view {
+            gfx_select!(device => global.texture_view_destroy(view));
+        }
+        gfx_select!(device => global.texture_destroy(self.texture));
+        gfx_select!(device => global.buffer_destroy(self.buffer));
+    }
+}
+
+/// A single replay of the trace.
+struct Player {
+    global: wgc::hub::Global<IdentityPassThroughFactory>,
+    device: wgc::id::DeviceId,
+    surface: Option<wgc::id::SurfaceId>,
+    actions: trace::Reader,
+    dir: PathBuf,
+    command_buffer_id_manager: wgc::hub::IdentityManager,
+    mapped_buffers: HashSet<wgc::id::BufferId>,
+    /// The number of frames presented so far.
+    frame: usize,
+    stop_frame: Option<usize>,
+    /// The swap chain replacement when there is no surface.
+    offscreen: Option<Offscreen>,
+    /// Allocates the IDs of the offscreen resources,
+    /// offset by `RESERVED_INDEX_BASE`.
+    reserved_id_manager: wgc::hub::IdentityManager,
+    dump: Option<(PathBuf, DumpFormat)>,
+}
+
+impl Player {
+    fn new(
+        options: &Options,
+        create_surface: impl FnOnce(
+            &wgc::hub::Global<IdentityPassThroughFactory>,
+        ) -> Option<wgc::id::SurfaceId>,
+    ) -> Self {
+        log::info!("Loading trace '{:?}'", options.dir);
+        let mut actions = trace::Reader::open(&options.dir).unwrap();
+        if let Some(backend) = options.backend {
+            actions.override_backend(backend);
+        }
+
+        let global =
+            wgc::hub::Global::new("player", IdentityPassThroughFactory, wgt::BackendBit::all());
+        let surface = create_surface(&global);
+
+        let device = match actions.next().map(Result::unwrap) {
+            Some(trace::Action::Init {
+                desc,
+                backend,
+                pipeline_cache_data,
+            }) => {
+                log::info!("Initializing the device for backend: {:?}", backend);
+                let adapter = global
+                    .pick_adapter(
+                        &wgc::instance::RequestAdapterOptions {
+                            power_preference: wgt::PowerPreference::Default,
+                            compatible_surface: surface,
+                        },
+                        unsafe { wgt::UnsafeExtensions::allow() },
+                        wgc::instance::AdapterInputs::IdSet(
+                            &[wgc::id::TypedId::zip(0, 0, backend)],
+                            |id| id.backend(),
+                        ),
+                    )
+                    .expect("Unable to find an adapter for selected backend");
+
+                let info = gfx_select!(adapter => global.adapter_get_info(adapter));
+                log::info!("Picked '{}'", info.name);
+                let pipeline_cache_data =
+                    pipeline_cache_data.map(|data| fs::read(options.dir.join(data)).unwrap());
+                gfx_select!(adapter => global.adapter_request_device(
+                    adapter,
+                    &desc,
+                    pipeline_cache_data.as_deref(),
+                    None,
+                    wgc::id::TypedId::zip(1, 0, wgt::Backend::Empty)
+                ))
+            }
+            _ => panic!("Expected Action::Init"),
+        };
+
+        Player {
+            global,
+            device,
+            surface,
+            actions,
+            dir: options.dir.clone(),
+            command_buffer_id_manager: wgc::hub::IdentityManager::default(),
+            mapped_buffers: HashSet::new(),
+            frame: 0,
+            stop_frame: options.stop_frame,
+            offscreen: None,
+            reserved_id_manager: wgc::hub::IdentityManager::default(),
+            dump: options.dump.clone().map(|dir| (dir, options.dump_format)),
+        }
+    }
+
+    /// Execute the actions up to the next presented frame.
+    ///
+    /// `on_swap_chain` is called before a swap chain gets created.
+    /// Returns `false` if there are no more frames to replay.
+    fn replay_frame(&mut self, mut on_swap_chain: impl FnMut(&wgt::SwapChainDescriptor)) -> bool {
+        if self.stop_frame.map_or(false, |stop| self.frame >= stop) {
+            return false;
+        }
+        let device = self.device;
+        let global = &self.global;
+        for action in self.actions.by_ref() {
+            match action.unwrap() {
+                trace::Action::CreateSwapChain { id, desc } => {
+                    on_swap_chain(&desc);
+                    match self.surface {
+                        Some(surface) => {
+                            log::info!("Initializing the swapchain");
+                            assert_eq!(id.to_surface_id(), surface);
+                            gfx_select!(device => global.device_create_swap_chain(
+                                device, surface, &desc
+                            ))
+                            .unwrap();
+                        }
+                        None => {
+                            log::info!("Initializing an offscreen swapchain");
+                            if let Some(old) = self.offscreen.take() {
+                                old.destroy(global, device);
+                            }
+                            let ids = (
+                                reserve_id(&mut self.reserved_id_manager, device.backend()),
+                                reserve_id(&mut self.reserved_id_manager, device.backend()),
+                            );
+                            self.offscreen = Some(Offscreen::new(global, device, id, desc, ids));
+                        }
+                    }
+                }
+                trace::Action::GetSwapChainTexture { id, parent_id } if self.surface.is_none() => {
+                    if let Some(view_id) = id {
+                        self.offscreen
+                            .as_mut()
+                            .expect("Swapchain has to be created first")
+                            .acquire(global, device, parent_id, view_id);
+                    }
+                }
+                trace::Action::PresentSwapChain(id) => {
+                    self.frame += 1;
+                    log::debug!("Presenting frame {}", self.frame);
+                    match self.offscreen {
+                        Some(ref mut offscreen) => offscreen.present(
+                            global,
+                            device,
+                            id,
+                            &mut self.command_buffer_id_manager,
+                            self.dump
+                                .as_ref()
+                                .map(|&(ref dir, format)| (dir.as_path(), format)),
+                            self.frame,
+                        ),
+                        None => gfx_select!(device => global.swap_chain_present(id)),
+                    }
+                    return true;
+                }
+                action => {
+                    if self.surface.is_none() {
+                        check_unreserved(&action);
+                    }
+                    gfx_select!(device => global.process(
+                        device,
+                        action,
+                        &self.dir,
+                        &mut self.command_buffer_id_manager,
+                        &mut self.mapped_buffers
+                    ));
+                }
+            }
+        }
+        false
+    }
+
+    fn wait_idle(&self) {
+        let device = self.device;
+        let global = &self.global;
+        gfx_select!(device => global.device_poll(device, true)).unwrap();
+    }
+}
+
+/// Measures the time it takes to replay the frames after the start frame.
+struct Timer {
+    start: Option<std::time::Instant>,
+    frames: usize,
+}
+
+impl Timer {
+    fn new() -> Self {
+        Timer {
+            start: None,
+            frames: 0,
+        }
+    }
+
+    fn frame_started(&mut self) {
+        if self.start.is_none() {
+            self.start = Some(std::time::Instant::now());
+        }
+    }
+
+    fn frame_finished(&mut self) {
+        self.frames += 1;
+    }
+
+    fn report(&self, run: usize) {
+        if let Some(start) = self.start {
+            let elapsed = start.elapsed();
+            log::info!(
+                "Run {}: replayed {} frames in {:.2} ms ({:.1} FPS)",
+                run,
+                self.frames,
+                elapsed.as_secs_f64() * 1000.0,
+                self.frames as f64 / elapsed.as_secs_f64(),
+            );
+        }
+    }
+}
+
+fn main() {
+    env_logger::init();
+
+    let options = match Options::from_args() {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}\n\n{}", message, USAGE);
+            std::process::exit(1);
         }
-        _ => panic!("Expected Action::Init"),
     };
+    log::debug!("{:?}", options);
 
-    log::info!("Executing actions");
+    #[cfg(feature = "winit")]
+    {
+        if !options.headless {
+            run_windowed(options);
+            return;
+        }
+    }
     #[cfg(not(feature = "winit"))]
     {
+        if !options.headless {
+            log::info!("Feature 'winit' is not enabled, running headless");
+        }
+    }
+    run_headless(options);
+}
+
+fn run_headless(options: Options) {
+    #[cfg(feature = "renderdoc")]
+    let mut rd = renderdoc::RenderDoc::<renderdoc::V110>::new()
+        .expect("Failed to connect to RenderDoc: are you running without it?");
+    if let Some(ref dir) = options.dump {
+        fs::create_dir_all(dir).unwrap();
+    }
+
+    for run in 1..=options.repeat {
+        let mut player = Player::new(&options, |_| None);
+        let mut timer = Timer::new();
+
+        log::info!("Executing actions");
         #[cfg(feature = "renderdoc")]
         rd.start_frame_capture(ptr::null(), ptr::null());
 
-        while let Some(action) = actions.pop() {
-            gfx_select!(device => global.process(device, action, &dir, &mut command_buffer_id_manager));
+        while player.frame + 1 < options.start_frame {
+            if !player.replay_frame(|_| ()) {
+                break;
+            }
+        }
+        loop {
+            timer.frame_started();
+            if !player.replay_frame(|_| ()) {
+                break;
+            }
+            timer.frame_finished();
+            if options.step {
+                println!("Frame {} done, press Enter to continue", player.frame);
+                let mut line = String::new();
+                std::io::stdin().read_line(&mut line).unwrap();
+            }
         }
 
         #[cfg(feature = "renderdoc")]
         rd.end_frame_capture(ptr::null(), ptr::null());
-        gfx_select!(device => global.device_poll(device, true));
+        player.wait_idle();
+        timer.report(run);
     }
-    #[cfg(feature = "winit")]
-    {
-        use winit::{
-            event::{ElementState, Event, KeyboardInput, VirtualKeyCode, WindowEvent},
-            event_loop::ControlFlow,
-        };
+}
 
-        let mut frame_count = 0;
-        event_loop.run(move |event, _, control_flow| {
-            *control_flow = ControlFlow::Poll;
-            match event {
-                Event::MainEventsCleared => {
-                    window.request_redraw();
-                }
-                Event::RedrawRequested(_) => loop {
-                    match actions.pop() {
-                        Some(trace::Action::CreateSwapChain { id, desc }) => {
-                            log::info!("Initializing the swapchain");
-                            assert_eq!(id.to_surface_id(), surface);
-                            window.set_inner_size(winit::dpi::PhysicalSize::new(
-                                desc.width,
-                                desc.height,
-                            ));
-                            gfx_select!(device => global.device_create_swap_chain(device, surface, &desc));
-                        }
-                        Some(trace::Action::PresentSwapChain(id)) => {
-                            frame_count += 1;
-                            log::debug!("Presenting frame {}", frame_count);
-                            gfx_select!(device => global.swap_chain_present(id));
-                            break;
-                        }
-                        Some(action) => {
-                            gfx_select!(device => global.process(device, action, &dir, &mut command_buffer_id_manager));
-                        }
-                        None => break,
-                    }
-                },
-                Event::WindowEvent { event, .. } => match event {
-                    WindowEvent::KeyboardInput {
-                        input:
-                            KeyboardInput {
-                                virtual_keycode: Some(VirtualKeyCode::Escape),
-                                state: ElementState::Pressed,
-                                ..
-                            },
-                        ..
+#[cfg(feature = "winit")]
+fn run_windowed(options: Options) {
+    use winit::{
+        event::{ElementState, Event, KeyboardInput, VirtualKeyCode, WindowEvent},
+        event_loop::{ControlFlow, EventLoop},
+        window::WindowBuilder,
+    };
+
+    log::info!("Creating a window");
+    let event_loop = EventLoop::new();
+    let window = WindowBuilder::new()
+        .with_title("wgpu player")
+        .with_resizable(false)
+        .build(&event_loop)
+        .unwrap();
+
+    let surface_id = wgc::id::TypedId::zip(0, 1, wgt::Backend::Empty);
+    let mut player = Some(Player::new(&options, |global| {
+        Some(global.instance_create_surface(&window, surface_id))
+    }));
+    let mut timer = Timer::new();
+    let mut run = 1;
+    let mut paused = false;
+
+    log::info!("Executing actions");
+    event_loop.run(move |event, _, control_flow| {
+        *control_flow = ControlFlow::Poll;
+        match event {
+            Event::MainEventsCleared => {
+                window.request_redraw();
+            }
+            Event::RedrawRequested(_) if !paused => {
+                let current = match player {
+                    Some(ref mut player) => player,
+                    None => return,
+                };
+                let set_size = |desc: &wgt::SwapChainDescriptor| {
+                    window.set_inner_size(winit::dpi::PhysicalSize::new(desc.width, desc.height));
+                };
+                // fast-forward to the start frame
+                while current.frame + 1 < options.start_frame {
+                    if !current.replay_frame(set_size) {
+                        break;
                     }
-                    | WindowEvent::CloseRequested => {
-                        *control_flow = ControlFlow::Exit;
+                }
+                timer.frame_started();
+                if current.replay_frame(set_size) {
+                    timer.frame_finished();
+                    if options.step {
+                        log::info!("Frame {} done, press Space to continue", current.frame);
+                        paused = true;
                     }
-                    _ => {}
-                },
-                Event::LoopDestroyed => {
-                    log::info!("Closing");
-                    gfx_select!(device => global.device_poll(device, true));
+                    return;
+                }
+
+                current.wait_idle();
+                timer.report(run);
+                // drop the previous replay before starting a new one on the same window
+                player = None;
+                if run < options.repeat {
+                    run += 1;
+                    timer = Timer::new();
+                    player = Some(Player::new(&options, |global| {
+                        Some(global.instance_create_surface(&window, surface_id))
+                    }));
+                }
+            }
+            Event::WindowEvent { event, .. } => match event {
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            virtual_keycode: Some(VirtualKeyCode::Space),
+                            state: ElementState::Pressed,
+                            ..
+                        },
+                    ..
+                } => {
+                    paused = false;
+                }
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            virtual_keycode: Some(VirtualKeyCode::Escape),
+                            state: ElementState::Pressed,
+                            ..
+                        },
+                    ..
+                }
+                | WindowEvent::CloseRequested => {
+                    *control_flow = ControlFlow::Exit;
                 }
                 _ => {}
+            },
+            Event::LoopDestroyed => {
+                log::info!("Closing");
+                if let Some(ref player) = player {
+                    player.wait_idle();
+                }
             }
-        });
-    }
+            _ => {}
+        }
+    });
 }
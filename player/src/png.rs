@@ -0,0 +1,173 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+/*! Minimal PNG writer for the frame dumps.
+ *
+ * The image data is stored without compression, which keeps the encoder
+ * tiny and dependency-free. The files are meant to be diffed, not shipped.
+!*/
+
+use std::io::{self, Write};
+
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n'];
+/// The largest block that deflate can store without compression.
+const MAX_STORED_BLOCK: usize = 0xFFFF;
+
+fn crc32(chunks: &[&[u8]]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in chunks.iter().flat_map(|chunk| chunk.iter()) {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+fn write_chunk(output: &mut impl Write, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    output.write_all(&(data.len() as u32).to_be_bytes())?;
+    output.write_all(kind)?;
+    output.write_all(data)?;
+    output.write_all(&crc32(&[kind, data]).to_be_bytes())
+}
+
+/// Wrap the data into a zlib stream made of stored deflate blocks.
+fn zlib_store(data: &[u8]) -> Vec<u8> {
+    let block_count = (data.len() + MAX_STORED_BLOCK - 1) / MAX_STORED_BLOCK;
+    let mut stream = Vec::with_capacity(data.len() + 5 * block_count.max(1) + 6);
+    stream.extend_from_slice(&[0x78, 0x01]);
+    let mut blocks = data.chunks(MAX_STORED_BLOCK).peekable();
+    if blocks.peek().is_none() {
+        stream.extend_from_slice(&[1, 0, 0, 0xFF, 0xFF]);
+    }
+    while let Some(block) = blocks.next() {
+        let is_final = blocks.peek().is_none();
+        let len = block.len() as u16;
+        stream.push(is_final as u8);
+        stream.extend_from_slice(&len.to_le_bytes());
+        stream.extend_from_slice(&(!len).to_le_bytes());
+        stream.extend_from_slice(block);
+    }
+
+    stream.extend_from_slice(&adler32(data).to_be_bytes());
+    stream
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in data {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+/// Write an 8-bit RGBA image, given as tightly packed rows.
+pub fn write_rgba8(
+    output: &mut impl Write,
+    width: u32,
+    height: u32,
+    pixels: &[u8],
+) -> io::Result<()> {
+    let row_size = width as usize * 4;
+    assert_eq!(pixels.len(), row_size * height as usize);
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&width.to_be_bytes());
+    header.extend_from_slice(&height.to_be_bytes());
+    // bit depth, color type (RGBA), compression, filter, interlace
+    header.extend_from_slice(&[8, 6, 0, 0, 0]);
+
+    // every scanline starts with its filter type, which is "none" here
+    let mut scanlines = Vec::with_capacity((row_size + 1) * height as usize);
+    for row in pixels.chunks(row_size) {
+        scanlines.push(0);
+        scanlines.extend_from_slice(row);
+    }
+
+    output.write_all(&SIGNATURE)?;
+    write_chunk(output, b"IHDR", &header)?;
+    write_chunk(output, b"IDAT", &zlib_store(&scanlines))?;
+    write_chunk(output, b"IEND", &[])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checksums() {
+        // Reference values of the CRC-32 and Adler-32 specifications.
+        assert_eq!(crc32(&[b"123456789"]), 0xCBF4_3926);
+        assert_eq!(crc32(&[b"1234", b"56789"]), 0xCBF4_3926);
+        assert_eq!(crc32(&[b"IEND"]), 0xAE42_6082);
+        assert_eq!(adler32(b""), 1);
+        assert_eq!(adler32(b"Wikipedia"), 0x11E6_0398);
+    }
+
+    /// Decode a zlib stream made of stored blocks, checking its framing.
+    fn zlib_unstore(stream: &[u8]) -> Vec<u8> {
+        assert_eq!((stream[0] as u16) << 8 | stream[1] as u16, 0x7801);
+        let mut data = Vec::new();
+        let mut rest = &stream[2..];
+        loop {
+            let (header, len, nlen) = (rest[0], &rest[1..3], &rest[3..5]);
+            let len = u16::from_le_bytes([len[0], len[1]]);
+            assert_eq!(!len, u16::from_le_bytes([nlen[0], nlen[1]]));
+            data.extend_from_slice(&rest[5..5 + len as usize]);
+            rest = &rest[5 + len as usize..];
+            if header & 1 != 0 {
+                break;
+            }
+        }
+        assert_eq!(rest, &adler32(&data).to_be_bytes()[..]);
+        data
+    }
+
+    #[test]
+    fn zlib_blocks() {
+        for &size in &[
+            0,
+            1,
+            MAX_STORED_BLOCK,
+            MAX_STORED_BLOCK + 1,
+            3 * MAX_STORED_BLOCK,
+        ] {
+            let data = (0..size).map(|i| (i * 7) as u8).collect::<Vec<_>>();
+            assert_eq!(zlib_unstore(&zlib_store(&data)), data);
+        }
+    }
+
+    #[test]
+    fn image() {
+        let (width, height) = (3, 2);
+        let pixels = (0..width * height * 4).map(|i| i as u8).collect::<Vec<_>>();
+        let mut file = Vec::new();
+        write_rgba8(&mut file, width, height, &pixels).unwrap();
+
+        assert_eq!(file[..8], SIGNATURE);
+        let mut chunks = Vec::new();
+        let mut rest = &file[8..];
+        while !rest.is_empty() {
+            let len = u32::from_be_bytes([rest[0], rest[1], rest[2], rest[3]]) as usize;
+            let (kind, data) = (&rest[4..8], &rest[8..8 + len]);
+            let crc = &rest[8 + len..12 + len];
+            assert_eq!(crc, &crc32(&[kind, data]).to_be_bytes()[..]);
+            chunks.push((kind, data));
+            rest = &rest[12 + len..];
+        }
+
+        let kinds = chunks.iter().map(|&(kind, _)| kind).collect::<Vec<_>>();
+        assert_eq!(kinds, [&b"IHDR"[..], b"IDAT", b"IEND"]);
+        assert_eq!(chunks[0].1, [0, 0, 0, 3, 0, 0, 0, 2, 8, 6, 0, 0, 0]);
+        let scanlines = zlib_unstore(chunks[1].1);
+        for (row, scanline) in pixels.chunks(12).zip(scanlines.chunks(13)) {
+            assert_eq!(scanline[0], 0);
+            assert_eq!(&scanline[1..], row);
+        }
+        assert_eq!(scanlines.len(), 26);
+        assert!(chunks[2].1.is_empty());
+    }
+}
@@ -15,7 +15,7 @@ use gfx_descriptor::{DescriptorCounts, DescriptorSet};
 use serde::Deserialize;
 #[cfg(feature = "trace")]
 use serde::Serialize;
-use std::borrow::Borrow;
+use std::{borrow::Borrow, ops::Range};
 
 #[derive(Clone, Debug)]
 pub enum BindGroupLayoutError {
@@ -26,15 +26,44 @@ pub enum BindGroupLayoutError {
     ZeroCount,
     /// Arrays of bindings unsupported for this type of binding
     ArrayUnsupported,
+    /// One of the immutable samplers is invalid.
+    InvalidSampler(SamplerId),
+    /// Immutable samplers are only allowed in sampler bindings.
+    UnexpectedImmutableSamplers(u32),
+    /// The number of immutable samplers doesn't match the count of the binding.
+    ImmutableSamplerCountMismatch {
+        binding: u32,
+        actual: usize,
+        expected: usize,
+    },
+    /// The immutable sampler is/is not a comparison sampler,
+    /// while the binding type indicates otherwise.
+    WrongSamplerComparison(u32),
+    /// The device is lost.
+    DeviceLost,
 }
 
 #[derive(Clone, Debug)]
 pub enum BindGroupError {
+    /// The bind group layout is invalid.
+    InvalidLayout(BindGroupLayoutId),
+    /// The bound buffer is invalid.
+    InvalidBuffer(BufferId),
+    /// The bound texture view is invalid.
+    InvalidTextureView(TextureViewId),
+    /// The bound sampler is invalid.
+    InvalidSampler(SamplerId),
     /// Number of bindings in bind group descriptor does not match
     /// the number of bindings defined in the bind group layout.
-    BindingsNumMismatch { actual: usize, expected: usize },
+    BindingsNumMismatch {
+        actual: usize,
+        expected: usize,
+    },
     /// Unable to find a corresponding declaration for the given binding,
     MissingBindingDeclaration(u32),
+    /// The binding uses the immutable samplers of the layout,
+    /// so it can't be provided by the bind group.
+    ImmutableSamplerBinding(u32),
     /// The given binding has a different type than the one in the layout.
     WrongBindingType {
         // Index of the binding
@@ -44,12 +73,57 @@ pub enum BindGroupError {
         // Human-readable description of expected types
         expected: &'static str,
     },
+    /// The bound texture view dimension doesn't match the layout.
+    WrongTextureViewDimension {
+        binding: u32,
+        layout_dimension: wgt::TextureViewDimension,
+        view_dimension: wgt::TextureViewDimension,
+    },
     /// The given sampler is/is not a comparison sampler,
     /// while the layout type indicates otherwise.
     WrongSamplerComparison,
+    /// Buffer offset must be a multiple of `BIND_BUFFER_ALIGNMENT`.
+    UnalignedBufferOffset(wgt::BufferAddress),
+    /// The bound buffer range doesn't fit in the buffer.
+    BindingRangeTooLarge {
+        range: Range<wgt::BufferAddress>,
+        size: wgt::BufferAddress,
+    },
+    /// The bound buffer range is smaller than the minimum binding size of the layout.
+    BindingSizeTooSmall {
+        actual: wgt::BufferAddress,
+        min: wgt::BufferAddress,
+    },
+    MissingBufferUsage {
+        actual: wgt::BufferUsage,
+        expected: wgt::BufferUsage,
+    },
+    MissingTextureUsage {
+        actual: wgt::TextureUsage,
+        expected: wgt::TextureUsage,
+    },
+    MissingCapability(wgt::Capabilities),
+    /// The number of texture views doesn't match the count declared in the layout.
+    BindingArrayLengthMismatch {
+        actual: usize,
+        expected: usize,
+    },
+    /// The binding is declared as a single item, but an array was provided.
+    SingleBindingExpected(u32),
+    /// The resource is used in conflicting ways within the bind group.
+    UsageConflict(u32),
+    /// Swap chain images can't be used in bind groups.
+    SwapChainImage,
+    /// Not enough memory to allocate the descriptor set.
+    OutOfMemory,
+    /// The device is lost.
+    DeviceLost,
 }
 
-pub(crate) type BindEntryMap = FastHashMap<u32, wgt::BindGroupLayoutEntry>;
+pub type BindGroupLayoutEntry = wgt::BindGroupLayoutEntryBase<SamplerId>;
+pub type BindGroupLayoutDescriptor<'a> = wgt::BindGroupLayoutDescriptorBase<'a, SamplerId>;
+
+pub(crate) type BindEntryMap = FastHashMap<u32, BindGroupLayoutEntry>;
 
 #[derive(Debug)]
 pub struct BindGroupLayout<B: hal::Backend> {
@@ -59,18 +133,84 @@ pub struct BindGroupLayout<B: hal::Backend> {
     pub(crate) entries: BindEntryMap,
     pub(crate) desc_counts: DescriptorCounts,
     pub(crate) dynamic_count: usize,
+    pub(crate) immutable_samplers: Vec<Stored<SamplerId>>,
 }
 
+/// Maximum number of push constant ranges in a pipeline layout,
+/// since each shader stage can be visible from at most one range.
+pub const SHADER_STAGE_COUNT: usize = 3;
+
 #[repr(C)]
 #[derive(Debug)]
 pub struct PipelineLayoutDescriptor {
     pub bind_group_layouts: *const BindGroupLayoutId,
     pub bind_group_layouts_length: usize,
+    pub push_constant_ranges: *const wgt::PushConstantRange,
+    pub push_constant_ranges_length: usize,
 }
 
 #[derive(Clone, Debug)]
 pub enum PipelineLayoutError {
     TooManyGroups(usize),
+    /// One of the bind group layouts is invalid.
+    InvalidBindGroupLayout(BindGroupLayoutId),
+    /// Push constant ranges require `Extensions::PUSH_CONSTANTS`.
+    MissingExtension(wgt::Extensions),
+    /// A shader stage is visible from more than one push constant range.
+    MoreThanOnePushConstantRangePerStage {
+        index: usize,
+        provided: wgt::ShaderStage,
+        intersected: wgt::ShaderStage,
+    },
+    /// The push constant range is empty, or its start is after its end.
+    InvalidPushConstantRange {
+        index: usize,
+        range: Range<u32>,
+    },
+    /// The push constant range ends beyond `Limits::max_push_constant_size`.
+    PushConstantRangeTooLarge {
+        index: usize,
+        range: Range<u32>,
+        max: u32,
+    },
+    /// The push constant range bounds must be multiples of `PUSH_CONSTANT_ALIGNMENT`.
+    MisalignedPushConstantRange {
+        index: usize,
+        bound: u32,
+    },
+    /// The device is lost.
+    DeviceLost,
+}
+
+/// Error encountered when setting push constants in a pass or a bundle.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PushConstantUploadError {
+    /// The offset or the end of the data is not a multiple of `PUSH_CONSTANT_ALIGNMENT`.
+    Unaligned(u32),
+    /// The data is not contained in the range of one of the provided stages.
+    TooLarge {
+        offset: u32,
+        end_offset: u32,
+        index: usize,
+        range: wgt::PushConstantRange,
+    },
+    /// The provided stages only partially cover the stages of a range.
+    PartialRangeMatch {
+        actual: wgt::ShaderStage,
+        index: usize,
+        matched: wgt::ShaderStage,
+    },
+    /// A range overlapping the data is visible from stages that were not provided.
+    MissingStages {
+        actual: wgt::ShaderStage,
+        index: usize,
+        missing: wgt::ShaderStage,
+    },
+    /// Some of the provided stages are not visible from any range.
+    UnmatchedStages {
+        actual: wgt::ShaderStage,
+        unmatched: wgt::ShaderStage,
+    },
 }
 
 #[derive(Debug)]
@@ -79,6 +219,68 @@ pub struct PipelineLayout<B: hal::Backend> {
     pub(crate) device_id: Stored<DeviceId>,
     pub(crate) life_guard: LifeGuard,
     pub(crate) bind_group_layout_ids: ArrayVec<[Stored<BindGroupLayoutId>; MAX_BIND_GROUPS]>,
+    pub(crate) push_constant_ranges: ArrayVec<[wgt::PushConstantRange; SHADER_STAGE_COUNT]>,
+}
+
+impl<B: hal::Backend> PipelineLayout<B> {
+    /// Validate push constant data, written at `offset` with `size_bytes` bytes, against
+    /// the ranges.
+    ///
+    /// Since a stage is visible from at most one range, it's enough to check that
+    /// the data is contained in the range of each provided stage, and that the
+    /// ranges overlapping the data are only visible from the provided stages.
+    pub(crate) fn validate_push_constant_ranges(
+        &self,
+        stages: wgt::ShaderStage,
+        offset: u32,
+        size_bytes: u32,
+    ) -> Result<(), PushConstantUploadError> {
+        let end_offset = offset.checked_add(size_bytes);
+        for &bound in Some(offset).iter().chain(end_offset.as_ref()) {
+            if bound % wgt::PUSH_CONSTANT_ALIGNMENT != 0 {
+                return Err(PushConstantUploadError::Unaligned(bound));
+            }
+        }
+        // An overflowing end is beyond all the ranges, so it's reported as too large.
+        let end_offset = end_offset.unwrap_or(u32::MAX);
+
+        let mut used_stages = wgt::ShaderStage::NONE;
+        for (index, range) in self.push_constant_ranges.iter().enumerate() {
+            if stages.contains(range.stages) {
+                if offset < range.range.start || range.range.end < end_offset {
+                    return Err(PushConstantUploadError::TooLarge {
+                        offset,
+                        end_offset,
+                        index,
+                        range: range.clone(),
+                    });
+                }
+                used_stages |= range.stages;
+            } else if stages.intersects(range.stages) {
+                return Err(PushConstantUploadError::PartialRangeMatch {
+                    actual: stages,
+                    index,
+                    matched: range.stages,
+                });
+            }
+
+            let overlaps = offset < range.range.end && range.range.start < end_offset;
+            if overlaps && !stages.contains(range.stages) {
+                return Err(PushConstantUploadError::MissingStages {
+                    actual: stages,
+                    index,
+                    missing: range.stages - stages,
+                });
+            }
+        }
+        if used_stages != stages {
+            return Err(PushConstantUploadError::UnmatchedStages {
+                actual: stages,
+                unmatched: stages - used_stages,
+            });
+        }
+        Ok(())
+    }
 }
 
 #[repr(C)]
@@ -106,10 +106,13 @@ impl<B: GfxBackend> CommandAllocator<B> {
         CommandBuffer {
             raw: vec![init],
             is_recording: true,
+            is_valid: true,
             recorded_thread_id: thread_id,
             device_id,
             trackers: TrackerSet::new(B::VARIANT),
             used_swap_chain: None,
+            query_resets: Vec::new(),
+            discarded_surfaces: Vec::new(),
             limits,
             private_features,
             #[cfg(feature = "trace")]
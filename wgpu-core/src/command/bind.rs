@@ -117,6 +117,21 @@ impl BindGroupEntry {
         }
     }
 
+    /// Returns the expected layout and the provided layout and group, if any.
+    pub(crate) fn info(
+        &self,
+    ) -> (
+        Option<BindGroupLayoutId>,
+        Option<(BindGroupLayoutId, BindGroupId)>,
+    ) {
+        (
+            self.expected_layout_id,
+            self.provided
+                .as_ref()
+                .map(|pair| (pair.layout_id, pair.group_id.value)),
+        )
+    }
+
     fn actual_value(&self) -> Option<BindGroupId> {
         self.expected_layout_id.and_then(|layout_id| {
             self.provided.as_ref().and_then(|pair| {
@@ -37,13 +37,13 @@
 !*/
 
 use crate::{
-    command::{PhantomSlice, RawPass, RenderCommand},
+    command::{PhantomSlice, RawPass, RenderCommand, RenderCommandError},
     conv,
-    device::{AttachmentData, Label, RenderPassContext, MAX_VERTEX_BUFFERS},
+    device::{AttachmentData, ErrorContext, Label, RenderPassContext, MAX_VERTEX_BUFFERS},
     hub::{GfxBackend, Global, GlobalIdentityHandlerFactory, Input, Storage, Token},
     id,
     resource::BufferUse,
-    track::TrackerSet,
+    track::{TrackerSet, UseExtendError},
     LifeGuard, RefCount, Stored, MAX_BIND_GROUPS,
 };
 use arrayvec::ArrayVec;
@@ -79,8 +79,16 @@ impl RenderBundleEncoder {
         self.raw.parent
     }
 
-    pub fn fill_commands(&mut self, commands: &[RenderCommand], offsets: &[wgt::DynamicOffset]) {
-        unsafe { self.raw.fill_render_commands(commands, offsets) }
+    pub fn fill_commands(
+        &mut self,
+        commands: &[RenderCommand],
+        offsets: &[wgt::DynamicOffset],
+        push_constant_data: &[u32],
+    ) {
+        unsafe {
+            self.raw
+                .fill_render_commands(commands, offsets, push_constant_data)
+        }
     }
 
     pub fn destroy(mut self) {
@@ -97,6 +105,7 @@ pub struct RenderBundle {
     // without re-binding anything on the pipeline change.
     commands: Vec<RenderCommand>,
     dynamic_offsets: Vec<wgt::DynamicOffset>,
+    push_constant_data: Vec<u32>,
     pub(crate) device_id: Stored<id::DeviceId>,
     pub(crate) used: TrackerSet,
     pub(crate) context: RenderPassContext,
@@ -122,10 +131,11 @@ impl RenderBundle {
         bind_group_guard: &Storage<crate::binding_model::BindGroup<B>, id::BindGroupId>,
         pipeline_guard: &Storage<crate::pipeline::RenderPipeline<B>, id::RenderPipelineId>,
         buffer_guard: &Storage<crate::resource::Buffer<B>, id::BufferId>,
-    ) {
+    ) -> Result<(), RenderCommandError> {
         use hal::command::CommandBuffer as _;
 
         let mut offsets = self.dynamic_offsets.as_slice();
+        let mut push_constant_data = self.push_constant_data.as_slice();
         let mut index_type = hal::IndexType::U16;
         let mut pipeline_layout_id = None::<id::PipelineLayoutId>;
 
@@ -137,9 +147,17 @@ impl RenderBundle {
                     bind_group_id,
                     phantom_offsets: _,
                 } => {
-                    let bind_group = &bind_group_guard[bind_group_id];
+                    let bind_group = bind_group_guard
+                        .get(bind_group_id)
+                        .map_err(|_| RenderCommandError::InvalidBindGroup(bind_group_id))?;
+                    let pipeline_layout_id =
+                        pipeline_layout_id.ok_or(RenderCommandError::MissingPipeline)?;
+                    let pipeline_layout =
+                        pipeline_layout_guard.get(pipeline_layout_id).map_err(|_| {
+                            RenderCommandError::InvalidPipelineLayout(pipeline_layout_id)
+                        })?;
                     comb.bind_graphics_descriptor_sets(
-                        &pipeline_layout_guard[pipeline_layout_id.unwrap()].raw,
+                        &pipeline_layout.raw,
                         index as usize,
                         iter::once(bind_group.raw.raw()),
                         &offsets[..num_dynamic_offsets as usize],
@@ -147,7 +165,9 @@ impl RenderBundle {
                     offsets = &offsets[num_dynamic_offsets as usize..];
                 }
                 RenderCommand::SetPipeline(pipeline_id) => {
-                    let pipeline = &pipeline_guard[pipeline_id];
+                    let pipeline = pipeline_guard
+                        .get(pipeline_id)
+                        .map_err(|_| RenderCommandError::InvalidPipeline(pipeline_id))?;
                     comb.bind_graphics_pipeline(&pipeline.raw);
                     index_type = conv::map_index_format(pipeline.index_format);
                     pipeline_layout_id = Some(pipeline.layout_id.value);
@@ -157,7 +177,9 @@ impl RenderBundle {
                     offset,
                     size,
                 } => {
-                    let buffer = &buffer_guard[buffer_id];
+                    let buffer = buffer_guard
+                        .get(buffer_id)
+                        .map_err(|_| RenderCommandError::InvalidBuffer(buffer_id))?;
                     let view = hal::buffer::IndexBufferView {
                         buffer: &buffer.raw,
                         range: hal::buffer::SubRange {
@@ -179,7 +201,9 @@ impl RenderBundle {
                     offset,
                     size,
                 } => {
-                    let buffer = &buffer_guard[buffer_id];
+                    let buffer = buffer_guard
+                        .get(buffer_id)
+                        .map_err(|_| RenderCommandError::InvalidBuffer(buffer_id))?;
                     let range = hal::buffer::SubRange {
                         offset,
                         size: if size != wgt::BufferSize::WHOLE {
@@ -190,6 +214,27 @@ impl RenderBundle {
                     };
                     comb.bind_vertex_buffers(slot, iter::once((&buffer.raw, range)));
                 }
+                RenderCommand::SetPushConstant {
+                    stages,
+                    offset,
+                    size_bytes,
+                    phantom_data: _,
+                } => {
+                    let count = (size_bytes / wgt::PUSH_CONSTANT_ALIGNMENT) as usize;
+                    let pipeline_layout_id =
+                        pipeline_layout_id.ok_or(RenderCommandError::MissingPipeline)?;
+                    let pipeline_layout =
+                        pipeline_layout_guard.get(pipeline_layout_id).map_err(|_| {
+                            RenderCommandError::InvalidPipelineLayout(pipeline_layout_id)
+                        })?;
+                    comb.push_graphics_constants(
+                        &pipeline_layout.raw,
+                        conv::map_shader_stage_flags(stages),
+                        offset,
+                        &push_constant_data[..count],
+                    );
+                    push_constant_data = &push_constant_data[count..];
+                }
                 RenderCommand::Draw {
                     vertex_count,
                     instance_count,
@@ -215,13 +260,87 @@ impl RenderBundle {
                     );
                 }
                 RenderCommand::DrawIndirect { buffer_id, offset } => {
-                    let buffer = &buffer_guard[buffer_id];
+                    let buffer = buffer_guard
+                        .get(buffer_id)
+                        .map_err(|_| RenderCommandError::InvalidBuffer(buffer_id))?;
                     comb.draw_indirect(&buffer.raw, offset, 1, 0);
                 }
                 RenderCommand::DrawIndexedIndirect { buffer_id, offset } => {
-                    let buffer = &buffer_guard[buffer_id];
+                    let buffer = buffer_guard
+                        .get(buffer_id)
+                        .map_err(|_| RenderCommandError::InvalidBuffer(buffer_id))?;
                     comb.draw_indexed_indirect(&buffer.raw, offset, 1, 0);
                 }
+                RenderCommand::MultiDrawIndirect {
+                    buffer_id,
+                    offset,
+                    count,
+                    indexed: false,
+                } => {
+                    let buffer = buffer_guard
+                        .get(buffer_id)
+                        .map_err(|_| RenderCommandError::InvalidBuffer(buffer_id))?;
+                    let stride = wgt::DRAW_INDIRECT_SIZE as u32;
+                    comb.draw_indirect(&buffer.raw, offset, count, stride);
+                }
+                RenderCommand::MultiDrawIndirect {
+                    buffer_id,
+                    offset,
+                    count,
+                    indexed: true,
+                } => {
+                    let buffer = buffer_guard
+                        .get(buffer_id)
+                        .map_err(|_| RenderCommandError::InvalidBuffer(buffer_id))?;
+                    let stride = wgt::DRAW_INDEXED_INDIRECT_SIZE as u32;
+                    comb.draw_indexed_indirect(&buffer.raw, offset, count, stride);
+                }
+                RenderCommand::MultiDrawIndirectCount {
+                    buffer_id,
+                    offset,
+                    count_buffer_id,
+                    count_buffer_offset,
+                    max_count,
+                    indexed: false,
+                } => {
+                    let buffer = buffer_guard
+                        .get(buffer_id)
+                        .map_err(|_| RenderCommandError::InvalidBuffer(buffer_id))?;
+                    let count_buffer = buffer_guard
+                        .get(count_buffer_id)
+                        .map_err(|_| RenderCommandError::InvalidBuffer(count_buffer_id))?;
+                    comb.draw_indirect_count(
+                        &buffer.raw,
+                        offset,
+                        &count_buffer.raw,
+                        count_buffer_offset,
+                        max_count,
+                        wgt::DRAW_INDIRECT_SIZE as u32,
+                    );
+                }
+                RenderCommand::MultiDrawIndirectCount {
+                    buffer_id,
+                    offset,
+                    count_buffer_id,
+                    count_buffer_offset,
+                    max_count,
+                    indexed: true,
+                } => {
+                    let buffer = buffer_guard
+                        .get(buffer_id)
+                        .map_err(|_| RenderCommandError::InvalidBuffer(buffer_id))?;
+                    let count_buffer = buffer_guard
+                        .get(count_buffer_id)
+                        .map_err(|_| RenderCommandError::InvalidBuffer(count_buffer_id))?;
+                    comb.draw_indexed_indirect_count(
+                        &buffer.raw,
+                        offset,
+                        &count_buffer.raw,
+                        count_buffer_offset,
+                        max_count,
+                        wgt::DRAW_INDEXED_INDIRECT_SIZE as u32,
+                    );
+                }
                 RenderCommand::PushDebugGroup {
                     color: _,
                     len: _,
@@ -234,6 +353,9 @@ impl RenderBundle {
                 } => unimplemented!(),
                 RenderCommand::PopDebugGroup => unimplemented!(),
                 RenderCommand::ExecuteBundle(_)
+                | RenderCommand::WriteTimestamp { .. }
+                | RenderCommand::BeginOcclusionQuery { .. }
+                | RenderCommand::EndOcclusionQuery
                 | RenderCommand::SetBlendColor(_)
                 | RenderCommand::SetStencilReference(_)
                 | RenderCommand::SetViewport { .. }
@@ -241,6 +363,8 @@ impl RenderBundle {
                 | RenderCommand::End => unreachable!(),
             }
         }
+
+        Ok(())
     }
 }
 
@@ -388,6 +512,8 @@ struct State {
     bind: ArrayVec<[BindState; MAX_BIND_GROUPS]>,
     raw_dynamic_offsets: Vec<wgt::DynamicOffset>,
     flat_dynamic_offsets: Vec<wgt::DynamicOffset>,
+    push_constant_data: Vec<u32>,
+    pipeline_layout_id: Option<id::PipelineLayoutId>,
     used_bind_groups: usize,
 }
 
@@ -497,14 +623,25 @@ impl State {
     }
 }
 
+/// Error encountered when finishing a render bundle.
+#[derive(Clone, Debug, PartialEq)]
+pub enum RenderBundleError {
+    /// A command failed, at the given byte offset in the `RawPass` stream.
+    Command {
+        offset: usize,
+        error: RenderCommandError,
+    },
+}
+
 impl<G: GlobalIdentityHandlerFactory> Global<G> {
     pub fn render_bundle_encoder_finish<B: GfxBackend>(
         &self,
         bundle_encoder: RenderBundleEncoder,
         desc: &wgt::RenderBundleDescriptor<Label>,
         id_in: Input<G, id::RenderBundleId>,
-    ) -> id::RenderBundleId {
+    ) -> (id::RenderBundleId, Option<RenderBundleError>) {
         let hub = B::hub(self);
+        let context = ErrorContext::with_raw_label("render_bundle_encoder_finish", &desc.label);
         let mut token = Token::root();
         let (device_guard, mut token) = hub.devices.read(&mut token);
 
@@ -525,6 +662,8 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                 bind: (0..MAX_BIND_GROUPS).map(|_| BindState::new()).collect(),
                 raw_dynamic_offsets: Vec::new(),
                 flat_dynamic_offsets: Vec::new(),
+                push_constant_data: Vec::new(),
+                pipeline_layout_id: None,
                 used_bind_groups: 0,
             };
             let mut commands = Vec::new();
@@ -537,12 +676,14 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                 first_vertex: 0,
                 first_instance: 0,
             };
-            loop {
+            let mut command_offset;
+            let result = loop {
                 assert!(
                     unsafe { peeker.add(RenderCommand::max_size()) <= raw_data_end },
                     "RenderCommand (size {}) is too big to fit within raw_data",
                     RenderCommand::max_size(),
                 );
+                command_offset = peeker as usize - data.as_ptr() as usize;
                 peeker = unsafe { RenderCommand::peek_from(peeker, &mut command) };
 
                 match command {
@@ -570,22 +711,32 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                             );
                         }
 
-                        let bind_group = state
-                            .trackers
-                            .bind_groups
-                            .use_extend(&*bind_group_guard, bind_group_id, (), ())
-                            .unwrap();
+                        let bind_group = match state.trackers.bind_groups.use_extend(
+                            &*bind_group_guard,
+                            bind_group_id,
+                            (),
+                            (),
+                        ) {
+                            Ok(bind_group) => bind_group,
+                            Err(_) => {
+                                break Err(RenderCommandError::InvalidBindGroup(bind_group_id))
+                            }
+                        };
                         assert_eq!(bind_group.dynamic_count, offsets.len());
 
                         state.set_bind_group(index, bind_group_id, bind_group.layout_id, offsets);
                         state.trackers.merge_extend(&bind_group.used);
                     }
                     RenderCommand::SetPipeline(pipeline_id) => {
-                        let pipeline = state
-                            .trackers
-                            .render_pipes
-                            .use_extend(&*pipeline_guard, pipeline_id, (), ())
-                            .unwrap();
+                        let pipeline = match state.trackers.render_pipes.use_extend(
+                            &*pipeline_guard,
+                            pipeline_id,
+                            (),
+                            (),
+                        ) {
+                            Ok(pipeline) => pipeline,
+                            Err(_) => break Err(RenderCommandError::InvalidPipeline(pipeline_id)),
+                        };
 
                         assert!(
                             bundle_encoder.context.compatible(&pipeline.pass_context),
@@ -593,7 +744,15 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                         );
                         //TODO: check read-only depth
 
-                        let layout = &pipeline_layout_guard[pipeline.layout_id.value];
+                        let layout = match pipeline_layout_guard.get(pipeline.layout_id.value) {
+                            Ok(layout) => layout,
+                            Err(_) => {
+                                break Err(RenderCommandError::InvalidPipelineLayout(
+                                    pipeline.layout_id.value,
+                                ))
+                            }
+                        };
+                        state.pipeline_layout_id = Some(pipeline.layout_id.value);
 
                         state.set_pipeline(
                             pipeline.index_format,
@@ -607,11 +766,20 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                         offset,
                         size,
                     } => {
-                        let buffer = state
-                            .trackers
-                            .buffers
-                            .use_extend(&*buffer_guard, buffer_id, (), BufferUse::INDEX)
-                            .unwrap();
+                        let buffer = match state.trackers.buffers.use_extend(
+                            &*buffer_guard,
+                            buffer_id,
+                            (),
+                            BufferUse::INDEX,
+                        ) {
+                            Ok(buffer) => buffer,
+                            Err(UseExtendError::InvalidResource) => {
+                                break Err(RenderCommandError::InvalidBuffer(buffer_id))
+                            }
+                            Err(UseExtendError::Conflict(_)) => {
+                                break Err(RenderCommandError::BufferUsageConflict(buffer_id))
+                            }
+                        };
                         assert!(buffer.usage.contains(wgt::BufferUsage::INDEX), "An invalid setIndexBuffer call has been made. The buffer usage is {:?} which does not contain required usage INDEX", buffer.usage);
 
                         let end = if size != wgt::BufferSize::WHOLE {
@@ -627,11 +795,20 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                         offset,
                         size,
                     } => {
-                        let buffer = state
-                            .trackers
-                            .buffers
-                            .use_extend(&*buffer_guard, buffer_id, (), BufferUse::VERTEX)
-                            .unwrap();
+                        let buffer = match state.trackers.buffers.use_extend(
+                            &*buffer_guard,
+                            buffer_id,
+                            (),
+                            BufferUse::VERTEX,
+                        ) {
+                            Ok(buffer) => buffer,
+                            Err(UseExtendError::InvalidResource) => {
+                                break Err(RenderCommandError::InvalidBuffer(buffer_id))
+                            }
+                            Err(UseExtendError::Conflict(_)) => {
+                                break Err(RenderCommandError::BufferUsageConflict(buffer_id))
+                            }
+                        };
                         assert!(
                             buffer.usage.contains(wgt::BufferUsage::VERTEX),
                             "An invalid setVertexBuffer call has been made. The buffer usage is {:?} which does not contain required usage VERTEX",
@@ -645,6 +822,42 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                         };
                         state.vertex[slot as usize].set_buffer(buffer_id, offset..end);
                     }
+                    RenderCommand::SetPushConstant {
+                        stages,
+                        offset,
+                        size_bytes,
+                        phantom_data,
+                    } => {
+                        let (new_peeker, data) = unsafe {
+                            phantom_data.decode_unaligned(
+                                peeker,
+                                (size_bytes / wgt::PUSH_CONSTANT_ALIGNMENT) as usize,
+                                raw_data_end,
+                            )
+                        };
+                        peeker = new_peeker;
+
+                        let pipeline_layout_id = match state.pipeline_layout_id {
+                            Some(id) => id,
+                            None => break Err(RenderCommandError::MissingPipeline),
+                        };
+                        let layout = match pipeline_layout_guard.get(pipeline_layout_id) {
+                            Ok(layout) => layout,
+                            Err(_) => {
+                                break Err(RenderCommandError::InvalidPipelineLayout(
+                                    pipeline_layout_id,
+                                ))
+                            }
+                        };
+                        if let Err(e) =
+                            layout.validate_push_constant_ranges(stages, offset, size_bytes)
+                        {
+                            break Err(RenderCommandError::PushConstant(e));
+                        }
+
+                        state.push_constant_data.extend_from_slice(data);
+                        commands.push(command);
+                    }
                     RenderCommand::Draw {
                         vertex_count,
                         instance_count,
@@ -699,11 +912,20 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                         buffer_id,
                         offset: _,
                     } => {
-                        let buffer = state
-                            .trackers
-                            .buffers
-                            .use_extend(&*buffer_guard, buffer_id, (), BufferUse::INDIRECT)
-                            .unwrap();
+                        let buffer = match state.trackers.buffers.use_extend(
+                            &*buffer_guard,
+                            buffer_id,
+                            (),
+                            BufferUse::INDIRECT,
+                        ) {
+                            Ok(buffer) => buffer,
+                            Err(UseExtendError::InvalidResource) => {
+                                break Err(RenderCommandError::InvalidBuffer(buffer_id))
+                            }
+                            Err(UseExtendError::Conflict(_)) => {
+                                break Err(RenderCommandError::BufferUsageConflict(buffer_id))
+                            }
+                        };
                         assert!(
                             buffer.usage.contains(wgt::BufferUsage::INDIRECT),
                             "An invalid drawIndirect call has been made. The buffer usage is {:?} which does not contain required usage INDIRECT",
@@ -718,11 +940,20 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                         buffer_id,
                         offset: _,
                     } => {
-                        let buffer = state
-                            .trackers
-                            .buffers
-                            .use_extend(&*buffer_guard, buffer_id, (), BufferUse::INDIRECT)
-                            .unwrap();
+                        let buffer = match state.trackers.buffers.use_extend(
+                            &*buffer_guard,
+                            buffer_id,
+                            (),
+                            BufferUse::INDIRECT,
+                        ) {
+                            Ok(buffer) => buffer,
+                            Err(UseExtendError::InvalidResource) => {
+                                break Err(RenderCommandError::InvalidBuffer(buffer_id))
+                            }
+                            Err(UseExtendError::Conflict(_)) => {
+                                break Err(RenderCommandError::BufferUsageConflict(buffer_id))
+                            }
+                        };
                         assert!(
                             buffer.usage.contains(wgt::BufferUsage::INDIRECT),
                             "An invalid drawIndexedIndirect call has been made. The buffer usage is {:?} which does not contain required usage INDIRECT",
@@ -734,7 +965,135 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                         commands.extend(state.flush_binds());
                         commands.push(command);
                     }
-                    RenderCommand::End => break,
+                    RenderCommand::MultiDrawIndirect {
+                        buffer_id,
+                        offset,
+                        count,
+                        indexed,
+                    } => {
+                        assert!(
+                            device
+                                .extensions
+                                .contains(wgt::Extensions::MULTI_DRAW_INDIRECT),
+                            "Extension MULTI_DRAW_INDIRECT must be enabled"
+                        );
+                        let buffer = match state.trackers.buffers.use_extend(
+                            &*buffer_guard,
+                            buffer_id,
+                            (),
+                            BufferUse::INDIRECT,
+                        ) {
+                            Ok(buffer) => buffer,
+                            Err(UseExtendError::InvalidResource) => {
+                                break Err(RenderCommandError::InvalidBuffer(buffer_id))
+                            }
+                            Err(UseExtendError::Conflict(_)) => {
+                                break Err(RenderCommandError::BufferUsageConflict(buffer_id))
+                            }
+                        };
+                        assert!(
+                            buffer.usage.contains(wgt::BufferUsage::INDIRECT),
+                            "An invalid multiDrawIndirect call has been made. The buffer usage is {:?} which does not contain required usage INDIRECT",
+                            buffer.usage
+                        );
+                        let stride = if indexed {
+                            wgt::DRAW_INDEXED_INDIRECT_SIZE
+                        } else {
+                            wgt::DRAW_INDIRECT_SIZE
+                        };
+                        let end_offset = offset + stride * count as wgt::BufferAddress;
+                        assert!(
+                            end_offset <= buffer.size,
+                            "Indirect draws end at {} which is beyond the buffer size {}",
+                            end_offset,
+                            buffer.size
+                        );
+
+                        if indexed {
+                            commands.extend(state.index.flush());
+                        }
+                        commands.extend(state.flush_vertices());
+                        commands.extend(state.flush_binds());
+                        commands.push(command);
+                    }
+                    RenderCommand::MultiDrawIndirectCount {
+                        buffer_id,
+                        offset,
+                        count_buffer_id,
+                        count_buffer_offset,
+                        max_count,
+                        indexed,
+                    } => {
+                        assert!(
+                            device
+                                .extensions
+                                .contains(wgt::Extensions::MULTI_DRAW_INDIRECT_COUNT),
+                            "Extension MULTI_DRAW_INDIRECT_COUNT must be enabled"
+                        );
+                        let stride = if indexed {
+                            wgt::DRAW_INDEXED_INDIRECT_SIZE
+                        } else {
+                            wgt::DRAW_INDIRECT_SIZE
+                        };
+                        let buffer = match state.trackers.buffers.use_extend(
+                            &*buffer_guard,
+                            buffer_id,
+                            (),
+                            BufferUse::INDIRECT,
+                        ) {
+                            Ok(buffer) => buffer,
+                            Err(UseExtendError::InvalidResource) => {
+                                break Err(RenderCommandError::InvalidBuffer(buffer_id))
+                            }
+                            Err(UseExtendError::Conflict(_)) => {
+                                break Err(RenderCommandError::BufferUsageConflict(buffer_id))
+                            }
+                        };
+                        let count_buffer = match state.trackers.buffers.use_extend(
+                            &*buffer_guard,
+                            count_buffer_id,
+                            (),
+                            BufferUse::INDIRECT,
+                        ) {
+                            Ok(buffer) => buffer,
+                            Err(UseExtendError::InvalidResource) => {
+                                break Err(RenderCommandError::InvalidBuffer(count_buffer_id))
+                            }
+                            Err(UseExtendError::Conflict(_)) => {
+                                break Err(RenderCommandError::BufferUsageConflict(count_buffer_id))
+                            }
+                        };
+                        let ranges = [
+                            (
+                                buffer,
+                                offset,
+                                offset + stride * max_count as wgt::BufferAddress,
+                            ),
+                            (count_buffer, count_buffer_offset, count_buffer_offset + 4),
+                        ];
+                        for &(buffer, begin, end) in ranges.iter() {
+                            assert!(
+                                buffer.usage.contains(wgt::BufferUsage::INDIRECT),
+                                "An invalid multiDrawIndirectCount call has been made. The buffer usage is {:?} which does not contain required usage INDIRECT",
+                                buffer.usage
+                            );
+                            assert!(
+                                end <= buffer.size,
+                                "Indirect range {}..{} is beyond the buffer size {}",
+                                begin,
+                                end,
+                                buffer.size
+                            );
+                        }
+
+                        if indexed {
+                            commands.extend(state.index.flush());
+                        }
+                        commands.extend(state.flush_vertices());
+                        commands.extend(state.flush_binds());
+                        commands.push(command);
+                    }
+                    RenderCommand::End => break Ok(()),
                     RenderCommand::PushDebugGroup {
                         color: _,
                         len: _,
@@ -747,6 +1106,9 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                     } => unimplemented!(),
                     RenderCommand::PopDebugGroup => unimplemented!(),
                     RenderCommand::ExecuteBundle(_)
+                    | RenderCommand::WriteTimestamp { .. }
+                    | RenderCommand::BeginOcclusionQuery { .. }
+                    | RenderCommand::EndOcclusionQuery
                     | RenderCommand::SetBlendColor(_)
                     | RenderCommand::SetStencilReference(_)
                     | RenderCommand::SetViewport { .. }
@@ -754,21 +1116,35 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                         unreachable!("not supported by a render bundle")
                     }
                 }
+            };
+            if let Err(error) = result {
+                Err(RenderBundleError::Command {
+                    offset: command_offset,
+                    error,
+                })
+            } else {
+                log::debug!("Render bundle {:?} = {:#?}", id_in, state.trackers);
+                let _ = desc.label; //TODO: actually use
+                                    //TODO: check if the device is still alive
+                Ok(RenderBundle {
+                    commands,
+                    dynamic_offsets: state.flat_dynamic_offsets,
+                    push_constant_data: state.push_constant_data,
+                    device_id: Stored {
+                        value: device_id,
+                        ref_count: device.life_guard.add_ref(),
+                    },
+                    used: state.trackers,
+                    context: bundle_encoder.context,
+                    life_guard: LifeGuard::new(),
+                })
             }
-
-            log::debug!("Render bundle {:?} = {:#?}", id_in, state.trackers);
-            let _ = desc.label; //TODO: actually use
-                                //TODO: check if the device is still alive
-            RenderBundle {
-                commands,
-                dynamic_offsets: state.flat_dynamic_offsets,
-                device_id: Stored {
-                    value: device_id,
-                    ref_count: device.life_guard.add_ref(),
-                },
-                used: state.trackers,
-                context: bundle_encoder.context,
-                life_guard: LifeGuard::new(),
+        };
+        let render_bundle = match render_bundle {
+            Ok(render_bundle) => render_bundle,
+            Err(error) => {
+                let id = hub.render_bundles.register_error(id_in, &mut token);
+                return (id, Some(device.capture(context, error)));
             }
         };
 
@@ -788,6 +1164,7 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                     desc: trace::RenderBundleDescriptor::new(desc.label, &bundle.context),
                     commands: bundle.commands.clone(),
                     dynamic_offsets: bundle.dynamic_offsets.clone(),
+                    push_constant_data: bundle.push_constant_data.clone(),
                 });
             }
             None => {}
@@ -799,7 +1176,7 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
             .bundles
             .init(id, ref_count, PhantomData)
             .unwrap();
-        id
+        (id, None)
     }
 }
 
@@ -874,6 +1251,35 @@ pub mod bundle_ffi {
         });
     }
 
+    /// # Safety
+    ///
+    /// This function is unsafe as there is no guarantee that the given pointer is
+    /// valid for `size_bytes` bytes.
+    #[no_mangle]
+    pub unsafe extern "C" fn wgpu_render_bundle_set_push_constants(
+        bundle_encoder: &mut RenderBundleEncoder,
+        stages: wgt::ShaderStage,
+        offset: u32,
+        size_bytes: u32,
+        data: *const u32,
+    ) {
+        assert_eq!(
+            size_bytes % wgt::PUSH_CONSTANT_ALIGNMENT,
+            0,
+            "Push constant size must be aligned to 4 bytes."
+        );
+        bundle_encoder.raw.encode(&RenderCommand::SetPushConstant {
+            stages,
+            offset,
+            size_bytes,
+            phantom_data: PhantomSlice::default(),
+        });
+        bundle_encoder.raw.encode_slice(slice::from_raw_parts(
+            data,
+            (size_bytes / wgt::PUSH_CONSTANT_ALIGNMENT) as usize,
+        ));
+    }
+
     #[no_mangle]
     pub unsafe extern "C" fn wgpu_render_bundle_draw(
         bundle_encoder: &mut RenderBundleEncoder,
@@ -930,6 +1336,82 @@ pub mod bundle_ffi {
             .encode(&RenderCommand::DrawIndexedIndirect { buffer_id, offset });
     }
 
+    #[no_mangle]
+    pub unsafe extern "C" fn wgpu_render_bundle_multi_draw_indirect(
+        bundle_encoder: &mut RenderBundleEncoder,
+        buffer_id: id::BufferId,
+        offset: BufferAddress,
+        count: u32,
+    ) {
+        bundle_encoder
+            .raw
+            .encode(&RenderCommand::MultiDrawIndirect {
+                buffer_id,
+                offset,
+                count,
+                indexed: false,
+            });
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn wgpu_render_bundle_multi_draw_indexed_indirect(
+        bundle_encoder: &mut RenderBundleEncoder,
+        buffer_id: id::BufferId,
+        offset: BufferAddress,
+        count: u32,
+    ) {
+        bundle_encoder
+            .raw
+            .encode(&RenderCommand::MultiDrawIndirect {
+                buffer_id,
+                offset,
+                count,
+                indexed: true,
+            });
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn wgpu_render_bundle_multi_draw_indirect_count(
+        bundle_encoder: &mut RenderBundleEncoder,
+        buffer_id: id::BufferId,
+        offset: BufferAddress,
+        count_buffer_id: id::BufferId,
+        count_buffer_offset: BufferAddress,
+        max_count: u32,
+    ) {
+        bundle_encoder
+            .raw
+            .encode(&RenderCommand::MultiDrawIndirectCount {
+                buffer_id,
+                offset,
+                count_buffer_id,
+                count_buffer_offset,
+                max_count,
+                indexed: false,
+            });
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn wgpu_render_bundle_multi_draw_indexed_indirect_count(
+        bundle_encoder: &mut RenderBundleEncoder,
+        buffer_id: id::BufferId,
+        offset: BufferAddress,
+        count_buffer_id: id::BufferId,
+        count_buffer_offset: BufferAddress,
+        max_count: u32,
+    ) {
+        bundle_encoder
+            .raw
+            .encode(&RenderCommand::MultiDrawIndirectCount {
+                buffer_id,
+                offset,
+                count_buffer_id,
+                count_buffer_offset,
+                max_count,
+                indexed: true,
+            });
+    }
+
     #[no_mangle]
     pub extern "C" fn wgpu_render_bundle_push_debug_group(
         _bundle_encoder: &mut RenderBundleEncoder,
@@ -0,0 +1,307 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+#[cfg(feature = "trace")]
+use crate::device::trace::Command as TraceCommand;
+use crate::{
+    command::{transfer::texture_block_info, CommandBuffer},
+    device::{all_buffer_stages, all_image_stages, ErrorContext},
+    hub::{GfxBackend, Global, GlobalIdentityHandlerFactory, Token},
+    id::{BufferId, CommandEncoderId, TextureId},
+    resource::{BufferUse, TextureUse},
+};
+
+use hal::command::CommandBuffer as _;
+use wgt::{BufferAddress, BufferSize, BufferUsage, ImageSubresourceRange, TextureUsage};
+
+use std::iter;
+
+/// Error encountered while attempting to clear a buffer or a texture.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ClearError {
+    /// The buffer is invalid.
+    InvalidBuffer(BufferId),
+    /// The texture is invalid.
+    InvalidTexture(TextureId),
+    /// The destination usage doesn't contain `COPY_DST`.
+    MissingCopyDstUsageFlag,
+    /// The clear size must be a multiple of `COPY_BUFFER_ALIGNMENT`.
+    UnalignedFillSize(BufferAddress),
+    /// The buffer offset must be a multiple of `COPY_BUFFER_ALIGNMENT`.
+    UnalignedBufferOffset(BufferAddress),
+    /// The clear overruns the bounds of the buffer.
+    BufferOverrun {
+        start_offset: BufferAddress,
+        end_offset: BufferAddress,
+        buffer_size: BufferAddress,
+    },
+    /// The texture doesn't have the requested aspect.
+    MissingTextureAspect {
+        texture_format: wgt::TextureFormat,
+        subresource_range_aspects: wgt::TextureAspect,
+    },
+    /// The mip level range doesn't fit in the texture.
+    InvalidMipLevelRange { base: u32, count: u32, total: u32 },
+    /// The array layer range doesn't fit in the texture.
+    InvalidArrayLayerRange { base: u32, count: u32, total: u32 },
+    /// Compressed textures can't be cleared.
+    CompressedTexture(wgt::TextureFormat),
+    /// The device is lost.
+    DeviceLost,
+}
+
+/// Returns the clear value that fills the given aspects of a texture with zeros.
+pub(crate) fn zero_clear_value(aspects: hal::format::Aspects) -> hal::command::ClearValue {
+    // All-zero bits are the same for float, signed and unsigned colors.
+    if aspects.contains(hal::format::Aspects::COLOR) {
+        hal::command::ClearValue {
+            color: hal::command::ClearColor { float32: [0.0; 4] },
+        }
+    } else {
+        hal::command::ClearValue {
+            depth_stencil: hal::command::ClearDepthStencil {
+                depth: 0.0,
+                stencil: 0,
+            },
+        }
+    }
+}
+
+impl<G: GlobalIdentityHandlerFactory> Global<G> {
+    pub fn command_encoder_clear_buffer<B: GfxBackend>(
+        &self,
+        command_encoder_id: CommandEncoderId,
+        dst: BufferId,
+        offset: BufferAddress,
+        size: BufferSize,
+    ) -> Result<(), ClearError> {
+        let hub = B::hub(self);
+        let context = ErrorContext::new("command_encoder_clear_buffer");
+        let mut token = Token::root();
+
+        let (device_guard, mut token) = hub.devices.read(&mut token);
+        let (mut cmb_guard, mut token) = hub.command_buffers.write(&mut token);
+        let cmb = &mut cmb_guard[command_encoder_id];
+        let device = &device_guard[cmb.device_id.value];
+        if device.is_lost() {
+            return Err(ClearError::DeviceLost);
+        }
+        let (buffer_guard, _) = hub.buffers.read(&mut token);
+
+        #[cfg(feature = "trace")]
+        match cmb.commands {
+            Some(ref mut list) => list.push(TraceCommand::ClearBuffer { dst, offset, size }),
+            None => (),
+        }
+
+        let dst_buffer = buffer_guard
+            .get(dst)
+            .map_err(|_| cmb.invalidate(device, context, ClearError::InvalidBuffer(dst)))?;
+        if !dst_buffer.usage.contains(BufferUsage::COPY_DST) {
+            return Err(cmb.invalidate(device, context, ClearError::MissingCopyDstUsageFlag));
+        }
+        if offset % wgt::COPY_BUFFER_ALIGNMENT != 0 {
+            return Err(cmb.invalidate(device, context, ClearError::UnalignedBufferOffset(offset)));
+        }
+        let end_offset = if size == BufferSize::WHOLE {
+            Some(dst_buffer.size)
+        } else {
+            if size.0 % wgt::COPY_BUFFER_ALIGNMENT != 0 {
+                return Err(cmb.invalidate(device, context, ClearError::UnalignedFillSize(size.0)));
+            }
+            offset.checked_add(size.0)
+        };
+        let end_offset = match end_offset {
+            Some(end_offset) if offset <= end_offset && end_offset <= dst_buffer.size => end_offset,
+            _ => {
+                return Err(cmb.invalidate(
+                    device,
+                    context,
+                    ClearError::BufferOverrun {
+                        start_offset: offset,
+                        end_offset: end_offset.unwrap_or(BufferAddress::MAX),
+                        buffer_size: dst_buffer.size,
+                    },
+                ))
+            }
+        };
+        if offset == end_offset {
+            log::trace!("Ignoring clear_buffer of size 0");
+            return Ok(());
+        }
+
+        // The clear is valid, only now the usage can be tracked.
+        let (dst_buffer, dst_pending) = cmb
+            .trackers
+            .buffers
+            .use_replace(&*buffer_guard, dst, (), BufferUse::COPY_DST)
+            .unwrap();
+        let dst_barrier = dst_pending.map(|pending| pending.into_hal(dst_buffer));
+
+        let cmb_raw = cmb.raw.last_mut().unwrap();
+        unsafe {
+            cmb_raw.pipeline_barrier(
+                all_buffer_stages()..hal::pso::PipelineStage::TRANSFER,
+                hal::memory::Dependencies::empty(),
+                dst_barrier,
+            );
+            cmb_raw.fill_buffer(
+                &dst_buffer.raw,
+                hal::buffer::SubRange {
+                    offset,
+                    size: Some(end_offset - offset),
+                },
+                0,
+            );
+        }
+        Ok(())
+    }
+
+    pub fn command_encoder_clear_texture<B: GfxBackend>(
+        &self,
+        command_encoder_id: CommandEncoderId,
+        dst: TextureId,
+        subresource_range: &ImageSubresourceRange,
+    ) -> Result<(), ClearError> {
+        let hub = B::hub(self);
+        let context = ErrorContext::new("command_encoder_clear_texture");
+        let mut token = Token::root();
+
+        let (device_guard, mut token) = hub.devices.read(&mut token);
+        let (mut cmb_guard, mut token) = hub.command_buffers.write(&mut token);
+        let cmb = &mut cmb_guard[command_encoder_id];
+        let device = &device_guard[cmb.device_id.value];
+        if device.is_lost() {
+            return Err(ClearError::DeviceLost);
+        }
+        let (_, mut token) = hub.buffers.read(&mut token); // skip token
+        let (texture_guard, _) = hub.textures.read(&mut token);
+
+        #[cfg(feature = "trace")]
+        match cmb.commands {
+            Some(ref mut list) => list.push(TraceCommand::ClearTexture {
+                dst,
+                subresource_range: *subresource_range,
+            }),
+            None => (),
+        }
+
+        let texture = texture_guard
+            .get(dst)
+            .map_err(|_| cmb.invalidate(device, context, ClearError::InvalidTexture(dst)))?;
+
+        let (block_width, block_height, _) =
+            texture_block_info(texture.format, cmb.private_features);
+        if (block_width, block_height) != (1, 1) {
+            return Err(cmb.invalidate(
+                device,
+                context,
+                ClearError::CompressedTexture(texture.format),
+            ));
+        }
+        if !texture.usage.contains(TextureUsage::COPY_DST) {
+            return Err(cmb.invalidate(device, context, ClearError::MissingCopyDstUsageFlag));
+        }
+
+        let full_aspects = texture.full_range.aspects;
+        let aspects = match subresource_range.aspect {
+            wgt::TextureAspect::All => full_aspects,
+            wgt::TextureAspect::DepthOnly => hal::format::Aspects::DEPTH,
+            wgt::TextureAspect::StencilOnly => hal::format::Aspects::STENCIL,
+        };
+        if !full_aspects.contains(aspects) {
+            return Err(cmb.invalidate(
+                device,
+                context,
+                ClearError::MissingTextureAspect {
+                    texture_format: texture.format,
+                    subresource_range_aspects: subresource_range.aspect,
+                },
+            ));
+        }
+
+        let level_end = texture.full_range.levels.end as u32;
+        let end_level = if subresource_range.level_count == 0 {
+            Some(level_end)
+        } else {
+            subresource_range
+                .base_mip_level
+                .checked_add(subresource_range.level_count)
+        };
+        let end_level = match end_level {
+            Some(end) if subresource_range.base_mip_level < end && end <= level_end => end,
+            _ => {
+                return Err(cmb.invalidate(
+                    device,
+                    context,
+                    ClearError::InvalidMipLevelRange {
+                        base: subresource_range.base_mip_level,
+                        count: subresource_range.level_count,
+                        total: level_end,
+                    },
+                ))
+            }
+        };
+        let layer_end = texture.full_range.layers.end as u32;
+        let end_layer = if subresource_range.array_layer_count == 0 {
+            Some(layer_end)
+        } else {
+            subresource_range
+                .base_array_layer
+                .checked_add(subresource_range.array_layer_count)
+        };
+        let end_layer = match end_layer {
+            Some(end) if subresource_range.base_array_layer < end && end <= layer_end => end,
+            _ => {
+                return Err(cmb.invalidate(
+                    device,
+                    context,
+                    ClearError::InvalidArrayLayerRange {
+                        base: subresource_range.base_array_layer,
+                        count: subresource_range.array_layer_count,
+                        total: layer_end,
+                    },
+                ))
+            }
+        };
+        let range = hal::image::SubresourceRange {
+            aspects,
+            levels: subresource_range.base_mip_level as hal::image::Level
+                ..end_level as hal::image::Level,
+            layers: subresource_range.base_array_layer as hal::image::Layer
+                ..end_layer as hal::image::Layer,
+        };
+
+        // The clear is valid, only now the usage can be tracked.
+        CommandBuffer::clear_discarded_surfaces(
+            cmb.raw.last_mut().unwrap(),
+            &mut cmb.trackers,
+            &mut cmb.discarded_surfaces,
+            iter::once(dst),
+            &*texture_guard,
+        );
+        let (dst_texture, dst_pending) = cmb
+            .trackers
+            .textures
+            .use_replace(&*texture_guard, dst, range.clone(), TextureUse::COPY_DST)
+            .unwrap();
+        let dst_barriers = dst_pending.map(|pending| pending.into_hal(dst_texture));
+
+        let cmb_raw = cmb.raw.last_mut().unwrap();
+        unsafe {
+            cmb_raw.pipeline_barrier(
+                all_image_stages()..hal::pso::PipelineStage::TRANSFER,
+                hal::memory::Dependencies::empty(),
+                dst_barriers,
+            );
+            cmb_raw.clear_image(
+                &dst_texture.raw,
+                hal::image::Layout::TransferDstOptimal,
+                zero_clear_value(aspects),
+                iter::once(range),
+            );
+        }
+        Ok(())
+    }
+}
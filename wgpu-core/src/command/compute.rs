@@ -3,11 +3,12 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use crate::{
+    binding_model::PushConstantUploadError,
     command::{
         bind::{Binder, LayoutChange},
-        CommandBuffer, PhantomSlice,
+        CommandBuffer, PhantomSlice, QueryError,
     },
-    device::all_buffer_stages,
+    device::{all_buffer_stages, ErrorContext},
     hub::{GfxBackend, Global, GlobalIdentityHandlerFactory, Token},
     id,
     resource::BufferUse,
@@ -25,6 +26,63 @@ enum PipelineState {
     Set,
 }
 
+/// Error validating a dispatch in a compute pass.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DispatchError {
+    /// The compute pipeline must be set before dispatching.
+    MissingPipeline,
+    /// The current pipeline layout is incompatible with a bound bind group.
+    IncompatibleBindGroup {
+        index: u32,
+        expected: id::BindGroupLayoutId,
+        provided: Option<(id::BindGroupLayoutId, id::BindGroupId)>,
+    },
+}
+
+/// Error encountered when executing a compute command.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ComputeCommandError {
+    /// The bind group is invalid.
+    InvalidBindGroup(id::BindGroupId),
+    /// The compute pipeline is invalid.
+    InvalidPipeline(id::ComputePipelineId),
+    /// The buffer is invalid.
+    InvalidBuffer(id::BufferId),
+    /// The bind group index is beyond the maximum number of bind groups.
+    BindGroupIndexOutOfRange { index: u8, max: u32 },
+    /// The number of dynamic offsets doesn't match the bind group.
+    InvalidDynamicOffsetCount { actual: usize, expected: usize },
+    /// A dynamic offset must be a multiple of `BIND_BUFFER_ALIGNMENT`.
+    UnalignedDynamicOffset(DynamicOffset),
+    /// The buffer usage doesn't contain the usage required by the command.
+    MissingBufferUsage {
+        actual: BufferUsage,
+        expected: BufferUsage,
+    },
+    /// There is no debug group to pop.
+    InvalidPopDebugGroup,
+    /// Push constants require a compute pipeline to be set.
+    MissingPipeline,
+    /// The push constant data doesn't match the pipeline layout ranges.
+    PushConstant(PushConstantUploadError),
+    /// The query is invalid or doesn't match the query set.
+    Query(QueryError),
+    /// The dispatch state is incomplete.
+    Dispatch(DispatchError),
+}
+
+/// Error encountered when running a compute pass.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ComputePassError {
+    /// A command failed, at the given byte offset in the `RawPass` stream.
+    Command {
+        offset: usize,
+        error: ComputeCommandError,
+    },
+    /// The device is lost.
+    DeviceLost,
+}
+
 #[derive(Clone, Copy, Debug, PeekPoke)]
 #[cfg_attr(feature = "trace", derive(serde::Serialize))]
 #[cfg_attr(feature = "replay", derive(serde::Deserialize))]
@@ -37,6 +95,12 @@ pub enum ComputeCommand {
         phantom_offsets: PhantomSlice<DynamicOffset>,
     },
     SetPipeline(id::ComputePipelineId),
+    SetPushConstant {
+        offset: u32,
+        size_bytes: u32,
+        #[cfg_attr(any(feature = "trace", feature = "replay"), serde(skip))]
+        phantom_data: PhantomSlice<u32>,
+    },
     Dispatch([u32; 3]),
     DispatchIndirect {
         buffer_id: id::BufferId,
@@ -55,15 +119,40 @@ pub enum ComputeCommand {
         #[cfg_attr(any(feature = "trace", feature = "replay"), serde(skip))]
         phantom_marker: PhantomSlice<u8>,
     },
+    WriteTimestamp {
+        query_set_id: id::QuerySetId,
+        query_index: u32,
+    },
     End,
 }
 
 #[derive(Debug)]
 struct State {
     binder: Binder,
+    pipeline: PipelineState,
     debug_scope_depth: u32,
 }
 
+impl State {
+    fn is_ready(&self) -> Result<(), DispatchError> {
+        let bind_mask = self.binder.invalid_mask();
+        if bind_mask != 0 {
+            let index = bind_mask.trailing_zeros();
+            let (expected, provided) = self.binder.entries[index as usize].info();
+            return Err(DispatchError::IncompatibleBindGroup {
+                index,
+                // invalid entries always have an expected layout
+                expected: expected.unwrap(),
+                provided,
+            });
+        }
+        if self.pipeline == PipelineState::Required {
+            return Err(DispatchError::MissingPipeline);
+        }
+        Ok(())
+    }
+}
+
 impl Default for ComputeCommand {
     fn default() -> Self {
         ComputeCommand::End
@@ -79,16 +168,24 @@ impl super::RawPass<id::CommandEncoderId> {
         &mut self,
         commands: &[ComputeCommand],
         mut offsets: &[DynamicOffset],
+        mut push_constant_data: &[u32],
     ) {
         for com in commands {
             self.encode(com);
-            if let ComputeCommand::SetBindGroup {
-                num_dynamic_offsets,
-                ..
-            } = *com
-            {
-                self.encode_slice(&offsets[..num_dynamic_offsets as usize]);
-                offsets = &offsets[num_dynamic_offsets as usize..];
+            match *com {
+                ComputeCommand::SetBindGroup {
+                    num_dynamic_offsets,
+                    ..
+                } => {
+                    self.encode_slice(&offsets[..num_dynamic_offsets as usize]);
+                    offsets = &offsets[num_dynamic_offsets as usize..];
+                }
+                ComputeCommand::SetPushConstant { size_bytes, .. } => {
+                    let count = (size_bytes / wgt::PUSH_CONSTANT_ALIGNMENT) as usize;
+                    self.encode_slice(&push_constant_data[..count]);
+                    push_constant_data = &push_constant_data[count..];
+                }
+                _ => {}
             }
         }
     }
@@ -112,33 +209,96 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         &self,
         encoder_id: id::CommandEncoderId,
         raw_data: &[u8],
-    ) {
+    ) -> Result<(), ComputePassError> {
         let hub = B::hub(self);
+        let context = ErrorContext::new("command_encoder_run_compute_pass");
         let mut token = Token::root();
 
+        let (device_guard, mut token) = hub.devices.read(&mut token);
         let (mut cmb_guard, mut token) = hub.command_buffers.write(&mut token);
         let cmb = &mut cmb_guard[encoder_id];
+        let device = &device_guard[cmb.device_id.value];
+        if device.is_lost() {
+            return Err(ComputePassError::DeviceLost);
+        }
         let raw = cmb.raw.last_mut().unwrap();
 
         let (_, mut token) = hub.render_bundles.read(&mut token);
         let (pipeline_layout_guard, mut token) = hub.pipeline_layouts.read(&mut token);
         let (bind_group_guard, mut token) = hub.bind_groups.read(&mut token);
         let (pipeline_guard, mut token) = hub.compute_pipelines.read(&mut token);
+        let (query_set_guard, mut token) = hub.query_sets.read(&mut token);
         let (buffer_guard, mut token) = hub.buffers.read(&mut token);
         let (texture_guard, _) = hub.textures.read(&mut token);
 
-        let mut pipeline_state = PipelineState::Required;
-
         let mut state = State {
             binder: Binder::new(cmb.limits.max_bind_groups),
+            pipeline: PipelineState::Required,
             debug_scope_depth: 0,
         };
 
         let mut peeker = raw_data.as_ptr();
         let raw_data_end = unsafe { raw_data.as_ptr().add(raw_data.len()) };
         let mut command = ComputeCommand::Dispatch([0; 3]); // dummy
-        loop {
+
+        #[cfg(feature = "trace")]
+        match cmb.commands {
+            Some(ref mut list) => {
+                let mut pass_commands = Vec::new();
+                let mut pass_dynamic_offsets = Vec::new();
+                let mut pass_push_constant_data = Vec::new();
+                loop {
+                    peeker = unsafe { ComputeCommand::peek_from(peeker, &mut command) };
+                    match command {
+                        ComputeCommand::SetBindGroup {
+                            num_dynamic_offsets,
+                            phantom_offsets,
+                            ..
+                        } => {
+                            let (new_peeker, offsets) = unsafe {
+                                phantom_offsets.decode_unaligned(
+                                    peeker,
+                                    num_dynamic_offsets as usize,
+                                    raw_data_end,
+                                )
+                            };
+                            peeker = new_peeker;
+                            pass_dynamic_offsets.extend_from_slice(offsets);
+                        }
+                        ComputeCommand::SetPushConstant {
+                            size_bytes,
+                            phantom_data,
+                            ..
+                        } => {
+                            let (new_peeker, data) = unsafe {
+                                phantom_data.decode_unaligned(
+                                    peeker,
+                                    (size_bytes / wgt::PUSH_CONSTANT_ALIGNMENT) as usize,
+                                    raw_data_end,
+                                )
+                            };
+                            peeker = new_peeker;
+                            pass_push_constant_data.extend_from_slice(data);
+                        }
+                        ComputeCommand::End => break,
+                        _ => {}
+                    }
+                    pass_commands.push(command);
+                }
+                list.push(crate::device::trace::Command::RunComputePass {
+                    commands: pass_commands,
+                    dynamic_offsets: pass_dynamic_offsets,
+                    push_constant_data: pass_push_constant_data,
+                });
+                peeker = raw_data.as_ptr();
+            }
+            None => {}
+        }
+
+        let mut command_offset;
+        let result = loop {
             assert!(unsafe { peeker.add(ComputeCommand::max_size()) } <= raw_data_end);
+            command_offset = peeker as usize - raw_data.as_ptr() as usize;
             peeker = unsafe { ComputeCommand::peek_from(peeker, &mut command) };
             match command {
                 ComputeCommand::SetBindGroup {
@@ -156,30 +316,47 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                     };
                     peeker = new_peeker;
 
-                    if cfg!(debug_assertions) {
-                        for off in offsets {
-                            assert_eq!(
-                                *off as BufferAddress % BIND_BUFFER_ALIGNMENT,
-                                0,
-                                "Misaligned dynamic buffer offset: {} does not align with {}",
-                                off,
-                                BIND_BUFFER_ALIGNMENT
-                            );
-                        }
+                    if index as u32 >= cmb.limits.max_bind_groups {
+                        break Err(ComputeCommandError::BindGroupIndexOutOfRange {
+                            index,
+                            max: cmb.limits.max_bind_groups,
+                        });
+                    }
+                    if let Some(&off) = offsets
+                        .iter()
+                        .find(|&&off| off as BufferAddress % BIND_BUFFER_ALIGNMENT != 0)
+                    {
+                        break Err(ComputeCommandError::UnalignedDynamicOffset(off));
                     }
 
-                    let bind_group = cmb
-                        .trackers
-                        .bind_groups
-                        .use_extend(&*bind_group_guard, bind_group_id, (), ())
-                        .unwrap();
-                    assert_eq!(bind_group.dynamic_count, offsets.len());
+                    let bind_group = match cmb.trackers.bind_groups.use_extend(
+                        &*bind_group_guard,
+                        bind_group_id,
+                        (),
+                        (),
+                    ) {
+                        Ok(bind_group) => bind_group,
+                        Err(_) => break Err(ComputeCommandError::InvalidBindGroup(bind_group_id)),
+                    };
+                    if bind_group.dynamic_count != offsets.len() {
+                        break Err(ComputeCommandError::InvalidDynamicOffsetCount {
+                            actual: offsets.len(),
+                            expected: bind_group.dynamic_count,
+                        });
+                    }
 
                     log::trace!(
                         "Encoding barriers on binding of {:?} to {:?}",
                         bind_group_id,
                         encoder_id
                     );
+                    CommandBuffer::clear_discarded_surfaces(
+                        raw,
+                        &mut cmb.trackers,
+                        &mut cmb.discarded_surfaces,
+                        bind_group.used.textures.used(),
+                        &*texture_guard,
+                    );
                     CommandBuffer::insert_barriers(
                         raw,
                         &mut cmb.trackers,
@@ -213,12 +390,16 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                     }
                 }
                 ComputeCommand::SetPipeline(pipeline_id) => {
-                    pipeline_state = PipelineState::Set;
-                    let pipeline = cmb
-                        .trackers
-                        .compute_pipes
-                        .use_extend(&*pipeline_guard, pipeline_id, (), ())
-                        .unwrap();
+                    let pipeline = match cmb.trackers.compute_pipes.use_extend(
+                        &*pipeline_guard,
+                        pipeline_id,
+                        (),
+                        (),
+                    ) {
+                        Ok(pipeline) => pipeline,
+                        Err(_) => break Err(ComputeCommandError::InvalidPipeline(pipeline_id)),
+                    };
+                    state.pipeline = PipelineState::Set;
 
                     unsafe {
                         raw.bind_compute_pipeline(&pipeline.raw);
@@ -260,29 +441,64 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                         }
                     }
                 }
+                ComputeCommand::SetPushConstant {
+                    offset,
+                    size_bytes,
+                    phantom_data,
+                } => {
+                    let (new_peeker, data) = unsafe {
+                        phantom_data.decode_unaligned(
+                            peeker,
+                            (size_bytes / wgt::PUSH_CONSTANT_ALIGNMENT) as usize,
+                            raw_data_end,
+                        )
+                    };
+                    peeker = new_peeker;
+
+                    let pipeline_layout_id = match state.binder.pipeline_layout_id {
+                        Some(id) => id,
+                        None => break Err(ComputeCommandError::MissingPipeline),
+                    };
+                    let pipeline_layout = &pipeline_layout_guard[pipeline_layout_id];
+                    if let Err(e) = pipeline_layout.validate_push_constant_ranges(
+                        wgt::ShaderStage::COMPUTE,
+                        offset,
+                        size_bytes,
+                    ) {
+                        break Err(ComputeCommandError::PushConstant(e));
+                    }
+
+                    unsafe {
+                        raw.push_compute_constants(&pipeline_layout.raw, offset, data);
+                    }
+                }
                 ComputeCommand::Dispatch(groups) => {
-                    assert_eq!(
-                        pipeline_state,
-                        PipelineState::Set,
-                        "Dispatch error: Pipeline is missing"
-                    );
+                    if let Err(e) = state.is_ready() {
+                        break Err(ComputeCommandError::Dispatch(e));
+                    }
                     unsafe {
                         raw.dispatch(groups);
                     }
                 }
                 ComputeCommand::DispatchIndirect { buffer_id, offset } => {
-                    assert_eq!(
-                        pipeline_state,
-                        PipelineState::Set,
-                        "Dispatch error: Pipeline is missing"
-                    );
-                    let (src_buffer, src_pending) = cmb.trackers.buffers.use_replace(
+                    if let Err(e) = state.is_ready() {
+                        break Err(ComputeCommandError::Dispatch(e));
+                    }
+                    let (src_buffer, src_pending) = match cmb.trackers.buffers.use_replace(
                         &*buffer_guard,
                         buffer_id,
                         (),
                         BufferUse::INDIRECT,
-                    );
-                    assert!(src_buffer.usage.contains(BufferUsage::INDIRECT));
+                    ) {
+                        Ok(pair) => pair,
+                        Err(_) => break Err(ComputeCommandError::InvalidBuffer(buffer_id)),
+                    };
+                    if !src_buffer.usage.contains(BufferUsage::INDIRECT) {
+                        break Err(ComputeCommandError::MissingBufferUsage {
+                            actual: src_buffer.usage,
+                            expected: BufferUsage::INDIRECT,
+                        });
+                    }
 
                     let barriers = src_pending.map(|pending| pending.into_hal(src_buffer));
 
@@ -308,15 +524,14 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
 
                     raw.begin_debug_marker(str::from_utf8(label).unwrap(), color)
                 },
-                ComputeCommand::PopDebugGroup => unsafe {
-                    assert_ne!(
-                        state.debug_scope_depth, 0,
-                        "Can't pop debug group, because number of pushed debug groups is zero!"
-                    );
+                ComputeCommand::PopDebugGroup => {
+                    if state.debug_scope_depth == 0 {
+                        break Err(ComputeCommandError::InvalidPopDebugGroup);
+                    }
                     state.debug_scope_depth -= 1;
 
-                    raw.end_debug_marker()
-                },
+                    unsafe { raw.end_debug_marker() }
+                }
                 ComputeCommand::InsertDebugMarker {
                     color,
                     len,
@@ -328,46 +543,43 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
 
                     raw.insert_debug_marker(str::from_utf8(label).unwrap(), color)
                 },
-                ComputeCommand::End => break,
-            }
-        }
-
-        #[cfg(feature = "trace")]
-        match cmb.commands {
-            Some(ref mut list) => {
-                let mut pass_commands = Vec::new();
-                let mut pass_dynamic_offsets = Vec::new();
-                peeker = raw_data.as_ptr();
-                loop {
-                    peeker = unsafe { ComputeCommand::peek_from(peeker, &mut command) };
-                    match command {
-                        ComputeCommand::SetBindGroup {
-                            num_dynamic_offsets,
-                            phantom_offsets,
-                            ..
-                        } => {
-                            let (new_peeker, offsets) = unsafe {
-                                phantom_offsets.decode_unaligned(
-                                    peeker,
-                                    num_dynamic_offsets as usize,
-                                    raw_data_end,
-                                )
-                            };
-                            peeker = new_peeker;
-                            pass_dynamic_offsets.extend_from_slice(offsets);
+                ComputeCommand::WriteTimestamp {
+                    query_set_id,
+                    query_index,
+                } => {
+                    let query_set = match cmb.trackers.query_sets.use_extend(
+                        &*query_set_guard,
+                        query_set_id,
+                        (),
+                        (),
+                    ) {
+                        Ok(query_set) => query_set,
+                        Err(_) => {
+                            break Err(ComputeCommandError::Query(QueryError::InvalidQuerySet(
+                                query_set_id,
+                            )))
                         }
-                        ComputeCommand::End => break,
-                        _ => {}
+                    };
+                    if let Err(e) = query_set.write_timestamp(raw, query_index) {
+                        break Err(ComputeCommandError::Query(e));
                     }
-                    pass_commands.push(command);
+                    cmb.query_resets.push((query_set_id, query_index));
                 }
-                list.push(crate::device::trace::Command::RunComputePass {
-                    commands: pass_commands,
-                    dynamic_offsets: pass_dynamic_offsets,
-                });
+                ComputeCommand::End => break Ok(()),
             }
-            None => {}
-        }
+        };
+
+        result.map_err(|error| {
+            // the pass is partially recorded, so the encoder can't be finished
+            cmb.is_valid = false;
+            device.capture(
+                context,
+                ComputePassError::Command {
+                    offset: command_offset,
+                    error,
+                },
+            )
+        })
     }
 }
 
@@ -410,6 +622,33 @@ pub mod compute_ffi {
         pass.encode(&ComputeCommand::SetPipeline(pipeline_id));
     }
 
+    /// # Safety
+    ///
+    /// This function is unsafe as there is no guarantee that the given pointer is
+    /// valid for `size_bytes` bytes.
+    #[no_mangle]
+    pub unsafe extern "C" fn wgpu_compute_pass_set_push_constants(
+        pass: &mut RawPass,
+        offset: u32,
+        size_bytes: u32,
+        data: *const u32,
+    ) {
+        assert_eq!(
+            size_bytes % wgt::PUSH_CONSTANT_ALIGNMENT,
+            0,
+            "Push constant size must be aligned to 4 bytes."
+        );
+        pass.encode(&ComputeCommand::SetPushConstant {
+            offset,
+            size_bytes,
+            phantom_data: PhantomSlice::default(),
+        });
+        pass.encode_slice(slice::from_raw_parts(
+            data,
+            (size_bytes / wgt::PUSH_CONSTANT_ALIGNMENT) as usize,
+        ));
+    }
+
     #[no_mangle]
     pub unsafe extern "C" fn wgpu_compute_pass_dispatch(
         pass: &mut RawPass,
@@ -429,6 +668,18 @@ pub mod compute_ffi {
         pass.encode(&ComputeCommand::DispatchIndirect { buffer_id, offset });
     }
 
+    #[no_mangle]
+    pub unsafe extern "C" fn wgpu_compute_pass_write_timestamp(
+        pass: &mut RawPass,
+        query_set_id: id::QuerySetId,
+        query_index: u32,
+    ) {
+        pass.encode(&ComputeCommand::WriteTimestamp {
+            query_set_id,
+            query_index,
+        });
+    }
+
     #[no_mangle]
     pub unsafe extern "C" fn wgpu_compute_pass_push_debug_group(
         pass: &mut RawPass,
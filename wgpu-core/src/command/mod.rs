@@ -5,21 +5,25 @@
 mod allocator;
 mod bind;
 mod bundle;
+mod clear;
 mod compute;
+mod query;
 mod render;
 mod transfer;
 
 pub(crate) use self::allocator::CommandAllocator;
 pub use self::bundle::*;
+pub use self::clear::*;
 pub use self::compute::*;
+pub use self::query::*;
 pub use self::render::*;
 pub use self::transfer::*;
 
 use crate::{
-    device::{all_buffer_stages, all_image_stages, MAX_COLOR_TARGETS},
+    device::{all_buffer_stages, all_image_stages, Device, ErrorContext, MAX_COLOR_TARGETS},
     hub::{GfxBackend, Global, GlobalIdentityHandlerFactory, Storage, Token},
     id,
-    resource::{Buffer, Texture},
+    resource::{Buffer, Texture, TextureUse},
     track::TrackerSet,
     PrivateFeatures, Stored,
 };
@@ -28,7 +32,7 @@ use hal::command::CommandBuffer as _;
 
 use peek_poke::PeekPoke;
 
-use std::{marker::PhantomData, mem, ptr, slice, thread::ThreadId};
+use std::{fmt, iter, marker::PhantomData, mem, ptr, slice, thread::ThreadId};
 
 #[derive(Clone, Copy, Debug, PeekPoke)]
 pub struct PhantomSlice<T>(PhantomData<T>);
@@ -157,18 +161,90 @@ impl<P: Copy> RawPass<P> {
 #[derive(Debug)]
 pub struct CommandBuffer<B: hal::Backend> {
     pub(crate) raw: Vec<B::CommandBuffer>,
-    is_recording: bool,
+    pub(crate) is_recording: bool,
+    /// Cleared when a pass fails after it started recording.
+    pub(crate) is_valid: bool,
     recorded_thread_id: ThreadId,
     pub(crate) device_id: Stored<id::DeviceId>,
     pub(crate) trackers: TrackerSet,
     pub(crate) used_swap_chain: Option<(Stored<id::SwapChainId>, B::Framebuffer)>,
+    /// Queries written by the command buffer, which are reset before it's executed.
+    pub(crate) query_resets: Vec<(id::QuerySetId, u32)>,
+    /// Surfaces discarded by `StoreOp::Clear` and not used since.
+    pub(crate) discarded_surfaces: Vec<DiscardedSurface>,
     limits: wgt::Limits,
     private_features: PrivateFeatures,
     #[cfg(feature = "trace")]
     pub(crate) commands: Option<Vec<crate::device::trace::Command>>,
 }
 
+/// Texture subresources whose contents were discarded by a render pass.
+///
+/// They have to read back as zeros, so they are either cleared before their next
+/// use in the same command buffer, or marked as uninitialized on submission.
+#[derive(Debug)]
+pub(crate) struct DiscardedSurface {
+    pub texture_id: id::TextureId,
+    pub range: hal::image::SubresourceRange,
+}
+
 impl<B: GfxBackend> CommandBuffer<B> {
+    /// Report the validation error of a command, which leaves the command buffer
+    /// invalid, and return it back.
+    pub(crate) fn invalidate<E: fmt::Debug>(
+        &mut self,
+        device: &Device<B>,
+        context: ErrorContext,
+        error: E,
+    ) -> E {
+        self.is_valid = false;
+        device.capture(context, error)
+    }
+
+    /// Clear the discarded surfaces of the given textures, which are about to be used.
+    pub(crate) fn clear_discarded_surfaces(
+        raw: &mut B::CommandBuffer,
+        base: &mut TrackerSet,
+        discarded_surfaces: &mut Vec<DiscardedSurface>,
+        texture_ids: impl Iterator<Item = id::TextureId>,
+        texture_guard: &Storage<Texture<B>, id::TextureId>,
+    ) {
+        if discarded_surfaces.is_empty() {
+            return;
+        }
+        for texture_id in texture_ids {
+            while let Some(index) = discarded_surfaces
+                .iter()
+                .position(|surface| surface.texture_id == texture_id)
+            {
+                let surface = discarded_surfaces.swap_remove(index);
+                log::trace!("Clearing discarded surface {:?}", surface);
+                let (texture, pending) = base
+                    .textures
+                    .use_replace(
+                        texture_guard,
+                        texture_id,
+                        surface.range.clone(),
+                        TextureUse::COPY_DST,
+                    )
+                    .unwrap();
+                unsafe {
+                    raw.pipeline_barrier(
+                        all_image_stages()..hal::pso::PipelineStage::TRANSFER,
+                        hal::memory::Dependencies::empty(),
+                        pending.map(|pending| pending.into_hal(texture)),
+                    );
+                    raw.clear_image(
+                        &texture.raw,
+                        hal::image::Layout::TransferDstOptimal,
+                        zero_clear_value(surface.range.aspects),
+                        iter::once(surface.range),
+                    );
+                }
+            }
+        }
+    }
+
     pub(crate) fn insert_barriers(
         raw: &mut B::CommandBuffer,
         base: &mut TrackerSet,
@@ -192,6 +268,7 @@ impl<B: GfxBackend> CommandBuffer<B> {
         base.views.merge_extend(&head.views).unwrap();
         base.bind_groups.merge_extend(&head.bind_groups).unwrap();
         base.samplers.merge_extend(&head.samplers).unwrap();
+        base.query_sets.merge_extend(&head.query_sets).unwrap();
         base.compute_pipes
             .merge_extend(&head.compute_pipes)
             .unwrap();
@@ -251,6 +328,19 @@ struct RawRenderPassDepthStencilAttachmentDescriptor {
 struct RawRenderTargets {
     colors: [RawRenderPassColorAttachmentDescriptor; MAX_COLOR_TARGETS],
     depth_stencil: RawRenderPassDepthStencilAttachmentDescriptor,
+    occlusion_query_set: u64,
+}
+
+#[derive(Clone, Debug)]
+pub enum CommandEncoderError {
+    /// The command encoder has already been finished.
+    NotRecording,
+    /// A pass failed half-way through, leaving the commands incomplete.
+    Invalid,
+    /// The swap chain frame used by the encoder has already been presented.
+    SwapChainFramePresented,
+    /// The device is lost.
+    DeviceLost,
 }
 
 impl<G: GlobalIdentityHandlerFactory> Global<G> {
@@ -258,25 +348,40 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         &self,
         encoder_id: id::CommandEncoderId,
         _desc: &wgt::CommandBufferDescriptor,
-    ) -> id::CommandBufferId {
+    ) -> Result<id::CommandBufferId, CommandEncoderError> {
         let hub = B::hub(self);
+        let context = ErrorContext::new("command_encoder_finish");
         let mut token = Token::root();
+        let (device_guard, mut token) = hub.devices.read(&mut token);
         let (swap_chain_guard, mut token) = hub.swap_chains.read(&mut token);
         //TODO: actually close the last recorded command buffer
         let (mut comb_guard, _) = hub.command_buffers.write(&mut token);
         let comb = &mut comb_guard[encoder_id];
-        assert!(comb.is_recording, "Command buffer must be recording");
+        let device = &device_guard[comb.device_id.value];
+        if device.is_lost() {
+            return Err(CommandEncoderError::DeviceLost);
+        }
+        if !comb.is_recording {
+            return Err(device.capture(context, CommandEncoderError::NotRecording));
+        }
+        if !comb.is_valid {
+            return Err(device.capture(context, CommandEncoderError::Invalid));
+        }
         comb.is_recording = false;
         // stop tracking the swapchain image, if used
         if let Some((ref sc_id, _)) = comb.used_swap_chain {
-            let view_id = swap_chain_guard[sc_id.value]
-                .acquired_view_id
-                .as_ref()
-                .expect("Used swap chain frame has already presented");
+            let view_id = match swap_chain_guard[sc_id.value].acquired_view_id {
+                Some(ref view_id) => view_id,
+                None => {
+                    return Err(
+                        device.capture(context, CommandEncoderError::SwapChainFramePresented)
+                    )
+                }
+            };
             comb.trackers.views.remove(view_id.value);
         }
         log::debug!("Command buffer {:?} {:#?}", encoder_id, comb.trackers);
-        encoder_id
+        Ok(encoder_id)
     }
 
     pub fn command_encoder_push_debug_group<B: GfxBackend>(
@@ -0,0 +1,281 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+#[cfg(feature = "trace")]
+use crate::device::trace::Command as TraceCommand;
+use crate::{
+    device::{all_buffer_stages, ErrorContext},
+    hub::{GfxBackend, Global, GlobalIdentityHandlerFactory, Token},
+    id::{BufferId, CommandEncoderId, QuerySetId},
+    resource::{BufferUse, QuerySet},
+};
+
+use hal::command::CommandBuffer as _;
+use wgt::{BufferAddress, BufferUsage};
+
+use std::ops::Range;
+
+/// Error encountered when writing or resolving queries.
+#[derive(Clone, Debug, PartialEq)]
+pub enum QueryError {
+    /// The query set is invalid.
+    InvalidQuerySet(QuerySetId),
+    /// The buffer is invalid.
+    InvalidBuffer(BufferId),
+    /// The queries are beyond the number of queries in the set.
+    OutOfBounds {
+        start_query: u32,
+        end_query: u32,
+        count: u32,
+    },
+    /// The query set doesn't contain queries of the required type.
+    IncompatibleType {
+        set_type: wgt::QueryType,
+        query_type: wgt::QueryType,
+    },
+    /// The destination usage doesn't contain `QUERY_RESOLVE`.
+    MissingQueryResolveUsageFlag,
+    /// The destination offset must be a multiple of `QUERY_RESOLVE_BUFFER_ALIGNMENT`.
+    UnalignedResolveOffset(BufferAddress),
+    /// The resolved queries overrun the destination buffer.
+    BufferOverrun {
+        start_offset: BufferAddress,
+        end_offset: BufferAddress,
+        buffer_size: BufferAddress,
+    },
+    /// The device is lost.
+    DeviceLost,
+}
+
+impl<B: hal::Backend> QuerySet<B> {
+    /// Check that `query_count` queries starting at `start_query` are within
+    /// the set and of the given type, returning their range.
+    fn validate_queries(
+        &self,
+        query_type: wgt::QueryType,
+        start_query: u32,
+        query_count: u32,
+    ) -> Result<Range<u32>, QueryError> {
+        if self.ty != query_type {
+            return Err(QueryError::IncompatibleType {
+                set_type: self.ty,
+                query_type,
+            });
+        }
+        match start_query.checked_add(query_count) {
+            Some(end_query) if end_query <= self.count => Ok(start_query..end_query),
+            _ => Err(QueryError::OutOfBounds {
+                start_query,
+                end_query: start_query.saturating_add(query_count),
+                count: self.count,
+            }),
+        }
+    }
+
+    /// Validate and record a timestamp write into the query at `query_index`.
+    ///
+    /// The query needs to be reset before the command buffer is executed,
+    /// which is the responsibility of the caller.
+    pub(crate) fn write_timestamp(
+        &self,
+        raw: &mut B::CommandBuffer,
+        query_index: u32,
+    ) -> Result<(), QueryError> {
+        self.validate_queries(wgt::QueryType::Timestamp, query_index, 1)?;
+        unsafe {
+            raw.write_timestamp(
+                hal::pso::PipelineStage::BOTTOM_OF_PIPE,
+                hal::query::Query {
+                    pool: &self.raw,
+                    id: query_index,
+                },
+            );
+        }
+        Ok(())
+    }
+
+    /// Validate and record the beginning of the occlusion query at `query_index`.
+    ///
+    /// Like with timestamps, resetting the query is up to the caller.
+    pub(crate) fn begin_occlusion_query(
+        &self,
+        raw: &mut B::CommandBuffer,
+        query_index: u32,
+    ) -> Result<(), QueryError> {
+        self.validate_queries(wgt::QueryType::Occlusion, query_index, 1)?;
+        unsafe {
+            raw.begin_query(
+                hal::query::Query {
+                    pool: &self.raw,
+                    id: query_index,
+                },
+                hal::query::ControlFlags::empty(),
+            );
+        }
+        Ok(())
+    }
+
+    /// Record the end of the occlusion query at `query_index`,
+    /// which has been validated by `begin_occlusion_query`.
+    pub(crate) fn end_occlusion_query(&self, raw: &mut B::CommandBuffer, query_index: u32) {
+        unsafe {
+            raw.end_query(hal::query::Query {
+                pool: &self.raw,
+                id: query_index,
+            });
+        }
+    }
+}
+
+impl<G: GlobalIdentityHandlerFactory> Global<G> {
+    pub fn command_encoder_write_timestamp<B: GfxBackend>(
+        &self,
+        command_encoder_id: CommandEncoderId,
+        query_set_id: QuerySetId,
+        query_index: u32,
+    ) -> Result<(), QueryError> {
+        let hub = B::hub(self);
+        let context = ErrorContext::new("command_encoder_write_timestamp");
+        let mut token = Token::root();
+
+        let (device_guard, mut token) = hub.devices.read(&mut token);
+        let (mut cmb_guard, mut token) = hub.command_buffers.write(&mut token);
+        let cmb = &mut cmb_guard[command_encoder_id];
+        let device = &device_guard[cmb.device_id.value];
+        if device.is_lost() {
+            return Err(QueryError::DeviceLost);
+        }
+        let (query_set_guard, _) = hub.query_sets.read(&mut token);
+
+        #[cfg(feature = "trace")]
+        match cmb.commands {
+            Some(ref mut list) => list.push(TraceCommand::WriteTimestamp {
+                query_set_id,
+                query_index,
+            }),
+            None => (),
+        }
+
+        let query_set = query_set_guard.get(query_set_id).map_err(|_| {
+            cmb.invalidate(device, context, QueryError::InvalidQuerySet(query_set_id))
+        })?;
+        query_set
+            .validate_queries(wgt::QueryType::Timestamp, query_index, 1)
+            .map_err(|e| cmb.invalidate(device, context, e))?;
+
+        // The query is valid, only now the query set can be tracked.
+        let query_set = cmb
+            .trackers
+            .query_sets
+            .use_extend(&*query_set_guard, query_set_id, (), ())
+            .unwrap();
+        query_set
+            .write_timestamp(cmb.raw.last_mut().unwrap(), query_index)
+            .map_err(|e| cmb.invalidate(device, context, e))?;
+        cmb.query_resets.push((query_set_id, query_index));
+        Ok(())
+    }
+
+    pub fn command_encoder_resolve_query_set<B: GfxBackend>(
+        &self,
+        command_encoder_id: CommandEncoderId,
+        query_set_id: QuerySetId,
+        start_query: u32,
+        query_count: u32,
+        destination: BufferId,
+        destination_offset: BufferAddress,
+    ) -> Result<(), QueryError> {
+        let hub = B::hub(self);
+        let context = ErrorContext::new("command_encoder_resolve_query_set");
+        let mut token = Token::root();
+
+        let (device_guard, mut token) = hub.devices.read(&mut token);
+        let (mut cmb_guard, mut token) = hub.command_buffers.write(&mut token);
+        let cmb = &mut cmb_guard[command_encoder_id];
+        let device = &device_guard[cmb.device_id.value];
+        if device.is_lost() {
+            return Err(QueryError::DeviceLost);
+        }
+        let (query_set_guard, mut token) = hub.query_sets.read(&mut token);
+        let (buffer_guard, _) = hub.buffers.read(&mut token);
+
+        #[cfg(feature = "trace")]
+        match cmb.commands {
+            Some(ref mut list) => list.push(TraceCommand::ResolveQuerySet {
+                query_set_id,
+                start_query,
+                query_count,
+                destination,
+                destination_offset,
+            }),
+            None => (),
+        }
+
+        let query_set = query_set_guard.get(query_set_id).map_err(|_| {
+            cmb.invalidate(device, context, QueryError::InvalidQuerySet(query_set_id))
+        })?;
+        let queries = query_set
+            .validate_queries(query_set.ty, start_query, query_count)
+            .map_err(|e| cmb.invalidate(device, context, e))?;
+
+        let dst_buffer = buffer_guard
+            .get(destination)
+            .map_err(|_| cmb.invalidate(device, context, QueryError::InvalidBuffer(destination)))?;
+        if !dst_buffer.usage.contains(BufferUsage::QUERY_RESOLVE) {
+            return Err(cmb.invalidate(device, context, QueryError::MissingQueryResolveUsageFlag));
+        }
+        if destination_offset % wgt::QUERY_RESOLVE_BUFFER_ALIGNMENT != 0 {
+            return Err(cmb.invalidate(
+                device,
+                context,
+                QueryError::UnalignedResolveOffset(destination_offset),
+            ));
+        }
+        let resolve_size = query_count as BufferAddress * wgt::QUERY_SIZE as BufferAddress;
+        match destination_offset.checked_add(resolve_size) {
+            Some(end_offset) if end_offset <= dst_buffer.size => (),
+            end_offset => {
+                return Err(cmb.invalidate(
+                    device,
+                    context,
+                    QueryError::BufferOverrun {
+                        start_offset: destination_offset,
+                        end_offset: end_offset.unwrap_or(BufferAddress::MAX),
+                        buffer_size: dst_buffer.size,
+                    },
+                ))
+            }
+        }
+
+        // The resolve is valid, only now the resources can be tracked.
+        let query_set = cmb
+            .trackers
+            .query_sets
+            .use_extend(&*query_set_guard, query_set_id, (), ())
+            .unwrap();
+        let (dst_buffer, dst_pending) = cmb
+            .trackers
+            .buffers
+            .use_replace(&*buffer_guard, destination, (), BufferUse::QUERY_RESOLVE)
+            .unwrap();
+        let barriers = dst_pending.map(|pending| pending.into_hal(dst_buffer));
+        let cmb_raw = cmb.raw.last_mut().unwrap();
+        unsafe {
+            cmb_raw.pipeline_barrier(
+                all_buffer_stages()..hal::pso::PipelineStage::TRANSFER,
+                hal::memory::Dependencies::empty(),
+                barriers,
+            );
+            cmb_raw.copy_query_pool_results(
+                &query_set.raw,
+                queries,
+                &dst_buffer.raw,
+                destination_offset,
+                wgt::QUERY_SIZE as BufferAddress,
+                hal::query::ResultFlags::BITS_64 | hal::query::ResultFlags::WAIT,
+            );
+        }
+        Ok(())
+    }
+}
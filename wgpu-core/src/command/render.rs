@@ -3,21 +3,23 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use crate::{
+    binding_model::PushConstantUploadError,
     command::{
         bind::{Binder, LayoutChange},
-        PassComponent, PhantomSlice, RawPass, RawRenderPassColorAttachmentDescriptor,
-        RawRenderPassDepthStencilAttachmentDescriptor, RawRenderTargets,
+        DiscardedSurface, PassComponent, PhantomSlice, QueryError, RawPass,
+        RawRenderPassColorAttachmentDescriptor, RawRenderPassDepthStencilAttachmentDescriptor,
+        RawRenderTargets,
     },
     conv,
     device::{
-        AttachmentData, FramebufferKey, RenderPassContext, RenderPassKey, MAX_COLOR_TARGETS,
-        MAX_VERTEX_BUFFERS,
+        AttachmentData, ErrorContext, FramebufferKey, RenderPassContext, RenderPassKey,
+        MAX_COLOR_TARGETS, MAX_VERTEX_BUFFERS,
     },
     hub::{GfxBackend, Global, GlobalIdentityHandlerFactory, Token},
     id,
     pipeline::PipelineFlags,
     resource::{BufferUse, TextureUse, TextureViewInner},
-    track::TrackerSet,
+    track::{TrackerSet, UseExtendError},
     Stored,
 };
 
@@ -30,7 +32,7 @@ use wgt::{
     RenderPassDepthStencilAttachmentDescriptorBase, StoreOp, TextureUsage, BIND_BUFFER_ALIGNMENT,
 };
 
-use std::{borrow::Borrow, collections::hash_map::Entry, fmt, iter, mem, ops::Range, slice, str};
+use std::{borrow::Borrow, collections::hash_map::Entry, iter, mem, ops::Range, slice, str};
 
 pub type RenderPassColorAttachmentDescriptor =
     RenderPassColorAttachmentDescriptorBase<id::TextureViewId>;
@@ -40,24 +42,62 @@ pub type RenderPassDepthStencilAttachmentDescriptor =
 fn is_depth_stencil_read_only(
     desc: &RenderPassDepthStencilAttachmentDescriptor,
     aspects: hal::format::Aspects,
-) -> bool {
+) -> Result<bool, RenderPassError> {
     if aspects.contains(hal::format::Aspects::DEPTH) && !desc.depth_read_only {
-        return false;
+        return Ok(false);
+    }
+    if (desc.depth_load_op, desc.depth_store_op) != (LoadOp::Load, StoreOp::Store) {
+        return Err(RenderPassError::InvalidDepthOps);
     }
-    assert_eq!(
-        (desc.depth_load_op, desc.depth_store_op),
-        (LoadOp::Load, StoreOp::Store),
-        "Unable to clear non-present/read-only depth"
-    );
     if aspects.contains(hal::format::Aspects::STENCIL) && !desc.stencil_read_only {
-        return false;
+        return Ok(false);
+    }
+    if (desc.stencil_load_op, desc.stencil_store_op) != (LoadOp::Load, StoreOp::Store) {
+        return Err(RenderPassError::InvalidStencilOps);
+    }
+    Ok(true)
+}
+
+fn check_extension(
+    enabled: wgt::Extensions,
+    required: wgt::Extensions,
+) -> Result<(), RenderCommandError> {
+    if enabled.contains(required) {
+        Ok(())
+    } else {
+        Err(RenderCommandError::MissingExtension(required - enabled))
+    }
+}
+
+/// Validate a buffer read by an indirect draw, for `count` elements of `stride`
+/// bytes starting at `offset`.
+fn validate_indirect_buffer(
+    usage: BufferUsage,
+    buffer_size: BufferAddress,
+    offset: BufferAddress,
+    stride: BufferAddress,
+    count: u32,
+) -> Result<(), RenderCommandError> {
+    if !usage.contains(BufferUsage::INDIRECT) {
+        return Err(RenderCommandError::MissingBufferUsage {
+            actual: usage,
+            expected: BufferUsage::INDIRECT,
+        });
+    }
+    if offset % wgt::INDIRECT_BUFFER_OFFSET_ALIGNMENT != 0 {
+        return Err(RenderCommandError::UnalignedIndirectBufferOffset(offset));
+    }
+    let end_offset = stride
+        .checked_mul(count as BufferAddress)
+        .and_then(|size| offset.checked_add(size));
+    match end_offset {
+        Some(end_offset) if end_offset <= buffer_size => Ok(()),
+        _ => Err(RenderCommandError::IndirectBufferOverrun {
+            begin_offset: offset,
+            end_offset: end_offset.unwrap_or(BufferAddress::MAX),
+            buffer_size,
+        }),
     }
-    assert_eq!(
-        (desc.stencil_load_op, desc.stencil_store_op),
-        (LoadOp::Load, StoreOp::Store),
-        "Unable to clear non-present/read-only stencil"
-    );
-    true
 }
 
 #[repr(C)]
@@ -66,6 +106,7 @@ pub struct RenderPassDescriptor<'a> {
     pub color_attachments: *const RenderPassColorAttachmentDescriptor,
     pub color_attachments_length: usize,
     pub depth_stencil_attachment: Option<&'a RenderPassDepthStencilAttachmentDescriptor>,
+    pub occlusion_query_set: Option<id::QuerySetId>,
 }
 
 #[derive(Clone, Copy, Debug, Default, PeekPoke)]
@@ -110,6 +151,13 @@ pub enum RenderCommand {
         depth_max: f32,
     },
     SetScissor(Rect<u32>),
+    SetPushConstant {
+        stages: wgt::ShaderStage,
+        offset: u32,
+        size_bytes: u32,
+        #[cfg_attr(any(feature = "trace", feature = "replay"), serde(skip))]
+        phantom_data: PhantomSlice<u32>,
+    },
     Draw {
         vertex_count: u32,
         instance_count: u32,
@@ -131,6 +179,20 @@ pub enum RenderCommand {
         buffer_id: id::BufferId,
         offset: BufferAddress,
     },
+    MultiDrawIndirect {
+        buffer_id: id::BufferId,
+        offset: BufferAddress,
+        count: u32,
+        indexed: bool,
+    },
+    MultiDrawIndirectCount {
+        buffer_id: id::BufferId,
+        offset: BufferAddress,
+        count_buffer_id: id::BufferId,
+        count_buffer_offset: BufferAddress,
+        max_count: u32,
+        indexed: bool,
+    },
     PushDebugGroup {
         color: u32,
         len: usize,
@@ -145,6 +207,14 @@ pub enum RenderCommand {
         phantom_marker: PhantomSlice<u8>,
     },
     ExecuteBundle(id::RenderBundleId),
+    WriteTimestamp {
+        query_set_id: id::QuerySetId,
+        query_index: u32,
+    },
+    BeginOcclusionQuery {
+        query_index: u32,
+    },
+    EndOcclusionQuery,
     End,
 }
 
@@ -194,6 +264,8 @@ impl RawPass<id::CommandEncoderId> {
             };
         }
 
+        targets.occlusion_query_set = desc.occlusion_query_set.map_or(0, |id| id.into_raw());
+
         pass.encode(&targets);
         pass
     }
@@ -204,16 +276,24 @@ impl<P: Copy> RawPass<P> {
         &mut self,
         commands: &[RenderCommand],
         mut offsets: &[DynamicOffset],
+        mut push_constant_data: &[u32],
     ) {
         for com in commands {
             self.encode(com);
-            if let RenderCommand::SetBindGroup {
-                num_dynamic_offsets,
-                ..
-            } = *com
-            {
-                self.encode_slice(&offsets[..num_dynamic_offsets as usize]);
-                offsets = &offsets[num_dynamic_offsets as usize..];
+            match *com {
+                RenderCommand::SetBindGroup {
+                    num_dynamic_offsets,
+                    ..
+                } => {
+                    self.encode_slice(&offsets[..num_dynamic_offsets as usize]);
+                    offsets = &offsets[num_dynamic_offsets as usize..];
+                }
+                RenderCommand::SetPushConstant { size_bytes, .. } => {
+                    let count = (size_bytes / wgt::PUSH_CONSTANT_ALIGNMENT) as usize;
+                    self.encode_slice(&push_constant_data[..count]);
+                    push_constant_data = &push_constant_data[count..];
+                }
+                _ => {}
             }
         }
     }
@@ -239,27 +319,135 @@ impl OptionalState {
     }
 }
 
-#[derive(PartialEq)]
-enum DrawError {
+/// Error validating a draw call.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DrawError {
+    /// A blend color is required to be set using `RenderPass::set_blend_color`.
     MissingBlendColor,
+    /// A stencil reference is required to be set using `RenderPass::set_stencil_reference`.
     MissingStencilReference,
+    /// The render pipeline must be set using `RenderPass::set_pipeline`.
     MissingPipeline,
+    /// The current render pipeline has a layout which is incompatible
+    /// with a currently set bind group. They first differ at `index`.
     IncompatibleBindGroup {
         index: u32,
-        //expected: BindGroupLayoutId,
-        //provided: Option<(BindGroupLayoutId, BindGroupId)>,
+        expected: id::BindGroupLayoutId,
+        provided: Option<(id::BindGroupLayoutId, id::BindGroupId)>,
     },
+    /// The draw extends beyond the bound vertex buffers.
+    VertexBeyondLimit { last_vertex: u32, vertex_limit: u32 },
+    /// The draw extends beyond the bound instance buffers.
+    InstanceBeyondLimit {
+        last_instance: u32,
+        instance_limit: u32,
+    },
+    /// The draw extends beyond the bound index buffer.
+    IndexBeyondLimit { last_index: u32, index_limit: u32 },
 }
 
-impl fmt::Debug for DrawError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            DrawError::MissingBlendColor => write!(f, "MissingBlendColor. A blend color is required to be set using RenderPass::set_blend_color."),
-            DrawError::MissingStencilReference => write!(f, "MissingStencilReference. A stencil reference is required to be set using RenderPass::set_stencil_reference."),
-            DrawError::MissingPipeline => write!(f, "MissingPipeline. You must first set the render pipeline using RenderPass::set_pipeline."),
-            DrawError::IncompatibleBindGroup { index } => write!(f, "IncompatibleBindGroup. The current render pipeline has a layout which is incompatible with a currently set bind group. They first differ at entry index {}.", index),
-        }
-    }
+/// Error encountered when executing a render command.
+#[derive(Clone, Debug, PartialEq)]
+pub enum RenderCommandError {
+    /// The bind group is invalid.
+    InvalidBindGroup(id::BindGroupId),
+    /// The render pipeline is invalid.
+    InvalidPipeline(id::RenderPipelineId),
+    /// The pipeline layout is invalid.
+    InvalidPipelineLayout(id::PipelineLayoutId),
+    /// The render bundle is invalid.
+    InvalidRenderBundle(id::RenderBundleId),
+    /// The buffer is invalid.
+    InvalidBuffer(id::BufferId),
+    /// The buffer is used with conflicting usages within the pass.
+    BufferUsageConflict(id::BufferId),
+    /// The bind group index is beyond the maximum number of bind groups.
+    BindGroupIndexOutOfRange { index: u8, max: u32 },
+    /// The number of dynamic offsets doesn't match the bind group.
+    InvalidDynamicOffsetCount { actual: usize, expected: usize },
+    /// A dynamic offset must be a multiple of `BIND_BUFFER_ALIGNMENT`.
+    UnalignedDynamicOffset(DynamicOffset),
+    /// The vertex buffer slot is beyond `MAX_VERTEX_BUFFERS`.
+    VertexBufferIndexOutOfRange(u32),
+    /// The buffer usage doesn't contain the usage required by the command.
+    MissingBufferUsage {
+        actual: BufferUsage,
+        expected: BufferUsage,
+    },
+    /// The render pipeline output formats and sample count don't match
+    /// the render pass attachments.
+    IncompatiblePipeline,
+    /// The render pipeline can't be used in a pass with a read-only depth-stencil.
+    IncompatibleReadOnlyDepthStencil,
+    /// The render bundle output formats don't match the render pass attachments.
+    IncompatibleBundle,
+    /// There is no debug group to pop.
+    InvalidPopDebugGroup,
+    /// Push constants require a render pipeline to be set.
+    MissingPipeline,
+    /// The push constant data doesn't match the pipeline layout ranges.
+    PushConstant(PushConstantUploadError),
+    /// The query is invalid or doesn't match the query set.
+    Query(QueryError),
+    /// Occlusion queries require an occlusion query set in the render pass descriptor.
+    MissingOcclusionQuerySet,
+    /// Occlusion queries can't be nested, and the query at this index is still active.
+    OcclusionQueryAlreadyActive(u32),
+    /// There is no active occlusion query to end.
+    NoActiveOcclusionQuery,
+    /// The occlusion query at this index is still active at the end of the pass.
+    UnclosedOcclusionQuery(u32),
+    /// The command requires an extension which isn't enabled.
+    MissingExtension(wgt::Extensions),
+    /// The indirect buffer offset must be a multiple of `INDIRECT_BUFFER_OFFSET_ALIGNMENT`.
+    UnalignedIndirectBufferOffset(BufferAddress),
+    /// The indirect draw arguments or the draw count extend beyond the buffer.
+    IndirectBufferOverrun {
+        begin_offset: BufferAddress,
+        end_offset: BufferAddress,
+        buffer_size: BufferAddress,
+    },
+    /// The draw state is incomplete or out of bounds.
+    Draw(DrawError),
+}
+
+/// Error encountered when running a render pass.
+#[derive(Clone, Debug, PartialEq)]
+pub enum RenderPassError {
+    /// The attachment texture view is invalid.
+    InvalidAttachment(id::TextureViewId),
+    /// A swap chain image can't be used as a depth-stencil attachment.
+    SwapChainImageAsDepthStencil,
+    /// All attachments must have the same extent.
+    MismatchedAttachmentExtent {
+        expected: hal::image::Extent,
+        actual: hal::image::Extent,
+    },
+    /// The attachment sample count is not supported by the adapter.
+    UnsupportedSampleCount(u8),
+    /// All color attachments must have the same sample count.
+    MismatchedSampleCount { expected: u8, actual: u8 },
+    /// An attachment with a resolve target must be multi-sampled.
+    InvalidResolveSourceSampleCount,
+    /// A resolve target must not be multi-sampled.
+    InvalidResolveTargetSampleCount,
+    /// The swap chain image doesn't match the one already used by the command buffer.
+    MismatchedSwapChainImage,
+    /// A read-only depth attachment can't be cleared or stored.
+    InvalidDepthOps,
+    /// A read-only stencil attachment can't be cleared or stored.
+    InvalidStencilOps,
+    /// The attachment texture usage doesn't contain `OUTPUT_ATTACHMENT`.
+    MissingOutputAttachmentUsage(id::TextureId),
+    /// The occlusion query set is invalid or doesn't contain occlusion queries.
+    OcclusionQuerySet(QueryError),
+    /// A command failed, at the given byte offset in the `RawPass` stream.
+    Command {
+        offset: usize,
+        error: RenderCommandError,
+    },
+    /// The device is lost.
+    DeviceLost,
 }
 
 #[derive(Debug, Default)]
@@ -343,6 +531,7 @@ struct State {
     index: IndexState,
     vertex: VertexState,
     debug_scope_depth: u32,
+    active_occlusion_query: Option<u32>,
 }
 
 impl State {
@@ -350,9 +539,13 @@ impl State {
         //TODO: vertex buffers
         let bind_mask = self.binder.invalid_mask();
         if bind_mask != 0 {
-            //let (expected, provided) = self.binder.entries[index as usize].info();
+            let index = bind_mask.trailing_zeros();
+            let (expected, provided) = self.binder.entries[index as usize].info();
             return Err(DrawError::IncompatibleBindGroup {
-                index: bind_mask.trailing_zeros(),
+                index,
+                // invalid entries always have an expected layout
+                expected: expected.unwrap(),
+                provided,
             });
         }
         if self.pipeline == OptionalState::Required {
@@ -383,8 +576,9 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         &self,
         encoder_id: id::CommandEncoderId,
         raw_data: &[u8],
-    ) {
+    ) -> Result<(), RenderPassError> {
         let hub = B::hub(self);
+        let error_context = ErrorContext::new("command_encoder_run_render_pass");
         let mut token = Token::root();
 
         let (device_guard, mut token) = hub.devices.read(&mut token);
@@ -393,16 +587,15 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         let mut trackers = TrackerSet::new(B::VARIANT);
         let cmb = &mut cmb_guard[encoder_id];
         let device = &device_guard[cmb.device_id.value];
-        let mut raw = device.com_allocator.extend(cmb);
-
-        unsafe {
-            raw.begin_primary(hal::command::CommandBufferFlags::ONE_TIME_SUBMIT);
+        if device.is_lost() {
+            return Err(RenderPassError::DeviceLost);
         }
 
         let (bundle_guard, mut token) = hub.render_bundles.read(&mut token);
         let (pipeline_layout_guard, mut token) = hub.pipeline_layouts.read(&mut token);
         let (bind_group_guard, mut token) = hub.bind_groups.read(&mut token);
         let (pipeline_guard, mut token) = hub.render_pipelines.read(&mut token);
+        let (query_set_guard, mut token) = hub.query_sets.read(&mut token);
         let (buffer_guard, mut token) = hub.buffers.read(&mut token);
         let (texture_guard, mut token) = hub.textures.read(&mut token);
         let (view_guard, _) = hub.texture_views.read(&mut token);
@@ -418,7 +611,6 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
             raw_data.len()
         );
         peeker = unsafe { RawRenderTargets::peek_from(peeker, &mut targets) };
-        #[cfg(feature = "trace")]
         let command_peeker_base = peeker;
 
         let color_attachments = targets
@@ -451,6 +643,99 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
             };
             Some(&depth_stencil_attachment_body)
         };
+        let occlusion_query_set_id = id::QuerySetId::from_raw(targets.occlusion_query_set);
+
+        let mut command = RenderCommand::Draw {
+            vertex_count: 0,
+            instance_count: 0,
+            first_vertex: 0,
+            first_instance: 0,
+        };
+
+        #[cfg(feature = "trace")]
+        match cmb.commands {
+            Some(ref mut list) => {
+                let mut pass_commands = Vec::new();
+                let mut pass_dynamic_offsets = Vec::new();
+                let mut pass_push_constant_data = Vec::new();
+                loop {
+                    peeker = unsafe { RenderCommand::peek_from(peeker, &mut command) };
+                    match command {
+                        RenderCommand::SetBindGroup {
+                            num_dynamic_offsets,
+                            phantom_offsets,
+                            ..
+                        } => {
+                            let (new_peeker, offsets) = unsafe {
+                                phantom_offsets.decode_unaligned(
+                                    peeker,
+                                    num_dynamic_offsets as usize,
+                                    raw_data_end,
+                                )
+                            };
+                            peeker = new_peeker;
+                            pass_dynamic_offsets.extend_from_slice(offsets);
+                        }
+                        RenderCommand::SetPushConstant {
+                            size_bytes,
+                            phantom_data,
+                            ..
+                        } => {
+                            let (new_peeker, data) = unsafe {
+                                phantom_data.decode_unaligned(
+                                    peeker,
+                                    (size_bytes / wgt::PUSH_CONSTANT_ALIGNMENT) as usize,
+                                    raw_data_end,
+                                )
+                            };
+                            peeker = new_peeker;
+                            pass_push_constant_data.extend_from_slice(data);
+                        }
+                        RenderCommand::End => break,
+                        _ => {}
+                    }
+                    pass_commands.push(command);
+                }
+                list.push(crate::device::trace::Command::RunRenderPass {
+                    target_colors: color_attachments.iter().cloned().collect(),
+                    target_depth_stencil: depth_stencil_attachment.cloned(),
+                    occlusion_query_set: occlusion_query_set_id,
+                    commands: pass_commands,
+                    dynamic_offsets: pass_dynamic_offsets,
+                    push_constant_data: pass_push_constant_data,
+                });
+                peeker = command_peeker_base;
+            }
+            None => {}
+        }
+
+        let occlusion_query_set = match occlusion_query_set_id {
+            Some(query_set_id) => {
+                let query_set = trackers
+                    .query_sets
+                    .use_extend(&*query_set_guard, query_set_id, (), ())
+                    .map_err(|_| {
+                        device.capture(
+                            error_context,
+                            RenderPassError::OcclusionQuerySet(QueryError::InvalidQuerySet(
+                                query_set_id,
+                            )),
+                        )
+                    })?;
+                if query_set.ty != wgt::QueryType::Occlusion {
+                    return Err(device.capture(
+                        error_context,
+                        RenderPassError::OcclusionQuerySet(QueryError::IncompatibleType {
+                            set_type: query_set.ty,
+                            query_type: wgt::QueryType::Occlusion,
+                        }),
+                    ));
+                }
+                Some((query_set_id, query_set))
+            }
+            None => None,
+        };
+
         // We default to false intentionally, even if depth-stencil isn't used at all.
         // This allows us to use the primary raw pipeline in `RenderPipeline`,
         // instead of the special read-only one, which would be `None`.
@@ -461,11 +746,13 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
             range: &'a hal::image::SubresourceRange,
             previous_use: Option<TextureUse>,
             new_use: TextureUse,
+            /// Aspects whose contents are discarded at the end of the pass.
+            discarded: hal::format::Aspects,
         }
         const MAX_TOTAL_ATTACHMENTS: usize = 2 * MAX_COLOR_TARGETS + 1;
         let mut output_attachments = ArrayVec::<[OutputAttachment; MAX_TOTAL_ATTACHMENTS]>::new();
 
-        let context = {
+        let (context, mut raw) = {
             use hal::device::Device as _;
 
             let samples_count_limit = device.hal_limits.framebuffer_color_sample_counts;
@@ -474,14 +761,24 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
             let mut extent = None;
             let mut used_swap_chain = None::<Stored<id::SwapChainId>>;
 
-            let sample_count = color_attachments
-                .get(0)
-                .map(|at| view_guard[at.attachment].samples)
-                .unwrap_or(1);
-            assert!(
-                sample_count & samples_count_limit != 0,
-                "Attachment sample_count must be supported by physical device limits"
-            );
+            let sample_count = match color_attachments.get(0) {
+                Some(at) => match view_guard.get(at.attachment) {
+                    Ok(view) => view.samples,
+                    Err(_) => {
+                        return Err(device.capture(
+                            error_context,
+                            RenderPassError::InvalidAttachment(at.attachment),
+                        ))
+                    }
+                },
+                None => 1,
+            };
+            if sample_count & samples_count_limit == 0 {
+                return Err(device.capture(
+                    error_context,
+                    RenderPassError::UnsupportedSampleCount(sample_count),
+                ));
+            }
 
             log::trace!(
                 "Encoding render pass begin in command buffer {:?}",
@@ -493,16 +790,32 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                         let view = trackers
                             .views
                             .use_extend(&*view_guard, at.attachment, (), ())
-                            .unwrap();
+                            .map_err(|_| {
+                                device.capture(
+                                    error_context,
+                                    RenderPassError::InvalidAttachment(at.attachment),
+                                )
+                            })?;
                         if let Some(ex) = extent {
-                            assert_eq!(ex, view.extent, "Extent state must match extent from view");
+                            if ex != view.extent {
+                                return Err(device.capture(
+                                    error_context,
+                                    RenderPassError::MismatchedAttachmentExtent {
+                                        expected: ex,
+                                        actual: view.extent,
+                                    },
+                                ));
+                            }
                         } else {
                             extent = Some(view.extent);
                         }
                         let source_id = match view.inner {
                             TextureViewInner::Native { ref source_id, .. } => source_id,
                             TextureViewInner::SwapChain { .. } => {
-                                panic!("Unexpected depth/stencil use of swapchain image!")
+                                return Err(device.capture(
+                                    error_context,
+                                    RenderPassError::SwapChainImageAsDepthStencil,
+                                ))
                             }
                         };
 
@@ -510,17 +823,27 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                         let previous_use = base_trackers
                             .textures
                             .query(source_id.value, view.range.clone());
-                        let new_use = if is_depth_stencil_read_only(at, view.range.aspects) {
+                        let is_read_only = is_depth_stencil_read_only(at, view.range.aspects)
+                            .map_err(|e| device.capture(error_context, e))?;
+                        let new_use = if is_read_only {
                             is_ds_read_only = true;
                             TextureUse::ATTACHMENT_READ
                         } else {
                             TextureUse::ATTACHMENT_WRITE
                         };
+                        let mut discarded = hal::format::Aspects::empty();
+                        if at.depth_store_op == StoreOp::Clear {
+                            discarded |= hal::format::Aspects::DEPTH;
+                        }
+                        if at.stencil_store_op == StoreOp::Clear {
+                            discarded |= hal::format::Aspects::STENCIL;
+                        }
                         output_attachments.push(OutputAttachment {
                             texture_id: source_id,
                             range: &view.range,
                             previous_use,
                             new_use,
+                            discarded: discarded & view.range.aspects,
                         });
 
                         let new_layout = conv::map_texture_state(new_use, view.range.aspects).1;
@@ -554,16 +877,34 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                     let view = trackers
                         .views
                         .use_extend(&*view_guard, at.attachment, (), ())
-                        .unwrap();
+                        .map_err(|_| {
+                            device.capture(
+                                error_context,
+                                RenderPassError::InvalidAttachment(at.attachment),
+                            )
+                        })?;
                     if let Some(ex) = extent {
-                        assert_eq!(ex, view.extent, "Extent state must match extent from view");
+                        if ex != view.extent {
+                            return Err(device.capture(
+                                error_context,
+                                RenderPassError::MismatchedAttachmentExtent {
+                                    expected: ex,
+                                    actual: view.extent,
+                                },
+                            ));
+                        }
                     } else {
                         extent = Some(view.extent);
                     }
-                    assert_eq!(
-                        view.samples, sample_count,
-                        "All attachments must have the same sample_count"
-                    );
+                    if view.samples != sample_count {
+                        return Err(device.capture(
+                            error_context,
+                            RenderPassError::MismatchedSampleCount {
+                                expected: sample_count,
+                                actual: view.samples,
+                            },
+                        ));
+                    }
 
                     let layouts = match view.inner {
                         TextureViewInner::Native { ref source_id, .. } => {
@@ -576,6 +917,10 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                                 range: &view.range,
                                 previous_use,
                                 new_use,
+                                discarded: match at.store_op {
+                                    StoreOp::Clear => hal::format::Aspects::COLOR,
+                                    StoreOp::Store => hal::format::Aspects::empty(),
+                                },
                             });
 
                             let new_layout =
@@ -590,10 +935,12 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                         }
                         TextureViewInner::SwapChain { ref source_id, .. } => {
                             if let Some((ref sc_id, _)) = cmb.used_swap_chain {
-                                assert_eq!(
-                                    source_id.value, sc_id.value,
-                                    "Texture view's swap chain must match swap chain in use"
-                                );
+                                if source_id.value != sc_id.value {
+                                    return Err(device.capture(
+                                        error_context,
+                                        RenderPassError::MismatchedSwapChainImage,
+                                    ));
+                                }
                             } else {
                                 assert!(used_swap_chain.is_none());
                                 used_swap_chain = Some(source_id.clone());
@@ -625,16 +972,35 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                     let view = trackers
                         .views
                         .use_extend(&*view_guard, resolve_target, (), ())
-                        .unwrap();
-                    assert_eq!(
-                        extent,
-                        Some(view.extent),
-                        "Extent state must match extent from view"
-                    );
-                    assert_eq!(
-                        view.samples, 1,
-                        "All resolve_targets must have a sample_count of 1"
-                    );
+                        .map_err(|_| {
+                            device.capture(
+                                error_context,
+                                RenderPassError::InvalidAttachment(resolve_target),
+                            )
+                        })?;
+                    if let Some(ex) = extent {
+                        if ex != view.extent {
+                            return Err(device.capture(
+                                error_context,
+                                RenderPassError::MismatchedAttachmentExtent {
+                                    expected: ex,
+                                    actual: view.extent,
+                                },
+                            ));
+                        }
+                    }
+                    if view.samples != 1 {
+                        return Err(device.capture(
+                            error_context,
+                            RenderPassError::InvalidResolveTargetSampleCount,
+                        ));
+                    }
+                    if sample_count == 1 {
+                        return Err(device.capture(
+                            error_context,
+                            RenderPassError::InvalidResolveSourceSampleCount,
+                        ));
+                    }
 
                     let layouts = match view.inner {
                         TextureViewInner::Native { ref source_id, .. } => {
@@ -647,6 +1013,7 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                                 range: &view.range,
                                 previous_use,
                                 new_use,
+                                discarded: hal::format::Aspects::empty(),
                             });
 
                             let new_layout =
@@ -661,10 +1028,12 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                         }
                         TextureViewInner::SwapChain { ref source_id, .. } => {
                             if let Some((ref sc_id, _)) = cmb.used_swap_chain {
-                                assert_eq!(
-                                    source_id.value, sc_id.value,
-                                    "Texture view's swap chain must match swap chain in use"
-                                );
+                                if source_id.value != sc_id.value {
+                                    return Err(device.capture(
+                                        error_context,
+                                        RenderPassError::MismatchedSwapChainImage,
+                                    ));
+                                }
                             } else {
                                 assert!(used_swap_chain.is_none());
                                 used_swap_chain = Some(source_id.clone());
@@ -696,6 +1065,22 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                 }
             };
 
+            for ot in output_attachments.iter() {
+                let texture = &texture_guard[ot.texture_id.value];
+                if !texture.usage.contains(TextureUsage::OUTPUT_ATTACHMENT) {
+                    return Err(device.capture(
+                        error_context,
+                        RenderPassError::MissingOutputAttachmentUsage(ot.texture_id.value),
+                    ));
+                }
+            }
+
+            // All the attachments are valid, start recording the pass.
+            let mut raw = device.com_allocator.extend(cmb);
+            unsafe {
+                raw.begin_primary(hal::command::CommandBufferFlags::ONE_TIME_SUBMIT);
+            }
+
             let mut render_pass_cache = device.render_passes.lock();
             let render_pass = match render_pass_cache.entry(rp_key.clone()) {
                 Entry::Occupied(e) => e.into_mut(),
@@ -719,19 +1104,7 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                             .zip(entry.key().resolves.iter())
                         {
                             let real_attachment_index = match at.resolve_target {
-                                Some(resolve_attachment) => {
-                                    assert_ne!(
-                                        view_guard[at.attachment].samples,
-                                        1,
-                                        "RenderPassColorAttachmentDescriptor's attachment with a resolve_target must be multi-sampled",
-                                    );
-                                    assert_eq!(
-                                        view_guard[resolve_attachment].samples,
-                                        1,
-                                        "RenderPassColorAttachmentDescriptor's resolve_target must not be multi-sampled",
-                                    );
-                                    attachment_index + i
-                                }
+                                Some(_) => attachment_index + i,
                                 None => hal::pass::ATTACHMENT_UNUSED,
                             };
                             resolve_ids.push((real_attachment_index, layout));
@@ -894,7 +1267,7 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                 );
             }
 
-            RenderPassContext {
+            let context = RenderPassContext {
                 attachments: AttachmentData {
                     colors: color_attachments
                         .iter()
@@ -909,7 +1282,8 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                         .map(|at| view_guard[at.attachment].format),
                 },
                 sample_count,
-            }
+            };
+            (context, raw)
         };
 
         let mut state = State {
@@ -920,21 +1294,18 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
             index: IndexState::default(),
             vertex: VertexState::default(),
             debug_scope_depth: 0,
+            active_occlusion_query: None,
         };
 
-        let mut command = RenderCommand::Draw {
-            vertex_count: 0,
-            instance_count: 0,
-            first_vertex: 0,
-            first_instance: 0,
-        };
-        loop {
+        let mut command_offset;
+        let result = loop {
             assert!(
                 unsafe { peeker.add(RenderCommand::max_size()) <= raw_data_end },
                 "RenderCommand (size {}) is too big to fit within raw_data (size {})",
                 RenderCommand::max_size(),
                 raw_data.len()
             );
+            command_offset = peeker as usize - command_peeker_base as usize;
             peeker = unsafe { RenderCommand::peek_from(peeker, &mut command) };
             match command {
                 RenderCommand::SetBindGroup {
@@ -952,23 +1323,34 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                     };
                     peeker = new_peeker;
 
-                    if cfg!(debug_assertions) {
-                        for off in offsets {
-                            assert_eq!(
-                                *off as BufferAddress % BIND_BUFFER_ALIGNMENT,
-                                0,
-                                "Misaligned dynamic buffer offset: {} does not align with {}",
-                                off,
-                                BIND_BUFFER_ALIGNMENT
-                            );
-                        }
+                    if index as u32 >= cmb.limits.max_bind_groups {
+                        break Err(RenderCommandError::BindGroupIndexOutOfRange {
+                            index,
+                            max: cmb.limits.max_bind_groups,
+                        });
+                    }
+                    if let Some(&off) = offsets
+                        .iter()
+                        .find(|&&off| off as BufferAddress % BIND_BUFFER_ALIGNMENT != 0)
+                    {
+                        break Err(RenderCommandError::UnalignedDynamicOffset(off));
                     }
 
-                    let bind_group = trackers
-                        .bind_groups
-                        .use_extend(&*bind_group_guard, bind_group_id, (), ())
-                        .unwrap();
-                    assert_eq!(bind_group.dynamic_count, offsets.len());
+                    let bind_group = match trackers.bind_groups.use_extend(
+                        &*bind_group_guard,
+                        bind_group_id,
+                        (),
+                        (),
+                    ) {
+                        Ok(bind_group) => bind_group,
+                        Err(_) => break Err(RenderCommandError::InvalidBindGroup(bind_group_id)),
+                    };
+                    if bind_group.dynamic_count != offsets.len() {
+                        break Err(RenderCommandError::InvalidDynamicOffsetCount {
+                            actual: offsets.len(),
+                            expected: bind_group.dynamic_count,
+                        });
+                    }
 
                     trackers.merge_extend(&bind_group.used);
 
@@ -997,21 +1379,27 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                     };
                 }
                 RenderCommand::SetPipeline(pipeline_id) => {
+                    let pipeline = match trackers.render_pipes.use_extend(
+                        &*pipeline_guard,
+                        pipeline_id,
+                        (),
+                        (),
+                    ) {
+                        Ok(pipeline) => pipeline,
+                        Err(_) => break Err(RenderCommandError::InvalidPipeline(pipeline_id)),
+                    };
+
+                    if !context.compatible(&pipeline.pass_context) {
+                        break Err(RenderCommandError::IncompatiblePipeline);
+                    }
+                    if is_ds_read_only
+                        && !pipeline
+                            .flags
+                            .contains(PipelineFlags::DEPTH_STENCIL_READ_ONLY)
+                    {
+                        break Err(RenderCommandError::IncompatibleReadOnlyDepthStencil);
+                    }
                     state.pipeline = OptionalState::Set;
-                    let pipeline = trackers
-                        .render_pipes
-                        .use_extend(&*pipeline_guard, pipeline_id, (), ())
-                        .unwrap();
-
-                    assert!(
-                        context.compatible(&pipeline.pass_context),
-                        "The render pipeline output formats and sample count do not match render pass attachment formats!"
-                    );
-                    assert!(
-                        !is_ds_read_only || pipeline.flags.contains(PipelineFlags::DEPTH_STENCIL_READ_ONLY),
-                        "Pipeline {:?} is not compatible with the depth-stencil read-only render pass",
-                        pipeline_id
-                    );
 
                     state
                         .blend_color
@@ -1104,11 +1492,26 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                     offset,
                     size,
                 } => {
-                    let buffer = trackers
-                        .buffers
-                        .use_extend(&*buffer_guard, buffer_id, (), BufferUse::INDEX)
-                        .unwrap();
-                    assert!(buffer.usage.contains(BufferUsage::INDEX), "An invalid setIndexBuffer call has been made. The buffer usage is {:?} which does not contain required usage INDEX", buffer.usage);
+                    let buffer = match trackers.buffers.use_extend(
+                        &*buffer_guard,
+                        buffer_id,
+                        (),
+                        BufferUse::INDEX,
+                    ) {
+                        Ok(buffer) => buffer,
+                        Err(UseExtendError::InvalidResource) => {
+                            break Err(RenderCommandError::InvalidBuffer(buffer_id))
+                        }
+                        Err(UseExtendError::Conflict(_)) => {
+                            break Err(RenderCommandError::BufferUsageConflict(buffer_id))
+                        }
+                    };
+                    if !buffer.usage.contains(BufferUsage::INDEX) {
+                        break Err(RenderCommandError::MissingBufferUsage {
+                            actual: buffer.usage,
+                            expected: BufferUsage::INDEX,
+                        });
+                    }
 
                     let end = if size != BufferSize::WHOLE {
                         offset + size.0
@@ -1137,11 +1540,29 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                     offset,
                     size,
                 } => {
-                    let buffer = trackers
-                        .buffers
-                        .use_extend(&*buffer_guard, buffer_id, (), BufferUse::VERTEX)
-                        .unwrap();
-                    assert!(buffer.usage.contains(BufferUsage::VERTEX), "An invalid setVertexBuffer call has been made. The buffer usage is {:?} which does not contain required usage VERTEX", buffer.usage);
+                    if slot as usize >= MAX_VERTEX_BUFFERS {
+                        break Err(RenderCommandError::VertexBufferIndexOutOfRange(slot));
+                    }
+                    let buffer = match trackers.buffers.use_extend(
+                        &*buffer_guard,
+                        buffer_id,
+                        (),
+                        BufferUse::VERTEX,
+                    ) {
+                        Ok(buffer) => buffer,
+                        Err(UseExtendError::InvalidResource) => {
+                            break Err(RenderCommandError::InvalidBuffer(buffer_id))
+                        }
+                        Err(UseExtendError::Conflict(_)) => {
+                            break Err(RenderCommandError::BufferUsageConflict(buffer_id))
+                        }
+                    };
+                    if !buffer.usage.contains(BufferUsage::VERTEX) {
+                        break Err(RenderCommandError::MissingBufferUsage {
+                            actual: buffer.usage,
+                            expected: BufferUsage::VERTEX,
+                        });
+                    }
                     let empty_slots = (1 + slot as usize).saturating_sub(state.vertex.inputs.len());
                     state
                         .vertex
@@ -1212,25 +1633,66 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                         raw.set_scissors(0, iter::once(r));
                     }
                 }
+                RenderCommand::SetPushConstant {
+                    stages,
+                    offset,
+                    size_bytes,
+                    phantom_data,
+                } => {
+                    let (new_peeker, data) = unsafe {
+                        phantom_data.decode_unaligned(
+                            peeker,
+                            (size_bytes / wgt::PUSH_CONSTANT_ALIGNMENT) as usize,
+                            raw_data_end,
+                        )
+                    };
+                    peeker = new_peeker;
+
+                    let pipeline_layout_id = match state.binder.pipeline_layout_id {
+                        Some(id) => id,
+                        None => break Err(RenderCommandError::MissingPipeline),
+                    };
+                    let pipeline_layout = &pipeline_layout_guard[pipeline_layout_id];
+                    if let Err(e) =
+                        pipeline_layout.validate_push_constant_ranges(stages, offset, size_bytes)
+                    {
+                        break Err(RenderCommandError::PushConstant(e));
+                    }
+
+                    unsafe {
+                        raw.push_graphics_constants(
+                            &pipeline_layout.raw,
+                            conv::map_shader_stage_flags(stages),
+                            offset,
+                            data,
+                        );
+                    }
+                }
                 RenderCommand::Draw {
                     vertex_count,
                     instance_count,
                     first_vertex,
                     first_instance,
                 } => {
-                    state.is_ready().unwrap();
-                    assert!(
-                        first_vertex + vertex_count <= state.vertex.vertex_limit,
-                        "Vertex {} extends beyond limit {}",
-                        first_vertex + vertex_count,
-                        state.vertex.vertex_limit
-                    );
-                    assert!(
-                        first_instance + instance_count <= state.vertex.instance_limit,
-                        "Instance {} extends beyond limit {}",
-                        first_instance + instance_count,
-                        state.vertex.instance_limit
-                    );
+                    if let Err(e) = state.is_ready() {
+                        break Err(RenderCommandError::Draw(e));
+                    }
+                    let last_vertex = first_vertex + vertex_count;
+                    let vertex_limit = state.vertex.vertex_limit;
+                    if last_vertex > vertex_limit {
+                        break Err(RenderCommandError::Draw(DrawError::VertexBeyondLimit {
+                            last_vertex,
+                            vertex_limit,
+                        }));
+                    }
+                    let last_instance = first_instance + instance_count;
+                    let instance_limit = state.vertex.instance_limit;
+                    if last_instance > instance_limit {
+                        break Err(RenderCommandError::Draw(DrawError::InstanceBeyondLimit {
+                            last_instance,
+                            instance_limit,
+                        }));
+                    }
 
                     unsafe {
                         raw.draw(
@@ -1246,21 +1708,27 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                     base_vertex,
                     first_instance,
                 } => {
-                    state.is_ready().unwrap();
+                    if let Err(e) = state.is_ready() {
+                        break Err(RenderCommandError::Draw(e));
+                    }
 
                     //TODO: validate that base_vertex + max_index() is within the provided range
-                    assert!(
-                        first_index + index_count <= state.index.limit,
-                        "Index {} extends beyond limit {}",
-                        first_index + index_count,
-                        state.index.limit
-                    );
-                    assert!(
-                        first_instance + instance_count <= state.vertex.instance_limit,
-                        "Instance {} extends beyond limit {}",
-                        first_instance + instance_count,
-                        state.vertex.instance_limit
-                    );
+                    let last_index = first_index + index_count;
+                    let index_limit = state.index.limit;
+                    if last_index > index_limit {
+                        break Err(RenderCommandError::Draw(DrawError::IndexBeyondLimit {
+                            last_index,
+                            index_limit,
+                        }));
+                    }
+                    let last_instance = first_instance + instance_count;
+                    let instance_limit = state.vertex.instance_limit;
+                    if last_instance > instance_limit {
+                        break Err(RenderCommandError::Draw(DrawError::InstanceBeyondLimit {
+                            last_instance,
+                            instance_limit,
+                        }));
+                    }
 
                     unsafe {
                         raw.draw_indexed(
@@ -1271,31 +1739,205 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                     }
                 }
                 RenderCommand::DrawIndirect { buffer_id, offset } => {
-                    state.is_ready().unwrap();
+                    if let Err(e) = state.is_ready() {
+                        break Err(RenderCommandError::Draw(e));
+                    }
 
-                    let buffer = trackers
-                        .buffers
-                        .use_extend(&*buffer_guard, buffer_id, (), BufferUse::INDIRECT)
-                        .unwrap();
-                    assert!(buffer.usage.contains(BufferUsage::INDIRECT), "An invalid drawIndirect call has been made. The buffer usage is {:?} which does not contain required usage INDIRECT", buffer.usage);
+                    let buffer = match trackers.buffers.use_extend(
+                        &*buffer_guard,
+                        buffer_id,
+                        (),
+                        BufferUse::INDIRECT,
+                    ) {
+                        Ok(buffer) => buffer,
+                        Err(UseExtendError::InvalidResource) => {
+                            break Err(RenderCommandError::InvalidBuffer(buffer_id))
+                        }
+                        Err(UseExtendError::Conflict(_)) => {
+                            break Err(RenderCommandError::BufferUsageConflict(buffer_id))
+                        }
+                    };
+                    if !buffer.usage.contains(BufferUsage::INDIRECT) {
+                        break Err(RenderCommandError::MissingBufferUsage {
+                            actual: buffer.usage,
+                            expected: BufferUsage::INDIRECT,
+                        });
+                    }
 
                     unsafe {
                         raw.draw_indirect(&buffer.raw, offset, 1, 0);
                     }
                 }
                 RenderCommand::DrawIndexedIndirect { buffer_id, offset } => {
-                    state.is_ready().unwrap();
+                    if let Err(e) = state.is_ready() {
+                        break Err(RenderCommandError::Draw(e));
+                    }
 
-                    let buffer = trackers
-                        .buffers
-                        .use_extend(&*buffer_guard, buffer_id, (), BufferUse::INDIRECT)
-                        .unwrap();
-                    assert!(buffer.usage.contains(BufferUsage::INDIRECT), "An invalid drawIndexedIndirect call has been made. The buffer usage is {:?} which does not contain required usage INDIRECT", buffer.usage);
+                    let buffer = match trackers.buffers.use_extend(
+                        &*buffer_guard,
+                        buffer_id,
+                        (),
+                        BufferUse::INDIRECT,
+                    ) {
+                        Ok(buffer) => buffer,
+                        Err(UseExtendError::InvalidResource) => {
+                            break Err(RenderCommandError::InvalidBuffer(buffer_id))
+                        }
+                        Err(UseExtendError::Conflict(_)) => {
+                            break Err(RenderCommandError::BufferUsageConflict(buffer_id))
+                        }
+                    };
+                    if !buffer.usage.contains(BufferUsage::INDIRECT) {
+                        break Err(RenderCommandError::MissingBufferUsage {
+                            actual: buffer.usage,
+                            expected: BufferUsage::INDIRECT,
+                        });
+                    }
 
                     unsafe {
                         raw.draw_indexed_indirect(&buffer.raw, offset, 1, 0);
                     }
                 }
+                RenderCommand::MultiDrawIndirect {
+                    buffer_id,
+                    offset,
+                    count,
+                    indexed,
+                } => {
+                    if let Err(e) =
+                        check_extension(device.extensions, wgt::Extensions::MULTI_DRAW_INDIRECT)
+                    {
+                        break Err(e);
+                    }
+                    if let Err(e) = state.is_ready() {
+                        break Err(RenderCommandError::Draw(e));
+                    }
+
+                    let buffer = match trackers.buffers.use_extend(
+                        &*buffer_guard,
+                        buffer_id,
+                        (),
+                        BufferUse::INDIRECT,
+                    ) {
+                        Ok(buffer) => buffer,
+                        Err(UseExtendError::InvalidResource) => {
+                            break Err(RenderCommandError::InvalidBuffer(buffer_id))
+                        }
+                        Err(UseExtendError::Conflict(_)) => {
+                            break Err(RenderCommandError::BufferUsageConflict(buffer_id))
+                        }
+                    };
+                    let stride = if indexed {
+                        wgt::DRAW_INDEXED_INDIRECT_SIZE
+                    } else {
+                        wgt::DRAW_INDIRECT_SIZE
+                    };
+                    if let Err(e) =
+                        validate_indirect_buffer(buffer.usage, buffer.size, offset, stride, count)
+                    {
+                        break Err(e);
+                    }
+
+                    unsafe {
+                        if indexed {
+                            raw.draw_indexed_indirect(&buffer.raw, offset, count, stride as u32);
+                        } else {
+                            raw.draw_indirect(&buffer.raw, offset, count, stride as u32);
+                        }
+                    }
+                }
+                RenderCommand::MultiDrawIndirectCount {
+                    buffer_id,
+                    offset,
+                    count_buffer_id,
+                    count_buffer_offset,
+                    max_count,
+                    indexed,
+                } => {
+                    if let Err(e) = check_extension(
+                        device.extensions,
+                        wgt::Extensions::MULTI_DRAW_INDIRECT_COUNT,
+                    ) {
+                        break Err(e);
+                    }
+                    if let Err(e) = state.is_ready() {
+                        break Err(RenderCommandError::Draw(e));
+                    }
+
+                    let buffer = match trackers.buffers.use_extend(
+                        &*buffer_guard,
+                        buffer_id,
+                        (),
+                        BufferUse::INDIRECT,
+                    ) {
+                        Ok(buffer) => buffer,
+                        Err(UseExtendError::InvalidResource) => {
+                            break Err(RenderCommandError::InvalidBuffer(buffer_id))
+                        }
+                        Err(UseExtendError::Conflict(_)) => {
+                            break Err(RenderCommandError::BufferUsageConflict(buffer_id))
+                        }
+                    };
+                    let count_buffer = match trackers.buffers.use_extend(
+                        &*buffer_guard,
+                        count_buffer_id,
+                        (),
+                        BufferUse::INDIRECT,
+                    ) {
+                        Ok(buffer) => buffer,
+                        Err(UseExtendError::InvalidResource) => {
+                            break Err(RenderCommandError::InvalidBuffer(count_buffer_id))
+                        }
+                        Err(UseExtendError::Conflict(_)) => {
+                            break Err(RenderCommandError::BufferUsageConflict(count_buffer_id))
+                        }
+                    };
+                    let stride = if indexed {
+                        wgt::DRAW_INDEXED_INDIRECT_SIZE
+                    } else {
+                        wgt::DRAW_INDIRECT_SIZE
+                    };
+                    if let Err(e) = validate_indirect_buffer(
+                        buffer.usage,
+                        buffer.size,
+                        offset,
+                        stride,
+                        max_count,
+                    ) {
+                        break Err(e);
+                    }
+                    if let Err(e) = validate_indirect_buffer(
+                        count_buffer.usage,
+                        count_buffer.size,
+                        count_buffer_offset,
+                        mem::size_of::<u32>() as BufferAddress,
+                        1,
+                    ) {
+                        break Err(e);
+                    }
+
+                    unsafe {
+                        if indexed {
+                            raw.draw_indexed_indirect_count(
+                                &buffer.raw,
+                                offset,
+                                &count_buffer.raw,
+                                count_buffer_offset,
+                                max_count,
+                                stride as u32,
+                            );
+                        } else {
+                            raw.draw_indirect_count(
+                                &buffer.raw,
+                                offset,
+                                &count_buffer.raw,
+                                count_buffer_offset,
+                                max_count,
+                                stride as u32,
+                            );
+                        }
+                    }
+                }
                 RenderCommand::PushDebugGroup {
                     color,
                     len,
@@ -1309,15 +1951,14 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
 
                     raw.begin_debug_marker(str::from_utf8(label).unwrap(), color)
                 },
-                RenderCommand::PopDebugGroup => unsafe {
-                    assert_ne!(
-                        state.debug_scope_depth, 0,
-                        "Can't pop debug group, because number of pushed debug groups is zero!"
-                    );
+                RenderCommand::PopDebugGroup => {
+                    if state.debug_scope_depth == 0 {
+                        break Err(RenderCommandError::InvalidPopDebugGroup);
+                    }
                     state.debug_scope_depth -= 1;
 
-                    raw.end_debug_marker()
-                },
+                    unsafe { raw.end_debug_marker() }
+                }
                 RenderCommand::InsertDebugMarker {
                     color,
                     len,
@@ -1330,17 +1971,22 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                     raw.insert_debug_marker(str::from_utf8(label).unwrap(), color)
                 },
                 RenderCommand::ExecuteBundle(bundle_id) => {
-                    let bundle = trackers
-                        .bundles
-                        .use_extend(&*bundle_guard, bundle_id, (), ())
-                        .unwrap();
+                    let bundle =
+                        match trackers
+                            .bundles
+                            .use_extend(&*bundle_guard, bundle_id, (), ())
+                        {
+                            Ok(bundle) => bundle,
+                            Err(_) => {
+                                break Err(RenderCommandError::InvalidRenderBundle(bundle_id))
+                            }
+                        };
 
-                    assert!(
-                        context.compatible(&bundle.context),
-                        "The render bundle output formats do not match render pass attachment formats!"
-                    );
+                    if !context.compatible(&bundle.context) {
+                        break Err(RenderCommandError::IncompatibleBundle);
+                    }
 
-                    unsafe {
+                    if let Err(e) = unsafe {
                         bundle.execute(
                             &mut raw,
                             &*pipeline_layout_guard,
@@ -1348,52 +1994,88 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                             &*pipeline_guard,
                             &*buffer_guard,
                         )
-                    };
+                    } {
+                        break Err(e);
+                    }
 
                     trackers.merge_extend(&bundle.used);
                     state.reset_bundle();
                 }
-                RenderCommand::End => break,
-            }
-        }
-
-        #[cfg(feature = "trace")]
-        match cmb.commands {
-            Some(ref mut list) => {
-                let mut pass_commands = Vec::new();
-                let mut pass_dynamic_offsets = Vec::new();
-                peeker = command_peeker_base;
-                loop {
-                    peeker = unsafe { RenderCommand::peek_from(peeker, &mut command) };
-                    match command {
-                        RenderCommand::SetBindGroup {
-                            num_dynamic_offsets,
-                            phantom_offsets,
-                            ..
-                        } => {
-                            let (new_peeker, offsets) = unsafe {
-                                phantom_offsets.decode_unaligned(
-                                    peeker,
-                                    num_dynamic_offsets as usize,
-                                    raw_data_end,
-                                )
-                            };
-                            peeker = new_peeker;
-                            pass_dynamic_offsets.extend_from_slice(offsets);
+                RenderCommand::WriteTimestamp {
+                    query_set_id,
+                    query_index,
+                } => {
+                    let query_set = match trackers.query_sets.use_extend(
+                        &*query_set_guard,
+                        query_set_id,
+                        (),
+                        (),
+                    ) {
+                        Ok(query_set) => query_set,
+                        Err(_) => {
+                            break Err(RenderCommandError::Query(QueryError::InvalidQuerySet(
+                                query_set_id,
+                            )))
                         }
-                        RenderCommand::End => break,
-                        _ => {}
+                    };
+                    if let Err(e) = query_set.write_timestamp(&mut raw, query_index) {
+                        break Err(RenderCommandError::Query(e));
                     }
-                    pass_commands.push(command);
+                    cmb.query_resets.push((query_set_id, query_index));
+                }
+                RenderCommand::BeginOcclusionQuery { query_index } => {
+                    let (query_set_id, query_set) = match occlusion_query_set {
+                        Some(pair) => pair,
+                        None => break Err(RenderCommandError::MissingOcclusionQuerySet),
+                    };
+                    if let Some(active_index) = state.active_occlusion_query {
+                        break Err(RenderCommandError::OcclusionQueryAlreadyActive(
+                            active_index,
+                        ));
+                    }
+                    if let Err(e) = query_set.begin_occlusion_query(&mut raw, query_index) {
+                        break Err(RenderCommandError::Query(e));
+                    }
+                    state.active_occlusion_query = Some(query_index);
+                    cmb.query_resets.push((query_set_id, query_index));
+                }
+                RenderCommand::EndOcclusionQuery => {
+                    let query_index = match state.active_occlusion_query.take() {
+                        Some(index) => index,
+                        None => break Err(RenderCommandError::NoActiveOcclusionQuery),
+                    };
+                    // an active query implies the query set is there
+                    let (_, query_set) = occlusion_query_set.unwrap();
+                    query_set.end_occlusion_query(&mut raw, query_index);
+                }
+                RenderCommand::End => {
+                    if let Some(query_index) = state.active_occlusion_query {
+                        break Err(RenderCommandError::UnclosedOcclusionQuery(query_index));
+                    }
+                    break Ok(());
                 }
-                list.push(crate::device::trace::Command::RunRenderPass {
-                    target_colors: color_attachments.into_iter().collect(),
-                    target_depth_stencil: depth_stencil_attachment.cloned(),
-                    commands: pass_commands,
-                    dynamic_offsets: pass_dynamic_offsets,
-                });
             }
-            None => {}
+        };
+        if let Err(error) = result {
+            // Close the pass and keep the raw command buffer with the encoder,
+            // which can't be finished any more.
+            if let (Some(query_index), Some((_, query_set))) =
+                (state.active_occlusion_query, occlusion_query_set)
+            {
+                query_set.end_occlusion_query(&mut raw, query_index);
+            }
+            unsafe {
+                raw.end_render_pass();
+            }
+            cmb.raw.push(raw);
+            cmb.is_valid = false;
+            return Err(device.capture(
+                error_context,
+                RenderPassError::Command {
+                    offset: command_offset,
+                    error,
+                },
+            ));
         }
 
         log::trace!("Merging {:?} with the render pass", encoder_id);
@@ -1401,14 +2083,8 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
             raw.end_render_pass();
         }
 
+        let mut discarded_surfaces = ArrayVec::<[DiscardedSurface; MAX_TOTAL_ATTACHMENTS]>::new();
         for ot in output_attachments {
-            let texture = &texture_guard[ot.texture_id.value];
-            assert!(
-                texture.usage.contains(TextureUsage::OUTPUT_ATTACHMENT),
-                "Texture usage {:?} must contain the usage flag OUTPUT_ATTACHMENT",
-                texture.usage
-            );
-
             // the tracker set of the pass is always in "extend" mode
             trackers
                 .textures
@@ -1434,8 +2110,25 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                     )
                     .unwrap();
             }
+
+            if !ot.discarded.is_empty() && cmb.private_features.zero_initialize {
+                discarded_surfaces.push(DiscardedSurface {
+                    texture_id: ot.texture_id.value,
+                    range: hal::image::SubresourceRange {
+                        aspects: ot.discarded,
+                        ..ot.range.clone()
+                    },
+                });
+            }
         }
 
+        super::CommandBuffer::clear_discarded_surfaces(
+            cmb.raw.last_mut().unwrap(),
+            &mut cmb.trackers,
+            &mut cmb.discarded_surfaces,
+            trackers.textures.used(),
+            &*texture_guard,
+        );
         super::CommandBuffer::insert_barriers(
             cmb.raw.last_mut().unwrap(),
             &mut cmb.trackers,
@@ -1447,6 +2140,24 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
             cmb.raw.last_mut().unwrap().finish();
         }
         cmb.raw.push(raw);
+
+        for surface in discarded_surfaces {
+            let texture_id = surface.texture_id;
+            let partial = surface.range.aspects != texture_guard[texture_id].full_range.aspects;
+            cmb.discarded_surfaces.push(surface);
+            if partial {
+                // The initialization of depth and stencil isn't tracked separately,
+                // so a discard of only one of them is cleared right away.
+                super::CommandBuffer::clear_discarded_surfaces(
+                    cmb.raw.last_mut().unwrap(),
+                    &mut cmb.trackers,
+                    &mut cmb.discarded_surfaces,
+                    iter::once(texture_id),
+                    &*texture_guard,
+                );
+            }
+        }
+        Ok(())
     }
 }
 
@@ -1563,6 +2274,35 @@ pub mod render_ffi {
         pass.encode(&RenderCommand::SetScissor(Rect { x, y, w, h }));
     }
 
+    /// # Safety
+    ///
+    /// This function is unsafe as there is no guarantee that the given pointer is
+    /// valid for `size_bytes` bytes.
+    #[no_mangle]
+    pub unsafe extern "C" fn wgpu_render_pass_set_push_constants(
+        pass: &mut RawPass,
+        stages: wgt::ShaderStage,
+        offset: u32,
+        size_bytes: u32,
+        data: *const u32,
+    ) {
+        assert_eq!(
+            size_bytes % wgt::PUSH_CONSTANT_ALIGNMENT,
+            0,
+            "Push constant size must be aligned to 4 bytes."
+        );
+        pass.encode(&RenderCommand::SetPushConstant {
+            stages,
+            offset,
+            size_bytes,
+            phantom_data: PhantomSlice::default(),
+        });
+        pass.encode_slice(slice::from_raw_parts(
+            data,
+            (size_bytes / wgt::PUSH_CONSTANT_ALIGNMENT) as usize,
+        ));
+    }
+
     #[no_mangle]
     pub unsafe extern "C" fn wgpu_render_pass_draw(
         pass: &mut RawPass,
@@ -1615,6 +2355,99 @@ pub mod render_ffi {
         pass.encode(&RenderCommand::DrawIndexedIndirect { buffer_id, offset });
     }
 
+    #[no_mangle]
+    pub unsafe extern "C" fn wgpu_render_pass_multi_draw_indirect(
+        pass: &mut RawPass,
+        buffer_id: id::BufferId,
+        offset: BufferAddress,
+        count: u32,
+    ) {
+        pass.encode(&RenderCommand::MultiDrawIndirect {
+            buffer_id,
+            offset,
+            count,
+            indexed: false,
+        });
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn wgpu_render_pass_multi_draw_indexed_indirect(
+        pass: &mut RawPass,
+        buffer_id: id::BufferId,
+        offset: BufferAddress,
+        count: u32,
+    ) {
+        pass.encode(&RenderCommand::MultiDrawIndirect {
+            buffer_id,
+            offset,
+            count,
+            indexed: true,
+        });
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn wgpu_render_pass_multi_draw_indirect_count(
+        pass: &mut RawPass,
+        buffer_id: id::BufferId,
+        offset: BufferAddress,
+        count_buffer_id: id::BufferId,
+        count_buffer_offset: BufferAddress,
+        max_count: u32,
+    ) {
+        pass.encode(&RenderCommand::MultiDrawIndirectCount {
+            buffer_id,
+            offset,
+            count_buffer_id,
+            count_buffer_offset,
+            max_count,
+            indexed: false,
+        });
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn wgpu_render_pass_multi_draw_indexed_indirect_count(
+        pass: &mut RawPass,
+        buffer_id: id::BufferId,
+        offset: BufferAddress,
+        count_buffer_id: id::BufferId,
+        count_buffer_offset: BufferAddress,
+        max_count: u32,
+    ) {
+        pass.encode(&RenderCommand::MultiDrawIndirectCount {
+            buffer_id,
+            offset,
+            count_buffer_id,
+            count_buffer_offset,
+            max_count,
+            indexed: true,
+        });
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn wgpu_render_pass_write_timestamp(
+        pass: &mut RawPass,
+        query_set_id: id::QuerySetId,
+        query_index: u32,
+    ) {
+        pass.encode(&RenderCommand::WriteTimestamp {
+            query_set_id,
+            query_index,
+        });
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn wgpu_render_pass_begin_occlusion_query(
+        pass: &mut RawPass,
+        query_index: u32,
+    ) {
+        pass.encode(&RenderCommand::BeginOcclusionQuery { query_index });
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn wgpu_render_pass_end_occlusion_query(pass: &mut RawPass) {
+        pass.encode(&RenderCommand::EndOcclusionQuery);
+    }
+
     #[no_mangle]
     pub unsafe extern "C" fn wgpu_render_pass_push_debug_group(
         pass: &mut RawPass,
@@ -1673,3 +2506,67 @@ pub mod render_ffi {
         pass.base
     }
 }
+
+#[test]
+fn test_indirect_extension() {
+    let enabled = wgt::Extensions::MULTI_DRAW_INDIRECT;
+    assert_eq!(
+        check_extension(enabled, wgt::Extensions::MULTI_DRAW_INDIRECT),
+        Ok(())
+    );
+    assert_eq!(
+        check_extension(enabled, wgt::Extensions::MULTI_DRAW_INDIRECT_COUNT),
+        Err(RenderCommandError::MissingExtension(
+            wgt::Extensions::MULTI_DRAW_INDIRECT_COUNT
+        ))
+    );
+}
+
+#[test]
+fn test_indirect_buffer_usage() {
+    let usage = BufferUsage::VERTEX | BufferUsage::COPY_DST;
+    assert_eq!(
+        validate_indirect_buffer(usage, 64, 0, wgt::DRAW_INDIRECT_SIZE, 1),
+        Err(RenderCommandError::MissingBufferUsage {
+            actual: usage,
+            expected: BufferUsage::INDIRECT,
+        })
+    );
+    assert_eq!(
+        validate_indirect_buffer(BufferUsage::INDIRECT, 64, 2, wgt::DRAW_INDIRECT_SIZE, 1),
+        Err(RenderCommandError::UnalignedIndirectBufferOffset(2))
+    );
+}
+
+#[test]
+fn test_indirect_buffer_overrun() {
+    let usage = BufferUsage::INDIRECT;
+    let stride = wgt::DRAW_INDEXED_INDIRECT_SIZE;
+    assert_eq!(validate_indirect_buffer(usage, 60, 20, stride, 2), Ok(()));
+    assert_eq!(validate_indirect_buffer(usage, 60, 0, stride, 0), Ok(()));
+    assert_eq!(
+        validate_indirect_buffer(usage, 60, 24, stride, 2),
+        Err(RenderCommandError::IndirectBufferOverrun {
+            begin_offset: 24,
+            end_offset: 64,
+            buffer_size: 60,
+        })
+    );
+    // the size of the arguments overflows
+    assert_eq!(
+        validate_indirect_buffer(usage, 60, BufferAddress::MAX - 3, stride, 1),
+        Err(RenderCommandError::IndirectBufferOverrun {
+            begin_offset: BufferAddress::MAX - 3,
+            end_offset: BufferAddress::MAX,
+            buffer_size: 60,
+        })
+    );
+    assert_eq!(
+        validate_indirect_buffer(usage, 60, 0, BufferAddress::MAX - 3, u32::MAX),
+        Err(RenderCommandError::IndirectBufferOverrun {
+            begin_offset: 0,
+            end_offset: BufferAddress::MAX,
+            buffer_size: 60,
+        })
+    );
+}
@@ -5,11 +5,13 @@
 #[cfg(feature = "trace")]
 use crate::device::trace::Command as TraceCommand;
 use crate::{
+    command::CommandBuffer,
     conv,
-    device::{all_buffer_stages, all_image_stages},
+    device::{all_buffer_stages, all_image_stages, ErrorContext},
     hub::{GfxBackend, Global, GlobalIdentityHandlerFactory, Storage, Token},
     id::{BufferId, CommandEncoderId, TextureId},
     resource::{BufferUse, Texture, TextureUse},
+    PrivateFeatures,
 };
 
 use hal::command::CommandBuffer as _;
@@ -39,57 +41,190 @@ pub struct TextureCopyView {
     pub origin: Origin3d,
 }
 
+/// Side of a copy operation.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CopySide {
+    Source,
+    Destination,
+}
+
+/// Dimension of a texture, as reported by `TransferError::TextureOverrun`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TextureErrorDimension {
+    X,
+    Y,
+    Z,
+}
+
+/// Error encountered while attempting a data transfer.
+#[derive(Clone, Debug)]
+pub enum TransferError {
+    /// The buffer is invalid.
+    InvalidBuffer(BufferId),
+    /// The texture is invalid.
+    InvalidTexture(TextureId),
+    /// The source usage doesn't contain `COPY_SRC`.
+    MissingCopySrcUsageFlag,
+    /// The destination usage doesn't contain `COPY_DST`.
+    MissingCopyDstUsageFlag,
+    /// The copy size must be a multiple of `COPY_BUFFER_ALIGNMENT`.
+    UnalignedCopySize(BufferAddress),
+    /// The buffer offset must be a multiple of `COPY_BUFFER_ALIGNMENT`,
+    /// or of the texel block size for texture copies.
+    UnalignedBufferOffset {
+        offset: BufferAddress,
+        side: CopySide,
+    },
+    /// The bytes per row must be a multiple of `COPY_BYTES_PER_ROW_ALIGNMENT`.
+    UnalignedBytesPerRow(u32),
+    /// The copy width must be a multiple of the texel block width.
+    UnalignedCopyWidth(u32),
+    /// The copy height must be a multiple of the texel block height.
+    UnalignedCopyHeight(u32),
+    /// The copy origin must be a multiple of the texel block dimensions.
+    UnalignedCopyOrigin { x: u32, y: u32 },
+    /// The rows per image must be a multiple of the texel block height.
+    UnalignedRowsPerImage(u32),
+    /// The bytes per row is smaller than a complete row of the copy.
+    InvalidBytesPerRow { bytes_per_row: u32, required: u32 },
+    /// The rows per image is smaller than the copy height.
+    InvalidRowsPerImage {
+        rows_per_image: u32,
+        copy_height: u32,
+    },
+    /// The rows per image must be non-zero when the copy depth is greater than 1.
+    MissingRowsPerImage,
+    /// The mip level is out of range.
+    InvalidMipLevel(u32),
+    /// Copies involving 1D textures must have a height and depth of 1.
+    InvalidCopySize1D { height: u32, depth: u32 },
+    /// The copy overruns the bounds of a buffer.
+    BufferOverrun {
+        start_offset: BufferAddress,
+        end_offset: BufferAddress,
+        buffer_size: BufferAddress,
+        side: CopySide,
+    },
+    /// The copy overruns the bounds of a texture.
+    TextureOverrun {
+        start_offset: u32,
+        end_offset: u32,
+        texture_size: u32,
+        dimension: TextureErrorDimension,
+        side: CopySide,
+    },
+    /// The source and destination textures have different aspects.
+    MismatchedAspects,
+    /// The source and destination textures have different dimensions.
+    MismatchedDimensions {
+        src: wgt::TextureDimension,
+        dst: wgt::TextureDimension,
+    },
+    /// The source and destination textures have different formats.
+    MismatchedTextureFormats {
+        src: wgt::TextureFormat,
+        dst: wgt::TextureFormat,
+    },
+    /// The source and destination textures have different sample counts.
+    MismatchedSampleCounts { src: u8, dst: u8 },
+    /// The device is lost.
+    DeviceLost,
+}
+
 impl TextureCopyView {
     //TODO: we currently access each texture twice for a transfer,
     // once only to get the aspect flags, which is unfortunate.
     pub(crate) fn to_hal<B: hal::Backend>(
         &self,
         texture_guard: &Storage<Texture<B>, TextureId>,
-    ) -> (
-        hal::image::SubresourceLayers,
-        hal::image::SubresourceRange,
-        hal::image::Offset,
-    ) {
-        let texture = &texture_guard[self.texture];
+        copy_size: &Extent3d,
+        side: CopySide,
+    ) -> Result<
+        (
+            hal::image::SubresourceLayers,
+            hal::image::SubresourceRange,
+            hal::image::Offset,
+        ),
+        TransferError,
+    > {
+        let texture = texture_guard
+            .get(self.texture)
+            .map_err(|_| TransferError::InvalidTexture(self.texture))?;
         let aspects = texture.full_range.aspects;
+        if self.mip_level >= texture.full_range.levels.end as u32 {
+            return Err(TransferError::InvalidMipLevel(self.mip_level));
+        }
         let level = self.mip_level as hal::image::Level;
-        let (layer, z) = match texture.dimension {
+        // The depth of a copy spans array layers for 1D and 2D textures.
+        let (layers, z) = match texture.dimension {
             wgt::TextureDimension::D1 | wgt::TextureDimension::D2 => {
-                (self.origin.z as hal::image::Layer, 0)
+                let layer_count = texture.kind.num_layers() as u32;
+                let end = self.origin.z.saturating_add(copy_size.depth);
+                if end > layer_count {
+                    return Err(TransferError::TextureOverrun {
+                        start_offset: self.origin.z,
+                        end_offset: end,
+                        texture_size: layer_count,
+                        dimension: TextureErrorDimension::Z,
+                        side,
+                    });
+                }
+                // both ends fit in `Layer`, since the layer count does
+                (
+                    self.origin.z as hal::image::Layer..end as hal::image::Layer,
+                    0,
+                )
             }
-            wgt::TextureDimension::D3 => (0, self.origin.z as i32),
+            wgt::TextureDimension::D3 => (0..1, self.origin.z as i32),
         };
 
         // TODO: Can't satisfy clippy here unless we modify
         // `hal::image::SubresourceRange` in gfx to use `std::ops::RangeBounds`.
         #[allow(clippy::range_plus_one)]
-        (
+        Ok((
             hal::image::SubresourceLayers {
                 aspects,
                 level: self.mip_level as hal::image::Level,
-                layers: layer..layer + 1,
+                layers: layers.clone(),
             },
             hal::image::SubresourceRange {
                 aspects,
                 levels: level..level + 1,
-                layers: layer..layer + 1,
+                layers,
             },
             hal::image::Offset {
                 x: self.origin.x as i32,
                 y: self.origin.y as i32,
                 z,
             },
-        )
+        ))
     }
 }
 
+/// Returns the texel block width, height and size in bytes of a texture format.
+///
+/// Uncompressed formats have 1x1 blocks, so the block size is the texel size.
+pub(crate) fn texture_block_info(
+    format: wgt::TextureFormat,
+    private_features: PrivateFeatures,
+) -> (u32, u32, u32) {
+    let desc = conv::map_texture_format(format, private_features).surface_desc();
+    (
+        desc.dim.0 as u32,
+        desc.dim.1 as u32,
+        desc.bits as u32 / BITS_PER_BYTE,
+    )
+}
+
 /// Function copied with minor modifications from webgpu standard https://gpuweb.github.io/gpuweb/#valid-texture-copy-range
 pub(crate) fn validate_linear_texture_data(
     layout: &TextureDataLayout,
     buffer_size: BufferAddress,
-    bytes_per_texel: BufferAddress,
+    buffer_side: CopySide,
+    bytes_per_block: BufferAddress,
+    (block_width, block_height): (u32, u32),
     copy_size: &Extent3d,
-) {
+) -> Result<(), TransferError> {
     // Convert all inputs to BufferAddress (u64) to prevent overflow issues
     let copy_width = copy_size.width as BufferAddress;
     let copy_height = copy_size.height as BufferAddress;
@@ -99,32 +234,19 @@ pub(crate) fn validate_linear_texture_data(
     let rows_per_image = layout.rows_per_image as BufferAddress;
     let bytes_per_row = layout.bytes_per_row as BufferAddress;
 
-    // TODO: Once compressed textures are supported, these needs to be fixed
-    let block_width: BufferAddress = 1;
-    let block_height: BufferAddress = 1;
-    let block_size = bytes_per_texel;
-
-    assert_eq!(
-        copy_width % block_width,
-        0,
-        "Copy width {} must be a multiple of texture block width {}",
-        copy_size.width,
-        block_width,
-    );
-    assert_eq!(
-        copy_height % block_height,
-        0,
-        "Copy height {} must be a multiple of texture block height {}",
-        copy_size.height,
-        block_height,
-    );
-    assert_eq!(
-        rows_per_image % block_height,
-        0,
-        "Rows per image {} must be a multiple of image format block height {}",
-        rows_per_image,
-        block_height,
-    );
+    let block_width = block_width as BufferAddress;
+    let block_height = block_height as BufferAddress;
+    let block_size = bytes_per_block;
+
+    if copy_width % block_width != 0 {
+        return Err(TransferError::UnalignedCopyWidth(copy_size.width));
+    }
+    if copy_height % block_height != 0 {
+        return Err(TransferError::UnalignedCopyHeight(copy_size.height));
+    }
+    if rows_per_image % block_height != 0 {
+        return Err(TransferError::UnalignedRowsPerImage(layout.rows_per_image));
+    }
 
     let bytes_in_a_complete_row = block_size * copy_width / block_width;
     let required_bytes_in_copy = if copy_width == 0 || copy_height == 0 || copy_depth == 0 {
@@ -142,118 +264,116 @@ pub(crate) fn validate_linear_texture_data(
         bytes_per_image * (copy_depth - 1) + bytes_in_last_slice
     };
 
-    if rows_per_image != 0 {
-        assert!(
-            rows_per_image >= copy_height,
-            "Rows per image {} must be greater or equal to copy_extent.height {}",
-            rows_per_image,
-            copy_height
-        )
+    if rows_per_image != 0 && rows_per_image < copy_height {
+        return Err(TransferError::InvalidRowsPerImage {
+            rows_per_image: layout.rows_per_image,
+            copy_height: copy_size.height,
+        });
     }
-    assert!(
-        offset + required_bytes_in_copy <= buffer_size,
-        "Texture copy using buffer indices {}..{} would overrun buffer of size {}",
-        offset,
-        offset + required_bytes_in_copy,
-        buffer_size
-    );
-    assert_eq!(
-        offset % block_size,
-        0,
-        "Buffer offset {} must be a multiple of image format block size {}",
-        offset,
-        block_size,
-    );
-    if copy_height > 1 {
-        assert!(
-            bytes_per_row >= bytes_in_a_complete_row,
-            "Bytes per row {} must be at least the size of {} {}-byte texel blocks ({})",
-            bytes_per_row,
-            copy_width / block_width,
-            block_size,
-            bytes_in_a_complete_row,
-        )
+    if offset + required_bytes_in_copy > buffer_size {
+        return Err(TransferError::BufferOverrun {
+            start_offset: offset,
+            end_offset: offset + required_bytes_in_copy,
+            buffer_size,
+            side: buffer_side,
+        });
     }
-    if copy_depth > 1 {
-        assert_ne!(
-            rows_per_image, 0,
-            "Rows per image {} must be set to a non zero value when copy depth > 1 ({})",
-            rows_per_image, copy_depth,
-        )
+    if offset % block_size != 0 {
+        return Err(TransferError::UnalignedBufferOffset {
+            offset,
+            side: buffer_side,
+        });
+    }
+    if copy_height > 1 && bytes_per_row < bytes_in_a_complete_row {
+        return Err(TransferError::InvalidBytesPerRow {
+            bytes_per_row: layout.bytes_per_row,
+            required: bytes_in_a_complete_row as u32,
+        });
+    }
+    if copy_depth > 1 && rows_per_image == 0 {
+        return Err(TransferError::MissingRowsPerImage);
     }
+    Ok(())
 }
 
 /// Function copied with minor modifications from webgpu standard https://gpuweb.github.io/gpuweb/#valid-texture-copy-range
 pub(crate) fn validate_texture_copy_range(
     texture_copy_view: &TextureCopyView,
     texture_dimension: hal::image::Kind,
+    texture_side: CopySide,
+    (block_width, block_height): (u32, u32),
     copy_size: &Extent3d,
-) {
-    // TODO: Once compressed textures are supported, these needs to be fixed
-    let block_width: u32 = 1;
-    let block_height: u32 = 1;
-
-    let mut extent = texture_dimension.level_extent(
-        texture_copy_view
-            .mip_level
-            .try_into()
-            .expect("Mip level must be < 256"),
-    );
+) -> Result<(), TransferError> {
+    let mip_level = texture_copy_view
+        .mip_level
+        .try_into()
+        .map_err(|_| TransferError::InvalidMipLevel(texture_copy_view.mip_level))?;
+    let mut extent = texture_dimension.level_extent(mip_level);
     match texture_dimension {
         hal::image::Kind::D1(..) => {
-            assert_eq!(
-                (copy_size.height, copy_size.depth),
-                (1, 1),
-                "Copies with 1D textures must have height and depth of 1. Currently: ({}, {})",
-                copy_size.height,
-                copy_size.depth,
-            );
+            if (copy_size.height, copy_size.depth) != (1, 1) {
+                return Err(TransferError::InvalidCopySize1D {
+                    height: copy_size.height,
+                    depth: copy_size.depth,
+                });
+            }
         }
         hal::image::Kind::D2(_, _, array_layers, _) => {
             extent.depth = array_layers as u32;
         }
         hal::image::Kind::D3(..) => {}
     };
+    // The physical size of a mip level is always a whole number of blocks.
+    extent.width = (extent.width + block_width - 1) / block_width * block_width;
+    extent.height = (extent.height + block_height - 1) / block_height * block_height;
+
+    if texture_copy_view.origin.x % block_width != 0
+        || texture_copy_view.origin.y % block_height != 0
+    {
+        return Err(TransferError::UnalignedCopyOrigin {
+            x: texture_copy_view.origin.x,
+            y: texture_copy_view.origin.y,
+        });
+    }
 
-    let x_copy_max = texture_copy_view.origin.x + copy_size.width;
-    assert!(
-        x_copy_max <= extent.width,
-        "Texture copy with X range {}..{} overruns texture width {}",
-        texture_copy_view.origin.x,
-        x_copy_max,
-        extent.width,
-    );
-    let y_copy_max = texture_copy_view.origin.y + copy_size.height;
-    assert!(
-        y_copy_max <= extent.height,
-        "Texture copy with Y range {}..{} overruns texture height {}",
-        texture_copy_view.origin.y,
-        y_copy_max,
-        extent.height,
-    );
-    let z_copy_max = texture_copy_view.origin.z + copy_size.depth;
-    assert!(
-        z_copy_max <= extent.depth,
-        "Texture copy with Z range {}..{} overruns texture depth {}",
-        texture_copy_view.origin.z,
-        z_copy_max,
-        extent.depth,
-    );
-
-    assert_eq!(
-        copy_size.width % block_width,
-        0,
-        "Copy width {} must be a multiple of texture block width {}",
-        copy_size.width,
-        block_width,
-    );
-    assert_eq!(
-        copy_size.height % block_height,
-        0,
-        "Copy height {} must be a multiple of texture block height {}",
-        copy_size.height,
-        block_height,
-    );
+    let x_copy_max = texture_copy_view.origin.x.saturating_add(copy_size.width);
+    if x_copy_max > extent.width {
+        return Err(TransferError::TextureOverrun {
+            start_offset: texture_copy_view.origin.x,
+            end_offset: x_copy_max,
+            texture_size: extent.width,
+            dimension: TextureErrorDimension::X,
+            side: texture_side,
+        });
+    }
+    let y_copy_max = texture_copy_view.origin.y.saturating_add(copy_size.height);
+    if y_copy_max > extent.height {
+        return Err(TransferError::TextureOverrun {
+            start_offset: texture_copy_view.origin.y,
+            end_offset: y_copy_max,
+            texture_size: extent.height,
+            dimension: TextureErrorDimension::Y,
+            side: texture_side,
+        });
+    }
+    let z_copy_max = texture_copy_view.origin.z.saturating_add(copy_size.depth);
+    if z_copy_max > extent.depth {
+        return Err(TransferError::TextureOverrun {
+            start_offset: texture_copy_view.origin.z,
+            end_offset: z_copy_max,
+            texture_size: extent.depth,
+            dimension: TextureErrorDimension::Z,
+            side: texture_side,
+        });
+    }
+
+    if copy_size.width % block_width != 0 {
+        return Err(TransferError::UnalignedCopyWidth(copy_size.width));
+    }
+    if copy_size.height % block_height != 0 {
+        return Err(TransferError::UnalignedCopyHeight(copy_size.height));
+    }
+    Ok(())
 }
 
 impl<G: GlobalIdentityHandlerFactory> Global<G> {
@@ -265,12 +385,18 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         destination: BufferId,
         destination_offset: BufferAddress,
         size: BufferAddress,
-    ) {
+    ) -> Result<(), TransferError> {
         let hub = B::hub(self);
+        let context = ErrorContext::new("command_encoder_copy_buffer_to_buffer");
         let mut token = Token::root();
 
+        let (device_guard, mut token) = hub.devices.read(&mut token);
         let (mut cmb_guard, mut token) = hub.command_buffers.write(&mut token);
         let cmb = &mut cmb_guard[command_encoder_id];
+        let device = &device_guard[cmb.device_id.value];
+        if device.is_lost() {
+            return Err(TransferError::DeviceLost);
+        }
         let (buffer_guard, _) = hub.buffers.read(&mut token);
         // we can't hold both src_pending and dst_pending in scope because they
         // borrow the buffer tracker mutably...
@@ -290,71 +416,86 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
 
         if size == 0 {
             log::trace!("Ignoring copy_buffer_to_buffer of size 0");
-            return;
+            return Ok(());
         }
 
-        let (src_buffer, src_pending) =
-            cmb.trackers
-                .buffers
-                .use_replace(&*buffer_guard, source, (), BufferUse::COPY_SRC);
-        assert!(
-            src_buffer.usage.contains(BufferUsage::COPY_SRC),
-            "Source buffer usage {:?} must contain usage flag COPY_SRC",
-            src_buffer.usage
-        );
-        barriers.extend(src_pending.map(|pending| pending.into_hal(src_buffer)));
+        let src_buffer = buffer_guard
+            .get(source)
+            .map_err(|_| cmb.invalidate(device, context, TransferError::InvalidBuffer(source)))?;
+        if !src_buffer.usage.contains(BufferUsage::COPY_SRC) {
+            return Err(cmb.invalidate(device, context, TransferError::MissingCopySrcUsageFlag));
+        }
+        let dst_buffer = buffer_guard.get(destination).map_err(|_| {
+            cmb.invalidate(device, context, TransferError::InvalidBuffer(destination))
+        })?;
+        if !dst_buffer.usage.contains(BufferUsage::COPY_DST) {
+            return Err(cmb.invalidate(device, context, TransferError::MissingCopyDstUsageFlag));
+        }
 
-        let (dst_buffer, dst_pending) =
-            cmb.trackers
-                .buffers
-                .use_replace(&*buffer_guard, destination, (), BufferUse::COPY_DST);
-        assert!(
-            dst_buffer.usage.contains(BufferUsage::COPY_DST),
-            "Destination buffer usage {:?} must contain usage flag COPY_DST",
-            dst_buffer.usage
-        );
-        barriers.extend(dst_pending.map(|pending| pending.into_hal(dst_buffer)));
+        if size % wgt::COPY_BUFFER_ALIGNMENT != 0 {
+            return Err(cmb.invalidate(device, context, TransferError::UnalignedCopySize(size)));
+        }
+        if source_offset % wgt::COPY_BUFFER_ALIGNMENT != 0 {
+            return Err(cmb.invalidate(
+                device,
+                context,
+                TransferError::UnalignedBufferOffset {
+                    offset: source_offset,
+                    side: CopySide::Source,
+                },
+            ));
+        }
+        if destination_offset % wgt::COPY_BUFFER_ALIGNMENT != 0 {
+            return Err(cmb.invalidate(
+                device,
+                context,
+                TransferError::UnalignedBufferOffset {
+                    offset: destination_offset,
+                    side: CopySide::Destination,
+                },
+            ));
+        }
 
-        assert_eq!(
-            size % wgt::COPY_BUFFER_ALIGNMENT,
-            0,
-            "Buffer copy size {} must be a multiple of {}",
-            size,
-            wgt::COPY_BUFFER_ALIGNMENT,
-        );
-        assert_eq!(
-            source_offset % wgt::COPY_BUFFER_ALIGNMENT,
-            0,
-            "Buffer source offset {} must be a multiple of {}",
-            source_offset,
-            wgt::COPY_BUFFER_ALIGNMENT,
-        );
-        assert_eq!(
-            destination_offset % wgt::COPY_BUFFER_ALIGNMENT,
-            0,
-            "Buffer destination offset {} must be a multiple of {}",
-            destination_offset,
-            wgt::COPY_BUFFER_ALIGNMENT,
-        );
+        let source_end_offset = source_offset.saturating_add(size);
+        let destination_end_offset = destination_offset.saturating_add(size);
+        if source_end_offset > src_buffer.size {
+            return Err(cmb.invalidate(
+                device,
+                context,
+                TransferError::BufferOverrun {
+                    start_offset: source_offset,
+                    end_offset: source_end_offset,
+                    buffer_size: src_buffer.size,
+                    side: CopySide::Source,
+                },
+            ));
+        }
+        if destination_end_offset > dst_buffer.size {
+            return Err(cmb.invalidate(
+                device,
+                context,
+                TransferError::BufferOverrun {
+                    start_offset: destination_offset,
+                    end_offset: destination_end_offset,
+                    buffer_size: dst_buffer.size,
+                    side: CopySide::Destination,
+                },
+            ));
+        }
 
-        let source_start_offset = source_offset;
-        let source_end_offset = source_offset + size;
-        let destination_start_offset = destination_offset;
-        let destination_end_offset = destination_offset + size;
-        assert!(
-            source_end_offset <= src_buffer.size,
-            "Buffer to buffer copy with indices {}..{} overruns source buffer of size {}",
-            source_start_offset,
-            source_end_offset,
-            src_buffer.size
-        );
-        assert!(
-            destination_end_offset <= dst_buffer.size,
-            "Buffer to buffer copy with indices {}..{} overruns destination buffer of size {}",
-            destination_start_offset,
-            destination_end_offset,
-            dst_buffer.size
-        );
+        // The copy is valid, only now the usages can be tracked.
+        let (src_buffer, src_pending) = cmb
+            .trackers
+            .buffers
+            .use_replace(&*buffer_guard, source, (), BufferUse::COPY_SRC)
+            .unwrap();
+        barriers.extend(src_pending.map(|pending| pending.into_hal(src_buffer)));
+        let (dst_buffer, dst_pending) = cmb
+            .trackers
+            .buffers
+            .use_replace(&*buffer_guard, destination, (), BufferUse::COPY_DST)
+            .unwrap();
+        barriers.extend(dst_pending.map(|pending| pending.into_hal(dst_buffer)));
 
         let region = hal::command::BufferCopy {
             src: source_offset,
@@ -370,6 +511,7 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
             );
             cmb_raw.copy_buffer(&src_buffer.raw, &dst_buffer.raw, iter::once(region));
         }
+        Ok(())
     }
 
     pub fn command_encoder_copy_buffer_to_texture<B: GfxBackend>(
@@ -378,14 +520,19 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         source: &BufferCopyView,
         destination: &TextureCopyView,
         copy_size: &Extent3d,
-    ) {
+    ) -> Result<(), TransferError> {
         let hub = B::hub(self);
+        let context = ErrorContext::new("command_encoder_copy_buffer_to_texture");
         let mut token = Token::root();
+        let (device_guard, mut token) = hub.devices.read(&mut token);
         let (mut cmb_guard, mut token) = hub.command_buffers.write(&mut token);
         let cmb = &mut cmb_guard[command_encoder_id];
+        let device = &device_guard[cmb.device_id.value];
+        if device.is_lost() {
+            return Err(TransferError::DeviceLost);
+        }
         let (buffer_guard, mut token) = hub.buffers.read(&mut token);
         let (texture_guard, _) = hub.textures.read(&mut token);
-        let (dst_layers, dst_range, dst_offset) = destination.to_hal(&*texture_guard);
 
         #[cfg(feature = "trace")]
         match cmb.commands {
@@ -397,50 +544,87 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
             None => (),
         }
 
-        if copy_size.width == 0 || copy_size.height == 0 || copy_size.width == 0 {
+        if copy_size.width == 0 || copy_size.height == 0 || copy_size.depth == 0 {
             log::trace!("Ignoring copy_buffer_to_texture of size 0");
-            return;
+            return Ok(());
         }
 
-        let (src_buffer, src_pending) = cmb.trackers.buffers.use_replace(
-            &*buffer_guard,
-            source.buffer,
-            (),
-            BufferUse::COPY_SRC,
-        );
-        assert!(src_buffer.usage.contains(BufferUsage::COPY_SRC));
-        let src_barriers = src_pending.map(|pending| pending.into_hal(src_buffer));
-
-        let (dst_texture, dst_pending) = cmb.trackers.textures.use_replace(
-            &*texture_guard,
-            destination.texture,
-            dst_range,
-            TextureUse::COPY_DST,
-        );
-        assert!(dst_texture.usage.contains(TextureUsage::COPY_DST));
-        let dst_barriers = dst_pending.map(|pending| pending.into_hal(dst_texture));
+        let src_buffer = buffer_guard.get(source.buffer).map_err(|_| {
+            cmb.invalidate(device, context, TransferError::InvalidBuffer(source.buffer))
+        })?;
+        if !src_buffer.usage.contains(BufferUsage::COPY_SRC) {
+            return Err(cmb.invalidate(device, context, TransferError::MissingCopySrcUsageFlag));
+        }
+        let dst_texture = texture_guard.get(destination.texture).map_err(|_| {
+            cmb.invalidate(
+                device,
+                context,
+                TransferError::InvalidTexture(destination.texture),
+            )
+        })?;
+        if !dst_texture.usage.contains(TextureUsage::COPY_DST) {
+            return Err(cmb.invalidate(device, context, TransferError::MissingCopyDstUsageFlag));
+        }
 
-        let bytes_per_texel = conv::map_texture_format(dst_texture.format, cmb.private_features)
-            .surface_desc()
-            .bits as u32
-            / BITS_PER_BYTE;
-        assert_eq!(wgt::COPY_BYTES_PER_ROW_ALIGNMENT % bytes_per_texel, 0);
-        assert_eq!(
-            source.layout.bytes_per_row % wgt::COPY_BYTES_PER_ROW_ALIGNMENT,
-            0,
-            "Source bytes per row ({}) must be a multiple of {}",
-            source.layout.bytes_per_row,
-            wgt::COPY_BYTES_PER_ROW_ALIGNMENT
-        );
-        validate_texture_copy_range(destination, dst_texture.kind, copy_size);
+        let (block_width, block_height, bytes_per_block) =
+            texture_block_info(dst_texture.format, cmb.private_features);
+        assert_eq!(wgt::COPY_BYTES_PER_ROW_ALIGNMENT % bytes_per_block, 0);
+        if source.layout.bytes_per_row % wgt::COPY_BYTES_PER_ROW_ALIGNMENT != 0 {
+            return Err(cmb.invalidate(
+                device,
+                context,
+                TransferError::UnalignedBytesPerRow(source.layout.bytes_per_row),
+            ));
+        }
+        validate_texture_copy_range(
+            destination,
+            dst_texture.kind,
+            CopySide::Destination,
+            (block_width, block_height),
+            copy_size,
+        )
+        .map_err(|e| cmb.invalidate(device, context, e))?;
         validate_linear_texture_data(
             &source.layout,
             src_buffer.size,
-            bytes_per_texel as BufferAddress,
+            CopySide::Source,
+            bytes_per_block as BufferAddress,
+            (block_width, block_height),
             copy_size,
+        )
+        .map_err(|e| cmb.invalidate(device, context, e))?;
+
+        let (dst_layers, dst_range, dst_offset) = destination
+            .to_hal(&*texture_guard, copy_size, CopySide::Destination)
+            .map_err(|e| cmb.invalidate(device, context, e))?;
+
+        // The copy is valid, only now the usages can be tracked.
+        CommandBuffer::clear_discarded_surfaces(
+            cmb.raw.last_mut().unwrap(),
+            &mut cmb.trackers,
+            &mut cmb.discarded_surfaces,
+            iter::once(destination.texture),
+            &*texture_guard,
         );
+        let (src_buffer, src_pending) = cmb
+            .trackers
+            .buffers
+            .use_replace(&*buffer_guard, source.buffer, (), BufferUse::COPY_SRC)
+            .unwrap();
+        let src_barriers = src_pending.map(|pending| pending.into_hal(src_buffer));
+        let (dst_texture, dst_pending) = cmb
+            .trackers
+            .textures
+            .use_replace(
+                &*texture_guard,
+                destination.texture,
+                dst_range,
+                TextureUse::COPY_DST,
+            )
+            .unwrap();
+        let dst_barriers = dst_pending.map(|pending| pending.into_hal(dst_texture));
 
-        let buffer_width = source.layout.bytes_per_row / bytes_per_texel;
+        let buffer_width = source.layout.bytes_per_row / bytes_per_block * block_width;
         let region = hal::command::BufferImageCopy {
             buffer_offset: source.layout.offset,
             buffer_width,
@@ -463,6 +647,7 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                 iter::once(region),
             );
         }
+        Ok(())
     }
 
     pub fn command_encoder_copy_texture_to_buffer<B: GfxBackend>(
@@ -471,14 +656,19 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         source: &TextureCopyView,
         destination: &BufferCopyView,
         copy_size: &Extent3d,
-    ) {
+    ) -> Result<(), TransferError> {
         let hub = B::hub(self);
+        let context = ErrorContext::new("command_encoder_copy_texture_to_buffer");
         let mut token = Token::root();
+        let (device_guard, mut token) = hub.devices.read(&mut token);
         let (mut cmb_guard, mut token) = hub.command_buffers.write(&mut token);
         let cmb = &mut cmb_guard[command_encoder_id];
+        let device = &device_guard[cmb.device_id.value];
+        if device.is_lost() {
+            return Err(TransferError::DeviceLost);
+        }
         let (buffer_guard, mut token) = hub.buffers.read(&mut token);
         let (texture_guard, _) = hub.textures.read(&mut token);
-        let (src_layers, src_range, src_offset) = source.to_hal(&*texture_guard);
 
         #[cfg(feature = "trace")]
         match cmb.commands {
@@ -490,58 +680,91 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
             None => (),
         }
 
-        if copy_size.width == 0 || copy_size.height == 0 || copy_size.width == 0 {
+        if copy_size.width == 0 || copy_size.height == 0 || copy_size.depth == 0 {
             log::trace!("Ignoring copy_texture_to_buffer of size 0");
-            return;
+            return Ok(());
         }
 
-        let (src_texture, src_pending) = cmb.trackers.textures.use_replace(
-            &*texture_guard,
-            source.texture,
-            src_range,
-            TextureUse::COPY_SRC,
-        );
-        assert!(
-            src_texture.usage.contains(TextureUsage::COPY_SRC),
-            "Source texture usage ({:?}) must contain usage flag COPY_SRC",
-            src_texture.usage
-        );
-        let src_barriers = src_pending.map(|pending| pending.into_hal(src_texture));
-
-        let (dst_buffer, dst_barriers) = cmb.trackers.buffers.use_replace(
-            &*buffer_guard,
-            destination.buffer,
-            (),
-            BufferUse::COPY_DST,
-        );
-        assert!(
-            dst_buffer.usage.contains(BufferUsage::COPY_DST),
-            "Destination buffer usage {:?} must contain usage flag COPY_DST",
-            dst_buffer.usage
-        );
-        let dst_barrier = dst_barriers.map(|pending| pending.into_hal(dst_buffer));
+        let src_texture = texture_guard.get(source.texture).map_err(|_| {
+            cmb.invalidate(
+                device,
+                context,
+                TransferError::InvalidTexture(source.texture),
+            )
+        })?;
+        if !src_texture.usage.contains(TextureUsage::COPY_SRC) {
+            return Err(cmb.invalidate(device, context, TransferError::MissingCopySrcUsageFlag));
+        }
+        let dst_buffer = buffer_guard.get(destination.buffer).map_err(|_| {
+            cmb.invalidate(
+                device,
+                context,
+                TransferError::InvalidBuffer(destination.buffer),
+            )
+        })?;
+        if !dst_buffer.usage.contains(BufferUsage::COPY_DST) {
+            return Err(cmb.invalidate(device, context, TransferError::MissingCopyDstUsageFlag));
+        }
 
-        let bytes_per_texel = conv::map_texture_format(src_texture.format, cmb.private_features)
-            .surface_desc()
-            .bits as u32
-            / BITS_PER_BYTE;
-        assert_eq!(wgt::COPY_BYTES_PER_ROW_ALIGNMENT % bytes_per_texel, 0);
-        assert_eq!(
-            destination.layout.bytes_per_row % wgt::COPY_BYTES_PER_ROW_ALIGNMENT,
-            0,
-            "Destination bytes per row ({}) must be a multiple of {}",
-            destination.layout.bytes_per_row,
-            wgt::COPY_BYTES_PER_ROW_ALIGNMENT
-        );
-        validate_texture_copy_range(source, src_texture.kind, copy_size);
+        let (block_width, block_height, bytes_per_block) =
+            texture_block_info(src_texture.format, cmb.private_features);
+        assert_eq!(wgt::COPY_BYTES_PER_ROW_ALIGNMENT % bytes_per_block, 0);
+        if destination.layout.bytes_per_row % wgt::COPY_BYTES_PER_ROW_ALIGNMENT != 0 {
+            return Err(cmb.invalidate(
+                device,
+                context,
+                TransferError::UnalignedBytesPerRow(destination.layout.bytes_per_row),
+            ));
+        }
+        validate_texture_copy_range(
+            source,
+            src_texture.kind,
+            CopySide::Source,
+            (block_width, block_height),
+            copy_size,
+        )
+        .map_err(|e| cmb.invalidate(device, context, e))?;
         validate_linear_texture_data(
             &destination.layout,
             dst_buffer.size,
-            bytes_per_texel as BufferAddress,
+            CopySide::Destination,
+            bytes_per_block as BufferAddress,
+            (block_width, block_height),
             copy_size,
+        )
+        .map_err(|e| cmb.invalidate(device, context, e))?;
+
+        let (src_layers, src_range, src_offset) = source
+            .to_hal(&*texture_guard, copy_size, CopySide::Source)
+            .map_err(|e| cmb.invalidate(device, context, e))?;
+
+        // The copy is valid, only now the usages can be tracked.
+        CommandBuffer::clear_discarded_surfaces(
+            cmb.raw.last_mut().unwrap(),
+            &mut cmb.trackers,
+            &mut cmb.discarded_surfaces,
+            iter::once(source.texture),
+            &*texture_guard,
         );
+        let (src_texture, src_pending) = cmb
+            .trackers
+            .textures
+            .use_replace(
+                &*texture_guard,
+                source.texture,
+                src_range,
+                TextureUse::COPY_SRC,
+            )
+            .unwrap();
+        let src_barriers = src_pending.map(|pending| pending.into_hal(src_texture));
+        let (dst_buffer, dst_barriers) = cmb
+            .trackers
+            .buffers
+            .use_replace(&*buffer_guard, destination.buffer, (), BufferUse::COPY_DST)
+            .unwrap();
+        let dst_barrier = dst_barriers.map(|pending| pending.into_hal(dst_buffer));
 
-        let buffer_width = destination.layout.bytes_per_row / bytes_per_texel;
+        let buffer_width = destination.layout.bytes_per_row / bytes_per_block * block_width;
         let region = hal::command::BufferImageCopy {
             buffer_offset: destination.layout.offset,
             buffer_width,
@@ -564,6 +787,7 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                 iter::once(region),
             );
         }
+        Ok(())
     }
 
     pub fn command_encoder_copy_texture_to_texture<B: GfxBackend>(
@@ -572,20 +796,23 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         source: &TextureCopyView,
         destination: &TextureCopyView,
         copy_size: &Extent3d,
-    ) {
+    ) -> Result<(), TransferError> {
         let hub = B::hub(self);
+        let context = ErrorContext::new("command_encoder_copy_texture_to_texture");
         let mut token = Token::root();
 
+        let (device_guard, mut token) = hub.devices.read(&mut token);
         let (mut cmb_guard, mut token) = hub.command_buffers.write(&mut token);
         let cmb = &mut cmb_guard[command_encoder_id];
+        let device = &device_guard[cmb.device_id.value];
+        if device.is_lost() {
+            return Err(TransferError::DeviceLost);
+        }
         let (_, mut token) = hub.buffers.read(&mut token); // skip token
         let (texture_guard, _) = hub.textures.read(&mut token);
         // we can't hold both src_pending and dst_pending in scope because they
         // borrow the buffer tracker mutably...
         let mut barriers = Vec::new();
-        let (src_layers, src_range, src_offset) = source.to_hal(&*texture_guard);
-        let (dst_layers, dst_range, dst_offset) = destination.to_hal(&*texture_guard);
-        assert_eq!(src_layers.aspects, dst_layers.aspects);
 
         #[cfg(feature = "trace")]
         match cmb.commands {
@@ -597,41 +824,127 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
             None => (),
         }
 
-        if copy_size.width == 0 || copy_size.height == 0 || copy_size.width == 0 {
+        if copy_size.width == 0 || copy_size.height == 0 || copy_size.depth == 0 {
             log::trace!("Ignoring copy_texture_to_texture of size 0");
-            return;
+            return Ok(());
         }
 
-        let (src_texture, src_pending) = cmb.trackers.textures.use_replace(
-            &*texture_guard,
-            source.texture,
-            src_range,
-            TextureUse::COPY_SRC,
-        );
-        assert!(
-            src_texture.usage.contains(TextureUsage::COPY_SRC),
-            "Source texture usage {:?} must contain usage flag COPY_SRC",
-            src_texture.usage
+        let src_texture = texture_guard.get(source.texture).map_err(|_| {
+            cmb.invalidate(
+                device,
+                context,
+                TransferError::InvalidTexture(source.texture),
+            )
+        })?;
+        if !src_texture.usage.contains(TextureUsage::COPY_SRC) {
+            return Err(cmb.invalidate(device, context, TransferError::MissingCopySrcUsageFlag));
+        }
+        let dst_texture = texture_guard.get(destination.texture).map_err(|_| {
+            cmb.invalidate(
+                device,
+                context,
+                TransferError::InvalidTexture(destination.texture),
+            )
+        })?;
+        if !dst_texture.usage.contains(TextureUsage::COPY_DST) {
+            return Err(cmb.invalidate(device, context, TransferError::MissingCopyDstUsageFlag));
+        }
+
+        if src_texture.dimension != dst_texture.dimension {
+            return Err(cmb.invalidate(
+                device,
+                context,
+                TransferError::MismatchedDimensions {
+                    src: src_texture.dimension,
+                    dst: dst_texture.dimension,
+                },
+            ));
+        }
+        if src_texture.format != dst_texture.format {
+            return Err(cmb.invalidate(
+                device,
+                context,
+                TransferError::MismatchedTextureFormats {
+                    src: src_texture.format,
+                    dst: dst_texture.format,
+                },
+            ));
+        }
+        let (src_samples, dst_samples) = (
+            src_texture.kind.num_samples(),
+            dst_texture.kind.num_samples(),
         );
-        barriers.extend(src_pending.map(|pending| pending.into_hal(src_texture)));
+        if src_samples != dst_samples {
+            return Err(cmb.invalidate(
+                device,
+                context,
+                TransferError::MismatchedSampleCounts {
+                    src: src_samples,
+                    dst: dst_samples,
+                },
+            ));
+        }
+        // the formats are equal, so are the blocks
+        let (block_width, block_height, _) =
+            texture_block_info(src_texture.format, cmb.private_features);
+        validate_texture_copy_range(
+            source,
+            src_texture.kind,
+            CopySide::Source,
+            (block_width, block_height),
+            copy_size,
+        )
+        .map_err(|e| cmb.invalidate(device, context, e))?;
+        validate_texture_copy_range(
+            destination,
+            dst_texture.kind,
+            CopySide::Destination,
+            (block_width, block_height),
+            copy_size,
+        )
+        .map_err(|e| cmb.invalidate(device, context, e))?;
+
+        let (src_layers, src_range, src_offset) = source
+            .to_hal(&*texture_guard, copy_size, CopySide::Source)
+            .map_err(|e| cmb.invalidate(device, context, e))?;
+        let (dst_layers, dst_range, dst_offset) = destination
+            .to_hal(&*texture_guard, copy_size, CopySide::Destination)
+            .map_err(|e| cmb.invalidate(device, context, e))?;
+        if src_layers.aspects != dst_layers.aspects {
+            return Err(cmb.invalidate(device, context, TransferError::MismatchedAspects));
+        }
 
-        let (dst_texture, dst_pending) = cmb.trackers.textures.use_replace(
+        // The copy is valid, only now the usages can be tracked.
+        CommandBuffer::clear_discarded_surfaces(
+            cmb.raw.last_mut().unwrap(),
+            &mut cmb.trackers,
+            &mut cmb.discarded_surfaces,
+            iter::once(source.texture).chain(iter::once(destination.texture)),
             &*texture_guard,
-            destination.texture,
-            dst_range,
-            TextureUse::COPY_DST,
-        );
-        assert!(
-            dst_texture.usage.contains(TextureUsage::COPY_DST),
-            "Destination texture usage {:?} must contain usage flag COPY_DST",
-            dst_texture.usage
         );
+        let (src_texture, src_pending) = cmb
+            .trackers
+            .textures
+            .use_replace(
+                &*texture_guard,
+                source.texture,
+                src_range,
+                TextureUse::COPY_SRC,
+            )
+            .unwrap();
+        barriers.extend(src_pending.map(|pending| pending.into_hal(src_texture)));
+        let (dst_texture, dst_pending) = cmb
+            .trackers
+            .textures
+            .use_replace(
+                &*texture_guard,
+                destination.texture,
+                dst_range,
+                TextureUse::COPY_DST,
+            )
+            .unwrap();
         barriers.extend(dst_pending.map(|pending| pending.into_hal(dst_texture)));
 
-        assert_eq!(src_texture.dimension, dst_texture.dimension);
-        validate_texture_copy_range(source, src_texture.kind, copy_size);
-        validate_texture_copy_range(destination, dst_texture.kind, copy_size);
-
         let region = hal::command::ImageCopy {
             src_subresource: src_layers,
             src_offset,
@@ -654,5 +967,6 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                 iter::once(region),
             );
         }
+        Ok(())
     }
 }
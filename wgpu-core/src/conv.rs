@@ -2,7 +2,7 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
-use crate::{resource, PrivateFeatures};
+use crate::{binding_model, resource, PrivateFeatures};
 
 pub fn map_buffer_usage(usage: wgt::BufferUsage) -> (hal::buffer::Usage, hal::memory::Properties) {
     use hal::buffer::Usage as U;
@@ -39,6 +39,9 @@ pub fn map_buffer_usage(usage: wgt::BufferUsage) -> (hal::buffer::Usage, hal::me
     if usage.contains(W::INDIRECT) {
         hal_usage |= U::INDIRECT;
     }
+    if usage.contains(W::QUERY_RESOLVE) {
+        hal_usage |= U::TRANSFER_DST;
+    }
 
     (hal_usage, hal_memory)
 }
@@ -75,7 +78,7 @@ pub fn map_texture_usage(
     value
 }
 
-pub fn map_binding_type(binding: &wgt::BindGroupLayoutEntry) -> hal::pso::DescriptorType {
+pub fn map_binding_type(binding: &binding_model::BindGroupLayoutEntry) -> hal::pso::DescriptorType {
     use hal::pso;
     use wgt::BindingType as Bt;
     match binding.ty {
@@ -173,6 +176,29 @@ pub fn map_color_state_descriptor(desc: &wgt::ColorStateDescriptor) -> hal::pso:
     }
 }
 
+pub fn map_logic_op(logic_op: wgt::LogicOp) -> hal::pso::LogicOp {
+    use hal::pso::LogicOp as H;
+    use wgt::LogicOp as Lo;
+    match logic_op {
+        Lo::Clear => H::Clear,
+        Lo::And => H::And,
+        Lo::AndReverse => H::AndReverse,
+        Lo::Copy => H::Copy,
+        Lo::AndInverted => H::AndInverted,
+        Lo::NoOp => H::NoOp,
+        Lo::Xor => H::Xor,
+        Lo::Or => H::Or,
+        Lo::Nor => H::Nor,
+        Lo::Equivalent => H::Equivalent,
+        Lo::Invert => H::Invert,
+        Lo::OrReverse => H::OrReverse,
+        Lo::CopyInverted => H::CopyInverted,
+        Lo::OrInverted => H::OrInverted,
+        Lo::Nand => H::Nand,
+        Lo::Set => H::Set,
+    }
+}
+
 fn map_color_write_flags(flags: wgt::ColorWrite) -> hal::pso::ColorMask {
     use hal::pso::ColorMask as H;
     use wgt::ColorWrite as Cw;
@@ -387,6 +413,64 @@ pub(crate) fn map_texture_format(
                 H::D32SfloatS8Uint
             }
         }
+
+        // BC compressed formats
+        Tf::Bc1RgbaUnorm => H::Bc1RgbaUnorm,
+        Tf::Bc1RgbaUnormSrgb => H::Bc1RgbaSrgb,
+        Tf::Bc2RgbaUnorm => H::Bc2Unorm,
+        Tf::Bc2RgbaUnormSrgb => H::Bc2Srgb,
+        Tf::Bc3RgbaUnorm => H::Bc3Unorm,
+        Tf::Bc3RgbaUnormSrgb => H::Bc3Srgb,
+        Tf::Bc4RUnorm => H::Bc4Unorm,
+        Tf::Bc4RSnorm => H::Bc4Snorm,
+        Tf::Bc5RgUnorm => H::Bc5Unorm,
+        Tf::Bc5RgSnorm => H::Bc5Snorm,
+        Tf::Bc6hRgbUfloat => H::Bc6hUfloat,
+        Tf::Bc6hRgbSfloat => H::Bc6hSfloat,
+        Tf::Bc7RgbaUnorm => H::Bc7Unorm,
+        Tf::Bc7RgbaUnormSrgb => H::Bc7Srgb,
+
+        // ETC2/EAC compressed formats
+        Tf::Etc2RgbUnorm => H::Etc2R8g8b8Unorm,
+        Tf::Etc2RgbUnormSrgb => H::Etc2R8g8b8Srgb,
+        Tf::Etc2RgbA1Unorm => H::Etc2R8g8b8a1Unorm,
+        Tf::Etc2RgbA1UnormSrgb => H::Etc2R8g8b8a1Srgb,
+        Tf::Etc2RgbA8Unorm => H::Etc2R8g8b8a8Unorm,
+        Tf::Etc2RgbA8UnormSrgb => H::Etc2R8g8b8a8Srgb,
+        Tf::EacRUnorm => H::EacR11Unorm,
+        Tf::EacRSnorm => H::EacR11Snorm,
+        Tf::EacRgUnorm => H::EacR11g11Unorm,
+        Tf::EacRgSnorm => H::EacR11g11Snorm,
+
+        // ASTC compressed formats
+        Tf::Astc4x4RgbaUnorm => H::Astc4x4Unorm,
+        Tf::Astc4x4RgbaUnormSrgb => H::Astc4x4Srgb,
+        Tf::Astc5x4RgbaUnorm => H::Astc5x4Unorm,
+        Tf::Astc5x4RgbaUnormSrgb => H::Astc5x4Srgb,
+        Tf::Astc5x5RgbaUnorm => H::Astc5x5Unorm,
+        Tf::Astc5x5RgbaUnormSrgb => H::Astc5x5Srgb,
+        Tf::Astc6x5RgbaUnorm => H::Astc6x5Unorm,
+        Tf::Astc6x5RgbaUnormSrgb => H::Astc6x5Srgb,
+        Tf::Astc6x6RgbaUnorm => H::Astc6x6Unorm,
+        Tf::Astc6x6RgbaUnormSrgb => H::Astc6x6Srgb,
+        Tf::Astc8x5RgbaUnorm => H::Astc8x5Unorm,
+        Tf::Astc8x5RgbaUnormSrgb => H::Astc8x5Srgb,
+        Tf::Astc8x6RgbaUnorm => H::Astc8x6Unorm,
+        Tf::Astc8x6RgbaUnormSrgb => H::Astc8x6Srgb,
+        Tf::Astc8x8RgbaUnorm => H::Astc8x8Unorm,
+        Tf::Astc8x8RgbaUnormSrgb => H::Astc8x8Srgb,
+        Tf::Astc10x5RgbaUnorm => H::Astc10x5Unorm,
+        Tf::Astc10x5RgbaUnormSrgb => H::Astc10x5Srgb,
+        Tf::Astc10x6RgbaUnorm => H::Astc10x6Unorm,
+        Tf::Astc10x6RgbaUnormSrgb => H::Astc10x6Srgb,
+        Tf::Astc10x8RgbaUnorm => H::Astc10x8Unorm,
+        Tf::Astc10x8RgbaUnormSrgb => H::Astc10x8Srgb,
+        Tf::Astc10x10RgbaUnorm => H::Astc10x10Unorm,
+        Tf::Astc10x10RgbaUnormSrgb => H::Astc10x10Srgb,
+        Tf::Astc12x10RgbaUnorm => H::Astc12x10Unorm,
+        Tf::Astc12x10RgbaUnormSrgb => H::Astc12x10Srgb,
+        Tf::Astc12x12RgbaUnorm => H::Astc12x12Unorm,
+        Tf::Astc12x12RgbaUnormSrgb => H::Astc12x12Srgb,
     }
 }
 
@@ -427,9 +511,12 @@ pub fn map_vertex_format(vertex_format: wgt::VertexFormat) -> hal::format::Forma
     }
 }
 
-fn checked_u32_as_u16(value: u32) -> u16 {
-    assert!(value <= ::std::u16::MAX as u32);
-    value as u16
+fn checked_u32_as_u16(value: u32) -> Result<u16, resource::TextureDimensionError> {
+    if value <= ::std::u16::MAX as u32 {
+        Ok(value as u16)
+    } else {
+        Err(resource::TextureDimensionError::LayerCount(value))
+    }
 }
 
 pub fn is_power_of_two(val: u32) -> bool {
@@ -444,28 +531,33 @@ pub fn map_texture_dimension_size(
         depth,
     }: wgt::Extent3d,
     sample_size: u32,
-) -> hal::image::Kind {
+) -> Result<hal::image::Kind, resource::TextureDimensionError> {
     use hal::image::Kind as H;
+    use resource::TextureDimensionError as Tde;
     use wgt::TextureDimension::*;
-    match dimension {
+    Ok(match dimension {
         D1 => {
-            assert_eq!(height, 1);
-            assert_eq!(sample_size, 1);
-            H::D1(width, checked_u32_as_u16(depth))
+            if height != 1 {
+                return Err(Tde::InvalidHeight);
+            }
+            if sample_size != 1 {
+                return Err(Tde::InvalidSampleCount(sample_size));
+            }
+            H::D1(width, checked_u32_as_u16(depth)?)
         }
         D2 => {
-            assert!(
-                sample_size <= 32 && is_power_of_two(sample_size),
-                "Invalid sample_count of {}",
-                sample_size
-            );
-            H::D2(width, height, checked_u32_as_u16(depth), sample_size as u8)
+            if sample_size > 32 || !is_power_of_two(sample_size) {
+                return Err(Tde::InvalidSampleCount(sample_size));
+            }
+            H::D2(width, height, checked_u32_as_u16(depth)?, sample_size as u8)
         }
         D3 => {
-            assert_eq!(sample_size, 1);
+            if sample_size != 1 {
+                return Err(Tde::InvalidSampleCount(sample_size));
+            }
             H::D3(width, height, depth)
         }
-    }
+    })
 }
 
 pub fn map_texture_view_dimension(dimension: wgt::TextureViewDimension) -> hal::image::ViewKind {
@@ -516,6 +608,9 @@ pub(crate) fn map_buffer_state(usage: resource::BufferUse) -> hal::buffer::State
     if usage.contains(W::INDIRECT) {
         access |= A::INDIRECT_COMMAND_READ;
     }
+    if usage.contains(W::QUERY_RESOLVE) {
+        access |= A::TRANSFER_WRITE;
+    }
 
     access
 }
@@ -581,7 +676,8 @@ pub fn map_load_store_ops(load: wgt::LoadOp, store: wgt::StoreOp) -> hal::pass::
             wgt::LoadOp::Load => hal::pass::AttachmentLoadOp::Load,
         },
         store: match store {
-            wgt::StoreOp::Clear => hal::pass::AttachmentStoreOp::DontCare, //TODO!
+            // The discarded contents are tracked and zeroed before they are read again.
+            wgt::StoreOp::Clear => hal::pass::AttachmentStoreOp::DontCare,
             wgt::StoreOp::Store => hal::pass::AttachmentStoreOp::Store,
         },
     }
@@ -634,8 +730,12 @@ pub fn map_rasterization_state_descriptor(
 ) -> hal::pso::Rasterizer {
     use hal::pso;
     pso::Rasterizer {
-        depth_clamping: false,
-        polygon_mode: pso::PolygonMode::Fill,
+        depth_clamping: desc.clamp_depth,
+        polygon_mode: match desc.polygon_mode {
+            wgt::PolygonMode::Fill => pso::PolygonMode::Fill,
+            wgt::PolygonMode::Line => pso::PolygonMode::Line,
+            wgt::PolygonMode::Point => pso::PolygonMode::Point,
+        },
         cull_face: match desc.cull_mode {
             wgt::CullMode::None => pso::Face::empty(),
             wgt::CullMode::Front => pso::Face::FRONT,
@@ -668,3 +768,10 @@ pub fn map_index_format(index_format: wgt::IndexFormat) -> hal::IndexType {
         wgt::IndexFormat::Uint32 => hal::IndexType::U32,
     }
 }
+
+pub fn map_query_type(ty: wgt::QueryType) -> hal::query::Type {
+    match ty {
+        wgt::QueryType::Timestamp => hal::query::Type::Timestamp,
+        wgt::QueryType::Occlusion => hal::query::Type::Occlusion,
+    }
+}
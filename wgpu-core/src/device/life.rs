@@ -28,6 +28,7 @@ pub struct SuspectedResources {
     pub(crate) textures: Vec<id::TextureId>,
     pub(crate) texture_views: Vec<id::TextureViewId>,
     pub(crate) samplers: Vec<id::SamplerId>,
+    pub(crate) query_sets: Vec<id::QuerySetId>,
     pub(crate) bind_groups: Vec<id::BindGroupId>,
     pub(crate) compute_pipelines: Vec<id::ComputePipelineId>,
     pub(crate) render_pipelines: Vec<id::RenderPipelineId>,
@@ -42,6 +43,7 @@ impl SuspectedResources {
         self.textures.clear();
         self.texture_views.clear();
         self.samplers.clear();
+        self.query_sets.clear();
         self.bind_groups.clear();
         self.compute_pipelines.clear();
         self.render_pipelines.clear();
@@ -55,6 +57,7 @@ impl SuspectedResources {
         self.textures.extend_from_slice(&other.textures);
         self.texture_views.extend_from_slice(&other.texture_views);
         self.samplers.extend_from_slice(&other.samplers);
+        self.query_sets.extend_from_slice(&other.query_sets);
         self.bind_groups.extend_from_slice(&other.bind_groups);
         self.compute_pipelines
             .extend_from_slice(&other.compute_pipelines);
@@ -72,6 +75,7 @@ impl SuspectedResources {
         self.textures.extend(trackers.textures.used());
         self.texture_views.extend(trackers.views.used());
         self.samplers.extend(trackers.samplers.used());
+        self.query_sets.extend(trackers.query_sets.used());
         self.bind_groups.extend(trackers.bind_groups.used());
         self.compute_pipelines.extend(trackers.compute_pipes.used());
         self.render_pipelines.extend(trackers.render_pipes.used());
@@ -88,6 +92,7 @@ struct NonReferencedResources<B: hal::Backend> {
     // at any point what resources are used in a submission.
     image_views: Vec<(id::TextureViewId, B::ImageView)>,
     samplers: Vec<B::Sampler>,
+    query_pools: Vec<B::QueryPool>,
     framebuffers: Vec<B::Framebuffer>,
     desc_sets: Vec<DescriptorSet<B>>,
     compute_pipes: Vec<B::ComputePipeline>,
@@ -103,6 +108,7 @@ impl<B: hal::Backend> NonReferencedResources<B> {
             images: Vec::new(),
             image_views: Vec::new(),
             samplers: Vec::new(),
+            query_pools: Vec::new(),
             framebuffers: Vec::new(),
             desc_sets: Vec::new(),
             compute_pipes: Vec::new(),
@@ -117,6 +123,7 @@ impl<B: hal::Backend> NonReferencedResources<B> {
         self.images.extend(other.images);
         self.image_views.extend(other.image_views);
         self.samplers.extend(other.samplers);
+        self.query_pools.extend(other.query_pools);
         self.framebuffers.extend(other.framebuffers);
         self.desc_sets.extend(other.desc_sets);
         self.compute_pipes.extend(other.compute_pipes);
@@ -153,6 +160,9 @@ impl<B: hal::Backend> NonReferencedResources<B> {
         for raw in self.samplers.drain(..) {
             device.destroy_sampler(raw);
         }
+        for raw in self.query_pools.drain(..) {
+            device.destroy_query_pool(raw);
+        }
         for raw in self.framebuffers.drain(..) {
             device.destroy_framebuffer(raw);
         }
@@ -194,6 +204,9 @@ struct ActiveSubmission<B: hal::Backend> {
 /// and register the buffer with either a submission in flight, or straight into `ready_to_map` vector.
 ///   3. When `ActiveSubmission` is retired, the mapped buffers associated with it are moved to `ready_to_map` vector.
 ///   4. Finally, `handle_mapping` issues all the callbacks.
+///
+/// If the device gets lost, `retire_all` moves every mapping to `ready_to_map`,
+/// and `handle_mapping` fails them with `ContextLost`.
 #[derive(Debug)]
 pub struct LifetimeTracker<B: hal::Backend> {
     /// Resources that the user has requested be mapped, but are still in use.
@@ -259,7 +272,7 @@ impl<B: hal::Backend> LifetimeTracker<B> {
         });
     }
 
-    fn wait_idle(&self, device: &B::Device) {
+    fn wait_idle(&self, device: &B::Device) -> Result<(), hal::device::DeviceLost> {
         if !self.active.is_empty() {
             log::debug!("Waiting for IDLE...");
             let status = unsafe {
@@ -270,26 +283,36 @@ impl<B: hal::Backend> LifetimeTracker<B> {
                 )
             };
             log::debug!("...Done");
-            assert_eq!(status, Ok(true), "GPU got stuck :(");
+            match status {
+                Err(hal::device::OomOrDeviceLost::DeviceLost(lost)) => return Err(lost),
+                other => assert_eq!(other, Ok(true), "GPU got stuck :("),
+            }
         }
+        Ok(())
     }
 
     /// Returns the last submission index that is done.
-    pub fn triage_submissions(&mut self, device: &B::Device, force_wait: bool) -> SubmissionIndex {
+    pub fn triage_submissions(
+        &mut self,
+        device: &B::Device,
+        force_wait: bool,
+    ) -> Result<SubmissionIndex, hal::device::DeviceLost> {
         if force_wait {
-            self.wait_idle(device);
+            self.wait_idle(device)?;
         }
         //TODO: enable when `is_sorted_by_key` is stable
         //debug_assert!(self.active.is_sorted_by_key(|a| a.index));
-        let done_count = self
-            .active
-            .iter()
-            .position(|a| unsafe { !device.get_fence_status(&a.fence).unwrap() })
-            .unwrap_or_else(|| self.active.len());
+        let mut done_count = 0;
+        for a in self.active.iter() {
+            if !unsafe { device.get_fence_status(&a.fence)? } {
+                break;
+            }
+            done_count += 1;
+        }
         let last_done = if done_count != 0 {
             self.active[done_count - 1].index
         } else {
-            return 0;
+            return Ok(0);
         };
 
         for a in self.active.drain(..done_count) {
@@ -301,7 +324,20 @@ impl<B: hal::Backend> LifetimeTracker<B> {
             }
         }
 
-        last_done
+        Ok(last_done)
+    }
+
+    /// Retire all the submissions in flight, since a lost device
+    /// is never going to finish them.
+    pub fn retire_all(&mut self, device: &B::Device) {
+        for a in self.active.drain(..) {
+            log::trace!("Active submission {} is abandoned", a.index);
+            self.free_resources.extend(a.last_resources);
+            self.ready_to_map.extend(a.mapped);
+            unsafe {
+                device.destroy_fence(a.fence);
+            }
+        }
     }
 
     pub fn cleanup(
@@ -439,6 +475,28 @@ impl<B: GfxBackend> LifetimeTracker<B> {
             }
         }
 
+        if !self.suspected_resources.query_sets.is_empty() {
+            let mut trackers = trackers.lock();
+            let (mut guard, _) = hub.query_sets.write(token);
+
+            for id in self.suspected_resources.query_sets.drain(..) {
+                if trackers.query_sets.remove_abandoned(id) {
+                    #[cfg(feature = "trace")]
+                    trace.map(|t| t.lock().add(trace::Action::DestroyQuerySet(id)));
+                    hub.query_sets.free_id(id);
+                    let res = guard.remove(id).unwrap();
+
+                    let submit_index = res.life_guard.submission_index.load(Ordering::Acquire);
+                    self.active
+                        .iter_mut()
+                        .find(|a| a.index == submit_index)
+                        .map_or(&mut self.free_resources, |a| &mut a.last_resources)
+                        .query_pools
+                        .push(res.raw);
+                }
+            }
+        }
+
         if !self.suspected_resources.buffers.is_empty() {
             let mut trackers = trackers.lock();
             let (mut guard, _) = hub.buffers.write(token);
@@ -520,6 +578,14 @@ impl<B: GfxBackend> LifetimeTracker<B> {
                     trace.map(|t| t.lock().add(trace::Action::DestroyBindGroupLayout(id)));
                     hub.bind_group_layouts.free_id(id);
                     let layout = guard.remove(id).unwrap();
+                    // The samplers are checked again on the next triage,
+                    // now that the layout doesn't keep them alive.
+                    self.suspected_resources.samplers.extend(
+                        layout
+                            .immutable_samplers
+                            .into_iter()
+                            .map(|stored| stored.value),
+                    );
                     self.free_resources.descriptor_set_layouts.push(layout.raw);
                 }
             }
@@ -646,6 +712,7 @@ impl<B: GfxBackend> LifetimeTracker<B> {
         hub: &Hub<B, G>,
         raw: &B::Device,
         trackers: &Mutex<TrackerSet>,
+        device_lost: bool,
         token: &mut Token<super::Device<B>>,
     ) -> Vec<super::BufferMapPendingCallback> {
         if self.ready_to_map.is_empty() {
@@ -674,7 +741,13 @@ impl<B: GfxBackend> LifetimeTracker<B> {
                     resource::BufferMapState::Waiting(pending_mapping) => pending_mapping,
                     _ => panic!("No pending mapping."),
                 };
-                let status = if mapping.sub_range.size.map_or(true, |x| x != 0) {
+                let status = if device_lost {
+                    log::debug!(
+                        "Mapping of {:?} failed because the device is lost",
+                        buffer_id
+                    );
+                    resource::BufferMapAsyncStatus::ContextLost
+                } else if mapping.sub_range.size.map_or(true, |x| x != 0) {
                     log::debug!("Buffer {:?} map state -> Active", buffer_id);
                     let host = mapping.op.host;
                     match super::map_buffer(raw, buffer, mapping.sub_range.clone(), host) {
@@ -7,8 +7,9 @@ use crate::{
     command, conv,
     hub::{GfxBackend, Global, GlobalIdentityHandlerFactory, Hub, Input, Token},
     id, pipeline, resource, swap_chain,
-    track::{BufferState, TextureState, TrackerSet},
-    validation, FastHashMap, LifeGuard, PrivateFeatures, Stored, SubmissionIndex, MAX_BIND_GROUPS,
+    track::{BufferState, InitTracker, TextureState, TrackerSet, UseExtendError},
+    validation, FastHashMap, LifeGuard, PrivateFeatures, RawString, Stored, SubmissionIndex,
+    MAX_BIND_GROUPS,
 };
 
 use arrayvec::ArrayVec;
@@ -27,8 +28,13 @@ use wgt::{
 };
 
 use std::{
-    collections::hash_map::Entry, ffi, iter, marker::PhantomData, mem, ops::Range, ptr, slice,
-    sync::atomic::Ordering,
+    collections::hash_map::Entry,
+    ffi, fmt, iter,
+    marker::PhantomData,
+    mem,
+    ops::Range,
+    ptr, slice,
+    sync::atomic::{AtomicBool, Ordering},
 };
 
 use spirv_headers::ExecutionModel;
@@ -38,11 +44,23 @@ mod queue;
 #[cfg(any(feature = "trace", feature = "replay"))]
 pub mod trace;
 
+pub use queue::{QueueSubmitError, QueueWriteError};
+
 use smallvec::SmallVec;
 #[cfg(feature = "trace")]
 use trace::{Action, Trace};
 
 pub type Label = *const std::os::raw::c_char;
+
+/// Encoding of the actions recorded into an API trace.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TraceFormat {
+    /// Human-readable RON, written into `trace.ron`.
+    Ron,
+    /// Length-prefixed bincode records, written into `trace.bin`.
+    /// Much faster to write and to replay.
+    Binary,
+}
 #[cfg(feature = "trace")]
 fn own_label(label: &Label) -> String {
     if label.is_null() {
@@ -86,6 +104,172 @@ pub enum HostMap {
     Write,
 }
 
+/// Kind of errors captured by an error scope.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ErrorFilter {
+    Validation,
+    OutOfMemory,
+}
+
+/// Error captured by an error scope.
+#[derive(Clone, Debug, PartialEq)]
+pub enum GpuError {
+    /// Validation error, with a human-readable description.
+    Validation(String),
+    OutOfMemory,
+}
+
+impl GpuError {
+    fn filter(&self) -> ErrorFilter {
+        match *self {
+            GpuError::Validation(_) => ErrorFilter::Validation,
+            GpuError::OutOfMemory => ErrorFilter::OutOfMemory,
+        }
+    }
+}
+
+/// Where an error is generated, reported to the uncaptured error handler.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct ErrorContext<'a> {
+    /// Name of the API entry point.
+    pub entry_point: &'static str,
+    /// Label of the object being created, if any.
+    pub label: Option<&'a str>,
+}
+
+impl<'a> ErrorContext<'a> {
+    pub(crate) fn new(entry_point: &'static str) -> Self {
+        ErrorContext {
+            entry_point,
+            label: None,
+        }
+    }
+
+    pub(crate) fn with_label(entry_point: &'static str, label: Option<&'a str>) -> Self {
+        ErrorContext { entry_point, label }
+    }
+
+    pub(crate) fn with_raw_label(entry_point: &'static str, label: &'a Label) -> Self {
+        let label = if label.is_null() {
+            None
+        } else {
+            unsafe { ffi::CStr::from_ptr(*label) }.to_str().ok()
+        };
+        ErrorContext { entry_point, label }
+    }
+}
+
+/// Error that wasn't captured by any error scope.
+#[derive(Clone, Debug)]
+pub struct UncapturedError {
+    pub error: GpuError,
+    /// Label of the object being created, if any.
+    pub label: Option<String>,
+    /// Name of the API entry point that generated the error.
+    pub entry_point: &'static str,
+}
+
+/// Handler of the errors not captured by any error scope.
+///
+/// The message, label, and entry point are only valid for the duration of the call.
+/// The label is null if the object has no label.
+pub type UncapturedErrorCallback = unsafe extern "C" fn(
+    filter: ErrorFilter,
+    message: RawString,
+    label: RawString,
+    entry_point: RawString,
+    userdata: *mut u8,
+);
+
+#[derive(Clone, Copy, Debug)]
+struct UncapturedErrorHandler {
+    callback: UncapturedErrorCallback,
+    user_data: *mut u8,
+}
+
+unsafe impl Send for UncapturedErrorHandler {}
+unsafe impl Sync for UncapturedErrorHandler {}
+
+impl UncapturedErrorHandler {
+    fn call(&self, error: UncapturedError) {
+        let message = match error.error {
+            GpuError::Validation(ref description) => description.as_str(),
+            GpuError::OutOfMemory => "Out of memory",
+        };
+        let to_c_string = |string: &str| ffi::CString::new(string.replace('\0', "")).unwrap();
+        let message = to_c_string(message);
+        let label = error.label.as_ref().map(|label| to_c_string(label));
+        let entry_point = to_c_string(error.entry_point);
+        unsafe {
+            (self.callback)(
+                error.error.filter(),
+                message.as_ptr(),
+                label.as_ref().map_or(ptr::null(), |label| label.as_ptr()),
+                entry_point.as_ptr(),
+                self.user_data,
+            )
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum PopErrorScopeError {
+    /// There are no error scopes pushed on the device.
+    EmptyStack,
+}
+
+#[derive(Debug)]
+struct ErrorScope {
+    filter: ErrorFilter,
+    /// The first error captured by this scope.
+    error: Option<GpuError>,
+}
+
+/// Stack of the error scopes pushed on a device.
+#[derive(Debug, Default)]
+struct ErrorScopeStack {
+    scopes: Vec<ErrorScope>,
+}
+
+impl ErrorScopeStack {
+    fn push(&mut self, filter: ErrorFilter) {
+        self.scopes.push(ErrorScope {
+            filter,
+            error: None,
+        });
+    }
+
+    fn pop(&mut self) -> Result<Option<GpuError>, PopErrorScopeError> {
+        match self.scopes.pop() {
+            Some(scope) => Ok(scope.error),
+            None => Err(PopErrorScopeError::EmptyStack),
+        }
+    }
+
+    /// Record an error in the innermost scope with a matching filter.
+    ///
+    /// Only the first error of a scope is kept. Returns the error back
+    /// if no scope captures it.
+    fn capture(&mut self, error: GpuError) -> Option<GpuError> {
+        let filter = error.filter();
+        match self
+            .scopes
+            .iter_mut()
+            .rev()
+            .find(|scope| scope.filter == filter)
+        {
+            Some(scope) => {
+                if scope.error.is_none() {
+                    scope.error = Some(error);
+                }
+                None
+            }
+            None => Some(error),
+        }
+    }
+}
+
 #[derive(Clone, Debug, Hash, PartialEq)]
 pub(crate) struct AttachmentData<T> {
     pub colors: ArrayVec<[T; MAX_COLOR_TARGETS]>,
@@ -123,6 +307,28 @@ impl RenderPassContext {
 type BufferMapResult = Result<ptr::NonNull<u8>, hal::device::MapError>;
 type BufferMapPendingCallback = (resource::BufferMapOperation, resource::BufferMapAsyncStatus);
 
+/// Zero out the parts of a buffer mapping that were never written to, so that
+/// they can't leak the previous contents of the memory, and mark them as initialized.
+///
+/// `mapping` starts at `offset` in the buffer. Returns true if anything was zeroed.
+fn zero_uninitialized_mapping(
+    status: &mut InitTracker<BufferAddress>,
+    mapping: &mut [u8],
+    offset: BufferAddress,
+) -> bool {
+    let end = offset + mapping.len() as BufferAddress;
+    let mut zeroed = false;
+    for range in status.drain(offset..end) {
+        let start = (range.start - offset) as usize;
+        let end = (range.end - offset) as usize;
+        for byte in mapping[start..end].iter_mut() {
+            *byte = 0;
+        }
+        zeroed = true;
+    }
+    zeroed
+}
+
 fn map_buffer<B: hal::Backend>(
     raw: &B::Device,
     buffer: &mut resource::Buffer<B>,
@@ -145,13 +351,31 @@ fn map_buffer<B: hal::Backend>(
 
     buffer.sync_mapped_writes = match kind {
         HostMap::Read if needs_sync => unsafe {
-            raw.invalidate_mapped_memory_ranges(iter::once((buffer.memory.memory(), segment)))
-                .unwrap();
+            raw.invalidate_mapped_memory_ranges(iter::once((
+                buffer.memory.memory(),
+                segment.clone(),
+            )))
+            .unwrap();
             None
         },
-        HostMap::Write if needs_sync => Some(segment),
+        HostMap::Write if needs_sync => Some(segment.clone()),
         _ => None,
     };
+
+    let end = match sub_range.size {
+        Some(size) => sub_range.offset + size,
+        None => buffer.size,
+    };
+    let mapping =
+        unsafe { slice::from_raw_parts_mut(ptr.as_ptr(), (end - sub_range.offset) as usize) };
+    let zeroed =
+        zero_uninitialized_mapping(&mut buffer.initialization_status, mapping, sub_range.offset);
+    if zeroed && needs_sync && kind == HostMap::Read {
+        unsafe {
+            raw.flush_mapped_memory_ranges(iter::once((buffer.memory.memory(), segment)))
+                .unwrap();
+        }
+    }
     Ok(ptr)
 }
 
@@ -164,11 +388,62 @@ fn unmap_buffer<B: hal::Backend>(raw: &B::Device, buffer: &mut resource::Buffer<
     }
 }
 
-//Note: this logic is specifically moved out of `handle_mapping()` in order to
-// have nothing locked by the time we execute users callback code.
-fn fire_map_callbacks<I: IntoIterator<Item = BufferMapPendingCallback>>(callbacks: I) {
-    for (operation, status) in callbacks {
-        unsafe { (operation.callback)(status, operation.user_data) }
+/// Reason for a device to be lost.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DeviceLostReason {
+    /// The backend reported the device as lost.
+    Unknown,
+    /// The device was destroyed by `device_destroy`.
+    Destroyed,
+}
+
+pub type DeviceLostCallback = unsafe extern "C" fn(reason: DeviceLostReason, userdata: *mut u8);
+
+#[derive(Debug)]
+pub(crate) struct DeviceLostClosure {
+    callback: DeviceLostCallback,
+    user_data: *mut u8,
+}
+
+unsafe impl Send for DeviceLostClosure {}
+unsafe impl Sync for DeviceLostClosure {}
+
+pub(crate) type DeviceLostPendingCallback = (DeviceLostClosure, DeviceLostReason);
+
+#[derive(Clone, Debug)]
+pub enum DeviceError {
+    /// The device is lost, and can't be used any more.
+    Lost,
+}
+
+/// User callbacks that are ready to be fired.
+#[derive(Default)]
+pub(crate) struct UserClosures {
+    mappings: Vec<BufferMapPendingCallback>,
+    uncaptured_errors: Vec<(UncapturedErrorHandler, UncapturedError)>,
+    pub(crate) device_lost: Vec<DeviceLostPendingCallback>,
+}
+
+impl UserClosures {
+    fn extend(&mut self, other: Self) {
+        self.mappings.extend(other.mappings);
+        self.uncaptured_errors.extend(other.uncaptured_errors);
+        self.device_lost.extend(other.device_lost);
+    }
+
+    //Note: this logic is specifically moved out of `handle_mapping()` in order to
+    // have nothing locked by the time we execute users callback code.
+    pub(crate) fn fire(self) {
+        for (operation, status) in self.mappings {
+            unsafe { (operation.callback)(status, operation.user_data) }
+        }
+        for (handler, error) in self.uncaptured_errors {
+            handler.call(error);
+        }
+        for (closure, reason) in self.device_lost {
+            unsafe { (closure.callback)(reason, closure.user_data) }
+        }
     }
 }
 
@@ -197,6 +472,17 @@ pub struct Device<B: hal::Backend> {
     //TODO: move this behind another mutex. This would allow several methods to switch
     // to borrow Device immutably, such as `write_buffer`, `write_texture`, and `buffer_unmap`.
     pending_writes: queue::PendingWrites<B>,
+    error_scopes: Mutex<ErrorScopeStack>,
+    uncaptured_error_handler: Mutex<Option<UncapturedErrorHandler>>,
+    /// Errors not captured by any scope, waiting to be passed to the handler.
+    uncaptured_errors: Mutex<Vec<UncapturedError>>,
+    /// Set once the device is lost. All the following calls fail.
+    lost: AtomicBool,
+    /// Why the device was lost, set along with `lost`.
+    lost_reason: Mutex<Option<DeviceLostReason>>,
+    lost_callback: Mutex<Option<DeviceLostClosure>>,
+    /// Pipeline cache used by the pipelines that don't specify their own.
+    pipeline_cache: Option<B::PipelineCache>,
     #[cfg(feature = "trace")]
     pub(crate) trace: Option<Mutex<Trace>>,
 }
@@ -211,9 +497,19 @@ impl<B: GfxBackend> Device<B> {
         private_features: PrivateFeatures,
         desc: &wgt::DeviceDescriptor,
         capabilities: wgt::Capabilities,
-        trace_path: Option<&std::path::Path>,
+        pipeline_cache_data: Option<&[u8]>,
+        trace_path: Option<(&std::path::Path, TraceFormat)>,
     ) -> Self {
         let com_allocator = command::CommandAllocator::new(queue_group.family, &raw);
+        // The driver validates the header of the initial data,
+        // and ignores it if it was produced by a different device.
+        let pipeline_cache = match unsafe { raw.create_pipeline_cache(pipeline_cache_data) } {
+            Ok(cache) => Some(cache),
+            Err(e) => {
+                log::warn!("Unable to create the device pipeline cache: {:?}", e);
+                None
+            }
+        };
         let heaps = unsafe {
             Heaps::new(
                 &mem_props,
@@ -249,11 +545,14 @@ impl<B: GfxBackend> Device<B> {
             life_tracker: Mutex::new(life::LifetimeTracker::new()),
             temp_suspected: life::SuspectedResources::default(),
             #[cfg(feature = "trace")]
-            trace: trace_path.and_then(|path| match Trace::new(path) {
+            trace: trace_path.and_then(|(path, format)| match Trace::new(path, format) {
                 Ok(mut trace) => {
+                    let pipeline_cache_data =
+                        pipeline_cache_data.map(|data| trace.make_binary("bin", data));
                     trace.add(Action::Init {
                         desc: desc.clone(),
                         backend: B::VARIANT,
+                        pipeline_cache_data,
                     });
                     Some(Mutex::new(trace))
                 }
@@ -268,6 +567,13 @@ impl<B: GfxBackend> Device<B> {
             extensions: desc.extensions.clone(),
             capabilities: capabilities.clone(),
             pending_writes: queue::PendingWrites::new(),
+            error_scopes: Mutex::new(ErrorScopeStack::default()),
+            uncaptured_error_handler: Mutex::new(None),
+            uncaptured_errors: Mutex::new(Vec::new()),
+            lost: AtomicBool::new(false),
+            lost_reason: Mutex::new(None),
+            lost_callback: Mutex::new(None),
+            pipeline_cache,
         }
     }
 
@@ -275,6 +581,77 @@ impl<B: GfxBackend> Device<B> {
         self.life_guard.submission_index.load(Ordering::Acquire)
     }
 
+    pub(crate) fn is_lost(&self) -> bool {
+        self.lost.load(Ordering::Acquire)
+    }
+
+    /// Mark the device as lost, and return the device-lost callback to fire
+    /// once nothing is locked. Only the first call has any effect.
+    pub(crate) fn lose(&self, reason: DeviceLostReason) -> Option<DeviceLostPendingCallback> {
+        let mut lost_reason = self.lost_reason.lock();
+        if lost_reason.is_some() {
+            return None;
+        }
+        log::error!("Device is lost: {:?}", reason);
+        *lost_reason = Some(reason);
+        self.lost.store(true, Ordering::Release);
+        self.lost_callback
+            .lock()
+            .take()
+            .map(|closure| (closure, reason))
+    }
+
+    /// Record an error in the innermost error scope with a matching filter.
+    ///
+    /// Errors that are not captured by any scope are queued for the
+    /// uncaptured error handler, or logged if there is none.
+    fn report_error(&self, context: ErrorContext, error: GpuError) {
+        let error = match self.error_scopes.lock().capture(error) {
+            Some(error) => error,
+            None => return,
+        };
+        match *self.uncaptured_error_handler.lock() {
+            Some(_) => {
+                self.uncaptured_errors.lock().push(UncapturedError {
+                    error,
+                    label: context.label.map(str::to_string),
+                    entry_point: context.entry_point,
+                });
+            }
+            None => log::error!(
+                "Uncaptured error in {} ({:?}): {:?}",
+                context.entry_point,
+                context.label,
+                error
+            ),
+        }
+    }
+
+    /// Report a validation error to the error scopes, and return it back.
+    pub(crate) fn capture<E: fmt::Debug>(&self, context: ErrorContext, error: E) -> E {
+        self.report_error(context, GpuError::Validation(format!("{:?}", error)));
+        error
+    }
+
+    /// Report an out-of-memory error to the error scopes, and return it back.
+    pub(crate) fn capture_oom<E>(&self, context: ErrorContext, error: E) -> E {
+        self.report_error(context, GpuError::OutOfMemory);
+        error
+    }
+
+    /// Take the uncaptured errors, to be passed to the handler once nothing is locked.
+    fn take_uncaptured_errors(&self) -> Vec<(UncapturedErrorHandler, UncapturedError)> {
+        let handler = match *self.uncaptured_error_handler.lock() {
+            Some(handler) => handler,
+            None => return Vec::new(),
+        };
+        self.uncaptured_errors
+            .lock()
+            .drain(..)
+            .map(|error| (handler, error))
+            .collect()
+    }
+
     fn lock_life_internal<'this, 'token: 'this>(
         tracker: &'this Mutex<life::LifetimeTracker<B>>,
         _token: &mut Token<'token, Self>,
@@ -294,7 +671,8 @@ impl<B: GfxBackend> Device<B> {
         hub: &Hub<B, G>,
         force_wait: bool,
         token: &mut Token<'token, Self>,
-    ) -> Vec<BufferMapPendingCallback> {
+    ) -> UserClosures {
+        let mut closures = UserClosures::default();
         let mut life_tracker = self.lock_life(token);
 
         life_tracker.triage_suspected(
@@ -306,15 +684,26 @@ impl<B: GfxBackend> Device<B> {
         );
         life_tracker.triage_mapped(hub, token);
         life_tracker.triage_framebuffers(hub, &mut *self.framebuffers.lock(), token);
-        let last_done = life_tracker.triage_submissions(&self.raw, force_wait);
-        let callbacks = life_tracker.handle_mapping(hub, &self.raw, &self.trackers, token);
+        let last_done = match life_tracker.triage_submissions(&self.raw, force_wait) {
+            Ok(last_done) => last_done,
+            Err(hal::device::DeviceLost) => {
+                closures
+                    .device_lost
+                    .extend(self.lose(DeviceLostReason::Unknown));
+                life_tracker.retire_all(&self.raw);
+                self.active_submission_index
+            }
+        };
+        closures.mappings =
+            life_tracker.handle_mapping(hub, &self.raw, &self.trackers, self.is_lost(), token);
         life_tracker.cleanup(&self.raw, &self.mem_allocator, &self.desc_allocator);
 
         self.life_guard
             .submission_index
             .store(last_done, Ordering::Release);
         self.com_allocator.maintain(&self.raw, last_done);
-        callbacks
+        closures.uncaptured_errors = self.take_uncaptured_errors();
+        closures
     }
 
     fn untrack<'this, 'token: 'this, G: GlobalIdentityHandlerFactory>(
@@ -330,6 +719,7 @@ impl<B: GfxBackend> Device<B> {
             let (bind_group_guard, mut token) = hub.bind_groups.read(&mut token);
             let (compute_pipe_guard, mut token) = hub.compute_pipelines.read(&mut token);
             let (render_pipe_guard, mut token) = hub.render_pipelines.read(&mut token);
+            let (query_set_guard, mut token) = hub.query_sets.read(&mut token);
             let (buffer_guard, mut token) = hub.buffers.read(&mut token);
             let (texture_guard, mut token) = hub.textures.read(&mut token);
             let (texture_view_guard, mut token) = hub.texture_views.read(&mut token);
@@ -360,6 +750,11 @@ impl<B: GfxBackend> Device<B> {
                     self.temp_suspected.samplers.push(id);
                 }
             }
+            for id in trackers.query_sets.used() {
+                if query_set_guard[id].life_guard.ref_count.is_none() {
+                    self.temp_suspected.query_sets.push(id);
+                }
+            }
             for id in trackers.compute_pipes.used() {
                 if compute_pipe_guard[id].life_guard.ref_count.is_none() {
                     self.temp_suspected.compute_pipelines.push(id);
@@ -382,13 +777,17 @@ impl<B: GfxBackend> Device<B> {
         self_id: id::DeviceId,
         desc: &wgt::BufferDescriptor<Label>,
         memory_kind: gfx_memory::Kind,
-    ) -> resource::Buffer<B> {
+    ) -> Result<resource::Buffer<B>, resource::CreateBufferError> {
         debug_assert_eq!(self_id.backend(), B::VARIANT);
         let (mut usage, _memory_properties) = conv::map_buffer_usage(desc.usage);
         if desc.mapped_at_creation && !desc.usage.contains(wgt::BufferUsage::MAP_WRITE) {
             // we are going to be copying into it, internally
             usage |= hal::buffer::Usage::TRANSFER_DST;
         }
+        if self.private_features.zero_initialize {
+            // uninitialized ranges are cleared with `fill_buffer` before the first use
+            usage |= hal::buffer::Usage::TRANSFER_DST;
+        }
 
         let mem_usage = {
             use gfx_memory::MemoryUsage;
@@ -405,18 +804,17 @@ impl<B: GfxBackend> Device<B> {
                 let is_native_only = self
                     .extensions
                     .contains(wgt::Extensions::MAPPABLE_PRIMARY_BUFFERS);
-                assert!(
-                    is_native_only,
-                    "MAP usage can only be combined with the opposite COPY, requested {:?}",
-                    desc.usage
-                );
+                if !is_native_only {
+                    return Err(resource::CreateBufferError::UsageMismatch(desc.usage));
+                }
                 MemoryUsage::Dynamic {
                     sparse_updates: false,
                 }
             }
         };
 
-        let mut buffer = unsafe { self.raw.create_buffer(desc.size.max(1), usage).unwrap() };
+        let mut buffer = unsafe { self.raw.create_buffer(desc.size.max(1), usage) }
+            .map_err(|_| resource::CreateBufferError::OutOfMemory)?;
         if !desc.label.is_null() {
             unsafe {
                 let label = ffi::CStr::from_ptr(desc.label).to_string_lossy();
@@ -424,11 +822,18 @@ impl<B: GfxBackend> Device<B> {
             };
         }
         let requirements = unsafe { self.raw.get_buffer_requirements(&buffer) };
-        let memory = self
-            .mem_allocator
-            .lock()
-            .allocate(&self.raw, &requirements, mem_usage, memory_kind)
-            .unwrap();
+        let memory = match self.mem_allocator.lock().allocate(
+            &self.raw,
+            &requirements,
+            mem_usage,
+            memory_kind,
+        ) {
+            Ok(memory) => memory,
+            Err(_) => {
+                unsafe { self.raw.destroy_buffer(buffer) };
+                return Err(resource::CreateBufferError::OutOfMemory);
+            }
+        };
 
         unsafe {
             self.raw
@@ -436,7 +841,7 @@ impl<B: GfxBackend> Device<B> {
                 .unwrap()
         };
 
-        resource::Buffer {
+        Ok(resource::Buffer {
             raw: buffer,
             device_id: Stored {
                 value: self_id,
@@ -446,43 +851,81 @@ impl<B: GfxBackend> Device<B> {
             memory,
             size: desc.size,
             full_range: (),
+            initialization_status: if self.private_features.zero_initialize {
+                InitTracker::new(0..desc.size)
+            } else {
+                InitTracker::initialized()
+            },
             sync_mapped_writes: None,
             map_state: resource::BufferMapState::Idle,
             life_guard: LifeGuard::new(),
-        }
+        })
     }
 
     fn create_texture(
         &self,
         self_id: id::DeviceId,
         desc: &wgt::TextureDescriptor<Label>,
-    ) -> resource::Texture<B> {
+    ) -> Result<resource::Texture<B>, resource::CreateTextureError> {
         debug_assert_eq!(self_id.backend(), B::VARIANT);
 
         // Ensure `D24Plus` textures cannot be copied
         match desc.format {
             TextureFormat::Depth24Plus | TextureFormat::Depth24PlusStencil8 => {
-                assert!(
-                    !desc
-                        .usage
-                        .intersects(wgt::TextureUsage::COPY_SRC | wgt::TextureUsage::COPY_DST),
-                    "D24Plus textures cannot be copied"
-                );
+                if desc
+                    .usage
+                    .intersects(wgt::TextureUsage::COPY_SRC | wgt::TextureUsage::COPY_DST)
+                {
+                    return Err(resource::CreateTextureError::CannotCopyD24Plus);
+                }
             }
             _ => {}
         }
 
-        let kind = conv::map_texture_dimension_size(desc.dimension, desc.size, desc.sample_count);
+        let required_extensions = desc.format.required_extensions();
+        if !self.extensions.contains(required_extensions) {
+            return Err(resource::CreateTextureError::MissingExtension(
+                required_extensions - self.extensions,
+            ));
+        }
+        if desc.format.is_compressed() {
+            if desc.dimension != TextureDimension::D2 {
+                return Err(resource::CreateTextureError::InvalidCompressedDimension(
+                    desc.dimension,
+                ));
+            }
+            let compressed_usage = wgt::TextureUsage::COPY_SRC
+                | wgt::TextureUsage::COPY_DST
+                | wgt::TextureUsage::SAMPLED;
+            if !compressed_usage.contains(desc.usage) {
+                return Err(resource::CreateTextureError::InvalidCompressedUsage(
+                    desc.usage,
+                ));
+            }
+            let (block_width, block_height, _) =
+                command::texture_block_info(desc.format, self.private_features);
+            if desc.size.width % block_width != 0 || desc.size.height % block_height != 0 {
+                return Err(resource::CreateTextureError::UnalignedCompressedSize(
+                    desc.size,
+                ));
+            }
+        }
+
+        let kind = conv::map_texture_dimension_size(desc.dimension, desc.size, desc.sample_count)
+            .map_err(resource::CreateTextureError::InvalidDimension)?;
         let format = conv::map_texture_format(desc.format, self.private_features);
         let aspects = format.surface_desc().aspects;
-        let usage = conv::map_texture_usage(desc.usage, aspects);
+        let mut usage = conv::map_texture_usage(desc.usage, aspects);
+        if self.private_features.zero_initialize {
+            // uninitialized subresources are cleared with `clear_image` before the first use
+            usage |= hal::image::Usage::TRANSFER_DST;
+        }
 
-        assert!(
-            (desc.mip_level_count as usize) < MAX_MIP_LEVELS,
-            "Texture descriptor mip level count ({}) must be less than device max mip levels ({})",
-            desc.mip_level_count,
-            MAX_MIP_LEVELS
-        );
+        if desc.mip_level_count as usize >= MAX_MIP_LEVELS {
+            return Err(resource::CreateTextureError::InvalidMipLevelCount(
+                desc.mip_level_count,
+            ));
+        }
         let mut view_capabilities = hal::image::ViewCapabilities::empty();
 
         // 2D textures with array layer counts that are multiples of 6 could be cubemaps
@@ -491,8 +934,6 @@ impl<B: GfxBackend> Device<B> {
             view_capabilities |= hal::image::ViewCapabilities::KIND_CUBE;
         };
 
-        // TODO: 2D arrays, cubemap arrays
-
         let mut image = unsafe {
             let mut image = self
                 .raw
@@ -504,7 +945,7 @@ impl<B: GfxBackend> Device<B> {
                     usage,
                     view_capabilities,
                 )
-                .unwrap();
+                .map_err(|_| resource::CreateTextureError::OutOfMemory)?;
             if !desc.label.is_null() {
                 let label = ffi::CStr::from_ptr(desc.label).to_string_lossy();
                 self.raw.set_image_name(&mut image, &label);
@@ -513,16 +954,18 @@ impl<B: GfxBackend> Device<B> {
         };
         let requirements = unsafe { self.raw.get_image_requirements(&image) };
 
-        let memory = self
-            .mem_allocator
-            .lock()
-            .allocate(
-                &self.raw,
-                &requirements,
-                gfx_memory::MemoryUsage::Private,
-                gfx_memory::Kind::General,
-            )
-            .unwrap();
+        let memory = match self.mem_allocator.lock().allocate(
+            &self.raw,
+            &requirements,
+            gfx_memory::MemoryUsage::Private,
+            gfx_memory::Kind::General,
+        ) {
+            Ok(memory) => memory,
+            Err(_) => {
+                unsafe { self.raw.destroy_image(image) };
+                return Err(resource::CreateTextureError::OutOfMemory);
+            }
+        };
 
         unsafe {
             self.raw
@@ -530,7 +973,7 @@ impl<B: GfxBackend> Device<B> {
                 .unwrap()
         };
 
-        resource::Texture {
+        Ok(resource::Texture {
             raw: image,
             device_id: Stored {
                 value: self_id,
@@ -545,74 +988,1093 @@ impl<B: GfxBackend> Device<B> {
                 levels: 0..desc.mip_level_count as hal::image::Level,
                 layers: 0..kind.num_layers(),
             },
+            initialization_status: (0..desc.mip_level_count)
+                .map(|_| {
+                    if self.private_features.zero_initialize {
+                        InitTracker::new(0..kind.num_layers())
+                    } else {
+                        InitTracker::initialized()
+                    }
+                })
+                .collect(),
             memory,
             life_guard: LifeGuard::new(),
-        }
+        })
     }
 
-    /// Create a compatible render pass with a given key.
-    ///
-    /// This functions doesn't consider the following aspects for compatibility:
-    ///  - image layouts
-    ///  - resolve attachments
-    fn create_compatible_render_pass(&self, key: &RenderPassKey) -> B::RenderPass {
-        let mut color_ids = [(0, hal::image::Layout::ColorAttachmentOptimal); MAX_COLOR_TARGETS];
-        for i in 0..key.colors.len() {
-            color_ids[i].0 = i;
-        }
-        let depth_id = key.depth_stencil.as_ref().map(|_| {
-            (
-                key.colors.len(),
-                hal::image::Layout::DepthStencilAttachmentOptimal,
-            )
-        });
-
-        let subpass = hal::pass::SubpassDesc {
-            colors: &color_ids[..key.colors.len()],
-            depth_stencil: depth_id.as_ref(),
-            inputs: &[],
-            resolves: &[],
-            preserves: &[],
-        };
-        let all = key.all().map(|(at, _)| at);
+    fn create_bind_group_layout<G: GlobalIdentityHandlerFactory>(
+        &self,
+        self_id: id::DeviceId,
+        label: Option<&str>,
+        entry_map: binding_model::BindEntryMap,
+        hub: &Hub<B, G>,
+        token: &mut Token<Self>,
+    ) -> Result<binding_model::BindGroupLayout<B>, binding_model::BindGroupLayoutError> {
+        use crate::binding_model::BindGroupLayoutError as Error;
 
-        unsafe {
-            self.raw
-                .create_render_pass(all, iter::once(subpass), &[])
-                .unwrap()
+        // Validate the count parameter
+        for entry in entry_map.values() {
+            if let Some(count) = entry.count {
+                if count == 0 {
+                    return Err(Error::ZeroCount);
+                }
+                match entry.ty {
+                    wgt::BindingType::SampledTexture { .. } => {
+                        if !self
+                            .capabilities
+                            .contains(wgt::Capabilities::SAMPLED_TEXTURE_BINDING_ARRAY)
+                        {
+                            return Err(Error::MissingCapability(
+                                wgt::Capabilities::SAMPLED_TEXTURE_BINDING_ARRAY,
+                            ));
+                        }
+                    }
+                    _ => return Err(Error::ArrayUnsupported),
+                }
+            }
         }
-    }
-}
 
-impl<B: hal::Backend> Device<B> {
-    pub(crate) fn destroy_bind_group(&self, bind_group: binding_model::BindGroup<B>) {
-        unsafe {
-            self.desc_allocator.lock().free(iter::once(bind_group.raw));
+        let (sampler_guard, _) = hub.samplers.read(token);
+        let mut immutable_samplers = Vec::new();
+        let mut raw_samplers = Vec::new();
+        for entry in entry_map.values() {
+            if entry.immutable_samplers.is_empty() {
+                continue;
+            }
+            let comparison = match entry.ty {
+                wgt::BindingType::Sampler { comparison } => comparison,
+                _ => return Err(Error::UnexpectedImmutableSamplers(entry.binding)),
+            };
+            let expected = entry.count.map_or(1, |count| count as usize);
+            if entry.immutable_samplers.len() != expected {
+                return Err(Error::ImmutableSamplerCountMismatch {
+                    binding: entry.binding,
+                    actual: entry.immutable_samplers.len(),
+                    expected,
+                });
+            }
+            for &sampler_id in entry.immutable_samplers.iter() {
+                let sampler = sampler_guard
+                    .get(sampler_id)
+                    .map_err(|_| Error::InvalidSampler(sampler_id))?;
+                if sampler.comparison != comparison {
+                    return Err(Error::WrongSamplerComparison(entry.binding));
+                }
+                raw_samplers.push(&sampler.raw);
+                immutable_samplers.push(Stored {
+                    value: sampler_id,
+                    ref_count: sampler.life_guard.add_ref(),
+                });
+            }
         }
-    }
 
-    pub(crate) fn destroy_buffer(&self, buffer: resource::Buffer<B>) {
-        unsafe {
-            self.mem_allocator.lock().free(&self.raw, buffer.memory);
-            self.raw.destroy_buffer(buffer.raw);
-        }
-    }
+        // The immutable samplers are consumed in the order of the bindings
+        // that use them, so both are collected in the same iteration order.
+        let raw_bindings = entry_map
+            .values()
+            .map(|entry| hal::pso::DescriptorSetLayoutBinding {
+                binding: entry.binding,
+                ty: conv::map_binding_type(entry),
+                count: entry
+                    .count
+                    .map_or(1, |v| v as hal::pso::DescriptorArrayIndex), //TODO: consolidate
+                stage_flags: conv::map_shader_stage_flags(entry.visibility),
+                immutable_samplers: !entry.immutable_samplers.is_empty(),
+            })
+            .collect::<Vec<_>>(); //TODO: avoid heap allocation
 
-    pub(crate) fn destroy_texture(&self, texture: resource::Texture<B>) {
-        unsafe {
-            self.mem_allocator.lock().free(&self.raw, texture.memory);
-            self.raw.destroy_image(texture.raw);
-        }
-    }
+        let raw = unsafe {
+            let mut raw_layout = self
+                .raw
+                .create_descriptor_set_layout(&raw_bindings, raw_samplers)
+                .unwrap();
+            if let Some(label) = label {
+                self.raw
+                    .set_descriptor_set_layout_name(&mut raw_layout, label);
+            }
+            raw_layout
+        };
 
-    /// Wait for idle and remove resources that we can, before we die.
-    pub(crate) fn prepare_to_die(&mut self) {
-        let mut life_tracker = self.life_tracker.lock();
-        life_tracker.triage_submissions(&self.raw, true);
-        life_tracker.cleanup(&self.raw, &self.mem_allocator, &self.desc_allocator);
+        let dynamic_count = entry_map
+            .values()
+            .filter(|entry| entry.has_dynamic_offset())
+            .count();
+
+        Ok(binding_model::BindGroupLayout {
+            raw,
+            device_id: Stored {
+                value: self_id,
+                ref_count: self.life_guard.add_ref(),
+            },
+            life_guard: LifeGuard::new(),
+            entries: entry_map,
+            desc_counts: raw_bindings.iter().cloned().collect(),
+            dynamic_count,
+            immutable_samplers,
+        })
     }
 
-    pub(crate) fn dispose(self) {
+    fn create_bind_group<G: GlobalIdentityHandlerFactory>(
+        &self,
+        self_id: id::DeviceId,
+        layout: &binding_model::BindGroupLayout<B>,
+        desc: &binding_model::BindGroupDescriptor,
+        hub: &Hub<B, G>,
+        token: &mut Token<binding_model::BindGroupLayout<B>>,
+    ) -> Result<binding_model::BindGroup<B>, BindGroupError> {
+        use crate::binding_model::BindingResource as Br;
+
+        // Check that the number of bindings in the descriptor matches
+        // the number of bindings in the layout, excluding the ones
+        // provided by immutable samplers.
+        let actual = desc.bindings.len();
+        let expected = layout
+            .entries
+            .values()
+            .filter(|entry| entry.immutable_samplers.is_empty())
+            .count();
+        if actual != expected {
+            return Err(BindGroupError::BindingsNumMismatch { expected, actual });
+        }
+
+        // fill out the descriptors
+        let mut used = TrackerSet::new(B::VARIANT);
+        let desc_set = {
+            let (buffer_guard, mut token) = hub.buffers.read(token);
+            let (texture_guard, mut token) = hub.textures.read(&mut token); //skip token
+            let (texture_view_guard, mut token) = hub.texture_views.read(&mut token);
+            let (sampler_guard, _) = hub.samplers.read(&mut token);
+
+            //TODO: group writes into contiguous sections
+            let mut writes = Vec::new();
+            for b in desc.bindings {
+                let binding = b.binding;
+                // Find the corresponding declaration in the layout
+                let decl = match layout.entries.get(&binding) {
+                    Some(decl) => decl,
+                    None => return Err(BindGroupError::MissingBindingDeclaration(binding)),
+                };
+                if !decl.immutable_samplers.is_empty() {
+                    return Err(BindGroupError::ImmutableSamplerBinding(binding));
+                }
+                let descriptors: SmallVec<[_; 1]> = match b.resource {
+                    Br::Buffer(ref bb) => {
+                        let (pub_usage, internal_use, min_size) = match decl.ty {
+                            wgt::BindingType::UniformBuffer {
+                                dynamic: _,
+                                min_binding_size,
+                            } => (
+                                wgt::BufferUsage::UNIFORM,
+                                resource::BufferUse::UNIFORM,
+                                min_binding_size,
+                            ),
+                            wgt::BindingType::StorageBuffer {
+                                dynamic: _,
+                                min_binding_size,
+                                readonly,
+                            } => (
+                                wgt::BufferUsage::STORAGE,
+                                if readonly {
+                                    resource::BufferUse::STORAGE_STORE
+                                } else {
+                                    resource::BufferUse::STORAGE_LOAD
+                                },
+                                min_binding_size,
+                            ),
+                            _ => {
+                                return Err(BindGroupError::WrongBindingType {
+                                    binding,
+                                    actual: decl.ty.clone(),
+                                    expected:
+                                        "UniformBuffer, StorageBuffer or ReadonlyStorageBuffer",
+                                })
+                            }
+                        };
+
+                        if bb.offset % BIND_BUFFER_ALIGNMENT != 0 {
+                            return Err(BindGroupError::UnalignedBufferOffset(bb.offset));
+                        }
+
+                        let buffer = used
+                            .buffers
+                            .use_extend(&*buffer_guard, bb.buffer_id, (), internal_use)
+                            .map_err(|e| match e {
+                                UseExtendError::InvalidResource => {
+                                    BindGroupError::InvalidBuffer(bb.buffer_id)
+                                }
+                                UseExtendError::Conflict(_) => {
+                                    BindGroupError::UsageConflict(binding)
+                                }
+                            })?;
+                        if !buffer.usage.contains(pub_usage) {
+                            return Err(BindGroupError::MissingBufferUsage {
+                                actual: buffer.usage,
+                                expected: pub_usage,
+                            });
+                        }
+                        let bind_size = if bb.size == BufferSize::WHOLE {
+                            buffer.size - bb.offset
+                        } else {
+                            let end = bb.offset + bb.size.0;
+                            if end > buffer.size {
+                                return Err(BindGroupError::BindingRangeTooLarge {
+                                    range: bb.offset..end,
+                                    size: buffer.size,
+                                });
+                            }
+                            bb.size.0
+                        };
+                        match min_size {
+                            Some(non_zero) if non_zero.get() > bind_size => {
+                                return Err(BindGroupError::BindingSizeTooSmall {
+                                    actual: bind_size,
+                                    min: non_zero.get(),
+                                })
+                            }
+                            _ => (),
+                        }
+
+                        let sub_range = hal::buffer::SubRange {
+                            offset: bb.offset,
+                            size: Some(bind_size),
+                        };
+                        SmallVec::from([hal::pso::Descriptor::Buffer(&buffer.raw, sub_range)])
+                    }
+                    Br::Sampler(id) => {
+                        match decl.ty {
+                            wgt::BindingType::Sampler { comparison } => {
+                                let sampler = used
+                                    .samplers
+                                    .use_extend(&*sampler_guard, id, (), ())
+                                    .map_err(|_| BindGroupError::InvalidSampler(id))?;
+
+                                // Check the actual sampler to also (not) be a comparison sampler
+                                if sampler.comparison != comparison {
+                                    return Err(BindGroupError::WrongSamplerComparison);
+                                }
+
+                                SmallVec::from([hal::pso::Descriptor::Sampler(&sampler.raw)])
+                            }
+                            _ => {
+                                return Err(BindGroupError::WrongBindingType {
+                                    binding,
+                                    actual: decl.ty.clone(),
+                                    expected: "Sampler",
+                                })
+                            }
+                        }
+                    }
+                    Br::TextureView(id) => {
+                        let view = used
+                            .views
+                            .use_extend(&*texture_view_guard, id, (), ())
+                            .map_err(|_| BindGroupError::InvalidTextureView(id))?;
+                        let (pub_usage, internal_use, layout_dimension) = match decl.ty {
+                            wgt::BindingType::SampledTexture { dimension, .. } => (
+                                wgt::TextureUsage::SAMPLED,
+                                resource::TextureUse::SAMPLED,
+                                dimension,
+                            ),
+                            wgt::BindingType::StorageTexture { dimension, readonly, .. } => (
+                                wgt::TextureUsage::STORAGE,
+                                if readonly {
+                                    resource::TextureUse::STORAGE_LOAD
+                                } else {
+                                    resource::TextureUse::STORAGE_STORE
+                                },
+                                dimension,
+                            ),
+                            _ => return Err(BindGroupError::WrongBindingType {
+                                binding,
+                                actual: decl.ty.clone(),
+                                expected: "SampledTexture, ReadonlyStorageTexture or WriteonlyStorageTexture"
+                            })
+                        };
+                        if view.dimension != layout_dimension {
+                            return Err(BindGroupError::WrongTextureViewDimension {
+                                binding,
+                                layout_dimension,
+                                view_dimension: view.dimension,
+                            });
+                        }
+                        match view.inner {
+                            resource::TextureViewInner::Native {
+                                ref raw,
+                                ref source_id,
+                            } => {
+                                // Careful here: the texture may no longer have its own ref count,
+                                // if it was deleted by the user.
+                                let texture = &texture_guard[source_id.value];
+                                used.textures
+                                    .change_extend(
+                                        source_id.value,
+                                        &source_id.ref_count,
+                                        view.range.clone(),
+                                        internal_use,
+                                    )
+                                    .map_err(|_| BindGroupError::UsageConflict(binding))?;
+                                if !texture.usage.contains(pub_usage) {
+                                    return Err(BindGroupError::MissingTextureUsage {
+                                        actual: texture.usage,
+                                        expected: pub_usage,
+                                    });
+                                }
+                                let image_layout =
+                                    conv::map_texture_state(internal_use, view.range.aspects).1;
+                                SmallVec::from([hal::pso::Descriptor::Image(raw, image_layout)])
+                            }
+                            resource::TextureViewInner::SwapChain { .. } => {
+                                return Err(BindGroupError::SwapChainImage)
+                            }
+                        }
+                    }
+                    Br::TextureViewArray(ref bindings_array) => {
+                        let required_caps = wgt::Capabilities::SAMPLED_TEXTURE_BINDING_ARRAY;
+                        if !self.capabilities.contains(required_caps) {
+                            return Err(BindGroupError::MissingCapability(required_caps));
+                        }
+
+                        match decl.count {
+                            Some(count) if count as usize == bindings_array.len() => (),
+                            Some(count) => {
+                                return Err(BindGroupError::BindingArrayLengthMismatch {
+                                    actual: bindings_array.len(),
+                                    expected: count as usize,
+                                })
+                            }
+                            None => return Err(BindGroupError::SingleBindingExpected(binding)),
+                        }
+
+                        let (pub_usage, internal_use, layout_dimension) = match decl.ty {
+                            wgt::BindingType::SampledTexture { dimension, .. } => (
+                                wgt::TextureUsage::SAMPLED,
+                                resource::TextureUse::SAMPLED,
+                                dimension,
+                            ),
+                            _ => {
+                                return Err(BindGroupError::WrongBindingType {
+                                    binding,
+                                    actual: decl.ty.clone(),
+                                    expected: "SampledTextureArray",
+                                })
+                            }
+                        };
+                        let mut descriptors = SmallVec::with_capacity(bindings_array.len());
+                        for &id in bindings_array.iter() {
+                            let view = used
+                                .views
+                                .use_extend(&*texture_view_guard, id, (), ())
+                                .map_err(|_| BindGroupError::InvalidTextureView(id))?;
+                            if view.dimension != layout_dimension {
+                                return Err(BindGroupError::WrongTextureViewDimension {
+                                    binding,
+                                    layout_dimension,
+                                    view_dimension: view.dimension,
+                                });
+                            }
+                            match view.inner {
+                                resource::TextureViewInner::Native {
+                                    ref raw,
+                                    ref source_id,
+                                } => {
+                                    // Careful here: the texture may no longer have its own ref count,
+                                    // if it was deleted by the user.
+                                    let texture = &texture_guard[source_id.value];
+                                    used.textures
+                                        .change_extend(
+                                            source_id.value,
+                                            &source_id.ref_count,
+                                            view.range.clone(),
+                                            internal_use,
+                                        )
+                                        .map_err(|_| BindGroupError::UsageConflict(binding))?;
+                                    if !texture.usage.contains(pub_usage) {
+                                        return Err(BindGroupError::MissingTextureUsage {
+                                            actual: texture.usage,
+                                            expected: pub_usage,
+                                        });
+                                    }
+                                    let image_layout =
+                                        conv::map_texture_state(internal_use, view.range.aspects).1;
+                                    descriptors
+                                        .push(hal::pso::Descriptor::Image(raw, image_layout));
+                                }
+                                resource::TextureViewInner::SwapChain { .. } => {
+                                    return Err(BindGroupError::SwapChainImage)
+                                }
+                            }
+                        }
+                        descriptors
+                    }
+                };
+                writes.alloc().init((binding, descriptors));
+            }
+
+            // All the bindings are valid, so we can allocate the descriptor set now.
+            let mut desc_set = unsafe {
+                let mut desc_sets = ArrayVec::<[_; 1]>::new();
+                self.desc_allocator
+                    .lock()
+                    .allocate(
+                        &self.raw,
+                        &layout.raw,
+                        &layout.desc_counts,
+                        1,
+                        &mut desc_sets,
+                    )
+                    .map_err(|_| BindGroupError::OutOfMemory)?;
+                desc_sets.pop().unwrap()
+            };
+
+            // Set the descriptor set's label for easier debugging.
+            if let Some(label) = desc.label {
+                unsafe {
+                    self.raw.set_descriptor_set_name(desc_set.raw_mut(), &label);
+                }
+            }
+
+            unsafe {
+                let raw_set = desc_set.raw();
+                self.raw
+                    .write_descriptor_sets(writes.into_iter().map(|(binding, descriptors)| {
+                        hal::pso::DescriptorSetWrite {
+                            set: raw_set,
+                            binding,
+                            array_offset: 0, //TODO
+                            descriptors,
+                        }
+                    }));
+            }
+            desc_set
+        };
+
+        Ok(binding_model::BindGroup {
+            raw: desc_set,
+            device_id: Stored {
+                value: self_id,
+                ref_count: self.life_guard.add_ref(),
+            },
+            layout_id: desc.layout,
+            life_guard: LifeGuard::new(),
+            used,
+            dynamic_count: layout.dynamic_count,
+        })
+    }
+
+    fn create_render_pipeline<G: GlobalIdentityHandlerFactory>(
+        &self,
+        self_id: id::DeviceId,
+        desc: &pipeline::RenderPipelineDescriptor,
+        hub: &Hub<B, G>,
+        token: &mut Token<Self>,
+    ) -> Result<pipeline::RenderPipeline<B>, pipeline::RenderPipelineError> {
+        let samples = {
+            let sc = desc.sample_count;
+            if sc == 0 || sc > 32 || !conv::is_power_of_two(sc) {
+                return Err(pipeline::RenderPipelineError::InvalidSampleCount(sc));
+            }
+            sc as u8
+        };
+
+        let color_states =
+            unsafe { slice::from_raw_parts(desc.color_states, desc.color_states_length) };
+        let depth_stencil_state = unsafe { desc.depth_stencil_state.as_ref() };
+
+        let rasterization_state = unsafe { desc.rasterization_state.as_ref() }.cloned();
+        if let Some(ref rs) = rasterization_state {
+            let mut required_extensions = wgt::Extensions::empty();
+            required_extensions.set(wgt::Extensions::DEPTH_CLAMPING, rs.clamp_depth);
+            required_extensions.set(
+                wgt::Extensions::NON_FILL_POLYGON_MODE,
+                rs.polygon_mode != wgt::PolygonMode::Fill,
+            );
+            if !self.extensions.contains(required_extensions) {
+                return Err(pipeline::RenderPipelineError::MissingExtension(
+                    required_extensions - self.extensions,
+                ));
+            }
+        }
+        let rasterizer = conv::map_rasterization_state_descriptor(
+            &rasterization_state.clone().unwrap_or_default(),
+        );
+
+        let mut interface = validation::StageInterface::default();
+        let mut validated_stages = wgt::ShaderStage::empty();
+
+        let desc_vbs = unsafe {
+            slice::from_raw_parts(
+                desc.vertex_state.vertex_buffers,
+                desc.vertex_state.vertex_buffers_length,
+            )
+        };
+        let mut vertex_strides = Vec::with_capacity(desc_vbs.len());
+        let mut vertex_buffers = Vec::with_capacity(desc_vbs.len());
+        let mut attributes = Vec::new();
+        for (i, vb_state) in desc_vbs.iter().enumerate() {
+            vertex_strides
+                .alloc()
+                .init((vb_state.array_stride, vb_state.step_mode));
+            if vb_state.attributes_length == 0 {
+                continue;
+            }
+            vertex_buffers.alloc().init(hal::pso::VertexBufferDesc {
+                binding: i as u32,
+                stride: vb_state.array_stride as u32,
+                rate: match vb_state.step_mode {
+                    InputStepMode::Vertex => hal::pso::VertexInputRate::Vertex,
+                    InputStepMode::Instance => hal::pso::VertexInputRate::Instance(1),
+                },
+            });
+            let desc_atts =
+                unsafe { slice::from_raw_parts(vb_state.attributes, vb_state.attributes_length) };
+            for attribute in desc_atts {
+                if attribute.offset >= 0x10000000 {
+                    return Err(
+                        pipeline::RenderPipelineError::InvalidVertexAttributeOffset {
+                            location: attribute.shader_location,
+                            offset: attribute.offset,
+                        },
+                    );
+                }
+                attributes.alloc().init(hal::pso::AttributeDesc {
+                    location: attribute.shader_location,
+                    binding: i as u32,
+                    element: hal::pso::Element {
+                        format: conv::map_vertex_format(attribute.format),
+                        offset: attribute.offset as u32,
+                    },
+                });
+                interface.insert(
+                    attribute.shader_location,
+                    validation::MaybeOwned::Owned(validation::map_vertex_format(attribute.format)),
+                );
+            }
+        }
+
+        let input_assembler = hal::pso::InputAssemblerDesc {
+            primitive: conv::map_primitive_topology(desc.primitive_topology),
+            with_adjacency: false,
+            restart_index: if desc.primitive_restart {
+                match desc.primitive_topology {
+                    wgt::PrimitiveTopology::LineStrip | wgt::PrimitiveTopology::TriangleStrip => {
+                        Some(conv::map_index_format(desc.vertex_state.index_format))
+                    }
+                    other => {
+                        return Err(
+                            pipeline::RenderPipelineError::PrimitiveRestartWithListTopology(other),
+                        )
+                    }
+                }
+            } else {
+                None
+            },
+        };
+
+        let logic_op = color_states.first().and_then(|state| state.logic_op);
+        for (i, state) in color_states.iter().enumerate() {
+            if state.logic_op != logic_op {
+                return Err(pipeline::RenderPipelineError::InconsistentLogicOp { index: i as u8 });
+            }
+            if state.logic_op.is_some()
+                && (state.color_blend != wgt::BlendDescriptor::REPLACE
+                    || state.alpha_blend != wgt::BlendDescriptor::REPLACE)
+            {
+                return Err(pipeline::RenderPipelineError::LogicOpWithBlending { index: i as u8 });
+            }
+        }
+        if logic_op.is_some() && !self.extensions.contains(wgt::Extensions::LOGIC_OP) {
+            return Err(pipeline::RenderPipelineError::MissingExtension(
+                wgt::Extensions::LOGIC_OP,
+            ));
+        }
+
+        let blender = hal::pso::BlendDesc {
+            logic_op: logic_op.map(conv::map_logic_op),
+            targets: color_states
+                .iter()
+                .map(conv::map_color_state_descriptor)
+                .collect(),
+        };
+        let depth_stencil = depth_stencil_state
+            .map(conv::map_depth_stencil_state_descriptor)
+            .unwrap_or_default();
+
+        let multisampling: Option<hal::pso::Multisampling> = if samples == 1 {
+            None
+        } else {
+            Some(hal::pso::Multisampling {
+                rasterization_samples: samples,
+                sample_shading: None,
+                sample_mask: desc.sample_mask as u64,
+                alpha_coverage: desc.alpha_to_coverage_enabled,
+                alpha_to_one: false,
+            })
+        };
+
+        // TODO
+        let baked_states = hal::pso::BakedStates {
+            viewport: None,
+            scissor: None,
+            blend_color: None,
+            depth_bounds: None,
+        };
+
+        let (raw_pipeline, layout_ref_count) = {
+            let (pipeline_layout_guard, mut token) = hub.pipeline_layouts.read(token);
+            let (bgl_guard, mut token) = hub.bind_group_layouts.read(&mut token);
+            let layout = pipeline_layout_guard
+                .get(desc.layout)
+                .map_err(|_| pipeline::RenderPipelineError::InvalidLayout(desc.layout))?;
+            let group_layouts = layout
+                .bind_group_layout_ids
+                .iter()
+                .map(|id| &bgl_guard[id.value].entries)
+                .collect::<ArrayVec<[&binding_model::BindEntryMap; MAX_BIND_GROUPS]>>();
+
+            let (shader_module_guard, mut token) = hub.shader_modules.read(&mut token);
+            let (pipeline_cache_guard, _) = hub.pipeline_caches.read(&mut token);
+            let cache = match desc.cache {
+                Some(cache_id) => Some(
+                    &pipeline_cache_guard
+                        .get(cache_id)
+                        .map_err(|_| pipeline::RenderPipelineError::InvalidPipelineCache(cache_id))?
+                        .raw,
+                ),
+                None => self.pipeline_cache.as_ref(),
+            };
+
+            let rp_key = RenderPassKey {
+                colors: color_states
+                    .iter()
+                    .map(|state| {
+                        let at = hal::pass::Attachment {
+                            format: Some(conv::map_texture_format(
+                                state.format,
+                                self.private_features,
+                            )),
+                            samples,
+                            ops: hal::pass::AttachmentOps::PRESERVE,
+                            stencil_ops: hal::pass::AttachmentOps::DONT_CARE,
+                            layouts: hal::image::Layout::General..hal::image::Layout::General,
+                        };
+                        (at, hal::image::Layout::ColorAttachmentOptimal)
+                    })
+                    .collect(),
+                // We can ignore the resolves as the vulkan specs says:
+                // As an additional special case, if two render passes have a single subpass,
+                // they are compatible even if they have different resolve attachment references
+                // or depth/stencil resolve modes but satisfy the other compatibility conditions.
+                resolves: ArrayVec::new(),
+                depth_stencil: depth_stencil_state.map(|state| {
+                    let at = hal::pass::Attachment {
+                        format: Some(conv::map_texture_format(
+                            state.format,
+                            self.private_features,
+                        )),
+                        samples,
+                        ops: hal::pass::AttachmentOps::PRESERVE,
+                        stencil_ops: hal::pass::AttachmentOps::PRESERVE,
+                        layouts: hal::image::Layout::General..hal::image::Layout::General,
+                    };
+                    (at, hal::image::Layout::DepthStencilAttachmentOptimal)
+                }),
+            };
+
+            let vertex = {
+                let entry_point_name =
+                    unsafe { ffi::CStr::from_ptr(desc.vertex_stage.entry_point) }
+                        .to_str()
+                        .to_owned()
+                        .unwrap();
+
+                let shader_module =
+                    shader_module_guard
+                        .get(desc.vertex_stage.module)
+                        .map_err(|_| {
+                            pipeline::RenderPipelineError::InvalidShaderModule(
+                                desc.vertex_stage.module,
+                            )
+                        })?;
+
+                if let Some(ref module) = shader_module.module {
+                    let flag = wgt::ShaderStage::VERTEX;
+                    interface = validation::check_stage(
+                        module,
+                        &group_layouts,
+                        &layout.push_constant_ranges,
+                        entry_point_name,
+                        ExecutionModel::Vertex,
+                        interface,
+                    )
+                    .map_err(|error| pipeline::RenderPipelineError::Stage { flag, error })?;
+                    validated_stages |= flag;
+                }
+
+                hal::pso::EntryPoint::<B> {
+                    entry: entry_point_name, // TODO
+                    module: &shader_module.raw,
+                    specialization: hal::pso::Specialization::EMPTY,
+                }
+            };
+
+            let fragment = match unsafe { desc.fragment_stage.as_ref() } {
+                Some(stage) => {
+                    let entry_point_name = unsafe { ffi::CStr::from_ptr(stage.entry_point) }
+                        .to_str()
+                        .to_owned()
+                        .unwrap();
+
+                    let shader_module = shader_module_guard.get(stage.module).map_err(|_| {
+                        pipeline::RenderPipelineError::InvalidShaderModule(stage.module)
+                    })?;
+
+                    if validated_stages == wgt::ShaderStage::VERTEX {
+                        if let Some(ref module) = shader_module.module {
+                            let flag = wgt::ShaderStage::FRAGMENT;
+                            interface = validation::check_stage(
+                                module,
+                                &group_layouts,
+                                &layout.push_constant_ranges,
+                                entry_point_name,
+                                ExecutionModel::Fragment,
+                                interface,
+                            )
+                            .map_err(|error| {
+                                pipeline::RenderPipelineError::Stage { flag, error }
+                            })?;
+                            validated_stages |= flag;
+                        }
+                    }
+
+                    Some(hal::pso::EntryPoint::<B> {
+                        entry: entry_point_name,
+                        module: &shader_module.raw,
+                        specialization: hal::pso::Specialization::EMPTY,
+                    })
+                }
+                None => None,
+            };
+
+            if validated_stages.contains(wgt::ShaderStage::FRAGMENT) {
+                for (i, state) in color_states.iter().enumerate() {
+                    let output = &interface[&(i as wgt::ShaderLocation)];
+                    if !validation::check_texture_format(state.format, output) {
+                        log::warn!(
+                            "Incompatible fragment output[{}]. Shader: {:?}. Expected: {:?}",
+                            i,
+                            state.format,
+                            &**output
+                        );
+                        return Err(pipeline::RenderPipelineError::IncompatibleOutputFormat {
+                            index: i as u8,
+                        });
+                    }
+                }
+            }
+
+            let shaders = hal::pso::GraphicsShaderSet {
+                vertex,
+                hull: None,
+                domain: None,
+                geometry: None,
+                fragment,
+            };
+
+            // TODO
+            let flags = hal::pso::PipelineCreationFlags::empty();
+
+            let mut render_pass_cache = self.render_passes.lock();
+            let pipeline_desc = hal::pso::GraphicsPipelineDesc {
+                shaders,
+                rasterizer,
+                vertex_buffers,
+                attributes,
+                input_assembler,
+                blender,
+                depth_stencil,
+                multisampling,
+                baked_states,
+                layout: &layout.raw,
+                subpass: hal::pass::Subpass {
+                    index: 0,
+                    main_pass: match render_pass_cache.entry(rp_key) {
+                        Entry::Occupied(e) => e.into_mut(),
+                        Entry::Vacant(e) => {
+                            let pass = self.create_compatible_render_pass(e.key());
+                            e.insert(pass)
+                        }
+                    },
+                },
+                flags,
+                parent: hal::pso::BasePipeline::None,
+            };
+            let pipeline = unsafe {
+                self.raw
+                    .create_graphics_pipeline(&pipeline_desc, cache)
+                    .unwrap()
+            };
+
+            (pipeline, layout.life_guard.add_ref())
+        };
+
+        let pass_context = RenderPassContext {
+            attachments: AttachmentData {
+                colors: color_states.iter().map(|state| state.format).collect(),
+                resolves: ArrayVec::new(),
+                depth_stencil: depth_stencil_state.map(|state| state.format),
+            },
+            sample_count: samples,
+        };
+
+        let mut flags = pipeline::PipelineFlags::empty();
+        for state in color_states {
+            if state.color_blend.uses_color() | state.alpha_blend.uses_color() {
+                flags |= pipeline::PipelineFlags::BLEND_COLOR;
+            }
+        }
+        if let Some(ds) = depth_stencil_state {
+            if ds.needs_stencil_reference() {
+                flags |= pipeline::PipelineFlags::STENCIL_REFERENCE;
+            }
+            if ds.is_read_only() {
+                flags |= pipeline::PipelineFlags::DEPTH_STENCIL_READ_ONLY;
+            }
+        }
+
+        Ok(pipeline::RenderPipeline {
+            raw: raw_pipeline,
+            layout_id: Stored {
+                value: desc.layout,
+                ref_count: layout_ref_count,
+            },
+            device_id: Stored {
+                value: self_id,
+                ref_count: self.life_guard.add_ref(),
+            },
+            pass_context,
+            flags,
+            index_format: desc.vertex_state.index_format,
+            vertex_strides,
+            life_guard: LifeGuard::new(),
+        })
+    }
+
+    fn create_compute_pipeline<G: GlobalIdentityHandlerFactory>(
+        &self,
+        self_id: id::DeviceId,
+        desc: &pipeline::ComputePipelineDescriptor,
+        hub: &Hub<B, G>,
+        token: &mut Token<Self>,
+    ) -> Result<pipeline::ComputePipeline<B>, pipeline::ComputePipelineError> {
+        let (raw_pipeline, layout_ref_count) = {
+            let (pipeline_layout_guard, mut token) = hub.pipeline_layouts.read(token);
+            let (bgl_guard, mut token) = hub.bind_group_layouts.read(&mut token);
+            let layout = pipeline_layout_guard
+                .get(desc.layout)
+                .map_err(|_| pipeline::ComputePipelineError::InvalidLayout(desc.layout))?;
+            let group_layouts = layout
+                .bind_group_layout_ids
+                .iter()
+                .map(|id| &bgl_guard[id.value].entries)
+                .collect::<ArrayVec<[&binding_model::BindEntryMap; MAX_BIND_GROUPS]>>();
+
+            let interface = validation::StageInterface::default();
+            let pipeline_stage = &desc.compute_stage;
+            let (shader_module_guard, mut token) = hub.shader_modules.read(&mut token);
+            let (pipeline_cache_guard, _) = hub.pipeline_caches.read(&mut token);
+            let cache = match desc.cache {
+                Some(cache_id) => Some(
+                    &pipeline_cache_guard
+                        .get(cache_id)
+                        .map_err(|_| {
+                            pipeline::ComputePipelineError::InvalidPipelineCache(cache_id)
+                        })?
+                        .raw,
+                ),
+                None => self.pipeline_cache.as_ref(),
+            };
+
+            let entry_point_name = unsafe { ffi::CStr::from_ptr(pipeline_stage.entry_point) }
+                .to_str()
+                .to_owned()
+                .unwrap();
+
+            let shader_module = shader_module_guard
+                .get(pipeline_stage.module)
+                .map_err(|_| {
+                    pipeline::ComputePipelineError::InvalidShaderModule(pipeline_stage.module)
+                })?;
+
+            if let Some(ref module) = shader_module.module {
+                let _ = validation::check_stage(
+                    module,
+                    &group_layouts,
+                    &layout.push_constant_ranges,
+                    entry_point_name,
+                    ExecutionModel::GLCompute,
+                    interface,
+                )
+                .map_err(pipeline::ComputePipelineError::Stage)?;
+            }
+
+            let shader = hal::pso::EntryPoint::<B> {
+                entry: entry_point_name, // TODO
+                module: &shader_module.raw,
+                specialization: hal::pso::Specialization::EMPTY,
+            };
+
+            // TODO
+            let flags = hal::pso::PipelineCreationFlags::empty();
+            // TODO
+            let parent = hal::pso::BasePipeline::None;
+
+            let pipeline_desc = hal::pso::ComputePipelineDesc {
+                shader,
+                layout: &layout.raw,
+                flags,
+                parent,
+            };
+
+            let pipeline = unsafe {
+                self.raw
+                    .create_compute_pipeline(&pipeline_desc, cache)
+                    .unwrap()
+            };
+            (pipeline, layout.life_guard.add_ref())
+        };
+
+        Ok(pipeline::ComputePipeline {
+            raw: raw_pipeline,
+            layout_id: Stored {
+                value: desc.layout,
+                ref_count: layout_ref_count,
+            },
+            device_id: Stored {
+                value: self_id,
+                ref_count: self.life_guard.add_ref(),
+            },
+            life_guard: LifeGuard::new(),
+        })
+    }
+
+    fn validate_push_constant_ranges(
+        &self,
+        ranges: &[wgt::PushConstantRange],
+    ) -> Result<(), binding_model::PipelineLayoutError> {
+        if !ranges.is_empty() && !self.extensions.contains(wgt::Extensions::PUSH_CONSTANTS) {
+            return Err(binding_model::PipelineLayoutError::MissingExtension(
+                wgt::Extensions::PUSH_CONSTANTS,
+            ));
+        }
+        let mut used_stages = wgt::ShaderStage::NONE;
+        for (index, pc) in ranges.iter().enumerate() {
+            if pc.stages.intersects(used_stages) {
+                return Err(
+                    binding_model::PipelineLayoutError::MoreThanOnePushConstantRangePerStage {
+                        index,
+                        provided: pc.stages,
+                        intersected: pc.stages & used_stages,
+                    },
+                );
+            }
+            used_stages |= pc.stages;
+
+            if pc.range.start >= pc.range.end {
+                return Err(
+                    binding_model::PipelineLayoutError::InvalidPushConstantRange {
+                        index,
+                        range: pc.range.clone(),
+                    },
+                );
+            }
+            if pc.range.end > self.limits.max_push_constant_size {
+                return Err(
+                    binding_model::PipelineLayoutError::PushConstantRangeTooLarge {
+                        index,
+                        range: pc.range.clone(),
+                        max: self.limits.max_push_constant_size,
+                    },
+                );
+            }
+            for &bound in &[pc.range.start, pc.range.end] {
+                if bound % wgt::PUSH_CONSTANT_ALIGNMENT != 0 {
+                    return Err(
+                        binding_model::PipelineLayoutError::MisalignedPushConstantRange {
+                            index,
+                            bound,
+                        },
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Create a compatible render pass with a given key.
+    ///
+    /// This functions doesn't consider the following aspects for compatibility:
+    ///  - image layouts
+    ///  - resolve attachments
+    fn create_compatible_render_pass(&self, key: &RenderPassKey) -> B::RenderPass {
+        let mut color_ids = [(0, hal::image::Layout::ColorAttachmentOptimal); MAX_COLOR_TARGETS];
+        for i in 0..key.colors.len() {
+            color_ids[i].0 = i;
+        }
+        let depth_id = key.depth_stencil.as_ref().map(|_| {
+            (
+                key.colors.len(),
+                hal::image::Layout::DepthStencilAttachmentOptimal,
+            )
+        });
+
+        let subpass = hal::pass::SubpassDesc {
+            colors: &color_ids[..key.colors.len()],
+            depth_stencil: depth_id.as_ref(),
+            inputs: &[],
+            resolves: &[],
+            preserves: &[],
+        };
+        let all = key.all().map(|(at, _)| at);
+
+        unsafe {
+            self.raw
+                .create_render_pass(all, iter::once(subpass), &[])
+                .unwrap()
+        }
+    }
+}
+
+impl<B: hal::Backend> Device<B> {
+    pub(crate) fn destroy_bind_group(&self, bind_group: binding_model::BindGroup<B>) {
+        unsafe {
+            self.desc_allocator.lock().free(iter::once(bind_group.raw));
+        }
+    }
+
+    pub(crate) fn destroy_buffer(&self, buffer: resource::Buffer<B>) {
+        unsafe {
+            self.mem_allocator.lock().free(&self.raw, buffer.memory);
+            self.raw.destroy_buffer(buffer.raw);
+        }
+    }
+
+    pub(crate) fn destroy_texture(&self, texture: resource::Texture<B>) {
+        unsafe {
+            self.mem_allocator.lock().free(&self.raw, texture.memory);
+            self.raw.destroy_image(texture.raw);
+        }
+    }
+
+    /// Wait for idle and remove resources that we can, before we die.
+    pub(crate) fn prepare_to_die(&mut self) {
+        let mut life_tracker = self.life_tracker.lock();
+        if life_tracker.triage_submissions(&self.raw, true).is_err() {
+            life_tracker.retire_all(&self.raw);
+        }
+        life_tracker.cleanup(&self.raw, &self.mem_allocator, &self.desc_allocator);
+    }
+
+    pub(crate) fn dispose(self) {
         let mut desc_alloc = self.desc_allocator.into_inner();
         let mut mem_alloc = self.mem_allocator.into_inner();
         self.pending_writes
@@ -627,6 +2089,9 @@ impl<B: hal::Backend> Device<B> {
             for (_, fbo) in self.framebuffers.lock().drain() {
                 self.raw.destroy_framebuffer(fbo);
             }
+            if let Some(cache) = self.pipeline_cache {
+                self.raw.destroy_pipeline_cache(cache);
+            }
         }
     }
 }
@@ -659,91 +2124,203 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         device.capabilities
     }
 
+    /// Retrieve the contents of the device pipeline cache, to be passed to
+    /// `adapter_request_device` on the next run.
+    pub fn device_get_pipeline_cache_data<B: GfxBackend>(
+        &self,
+        device_id: id::DeviceId,
+    ) -> Result<Vec<u8>, pipeline::PipelineCacheError> {
+        let hub = B::hub(self);
+        let mut token = Token::root();
+        let (device_guard, _) = hub.devices.read(&mut token);
+        let device = &device_guard[device_id];
+
+        match device.pipeline_cache {
+            Some(ref cache) => unsafe { device.raw.get_pipeline_cache_data(cache) }.map_err(|_| {
+                device.capture_oom(
+                    ErrorContext::new("device_get_pipeline_cache_data"),
+                    pipeline::PipelineCacheError::OutOfMemory,
+                )
+            }),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    pub fn device_push_error_scope<B: GfxBackend>(
+        &self,
+        device_id: id::DeviceId,
+        filter: ErrorFilter,
+    ) {
+        let hub = B::hub(self);
+        let mut token = Token::root();
+        let (device_guard, _) = hub.devices.read(&mut token);
+        let device = &device_guard[device_id];
+
+        device.error_scopes.lock().push(filter);
+    }
+
+    pub fn device_pop_error_scope<B: GfxBackend>(
+        &self,
+        device_id: id::DeviceId,
+    ) -> Result<Option<GpuError>, PopErrorScopeError> {
+        let hub = B::hub(self);
+        let mut token = Token::root();
+        let (device_guard, _) = hub.devices.read(&mut token);
+        let device = &device_guard[device_id];
+
+        device.error_scopes.lock().pop()
+    }
+
+    /// Set the handler of the errors that are not captured by any error scope.
+    ///
+    /// The handler is called with nothing locked, when the device is polled
+    /// or the queue is submitted to.
+    pub fn device_set_uncaptured_error_handler<B: GfxBackend>(
+        &self,
+        device_id: id::DeviceId,
+        callback: UncapturedErrorCallback,
+        user_data: *mut u8,
+    ) {
+        let hub = B::hub(self);
+        let mut token = Token::root();
+        let (device_guard, _) = hub.devices.read(&mut token);
+        let device = &device_guard[device_id];
+
+        *device.uncaptured_error_handler.lock() = Some(UncapturedErrorHandler {
+            callback,
+            user_data,
+        });
+    }
+
     pub fn device_create_buffer<B: GfxBackend>(
         &self,
         device_id: id::DeviceId,
         desc: &wgt::BufferDescriptor<Label>,
         id_in: Input<G, id::BufferId>,
-    ) -> id::BufferId {
+    ) -> (id::BufferId, Option<resource::CreateBufferError>) {
         let hub = B::hub(self);
+        let context = ErrorContext::with_raw_label("device_create_buffer", &desc.label);
         let mut token = Token::root();
 
         log::info!("Create buffer {:?} with ID {:?}", desc, id_in);
 
         let (device_guard, mut token) = hub.devices.read(&mut token);
-        let device = &device_guard[device_id];
-        let mut buffer = device.create_buffer(device_id, desc, gfx_memory::Kind::General);
-        let ref_count = buffer.life_guard.add_ref();
-
-        let buffer_use = if !desc.mapped_at_creation {
-            resource::BufferUse::EMPTY
-        } else if desc.usage.contains(wgt::BufferUsage::MAP_WRITE) {
-            // buffer is mappable, so we are just doing that at start
-            match map_buffer(
-                &device.raw,
-                &mut buffer,
-                hal::buffer::SubRange::WHOLE,
-                HostMap::Write,
-            ) {
-                Ok(ptr) => {
-                    buffer.map_state = resource::BufferMapState::Active {
-                        ptr,
-                        sub_range: hal::buffer::SubRange::WHOLE,
-                        host: HostMap::Write,
-                    };
+        let error = loop {
+            let device = &device_guard[device_id];
+            if device.is_lost() {
+                break resource::CreateBufferError::DeviceLost;
+            }
+            let mut buffer = match device.create_buffer(device_id, desc, gfx_memory::Kind::General)
+            {
+                Ok(buffer) => buffer,
+                Err(e @ resource::CreateBufferError::OutOfMemory) => {
+                    break device.capture_oom(context, e)
                 }
-                Err(e) => {
-                    log::error!("failed to create buffer in a mapped state: {:?}", e);
+                Err(e) => break device.capture(context, e),
+            };
+
+            let buffer_use = if !desc.mapped_at_creation {
+                resource::BufferUse::EMPTY
+            } else if desc.usage.contains(wgt::BufferUsage::MAP_WRITE) {
+                // buffer is mappable, so we are just doing that at start
+                match map_buffer(
+                    &device.raw,
+                    &mut buffer,
+                    hal::buffer::SubRange::WHOLE,
+                    HostMap::Write,
+                ) {
+                    Ok(ptr) => {
+                        buffer.map_state = resource::BufferMapState::Active {
+                            ptr,
+                            sub_range: hal::buffer::SubRange::WHOLE,
+                            host: HostMap::Write,
+                        };
+                    }
+                    Err(hal::device::MapError::OutOfMemory(_)) => {
+                        device.destroy_buffer(buffer);
+                        break device
+                            .capture_oom(context, resource::CreateBufferError::OutOfMemory);
+                    }
+                    Err(e) => {
+                        log::error!("failed to create buffer in a mapped state: {:?}", e);
+                        device.destroy_buffer(buffer);
+                        break device.capture(context, resource::CreateBufferError::MapFailed);
+                    }
+                };
+                resource::BufferUse::MAP_WRITE
+            } else {
+                // buffer needs staging area for initialization only
+                let mut stage = match device.create_buffer(
+                    device_id,
+                    &wgt::BufferDescriptor {
+                        label: b"<init_buffer>\0".as_ptr() as *const _,
+                        size: desc.size,
+                        usage: wgt::BufferUsage::MAP_WRITE | wgt::BufferUsage::COPY_SRC,
+                        mapped_at_creation: false,
+                    },
+                    gfx_memory::Kind::Linear,
+                ) {
+                    Ok(stage) => stage,
+                    Err(e @ resource::CreateBufferError::OutOfMemory) => {
+                        device.destroy_buffer(buffer);
+                        break device.capture_oom(context, e);
+                    }
+                    Err(e) => {
+                        device.destroy_buffer(buffer);
+                        break device.capture(context, e);
+                    }
+                };
+                let mapping = stage
+                    .memory
+                    .map(&device.raw, hal::memory::Segment::ALL)
+                    .map(|mapped| mapped.ptr());
+                let ptr = match mapping {
+                    Ok(ptr) => ptr,
+                    Err(e) => {
+                        device.destroy_buffer(stage);
+                        device.destroy_buffer(buffer);
+                        break match e {
+                            hal::device::MapError::OutOfMemory(_) => device
+                                .capture_oom(context, resource::CreateBufferError::OutOfMemory),
+                            _ => device.capture(context, resource::CreateBufferError::MapFailed),
+                        };
+                    }
+                };
+                // the whole buffer is copied from the staging area on unmap
+                if !buffer.initialization_status.drain(0..desc.size).is_empty() {
+                    unsafe { ptr::write_bytes(ptr.as_ptr(), 0, desc.size as usize) };
                 }
+                buffer.map_state = resource::BufferMapState::Init {
+                    ptr,
+                    stage_buffer: stage.raw,
+                    stage_memory: stage.memory,
+                };
+                resource::BufferUse::COPY_DST
             };
-            resource::BufferUse::MAP_WRITE
-        } else {
-            // buffer needs staging area for initialization only
-            let mut stage = device.create_buffer(
-                device_id,
-                &wgt::BufferDescriptor {
-                    label: b"<init_buffer>\0".as_ptr() as *const _,
-                    size: desc.size,
-                    usage: wgt::BufferUsage::MAP_WRITE | wgt::BufferUsage::COPY_SRC,
-                    mapped_at_creation: false,
-                },
-                gfx_memory::Kind::Linear,
-            );
-            let ptr = stage
-                .memory
-                .map(&device.raw, hal::memory::Segment::ALL)
-                .unwrap()
-                .ptr();
-            buffer.map_state = resource::BufferMapState::Init {
-                ptr,
-                stage_buffer: stage.raw,
-                stage_memory: stage.memory,
+
+            let ref_count = buffer.life_guard.add_ref();
+            let id = hub.buffers.register_identity(id_in, buffer, &mut token);
+            log::info!("Created buffer {:?} with {:?}", id, desc);
+            #[cfg(feature = "trace")]
+            match device.trace {
+                Some(ref trace) => trace.lock().add(trace::Action::CreateBuffer {
+                    id,
+                    desc: desc.map_label(own_label),
+                }),
+                None => (),
             };
-            resource::BufferUse::COPY_DST
-        };
 
-        let id = hub.buffers.register_identity(id_in, buffer, &mut token);
-        log::info!("Created buffer {:?} with {:?}", id, desc);
-        #[cfg(feature = "trace")]
-        match device.trace {
-            Some(ref trace) => {
-                let mut desc = desc.map_label(own_label);
-                let mapped_at_creation = mem::replace(&mut desc.mapped_at_creation, false);
-                if mapped_at_creation && !desc.usage.contains(wgt::BufferUsage::MAP_WRITE) {
-                    desc.usage |= wgt::BufferUsage::COPY_DST;
-                }
-                trace.lock().add(trace::Action::CreateBuffer { id, desc })
-            }
-            None => (),
+            device
+                .trackers
+                .lock()
+                .buffers
+                .init(id, ref_count, BufferState::with_usage(buffer_use))
+                .unwrap();
+            return (id, None);
         };
 
-        device
-            .trackers
-            .lock()
-            .buffers
-            .init(id, ref_count, BufferState::with_usage(buffer_use))
-            .unwrap();
-        id
+        let id = hub.buffers.register_error(id_in, &mut token);
+        (id, Some(error))
     }
 
     #[cfg(feature = "replay")]
@@ -757,10 +2334,11 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         let (device_guard, mut token) = hub.devices.read(&mut token);
         let last_submission = {
             let (buffer_guard, _) = hub.buffers.write(&mut token);
-            buffer_guard[buffer_id]
-                .life_guard
-                .submission_index
-                .load(Ordering::Acquire)
+            match buffer_guard.get(buffer_id) {
+                Ok(buffer) => buffer.life_guard.submission_index.load(Ordering::Acquire),
+                // there is nothing to wait for on an invalid buffer
+                Err(_) => return,
+            }
         };
 
         let device = &device_guard[device_id];
@@ -770,9 +2348,12 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                 last_submission,
                 buffer_id
             );
-            device
+            if let Err(e) = device
                 .lock_life(&mut token)
-                .triage_submissions(&device.raw, true);
+                .triage_submissions(&device.raw, true)
+            {
+                log::error!("Unable to wait for buffer {:?}: {:?}", buffer_id, e);
+            }
         }
     }
 
@@ -782,19 +2363,32 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         buffer_id: id::BufferId,
         offset: BufferAddress,
         data: &[u8],
-    ) {
+    ) -> Result<(), resource::BufferAccessError> {
         let hub = B::hub(self);
+        let context = ErrorContext::new("device_set_buffer_sub_data");
         let mut token = Token::root();
 
         let (device_guard, mut token) = hub.devices.read(&mut token);
         let (mut buffer_guard, _) = hub.buffers.write(&mut token);
         let device = &device_guard[device_id];
-        let mut buffer = &mut buffer_guard[buffer_id];
-        assert!(
-            buffer.usage.contains(wgt::BufferUsage::MAP_WRITE),
-            "Buffer usage {:?} must contain usage flag MAP_WRITE",
-            buffer.usage
-        );
+        let mut buffer = match buffer_guard.get_mut(buffer_id) {
+            Ok(buffer) => buffer,
+            Err(_) => {
+                return Err(device.capture(
+                    context,
+                    resource::BufferAccessError::InvalidBuffer(buffer_id),
+                ))
+            }
+        };
+        if !buffer.usage.contains(wgt::BufferUsage::MAP_WRITE) {
+            return Err(device.capture(
+                context,
+                resource::BufferAccessError::MissingUsage {
+                    actual: buffer.usage,
+                    expected: wgt::BufferUsage::MAP_WRITE,
+                },
+            ));
+        }
         //assert!(buffer isn't used by the GPU);
 
         #[cfg(feature = "trace")]
@@ -826,11 +2420,12 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
             },
             Err(e) => {
                 log::error!("failed to map a buffer: {:?}", e);
-                return;
+                return Err(device.capture(context, resource::BufferAccessError::MapFailed));
             }
         }
 
         unmap_buffer(&device.raw, buffer);
+        Ok(())
     }
 
     pub fn device_get_buffer_sub_data<B: GfxBackend>(
@@ -839,19 +2434,32 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         buffer_id: id::BufferId,
         offset: BufferAddress,
         data: &mut [u8],
-    ) {
+    ) -> Result<(), resource::BufferAccessError> {
         let hub = B::hub(self);
+        let context = ErrorContext::new("device_get_buffer_sub_data");
         let mut token = Token::root();
 
         let (device_guard, mut token) = hub.devices.read(&mut token);
         let (mut buffer_guard, _) = hub.buffers.write(&mut token);
         let device = &device_guard[device_id];
-        let mut buffer = &mut buffer_guard[buffer_id];
-        assert!(
-            buffer.usage.contains(wgt::BufferUsage::MAP_READ),
-            "Buffer usage {:?} must contain usage flag MAP_READ",
-            buffer.usage
-        );
+        let mut buffer = match buffer_guard.get_mut(buffer_id) {
+            Ok(buffer) => buffer,
+            Err(_) => {
+                return Err(device.capture(
+                    context,
+                    resource::BufferAccessError::InvalidBuffer(buffer_id),
+                ))
+            }
+        };
+        if !buffer.usage.contains(wgt::BufferUsage::MAP_READ) {
+            return Err(device.capture(
+                context,
+                resource::BufferAccessError::MissingUsage {
+                    actual: buffer.usage,
+                    expected: wgt::BufferUsage::MAP_READ,
+                },
+            ));
+        }
         //assert!(buffer isn't used by the GPU);
 
         match map_buffer(
@@ -868,11 +2476,12 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
             },
             Err(e) => {
                 log::error!("failed to map a buffer: {:?}", e);
-                return;
+                return Err(device.capture(context, resource::BufferAccessError::MapFailed));
             }
         }
 
         unmap_buffer(&device.raw, buffer);
+        Ok(())
     }
 
     pub fn buffer_destroy<B: GfxBackend>(&self, buffer_id: id::BufferId) {
@@ -882,7 +2491,13 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         log::info!("Buffer {:?} is dropped", buffer_id);
         let device_id = {
             let (mut buffer_guard, _) = hub.buffers.write(&mut token);
-            let buffer = &mut buffer_guard[buffer_id];
+            let buffer = match buffer_guard.get_mut(buffer_id) {
+                Ok(buffer) => buffer,
+                Err(_) => {
+                    hub.buffers.unregister_locked(buffer_id, &mut *buffer_guard);
+                    return;
+                }
+            };
             buffer.life_guard.ref_count.take();
             buffer.device_id.value
         };
@@ -899,1471 +2514,1279 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         device_id: id::DeviceId,
         desc: &wgt::TextureDescriptor<Label>,
         id_in: Input<G, id::TextureId>,
-    ) -> id::TextureId {
-        let hub = B::hub(self);
-        let mut token = Token::root();
-
-        let (device_guard, mut token) = hub.devices.read(&mut token);
-        let device = &device_guard[device_id];
-        let texture = device.create_texture(device_id, desc);
-        let range = texture.full_range.clone();
-        let ref_count = texture.life_guard.add_ref();
-
-        let id = hub.textures.register_identity(id_in, texture, &mut token);
-        #[cfg(feature = "trace")]
-        match device.trace {
-            Some(ref trace) => trace.lock().add(trace::Action::CreateTexture {
-                id,
-                desc: desc.map_label(own_label),
-            }),
-            None => (),
-        };
-
-        device
-            .trackers
-            .lock()
-            .textures
-            .init(id, ref_count, TextureState::with_range(&range))
-            .unwrap();
-        id
-    }
-
-    pub fn texture_destroy<B: GfxBackend>(&self, texture_id: id::TextureId) {
-        let hub = B::hub(self);
-        let mut token = Token::root();
-
-        let device_id = {
-            let (mut texture_guard, _) = hub.textures.write(&mut token);
-            let texture = &mut texture_guard[texture_id];
-            texture.life_guard.ref_count.take();
-            texture.device_id.value
-        };
-
-        let (device_guard, mut token) = hub.devices.read(&mut token);
-        device_guard[device_id]
-            .lock_life(&mut token)
-            .future_suspected_textures
-            .push(texture_id);
-    }
-
-    pub fn texture_create_view<B: GfxBackend>(
-        &self,
-        texture_id: id::TextureId,
-        desc: Option<&wgt::TextureViewDescriptor<Label>>,
-        id_in: Input<G, id::TextureViewId>,
-    ) -> id::TextureViewId {
+    ) -> (id::TextureId, Option<resource::CreateTextureError>) {
         let hub = B::hub(self);
+        let context = ErrorContext::with_raw_label("device_create_texture", &desc.label);
         let mut token = Token::root();
 
         let (device_guard, mut token) = hub.devices.read(&mut token);
-        let (texture_guard, mut token) = hub.textures.read(&mut token);
-        let texture = &texture_guard[texture_id];
-        let device = &device_guard[texture.device_id.value];
-
-        let (format, view_kind, range) = match desc {
-            Some(desc) => {
-                let kind = conv::map_texture_view_dimension(desc.dimension);
-                let end_level = if desc.level_count == 0 {
-                    texture.full_range.levels.end
-                } else {
-                    (desc.base_mip_level + desc.level_count) as u8
-                };
-                let end_layer = if desc.array_layer_count == 0 {
-                    texture.full_range.layers.end
-                } else {
-                    (desc.base_array_layer + desc.array_layer_count) as u16
-                };
-                let range = hal::image::SubresourceRange {
-                    aspects: texture.full_range.aspects,
-                    levels: desc.base_mip_level as u8..end_level,
-                    layers: desc.base_array_layer as u16..end_layer,
-                };
-                (desc.format, kind, range)
-            }
-            None => {
-                let kind = match texture.kind {
-                    hal::image::Kind::D1(_, 1) => hal::image::ViewKind::D1,
-                    hal::image::Kind::D1(..) => hal::image::ViewKind::D1Array,
-                    hal::image::Kind::D2(_, _, 1, _) => hal::image::ViewKind::D2,
-                    hal::image::Kind::D2(..) => hal::image::ViewKind::D2Array,
-                    hal::image::Kind::D3(..) => hal::image::ViewKind::D3,
-                };
-                (texture.format, kind, texture.full_range.clone())
+        let error = loop {
+            let device = &device_guard[device_id];
+            if device.is_lost() {
+                break resource::CreateTextureError::DeviceLost;
             }
-        };
-
-        let raw = unsafe {
-            device
-                .raw
-                .create_image_view(
-                    &texture.raw,
-                    view_kind,
-                    conv::map_texture_format(format, device.private_features),
-                    hal::format::Swizzle::NO,
-                    range.clone(),
-                )
-                .unwrap()
-        };
-
-        let view = resource::TextureView {
-            inner: resource::TextureViewInner::Native {
-                raw,
-                source_id: Stored {
-                    value: texture_id,
-                    ref_count: texture.life_guard.add_ref(),
-                },
-            },
-            format: texture.format,
-            extent: texture.kind.extent().at_level(range.levels.start),
-            samples: texture.kind.num_samples(),
-            range,
-            life_guard: LifeGuard::new(),
-        };
-        let ref_count = view.life_guard.add_ref();
-
-        let id = hub.texture_views.register_identity(id_in, view, &mut token);
-        #[cfg(feature = "trace")]
-        match device.trace {
-            Some(ref trace) => trace.lock().add(trace::Action::CreateTextureView {
-                id,
-                parent_id: texture_id,
-                desc: desc.map(|d| d.map_label(own_label)),
-            }),
-            None => (),
-        };
-
-        device
-            .trackers
-            .lock()
-            .views
-            .init(id, ref_count, PhantomData)
-            .unwrap();
-        id
-    }
-
-    pub fn texture_view_destroy<B: GfxBackend>(&self, texture_view_id: id::TextureViewId) {
-        let hub = B::hub(self);
-        let mut token = Token::root();
-
-        let device_id = {
-            let (texture_guard, mut token) = hub.textures.read(&mut token);
-            let (mut texture_view_guard, _) = hub.texture_views.write(&mut token);
-
-            let view = &mut texture_view_guard[texture_view_id];
-            view.life_guard.ref_count.take();
-            match view.inner {
-                resource::TextureViewInner::Native { ref source_id, .. } => {
-                    texture_guard[source_id.value].device_id.value
-                }
-                resource::TextureViewInner::SwapChain { .. } => {
-                    panic!("Can't destroy a swap chain image")
+            let texture = match device.create_texture(device_id, desc) {
+                Ok(texture) => texture,
+                Err(e @ resource::CreateTextureError::OutOfMemory) => {
+                    break device.capture_oom(context, e)
                 }
-            }
-        };
-
-        let (device_guard, mut token) = hub.devices.read(&mut token);
-        device_guard[device_id]
-            .lock_life(&mut token)
-            .suspected_resources
-            .texture_views
-            .push(texture_view_id);
-    }
-
-    pub fn device_create_sampler<B: GfxBackend>(
-        &self,
-        device_id: id::DeviceId,
-        desc: &wgt::SamplerDescriptor<Label>,
-        id_in: Input<G, id::SamplerId>,
-    ) -> id::SamplerId {
-        let hub = B::hub(self);
-        let mut token = Token::root();
-        let (device_guard, mut token) = hub.devices.read(&mut token);
-        let device = &device_guard[device_id];
-
-        if let Some(clamp) = desc.anisotropy_clamp {
-            assert!(
-                device.extensions.contains(wgt::Extensions::ANISOTROPIC_FILTERING),
-                "Anisotropic clamp may only be used when the anisotropic filtering extension is enabled"
-            );
-            let valid_clamp = clamp <= MAX_ANISOTROPY && conv::is_power_of_two(clamp as u32);
-            assert!(
-                valid_clamp,
-                "Anisotropic clamp must be one of the values: 1, 2, 4, 8, or 16"
-            );
-        }
-
-        let info = hal::image::SamplerDesc {
-            min_filter: conv::map_filter(desc.min_filter),
-            mag_filter: conv::map_filter(desc.mag_filter),
-            mip_filter: conv::map_filter(desc.mipmap_filter),
-            wrap_mode: (
-                conv::map_wrap(desc.address_mode_u),
-                conv::map_wrap(desc.address_mode_v),
-                conv::map_wrap(desc.address_mode_w),
-            ),
-            lod_bias: hal::image::Lod(0.0),
-            lod_range: hal::image::Lod(desc.lod_min_clamp)..hal::image::Lod(desc.lod_max_clamp),
-            comparison: desc.compare.and_then(conv::map_compare_function),
-            border: hal::image::PackedColor(0),
-            normalized: true,
-            anisotropy_clamp: desc.anisotropy_clamp,
-        };
+                Err(e) => break device.capture(context, e),
+            };
+            let range = texture.full_range.clone();
+            let ref_count = texture.life_guard.add_ref();
 
-        let sampler = resource::Sampler {
-            raw: unsafe { device.raw.create_sampler(&info).unwrap() },
-            device_id: Stored {
-                value: device_id,
-                ref_count: device.life_guard.add_ref(),
-            },
-            life_guard: LifeGuard::new(),
-            comparison: info.comparison.is_some(),
-        };
-        let ref_count = sampler.life_guard.add_ref();
+            let id = hub.textures.register_identity(id_in, texture, &mut token);
+            #[cfg(feature = "trace")]
+            match device.trace {
+                Some(ref trace) => trace.lock().add(trace::Action::CreateTexture {
+                    id,
+                    desc: desc.map_label(own_label),
+                }),
+                None => (),
+            };
 
-        let id = hub.samplers.register_identity(id_in, sampler, &mut token);
-        #[cfg(feature = "trace")]
-        match device.trace {
-            Some(ref trace) => trace.lock().add(trace::Action::CreateSampler {
-                id,
-                desc: desc.map_label(own_label),
-            }),
-            None => (),
+            device
+                .trackers
+                .lock()
+                .textures
+                .init(id, ref_count, TextureState::with_range(&range))
+                .unwrap();
+            return (id, None);
         };
 
-        device
-            .trackers
-            .lock()
-            .samplers
-            .init(id, ref_count, PhantomData)
-            .unwrap();
-        id
+        let id = hub.textures.register_error(id_in, &mut token);
+        (id, Some(error))
     }
 
-    pub fn sampler_destroy<B: GfxBackend>(&self, sampler_id: id::SamplerId) {
+    pub fn texture_destroy<B: GfxBackend>(&self, texture_id: id::TextureId) {
         let hub = B::hub(self);
         let mut token = Token::root();
 
         let device_id = {
-            let (mut sampler_guard, _) = hub.samplers.write(&mut token);
-            let sampler = &mut sampler_guard[sampler_id];
-            sampler.life_guard.ref_count.take();
-            sampler.device_id.value
-        };
-
-        let (device_guard, mut token) = hub.devices.read(&mut token);
-        device_guard[device_id]
-            .lock_life(&mut token)
-            .suspected_resources
-            .samplers
-            .push(sampler_id);
-    }
-
-    pub fn device_create_bind_group_layout<B: GfxBackend>(
-        &self,
-        device_id: id::DeviceId,
-        desc: &wgt::BindGroupLayoutDescriptor,
-        id_in: Input<G, id::BindGroupLayoutId>,
-    ) -> Result<id::BindGroupLayoutId, binding_model::BindGroupLayoutError> {
-        let mut token = Token::root();
-        let hub = B::hub(self);
-        let mut entry_map = FastHashMap::default();
-        for entry in desc.bindings {
-            if entry_map.insert(entry.binding, entry.clone()).is_some() {
-                return Err(binding_model::BindGroupLayoutError::ConflictBinding(
-                    entry.binding,
-                ));
-            }
-        }
-
-        // TODO: deduplicate the bind group layouts at some level.
-        // We can't do it right here, because in the remote scenario
-        // the client need to know if the same ID can be used, or not.
-        if false {
-            let (bgl_guard, _) = hub.bind_group_layouts.read(&mut token);
-            let bind_group_layout_id = bgl_guard
-                .iter(device_id.backend())
-                .find(|(_, bgl)| bgl.entries == entry_map);
-
-            if let Some((id, _)) = bind_group_layout_id {
-                return Ok(id);
-            }
-        }
-
-        let (device_guard, mut token) = hub.devices.read(&mut token);
-        let device = &device_guard[device_id];
-
-        // Validate the count parameter
-        for binding in desc
-            .bindings
-            .iter()
-            .filter(|binding| binding.count.is_some())
-        {
-            if let Some(count) = binding.count {
-                if count == 0 {
-                    return Err(binding_model::BindGroupLayoutError::ZeroCount);
-                }
-                match binding.ty {
-                    wgt::BindingType::SampledTexture { .. } => {
-                        if !device
-                            .capabilities
-                            .contains(wgt::Capabilities::SAMPLED_TEXTURE_BINDING_ARRAY)
-                        {
-                            return Err(binding_model::BindGroupLayoutError::MissingCapability(
-                                wgt::Capabilities::SAMPLED_TEXTURE_BINDING_ARRAY,
-                            ));
-                        }
-                    }
-                    _ => return Err(binding_model::BindGroupLayoutError::ArrayUnsupported),
+            let (mut texture_guard, _) = hub.textures.write(&mut token);
+            let texture = match texture_guard.get_mut(texture_id) {
+                Ok(texture) => texture,
+                Err(_) => {
+                    hub.textures
+                        .unregister_locked(texture_id, &mut *texture_guard);
+                    return;
                 }
-            } else {
-                unreachable!() // programming bug
-            }
-        }
-
-        let raw_bindings = desc
-            .bindings
-            .iter()
-            .map(|binding| hal::pso::DescriptorSetLayoutBinding {
-                binding: binding.binding,
-                ty: conv::map_binding_type(binding),
-                count: binding
-                    .count
-                    .map_or(1, |v| v as hal::pso::DescriptorArrayIndex), //TODO: consolidate
-                stage_flags: conv::map_shader_stage_flags(binding.visibility),
-                immutable_samplers: false, // TODO
-            })
-            .collect::<Vec<_>>(); //TODO: avoid heap allocation
-
-        let raw = unsafe {
-            let mut raw_layout = device
-                .raw
-                .create_descriptor_set_layout(&raw_bindings, &[])
-                .unwrap();
-            if let Some(label) = desc.label {
-                device
-                    .raw
-                    .set_descriptor_set_layout_name(&mut raw_layout, label);
-            }
-            raw_layout
-        };
-
-        let layout = binding_model::BindGroupLayout {
-            raw,
-            device_id: Stored {
-                value: device_id,
-                ref_count: device.life_guard.add_ref(),
-            },
-            life_guard: LifeGuard::new(),
-            entries: entry_map,
-            desc_counts: raw_bindings.iter().cloned().collect(),
-            dynamic_count: desc
-                .bindings
-                .iter()
-                .filter(|b| b.has_dynamic_offset())
-                .count(),
-        };
-
-        let id = hub
-            .bind_group_layouts
-            .register_identity(id_in, layout, &mut token);
-        #[cfg(feature = "trace")]
-        match device.trace {
-            Some(ref trace) => trace.lock().add(trace::Action::CreateBindGroupLayout {
-                id,
-                label: desc.label.map_or_else(String::new, str::to_string),
-                entries: desc.bindings.to_owned(),
-            }),
-            None => (),
-        };
-        Ok(id)
-    }
-
-    pub fn bind_group_layout_destroy<B: GfxBackend>(
-        &self,
-        bind_group_layout_id: id::BindGroupLayoutId,
-    ) {
-        let hub = B::hub(self);
-        let mut token = Token::root();
-        let (device_id, ref_count) = {
-            let (mut bind_group_layout_guard, _) = hub.bind_group_layouts.write(&mut token);
-            let layout = &mut bind_group_layout_guard[bind_group_layout_id];
-            (
-                layout.device_id.value,
-                layout.life_guard.ref_count.take().unwrap(),
-            )
+            };
+            texture.life_guard.ref_count.take();
+            texture.device_id.value
         };
 
         let (device_guard, mut token) = hub.devices.read(&mut token);
         device_guard[device_id]
             .lock_life(&mut token)
-            .suspected_resources
-            .bind_group_layouts
-            .push(Stored {
-                value: bind_group_layout_id,
-                ref_count,
-            });
+            .future_suspected_textures
+            .push(texture_id);
     }
 
-    pub fn device_create_pipeline_layout<B: GfxBackend>(
+    pub fn texture_create_view<B: GfxBackend>(
         &self,
-        device_id: id::DeviceId,
-        desc: &binding_model::PipelineLayoutDescriptor,
-        id_in: Input<G, id::PipelineLayoutId>,
-    ) -> Result<id::PipelineLayoutId, binding_model::PipelineLayoutError> {
+        texture_id: id::TextureId,
+        desc: Option<&wgt::TextureViewDescriptor<Label>>,
+        id_in: Input<G, id::TextureViewId>,
+    ) -> (id::TextureViewId, Option<resource::CreateTextureViewError>) {
         let hub = B::hub(self);
+        let context = desc.map_or(ErrorContext::new("texture_create_view"), |desc| {
+            ErrorContext::with_raw_label("texture_create_view", &desc.label)
+        });
         let mut token = Token::root();
 
         let (device_guard, mut token) = hub.devices.read(&mut token);
-        let device = &device_guard[device_id];
-        let bind_group_layout_ids = unsafe {
-            slice::from_raw_parts(desc.bind_group_layouts, desc.bind_group_layouts_length)
-        };
+        let (texture_guard, mut token) = hub.textures.read(&mut token);
+        let error = loop {
+            let texture = match texture_guard.get(texture_id) {
+                Ok(texture) => texture,
+                Err(_) => break resource::CreateTextureViewError::InvalidTexture(texture_id),
+            };
+            let device = &device_guard[texture.device_id.value];
+            if device.is_lost() {
+                break resource::CreateTextureViewError::DeviceLost;
+            }
 
-        if desc.bind_group_layouts_length > (device.limits.max_bind_groups as usize) {
-            return Err(binding_model::PipelineLayoutError::TooManyGroups(
-                desc.bind_group_layouts_length,
-            ));
-        }
+            let (format, dimension, view_kind, range) = match desc {
+                Some(desc) => {
+                    let kind = conv::map_texture_view_dimension(desc.dimension);
+                    let required_texture_dimension = match desc.dimension {
+                        wgt::TextureViewDimension::D1 => TextureDimension::D1,
+                        wgt::TextureViewDimension::D2
+                        | wgt::TextureViewDimension::D2Array
+                        | wgt::TextureViewDimension::Cube
+                        | wgt::TextureViewDimension::CubeArray => TextureDimension::D2,
+                        wgt::TextureViewDimension::D3 => TextureDimension::D3,
+                    };
+                    if texture.dimension != required_texture_dimension {
+                        break device.capture(
+                            context,
+                            resource::CreateTextureViewError::InvalidTextureViewDimension {
+                                view: desc.dimension,
+                                texture: texture.dimension,
+                            },
+                        );
+                    }
+                    let level_end = texture.full_range.levels.end as u32;
+                    let end_level = if desc.level_count == 0 {
+                        Some(level_end)
+                    } else {
+                        desc.base_mip_level.checked_add(desc.level_count)
+                    };
+                    let end_level = match end_level {
+                        Some(end) if desc.base_mip_level < end && end <= level_end => end,
+                        _ => {
+                            break device.capture(
+                                context,
+                                resource::CreateTextureViewError::InvalidMipLevelRange {
+                                    base: desc.base_mip_level,
+                                    count: desc.level_count,
+                                    total: level_end,
+                                },
+                            )
+                        }
+                    };
+                    let layer_end = texture.full_range.layers.end as u32;
+                    let end_layer = if desc.array_layer_count == 0 {
+                        Some(layer_end)
+                    } else {
+                        desc.base_array_layer.checked_add(desc.array_layer_count)
+                    };
+                    let end_layer = match end_layer {
+                        Some(end) if desc.base_array_layer < end && end <= layer_end => end,
+                        _ => {
+                            break device.capture(
+                                context,
+                                resource::CreateTextureViewError::InvalidArrayLayerRange {
+                                    base: desc.base_array_layer,
+                                    count: desc.array_layer_count,
+                                    total: layer_end,
+                                },
+                            )
+                        }
+                    };
+                    let layer_count = end_layer - desc.base_array_layer;
+                    let valid_layer_count = match desc.dimension {
+                        wgt::TextureViewDimension::D1
+                        | wgt::TextureViewDimension::D2
+                        | wgt::TextureViewDimension::D3 => layer_count == 1,
+                        wgt::TextureViewDimension::D2Array => true,
+                        wgt::TextureViewDimension::Cube => layer_count == 6,
+                        wgt::TextureViewDimension::CubeArray => layer_count % 6 == 0,
+                    };
+                    if !valid_layer_count {
+                        break device.capture(
+                            context,
+                            resource::CreateTextureViewError::InvalidArrayLayerCount {
+                                dimension: desc.dimension,
+                                count: layer_count,
+                            },
+                        );
+                    }
+                    if let wgt::TextureViewDimension::Cube | wgt::TextureViewDimension::CubeArray =
+                        desc.dimension
+                    {
+                        let extent = texture.kind.extent();
+                        if extent.width != extent.height {
+                            break device.capture(
+                                context,
+                                resource::CreateTextureViewError::InvalidCubeExtent {
+                                    width: extent.width,
+                                    height: extent.height,
+                                },
+                            );
+                        }
+                    }
+                    let range = hal::image::SubresourceRange {
+                        aspects: texture.full_range.aspects,
+                        levels: desc.base_mip_level as u8..end_level as u8,
+                        layers: desc.base_array_layer as u16..end_layer as u16,
+                    };
+                    (desc.format, desc.dimension, kind, range)
+                }
+                None => {
+                    use wgt::TextureViewDimension as Tvd;
+                    let (dimension, kind) = match texture.kind {
+                        hal::image::Kind::D1(_, 1) => (Tvd::D1, hal::image::ViewKind::D1),
+                        hal::image::Kind::D1(..) => (Tvd::D1, hal::image::ViewKind::D1Array),
+                        hal::image::Kind::D2(_, _, 1, _) => (Tvd::D2, hal::image::ViewKind::D2),
+                        hal::image::Kind::D2(..) => (Tvd::D2Array, hal::image::ViewKind::D2Array),
+                        hal::image::Kind::D3(..) => (Tvd::D3, hal::image::ViewKind::D3),
+                    };
+                    (texture.format, dimension, kind, texture.full_range.clone())
+                }
+            };
 
-        // TODO: push constants
-        let pipeline_layout = {
-            let (bind_group_layout_guard, _) = hub.bind_group_layouts.read(&mut token);
-            let descriptor_set_layouts = bind_group_layout_ids
-                .iter()
-                .map(|&id| &bind_group_layout_guard[id].raw);
-            unsafe {
-                device
-                    .raw
-                    .create_pipeline_layout(descriptor_set_layouts, &[])
-            }
-            .unwrap()
-        };
+            let raw = match unsafe {
+                device.raw.create_image_view(
+                    &texture.raw,
+                    view_kind,
+                    conv::map_texture_format(format, device.private_features),
+                    hal::format::Swizzle::NO,
+                    range.clone(),
+                )
+            } {
+                Ok(raw) => raw,
+                Err(_) => {
+                    break device
+                        .capture_oom(context, resource::CreateTextureViewError::OutOfMemory)
+                }
+            };
 
-        let layout = binding_model::PipelineLayout {
-            raw: pipeline_layout,
-            device_id: Stored {
-                value: device_id,
-                ref_count: device.life_guard.add_ref(),
-            },
-            life_guard: LifeGuard::new(),
-            bind_group_layout_ids: {
-                let (bind_group_layout_guard, _) = hub.bind_group_layouts.read(&mut token);
-                bind_group_layout_ids
-                    .iter()
-                    .map(|&id| Stored {
-                        value: id,
-                        ref_count: bind_group_layout_guard[id].life_guard.add_ref(),
-                    })
-                    .collect()
-            },
-        };
+            let view = resource::TextureView {
+                inner: resource::TextureViewInner::Native {
+                    raw,
+                    source_id: Stored {
+                        value: texture_id,
+                        ref_count: texture.life_guard.add_ref(),
+                    },
+                },
+                format: texture.format,
+                dimension,
+                extent: texture.kind.extent().at_level(range.levels.start),
+                samples: texture.kind.num_samples(),
+                range,
+                life_guard: LifeGuard::new(),
+            };
+            let ref_count = view.life_guard.add_ref();
 
-        let id = hub
-            .pipeline_layouts
-            .register_identity(id_in, layout, &mut token);
-        #[cfg(feature = "trace")]
-        match device.trace {
-            Some(ref trace) => trace.lock().add(trace::Action::CreatePipelineLayout {
-                id,
-                bind_group_layouts: bind_group_layout_ids.to_owned(),
-            }),
-            None => (),
+            let id = hub.texture_views.register_identity(id_in, view, &mut token);
+            #[cfg(feature = "trace")]
+            match device.trace {
+                Some(ref trace) => trace.lock().add(trace::Action::CreateTextureView {
+                    id,
+                    parent_id: texture_id,
+                    desc: desc.map(|d| d.map_label(own_label)),
+                }),
+                None => (),
+            };
+
+            device
+                .trackers
+                .lock()
+                .views
+                .init(id, ref_count, PhantomData)
+                .unwrap();
+            return (id, None);
         };
-        Ok(id)
+
+        let id = hub.texture_views.register_error(id_in, &mut token);
+        (id, Some(error))
     }
 
-    pub fn pipeline_layout_destroy<B: GfxBackend>(&self, pipeline_layout_id: id::PipelineLayoutId) {
+    pub fn texture_view_destroy<B: GfxBackend>(&self, texture_view_id: id::TextureViewId) {
         let hub = B::hub(self);
         let mut token = Token::root();
-        let (device_id, ref_count) = {
-            let (mut pipeline_layout_guard, _) = hub.pipeline_layouts.write(&mut token);
-            let layout = &mut pipeline_layout_guard[pipeline_layout_id];
-            (
-                layout.device_id.value,
-                layout.life_guard.ref_count.take().unwrap(),
-            )
+
+        let device_id = {
+            let (texture_guard, mut token) = hub.textures.read(&mut token);
+            let (mut texture_view_guard, _) = hub.texture_views.write(&mut token);
+
+            let view = match texture_view_guard.get_mut(texture_view_id) {
+                Ok(view) => view,
+                Err(_) => {
+                    hub.texture_views
+                        .unregister_locked(texture_view_id, &mut *texture_view_guard);
+                    return;
+                }
+            };
+            view.life_guard.ref_count.take();
+            match view.inner {
+                resource::TextureViewInner::Native { ref source_id, .. } => {
+                    texture_guard[source_id.value].device_id.value
+                }
+                resource::TextureViewInner::SwapChain { .. } => {
+                    panic!("Can't destroy a swap chain image")
+                }
+            }
         };
 
         let (device_guard, mut token) = hub.devices.read(&mut token);
         device_guard[device_id]
             .lock_life(&mut token)
             .suspected_resources
-            .pipeline_layouts
-            .push(Stored {
-                value: pipeline_layout_id,
-                ref_count,
-            });
+            .texture_views
+            .push(texture_view_id);
     }
 
-    pub fn device_create_bind_group<B: GfxBackend>(
+    pub fn device_create_sampler<B: GfxBackend>(
         &self,
         device_id: id::DeviceId,
-        desc: &binding_model::BindGroupDescriptor,
-        id_in: Input<G, id::BindGroupId>,
-    ) -> Result<id::BindGroupId, BindGroupError> {
-        use crate::binding_model::BindingResource as Br;
-
+        desc: &wgt::SamplerDescriptor<Label>,
+        id_in: Input<G, id::SamplerId>,
+    ) -> (id::SamplerId, Option<resource::CreateSamplerError>) {
         let hub = B::hub(self);
+        let context = ErrorContext::with_raw_label("device_create_sampler", &desc.label);
         let mut token = Token::root();
-
         let (device_guard, mut token) = hub.devices.read(&mut token);
-        let device = &device_guard[device_id];
-        let (bind_group_layout_guard, mut token) = hub.bind_group_layouts.read(&mut token);
-        let bind_group_layout = &bind_group_layout_guard[desc.layout];
+        let error = loop {
+            let device = &device_guard[device_id];
+            if device.is_lost() {
+                break resource::CreateSamplerError::DeviceLost;
+            }
 
-        // Check that the number of bindings in the descriptor matches
-        // the number of bindings in the layout.
-        let actual = desc.bindings.len();
-        let expected = bind_group_layout.entries.len();
-        if actual != expected {
-            return Err(BindGroupError::BindingsNumMismatch { expected, actual });
-        }
+            if let Some(clamp) = desc.anisotropy_clamp {
+                if !device
+                    .extensions
+                    .contains(wgt::Extensions::ANISOTROPIC_FILTERING)
+                {
+                    break device.capture(
+                        context,
+                        resource::CreateSamplerError::MissingExtension(
+                            wgt::Extensions::ANISOTROPIC_FILTERING,
+                        ),
+                    );
+                }
+                let valid_clamp = clamp <= MAX_ANISOTROPY && conv::is_power_of_two(clamp as u32);
+                if !valid_clamp {
+                    break device.capture(
+                        context,
+                        resource::CreateSamplerError::InvalidAnisotropyClamp(clamp),
+                    );
+                }
+            }
+
+            let info = hal::image::SamplerDesc {
+                min_filter: conv::map_filter(desc.min_filter),
+                mag_filter: conv::map_filter(desc.mag_filter),
+                mip_filter: conv::map_filter(desc.mipmap_filter),
+                wrap_mode: (
+                    conv::map_wrap(desc.address_mode_u),
+                    conv::map_wrap(desc.address_mode_v),
+                    conv::map_wrap(desc.address_mode_w),
+                ),
+                lod_bias: hal::image::Lod(0.0),
+                lod_range: hal::image::Lod(desc.lod_min_clamp)..hal::image::Lod(desc.lod_max_clamp),
+                comparison: desc.compare.and_then(conv::map_compare_function),
+                border: hal::image::PackedColor(0),
+                normalized: true,
+                anisotropy_clamp: desc.anisotropy_clamp,
+            };
+
+            let raw = match unsafe { device.raw.create_sampler(&info) } {
+                Ok(raw) => raw,
+                Err(_) => {
+                    break device.capture_oom(context, resource::CreateSamplerError::OutOfMemory)
+                }
+            };
+            let sampler = resource::Sampler {
+                raw,
+                device_id: Stored {
+                    value: device_id,
+                    ref_count: device.life_guard.add_ref(),
+                },
+                life_guard: LifeGuard::new(),
+                comparison: info.comparison.is_some(),
+            };
+            let ref_count = sampler.life_guard.add_ref();
+
+            let id = hub.samplers.register_identity(id_in, sampler, &mut token);
+            #[cfg(feature = "trace")]
+            match device.trace {
+                Some(ref trace) => trace.lock().add(trace::Action::CreateSampler {
+                    id,
+                    desc: desc.map_label(own_label),
+                }),
+                None => (),
+            };
 
-        let mut desc_set = unsafe {
-            let mut desc_sets = ArrayVec::<[_; 1]>::new();
             device
-                .desc_allocator
+                .trackers
                 .lock()
-                .allocate(
-                    &device.raw,
-                    &bind_group_layout.raw,
-                    &bind_group_layout.desc_counts,
-                    1,
-                    &mut desc_sets,
-                )
+                .samplers
+                .init(id, ref_count, PhantomData)
                 .unwrap();
-            desc_sets.pop().unwrap()
+            return (id, None);
         };
 
-        // Set the descriptor set's label for easier debugging.
-        if let Some(label) = desc.label {
-            unsafe {
-                device
-                    .raw
-                    .set_descriptor_set_name(desc_set.raw_mut(), &label);
-            }
-        }
-
-        // Rebind `desc_set` as immutable
-        let desc_set = desc_set;
-
-        // fill out the descriptors
-        let mut used = TrackerSet::new(B::VARIANT);
-        {
-            let (buffer_guard, mut token) = hub.buffers.read(&mut token);
-            let (texture_guard, mut token) = hub.textures.read(&mut token); //skip token
-            let (texture_view_guard, mut token) = hub.texture_views.read(&mut token);
-            let (sampler_guard, _) = hub.samplers.read(&mut token);
-
-            //TODO: group writes into contiguous sections
-            let mut writes = Vec::new();
-            for b in desc.bindings {
-                let binding = b.binding;
-                // Find the corresponding declaration in the layout
-                let decl = bind_group_layout
-                    .entries
-                    .get(&binding)
-                    .ok_or(BindGroupError::MissingBindingDeclaration(binding))?;
-                let descriptors: SmallVec<[_; 1]> = match b.resource {
-                    Br::Buffer(ref bb) => {
-                        let (pub_usage, internal_use, min_size) = match decl.ty {
-                            wgt::BindingType::UniformBuffer {
-                                dynamic: _,
-                                min_binding_size,
-                            } => (
-                                wgt::BufferUsage::UNIFORM,
-                                resource::BufferUse::UNIFORM,
-                                min_binding_size,
-                            ),
-                            wgt::BindingType::StorageBuffer {
-                                dynamic: _,
-                                min_binding_size,
-                                readonly,
-                            } => (
-                                wgt::BufferUsage::STORAGE,
-                                if readonly {
-                                    resource::BufferUse::STORAGE_STORE
-                                } else {
-                                    resource::BufferUse::STORAGE_LOAD
-                                },
-                                min_binding_size,
-                            ),
-                            _ => {
-                                return Err(BindGroupError::WrongBindingType {
-                                    binding,
-                                    actual: decl.ty.clone(),
-                                    expected:
-                                        "UniformBuffer, StorageBuffer or ReadonlyStorageBuffer",
-                                })
-                            }
-                        };
-
-                        assert_eq!(
-                            bb.offset % BIND_BUFFER_ALIGNMENT,
-                            0,
-                            "Buffer offset {} must be a multiple of BIND_BUFFER_ALIGNMENT",
-                            bb.offset
-                        );
+        let id = hub.samplers.register_error(id_in, &mut token);
+        (id, Some(error))
+    }
 
-                        let buffer = used
-                            .buffers
-                            .use_extend(&*buffer_guard, bb.buffer_id, (), internal_use)
-                            .unwrap();
-                        assert!(
-                            buffer.usage.contains(pub_usage),
-                            "Buffer usage {:?} must contain usage flag(s) {:?}",
-                            buffer.usage,
-                            pub_usage
-                        );
-                        let bind_size = if bb.size == BufferSize::WHOLE {
-                            buffer.size - bb.offset
-                        } else {
-                            let end = bb.offset + bb.size.0;
-                            assert!(
-                                end <= buffer.size,
-                                "Bound buffer range {:?} does not fit in buffer size {}",
-                                bb.offset..end,
-                                buffer.size
-                            );
-                            bb.size.0
-                        };
-                        match min_size {
-                            Some(non_zero) if non_zero.get() > bind_size => panic!(
-                                "Minimum buffer binding size {} is not respected with size {}",
-                                non_zero.get(),
-                                bind_size
-                            ),
-                            _ => (),
-                        }
+    pub fn sampler_destroy<B: GfxBackend>(&self, sampler_id: id::SamplerId) {
+        let hub = B::hub(self);
+        let mut token = Token::root();
 
-                        let sub_range = hal::buffer::SubRange {
-                            offset: bb.offset,
-                            size: Some(bind_size),
-                        };
-                        SmallVec::from([hal::pso::Descriptor::Buffer(&buffer.raw, sub_range)])
-                    }
-                    Br::Sampler(id) => {
-                        match decl.ty {
-                            wgt::BindingType::Sampler { comparison } => {
-                                let sampler = used
-                                    .samplers
-                                    .use_extend(&*sampler_guard, id, (), ())
-                                    .unwrap();
+        let device_id = {
+            let (mut sampler_guard, _) = hub.samplers.write(&mut token);
+            let sampler = match sampler_guard.get_mut(sampler_id) {
+                Ok(sampler) => sampler,
+                Err(_) => {
+                    hub.samplers
+                        .unregister_locked(sampler_id, &mut *sampler_guard);
+                    return;
+                }
+            };
+            sampler.life_guard.ref_count.take();
+            sampler.device_id.value
+        };
 
-                                // Check the actual sampler to also (not) be a comparison sampler
-                                if sampler.comparison != comparison {
-                                    return Err(BindGroupError::WrongSamplerComparison);
-                                }
+        let (device_guard, mut token) = hub.devices.read(&mut token);
+        device_guard[device_id]
+            .lock_life(&mut token)
+            .suspected_resources
+            .samplers
+            .push(sampler_id);
+    }
 
-                                SmallVec::from([hal::pso::Descriptor::Sampler(&sampler.raw)])
-                            }
-                            _ => {
-                                return Err(BindGroupError::WrongBindingType {
-                                    binding,
-                                    actual: decl.ty.clone(),
-                                    expected: "Sampler",
-                                })
-                            }
-                        }
-                    }
-                    Br::TextureView(id) => {
-                        let view = used
-                            .views
-                            .use_extend(&*texture_view_guard, id, (), ())
-                            .unwrap();
-                        let (pub_usage, internal_use) = match decl.ty {
-                            wgt::BindingType::SampledTexture { .. } => (
-                                wgt::TextureUsage::SAMPLED,
-                                resource::TextureUse::SAMPLED,
-                            ),
-                            wgt::BindingType::StorageTexture { readonly, .. } => (
-                                wgt::TextureUsage::STORAGE,
-                                if readonly {
-                                    resource::TextureUse::STORAGE_LOAD
-                                } else {
-                                    resource::TextureUse::STORAGE_STORE
-                                },
+    pub fn device_create_query_set<B: GfxBackend>(
+        &self,
+        device_id: id::DeviceId,
+        desc: &wgt::QuerySetDescriptor<Label>,
+        id_in: Input<G, id::QuerySetId>,
+    ) -> (id::QuerySetId, Option<resource::CreateQuerySetError>) {
+        let hub = B::hub(self);
+        let context = ErrorContext::with_raw_label("device_create_query_set", &desc.label);
+        let mut token = Token::root();
+        let (device_guard, mut token) = hub.devices.read(&mut token);
+        let error = loop {
+            let device = &device_guard[device_id];
+            if device.is_lost() {
+                break resource::CreateQuerySetError::DeviceLost;
+            }
+
+            match desc.ty {
+                wgt::QueryType::Timestamp => {
+                    if !device.extensions.contains(wgt::Extensions::TIMESTAMP_QUERY) {
+                        break device.capture(
+                            context,
+                            resource::CreateQuerySetError::MissingExtension(
+                                wgt::Extensions::TIMESTAMP_QUERY,
                             ),
-                            _ => return Err(BindGroupError::WrongBindingType {
-                                binding,
-                                actual: decl.ty.clone(),
-                                expected: "SampledTexture, ReadonlyStorageTexture or WriteonlyStorageTexture"
-                            })
-                        };
-                        match view.inner {
-                            resource::TextureViewInner::Native {
-                                ref raw,
-                                ref source_id,
-                            } => {
-                                // Careful here: the texture may no longer have its own ref count,
-                                // if it was deleted by the user.
-                                let texture = &texture_guard[source_id.value];
-                                used.textures
-                                    .change_extend(
-                                        source_id.value,
-                                        &source_id.ref_count,
-                                        view.range.clone(),
-                                        internal_use,
-                                    )
-                                    .unwrap();
-                                assert!(
-                                    texture.usage.contains(pub_usage),
-                                    "Texture usage {:?} must contain usage flag(s) {:?}",
-                                    texture.usage,
-                                    pub_usage
-                                );
-                                let image_layout =
-                                    conv::map_texture_state(internal_use, view.range.aspects).1;
-                                SmallVec::from([hal::pso::Descriptor::Image(raw, image_layout)])
-                            }
-                            resource::TextureViewInner::SwapChain { .. } => {
-                                panic!("Unable to create a bind group with a swap chain image")
-                            }
-                        }
-                    }
-                    Br::TextureViewArray(ref bindings_array) => {
-                        assert!(
-                            device.capabilities.contains(wgt::Capabilities::SAMPLED_TEXTURE_BINDING_ARRAY),
-                            "Capability SAMPLED_TEXTURE_BINDING_ARRAY must be supported to use TextureViewArrays in a bind group"
                         );
-
-                        if let Some(count) = decl.count {
-                            assert_eq!(
-                                count as usize,
-                                bindings_array.len(),
-                                "Binding count declared with {} items, but {} items were provided",
-                                count,
-                                bindings_array.len()
-                            );
-                        } else {
-                            panic!(
-                                "Binding declared as a single item, but bind group is using it as an array",
-                            );
-                        }
-
-                        let (pub_usage, internal_use) = match decl.ty {
-                            wgt::BindingType::SampledTexture { .. } => {
-                                (wgt::TextureUsage::SAMPLED, resource::TextureUse::SAMPLED)
-                            }
-                            _ => {
-                                return Err(BindGroupError::WrongBindingType {
-                                    binding,
-                                    actual: decl.ty.clone(),
-                                    expected: "SampledTextureArray",
-                                })
-                            }
-                        };
-                        bindings_array
-                            .iter()
-                            .map(|&id| {
-                                let view = used
-                                    .views
-                                    .use_extend(&*texture_view_guard, id, (), ())
-                                    .unwrap();
-                                match view.inner {
-                                    resource::TextureViewInner::Native {
-                                        ref raw,
-                                        ref source_id,
-                                    } => {
-                                        // Careful here: the texture may no longer have its own ref count,
-                                        // if it was deleted by the user.
-                                        let texture = &texture_guard[source_id.value];
-                                        used.textures
-                                            .change_extend(
-                                                source_id.value,
-                                                &source_id.ref_count,
-                                                view.range.clone(),
-                                                internal_use,
-                                            )
-                                            .unwrap();
-                                        assert!(
-                                            texture.usage.contains(pub_usage),
-                                            "Texture usage {:?} must contain usage flag(s) {:?}",
-                                            texture.usage,
-                                            pub_usage
-                                        );
-                                        let image_layout = conv::map_texture_state(
-                                            internal_use,
-                                            view.range.aspects,
-                                        )
-                                        .1;
-                                        hal::pso::Descriptor::Image(raw, image_layout)
-                                    }
-                                    resource::TextureViewInner::SwapChain { .. } => panic!(
-                                        "Unable to create a bind group with a swap chain image"
-                                    ),
-                                }
-                            })
-                            .collect()
                     }
-                };
-                writes.alloc().init(hal::pso::DescriptorSetWrite {
-                    set: desc_set.raw(),
-                    binding: b.binding,
-                    array_offset: 0, //TODO
-                    descriptors,
-                });
+                }
+                wgt::QueryType::Occlusion => {}
             }
-
-            unsafe {
-                device.raw.write_descriptor_sets(writes);
+            if desc.count == 0 || desc.count > wgt::QUERY_SET_MAX_QUERIES {
+                break device.capture(
+                    context,
+                    resource::CreateQuerySetError::InvalidCount(desc.count),
+                );
             }
-        }
 
-        let bind_group = binding_model::BindGroup {
-            raw: desc_set,
-            device_id: Stored {
-                value: device_id,
-                ref_count: device.life_guard.add_ref(),
-            },
-            layout_id: desc.layout,
-            life_guard: LifeGuard::new(),
-            used,
-            dynamic_count: bind_group_layout.dynamic_count,
-        };
-        let ref_count = bind_group.life_guard.add_ref();
+            let raw = match unsafe {
+                device
+                    .raw
+                    .create_query_pool(conv::map_query_type(desc.ty), desc.count)
+            } {
+                Ok(raw) => raw,
+                Err(_) => {
+                    break device.capture_oom(context, resource::CreateQuerySetError::OutOfMemory)
+                }
+            };
+            let query_set = resource::QuerySet {
+                raw,
+                device_id: Stored {
+                    value: device_id,
+                    ref_count: device.life_guard.add_ref(),
+                },
+                life_guard: LifeGuard::new(),
+                ty: desc.ty,
+                count: desc.count,
+            };
+            let ref_count = query_set.life_guard.add_ref();
 
-        let id = hub
-            .bind_groups
-            .register_identity(id_in, bind_group, &mut token);
-        log::debug!(
-            "Bind group {:?} {:#?}",
-            id,
-            hub.bind_groups.read(&mut token).0[id].used
-        );
-        #[cfg(feature = "trace")]
-        match device.trace {
-            Some(ref trace) => trace.lock().add(trace::Action::CreateBindGroup {
-                id,
-                label: desc.label.map_or_else(String::new, str::to_string),
-                layout_id: desc.layout,
-                entries: desc
-                    .bindings
-                    .iter()
-                    .map(|entry| {
-                        let res = match entry.resource {
-                            Br::Buffer(ref binding) => trace::BindingResource::Buffer {
-                                id: binding.buffer_id,
-                                offset: binding.offset,
-                                size: binding.size,
-                            },
-                            Br::TextureView(id) => trace::BindingResource::TextureView(id),
-                            Br::Sampler(id) => trace::BindingResource::Sampler(id),
-                            Br::TextureViewArray(ref binding_array) => {
-                                trace::BindingResource::TextureViewArray(binding_array.to_vec())
-                            }
-                        };
-                        (entry.binding, res)
-                    })
-                    .collect(),
-            }),
-            None => (),
+            let id = hub
+                .query_sets
+                .register_identity(id_in, query_set, &mut token);
+            #[cfg(feature = "trace")]
+            match device.trace {
+                Some(ref trace) => trace.lock().add(trace::Action::CreateQuerySet {
+                    id,
+                    desc: desc.map_label(own_label),
+                }),
+                None => (),
+            };
+
+            device
+                .trackers
+                .lock()
+                .query_sets
+                .init(id, ref_count, PhantomData)
+                .unwrap();
+            return (id, None);
         };
 
-        device
-            .trackers
-            .lock()
-            .bind_groups
-            .init(id, ref_count, PhantomData)
-            .unwrap();
-        Ok(id)
+        let id = hub.query_sets.register_error(id_in, &mut token);
+        (id, Some(error))
     }
 
-    pub fn bind_group_destroy<B: GfxBackend>(&self, bind_group_id: id::BindGroupId) {
+    pub fn query_set_destroy<B: GfxBackend>(&self, query_set_id: id::QuerySetId) {
         let hub = B::hub(self);
         let mut token = Token::root();
 
         let device_id = {
-            let (mut bind_group_guard, _) = hub.bind_groups.write(&mut token);
-            let bind_group = &mut bind_group_guard[bind_group_id];
-            bind_group.life_guard.ref_count.take();
-            bind_group.device_id.value
+            let (mut query_set_guard, _) = hub.query_sets.write(&mut token);
+            let query_set = match query_set_guard.get_mut(query_set_id) {
+                Ok(query_set) => query_set,
+                Err(_) => {
+                    hub.query_sets
+                        .unregister_locked(query_set_id, &mut *query_set_guard);
+                    return;
+                }
+            };
+            query_set.life_guard.ref_count.take();
+            query_set.device_id.value
         };
 
         let (device_guard, mut token) = hub.devices.read(&mut token);
         device_guard[device_id]
             .lock_life(&mut token)
             .suspected_resources
-            .bind_groups
-            .push(bind_group_id);
+            .query_sets
+            .push(query_set_id);
     }
 
-    pub fn device_create_shader_module<B: GfxBackend>(
+    pub fn device_create_bind_group_layout<B: GfxBackend>(
         &self,
         device_id: id::DeviceId,
-        source: pipeline::ShaderModuleSource,
-        id_in: Input<G, id::ShaderModuleId>,
-    ) -> id::ShaderModuleId {
-        let hub = B::hub(self);
+        desc: &binding_model::BindGroupLayoutDescriptor,
+        id_in: Input<G, id::BindGroupLayoutId>,
+    ) -> (
+        id::BindGroupLayoutId,
+        Option<binding_model::BindGroupLayoutError>,
+    ) {
         let mut token = Token::root();
+        let hub = B::hub(self);
+        let context = ErrorContext::with_label("device_create_bind_group_layout", desc.label);
         let (device_guard, mut token) = hub.devices.read(&mut token);
-        let device = &device_guard[device_id];
-        let spv_owned;
-        let spv_flags = if cfg!(debug_assertions) {
-            naga::back::spv::WriterFlags::DEBUG
-        } else {
-            naga::back::spv::WriterFlags::empty()
-        };
-
-        let (spv, naga) = match source {
-            pipeline::ShaderModuleSource::SpirV(spv) => {
-                let module = if device.private_features.shader_validation {
-                    // Parse the given shader code and store its representation.
-                    let spv_iter = spv.into_iter().cloned();
-                    naga::front::spv::Parser::new(spv_iter)
-                        .parse()
-                        .map_err(|err| {
-                            log::warn!("Failed to parse shader SPIR-V code: {:?}", err);
-                            log::warn!("Shader module will not be validated");
-                        })
-                        .ok()
-                } else {
-                    None
-                };
-                (spv, module)
-            }
-            pipeline::ShaderModuleSource::Wgsl(code) => {
-                let module = naga::front::wgsl::parse_str(code).unwrap();
-                spv_owned = naga::back::spv::Writer::new(&module.header, spv_flags).write(&module);
-                (
-                    spv_owned.as_slice(),
-                    if device.private_features.shader_validation {
-                        Some(module)
-                    } else {
-                        None
-                    },
-                )
+        let error = loop {
+            let device = &device_guard[device_id];
+            if device.is_lost() {
+                break binding_model::BindGroupLayoutError::DeviceLost;
             }
-            pipeline::ShaderModuleSource::Naga(module) => {
-                spv_owned = naga::back::spv::Writer::new(&module.header, spv_flags).write(&module);
-                (
-                    spv_owned.as_slice(),
-                    if device.private_features.shader_validation {
-                        Some(module)
-                    } else {
-                        None
-                    },
-                )
+
+            let mut entry_map = FastHashMap::default();
+            if let Some(entry) = desc
+                .bindings
+                .iter()
+                .find(|entry| entry_map.insert(entry.binding, entry.clone()).is_some())
+            {
+                break device.capture(
+                    context,
+                    binding_model::BindGroupLayoutError::ConflictBinding(entry.binding),
+                );
+            }
+
+            // TODO: deduplicate the bind group layouts at some level.
+            // We can't do it right here, because in the remote scenario
+            // the client need to know if the same ID can be used, or not.
+            if false {
+                let (bgl_guard, _) = hub.bind_group_layouts.read(&mut token);
+                let bind_group_layout_id = bgl_guard
+                    .iter(device_id.backend())
+                    .find(|(_, bgl)| bgl.entries == entry_map);
+
+                if let Some((id, _)) = bind_group_layout_id {
+                    return (id, None);
+                }
             }
+
+            let layout = match device
+                .create_bind_group_layout(device_id, desc.label, entry_map, hub, &mut token)
+            {
+                Ok(layout) => layout,
+                Err(e) => break device.capture(context, e),
+            };
+
+            let id = hub
+                .bind_group_layouts
+                .register_identity(id_in, layout, &mut token);
+            #[cfg(feature = "trace")]
+            match device.trace {
+                Some(ref trace) => trace.lock().add(trace::Action::CreateBindGroupLayout {
+                    id,
+                    label: desc.label.map_or_else(String::new, str::to_string),
+                    entries: desc.bindings.to_owned(),
+                }),
+                None => (),
+            };
+            return (id, None);
         };
 
-        let shader = pipeline::ShaderModule {
-            raw: unsafe { device.raw.create_shader_module(spv).unwrap() },
-            device_id: Stored {
-                value: device_id,
-                ref_count: device.life_guard.add_ref(),
-            },
-            module: naga,
+        let id = hub.bind_group_layouts.register_error(id_in, &mut token);
+        (id, Some(error))
+    }
+
+    pub fn bind_group_layout_destroy<B: GfxBackend>(
+        &self,
+        bind_group_layout_id: id::BindGroupLayoutId,
+    ) {
+        let hub = B::hub(self);
+        let mut token = Token::root();
+        let (device_id, ref_count) = {
+            let (mut bind_group_layout_guard, _) = hub.bind_group_layouts.write(&mut token);
+            let layout = match bind_group_layout_guard.get_mut(bind_group_layout_id) {
+                Ok(layout) => layout,
+                Err(_) => {
+                    hub.bind_group_layouts
+                        .unregister_locked(bind_group_layout_id, &mut *bind_group_layout_guard);
+                    return;
+                }
+            };
+            (
+                layout.device_id.value,
+                layout.life_guard.ref_count.take().unwrap(),
+            )
         };
 
-        let id = hub
-            .shader_modules
-            .register_identity(id_in, shader, &mut token);
-        #[cfg(feature = "trace")]
-        match device.trace {
-            Some(ref trace) => {
-                let mut trace = trace.lock();
-                let data = trace.make_binary("spv", unsafe {
-                    slice::from_raw_parts(spv.as_ptr() as *const u8, spv.len() * 4)
-                });
-                trace.add(trace::Action::CreateShaderModule { id, data });
-            }
-            None => {}
+        let (device_guard, mut token) = hub.devices.read(&mut token);
+        device_guard[device_id]
+            .lock_life(&mut token)
+            .suspected_resources
+            .bind_group_layouts
+            .push(Stored {
+                value: bind_group_layout_id,
+                ref_count,
+            });
+    }
+
+    pub fn device_create_pipeline_layout<B: GfxBackend>(
+        &self,
+        device_id: id::DeviceId,
+        desc: &binding_model::PipelineLayoutDescriptor,
+        id_in: Input<G, id::PipelineLayoutId>,
+    ) -> (
+        id::PipelineLayoutId,
+        Option<binding_model::PipelineLayoutError>,
+    ) {
+        let hub = B::hub(self);
+        let context = ErrorContext::new("device_create_pipeline_layout");
+        let mut token = Token::root();
+
+        let (device_guard, mut token) = hub.devices.read(&mut token);
+        let error = loop {
+            let device = &device_guard[device_id];
+            if device.is_lost() {
+                break binding_model::PipelineLayoutError::DeviceLost;
+            }
+            let bind_group_layout_ids = unsafe {
+                slice::from_raw_parts(desc.bind_group_layouts, desc.bind_group_layouts_length)
+            };
+
+            if desc.bind_group_layouts_length > (device.limits.max_bind_groups as usize) {
+                break device.capture(
+                    context,
+                    binding_model::PipelineLayoutError::TooManyGroups(
+                        desc.bind_group_layouts_length,
+                    ),
+                );
+            }
+
+            let push_constant_ranges = if desc.push_constant_ranges_length == 0 {
+                &[][..]
+            } else {
+                unsafe {
+                    slice::from_raw_parts(
+                        desc.push_constant_ranges,
+                        desc.push_constant_ranges_length,
+                    )
+                }
+            };
+            if let Err(e) = device.validate_push_constant_ranges(push_constant_ranges) {
+                break device.capture(context, e);
+            }
+
+            let (pipeline_layout, stored_layouts) = {
+                let (bind_group_layout_guard, _) = hub.bind_group_layouts.read(&mut token);
+                let bind_group_layouts = match bind_group_layout_ids
+                    .iter()
+                    .map(|&id| bind_group_layout_guard.get(id).map_err(|_| id))
+                    .collect::<Result<Vec<_>, _>>()
+                {
+                    Ok(layouts) => layouts,
+                    Err(id) => {
+                        break device.capture(
+                            context,
+                            binding_model::PipelineLayoutError::InvalidBindGroupLayout(id),
+                        )
+                    }
+                };
+                let descriptor_set_layouts = bind_group_layouts.iter().map(|bgl| &bgl.raw);
+                let push_constants = push_constant_ranges
+                    .iter()
+                    .map(|pc| (conv::map_shader_stage_flags(pc.stages), pc.range.clone()));
+                let raw = unsafe {
+                    device
+                        .raw
+                        .create_pipeline_layout(descriptor_set_layouts, push_constants)
+                }
+                .unwrap();
+                let stored_layouts = bind_group_layout_ids
+                    .iter()
+                    .zip(bind_group_layouts)
+                    .map(|(&id, bgl)| Stored {
+                        value: id,
+                        ref_count: bgl.life_guard.add_ref(),
+                    })
+                    .collect();
+                (raw, stored_layouts)
+            };
+
+            let layout = binding_model::PipelineLayout {
+                raw: pipeline_layout,
+                device_id: Stored {
+                    value: device_id,
+                    ref_count: device.life_guard.add_ref(),
+                },
+                life_guard: LifeGuard::new(),
+                bind_group_layout_ids: stored_layouts,
+                push_constant_ranges: push_constant_ranges.iter().cloned().collect(),
+            };
+
+            let id = hub
+                .pipeline_layouts
+                .register_identity(id_in, layout, &mut token);
+            #[cfg(feature = "trace")]
+            match device.trace {
+                Some(ref trace) => trace.lock().add(trace::Action::CreatePipelineLayout {
+                    id,
+                    bind_group_layouts: bind_group_layout_ids.to_owned(),
+                    push_constant_ranges: push_constant_ranges.to_owned(),
+                }),
+                None => (),
+            };
+            return (id, None);
         };
-        id
+
+        let id = hub.pipeline_layouts.register_error(id_in, &mut token);
+        (id, Some(error))
     }
 
-    pub fn shader_module_destroy<B: GfxBackend>(&self, shader_module_id: id::ShaderModuleId) {
+    pub fn pipeline_layout_destroy<B: GfxBackend>(&self, pipeline_layout_id: id::PipelineLayoutId) {
         let hub = B::hub(self);
         let mut token = Token::root();
-        let (device_guard, mut token) = hub.devices.read(&mut token);
-        let (module, _) = hub.shader_modules.unregister(shader_module_id, &mut token);
-
-        let device = &device_guard[module.device_id.value];
-        #[cfg(feature = "trace")]
-        match device.trace {
-            Some(ref trace) => trace
-                .lock()
-                .add(trace::Action::DestroyShaderModule(shader_module_id)),
-            None => (),
+        let (device_id, ref_count) = {
+            let (mut pipeline_layout_guard, _) = hub.pipeline_layouts.write(&mut token);
+            let layout = match pipeline_layout_guard.get_mut(pipeline_layout_id) {
+                Ok(layout) => layout,
+                Err(_) => {
+                    hub.pipeline_layouts
+                        .unregister_locked(pipeline_layout_id, &mut *pipeline_layout_guard);
+                    return;
+                }
+            };
+            (
+                layout.device_id.value,
+                layout.life_guard.ref_count.take().unwrap(),
+            )
         };
-        unsafe {
-            device.raw.destroy_shader_module(module.raw);
-        }
+
+        let (device_guard, mut token) = hub.devices.read(&mut token);
+        device_guard[device_id]
+            .lock_life(&mut token)
+            .suspected_resources
+            .pipeline_layouts
+            .push(Stored {
+                value: pipeline_layout_id,
+                ref_count,
+            });
     }
 
-    pub fn device_create_command_encoder<B: GfxBackend>(
+    pub fn device_create_bind_group<B: GfxBackend>(
         &self,
         device_id: id::DeviceId,
-        desc: &wgt::CommandEncoderDescriptor<Label>,
-        id_in: Input<G, id::CommandEncoderId>,
-    ) -> id::CommandEncoderId {
+        desc: &binding_model::BindGroupDescriptor,
+        id_in: Input<G, id::BindGroupId>,
+    ) -> (id::BindGroupId, Option<BindGroupError>) {
+        use crate::binding_model::BindingResource as Br;
+
         let hub = B::hub(self);
+        let context = ErrorContext::with_label("device_create_bind_group", desc.label);
         let mut token = Token::root();
 
         let (device_guard, mut token) = hub.devices.read(&mut token);
-        let device = &device_guard[device_id];
-
-        let dev_stored = Stored {
-            value: device_id,
-            ref_count: device.life_guard.add_ref(),
-        };
-
-        let mut command_buffer = device.com_allocator.allocate(
-            dev_stored,
-            &device.raw,
-            device.limits.clone(),
-            device.private_features,
-            #[cfg(feature = "trace")]
-            device.trace.is_some(),
-        );
-
-        unsafe {
-            let raw_command_buffer = command_buffer.raw.last_mut().unwrap();
-            if !desc.label.is_null() {
-                let label = ffi::CStr::from_ptr(desc.label).to_string_lossy();
-                device
-                    .raw
-                    .set_command_buffer_name(raw_command_buffer, &label);
+        let error = loop {
+            let device = &device_guard[device_id];
+            if device.is_lost() {
+                break BindGroupError::DeviceLost;
             }
-            raw_command_buffer.begin_primary(hal::command::CommandBufferFlags::ONE_TIME_SUBMIT);
-        }
+            let (bind_group_layout_guard, mut token) = hub.bind_group_layouts.read(&mut token);
+            let bind_group_layout = match bind_group_layout_guard.get(desc.layout) {
+                Ok(layout) => layout,
+                Err(_) => {
+                    break device.capture(context, BindGroupError::InvalidLayout(desc.layout))
+                }
+            };
 
-        hub.command_buffers
-            .register_identity(id_in, command_buffer, &mut token)
-    }
+            let bind_group =
+                match device.create_bind_group(device_id, bind_group_layout, desc, hub, &mut token)
+                {
+                    Ok(bind_group) => bind_group,
+                    Err(e @ BindGroupError::OutOfMemory) => break device.capture_oom(context, e),
+                    Err(e) => break device.capture(context, e),
+                };
+            let ref_count = bind_group.life_guard.add_ref();
 
-    pub fn command_encoder_destroy<B: GfxBackend>(&self, command_encoder_id: id::CommandEncoderId) {
-        let hub = B::hub(self);
-        let mut token = Token::root();
+            let id = hub
+                .bind_groups
+                .register_identity(id_in, bind_group, &mut token);
+            log::debug!(
+                "Bind group {:?} {:#?}",
+                id,
+                hub.bind_groups.read(&mut token).0[id].used
+            );
+            #[cfg(feature = "trace")]
+            match device.trace {
+                Some(ref trace) => trace.lock().add(trace::Action::CreateBindGroup {
+                    id,
+                    label: desc.label.map_or_else(String::new, str::to_string),
+                    layout_id: desc.layout,
+                    entries: desc
+                        .bindings
+                        .iter()
+                        .map(|entry| {
+                            let res = match entry.resource {
+                                Br::Buffer(ref binding) => trace::BindingResource::Buffer {
+                                    id: binding.buffer_id,
+                                    offset: binding.offset,
+                                    size: binding.size,
+                                },
+                                Br::TextureView(id) => trace::BindingResource::TextureView(id),
+                                Br::Sampler(id) => trace::BindingResource::Sampler(id),
+                                Br::TextureViewArray(ref binding_array) => {
+                                    trace::BindingResource::TextureViewArray(binding_array.to_vec())
+                                }
+                            };
+                            (entry.binding, res)
+                        })
+                        .collect(),
+                }),
+                None => (),
+            };
 
-        let (mut device_guard, mut token) = hub.devices.write(&mut token);
-        let comb = {
-            let (mut command_buffer_guard, _) = hub.command_buffers.write(&mut token);
-            command_buffer_guard.remove(command_encoder_id).unwrap()
+            device
+                .trackers
+                .lock()
+                .bind_groups
+                .init(id, ref_count, PhantomData)
+                .unwrap();
+            return (id, None);
         };
 
-        let device = &mut device_guard[comb.device_id.value];
-        device.untrack::<G>(&hub, &comb.trackers, &mut token);
-        device.com_allocator.discard(comb);
-    }
-
-    pub fn command_buffer_destroy<B: GfxBackend>(&self, command_buffer_id: id::CommandBufferId) {
-        self.command_encoder_destroy::<B>(command_buffer_id)
-    }
-
-    pub fn device_create_render_bundle_encoder(
-        &self,
-        device_id: id::DeviceId,
-        desc: &wgt::RenderBundleEncoderDescriptor,
-    ) -> id::RenderBundleEncoderId {
-        let encoder = command::RenderBundleEncoder::new(desc, device_id);
-        Box::into_raw(Box::new(encoder))
-    }
-
-    pub fn render_bundle_encoder_destroy(
-        &self,
-        render_bundle_encoder: command::RenderBundleEncoder,
-    ) {
-        render_bundle_encoder.destroy();
+        let id = hub.bind_groups.register_error(id_in, &mut token);
+        (id, Some(error))
     }
 
-    pub fn render_bundle_destroy<B: GfxBackend>(&self, render_bundle_id: id::RenderBundleId) {
+    pub fn bind_group_destroy<B: GfxBackend>(&self, bind_group_id: id::BindGroupId) {
         let hub = B::hub(self);
         let mut token = Token::root();
 
-        let (device_guard, mut token) = hub.devices.read(&mut token);
         let device_id = {
-            let (mut bundle_guard, _) = hub.render_bundles.write(&mut token);
-            let bundle = &mut bundle_guard[render_bundle_id];
-            bundle.life_guard.ref_count.take();
-            bundle.device_id.value
+            let (mut bind_group_guard, _) = hub.bind_groups.write(&mut token);
+            let bind_group = match bind_group_guard.get_mut(bind_group_id) {
+                Ok(bind_group) => bind_group,
+                Err(_) => {
+                    hub.bind_groups
+                        .unregister_locked(bind_group_id, &mut *bind_group_guard);
+                    return;
+                }
+            };
+            bind_group.life_guard.ref_count.take();
+            bind_group.device_id.value
         };
 
+        let (device_guard, mut token) = hub.devices.read(&mut token);
         device_guard[device_id]
             .lock_life(&mut token)
             .suspected_resources
-            .render_bundles
-            .push(render_bundle_id);
+            .bind_groups
+            .push(bind_group_id);
     }
 
-    pub fn device_create_render_pipeline<B: GfxBackend>(
+    pub fn device_create_shader_module<B: GfxBackend>(
         &self,
         device_id: id::DeviceId,
-        desc: &pipeline::RenderPipelineDescriptor,
-        id_in: Input<G, id::RenderPipelineId>,
-    ) -> Result<id::RenderPipelineId, pipeline::RenderPipelineError> {
+        source: pipeline::ShaderModuleSource,
+        id_in: Input<G, id::ShaderModuleId>,
+    ) -> (
+        id::ShaderModuleId,
+        Option<pipeline::CreateShaderModuleError>,
+    ) {
         let hub = B::hub(self);
+        let context = ErrorContext::new("device_create_shader_module");
         let mut token = Token::root();
-
-        let samples = {
-            let sc = desc.sample_count;
-            if sc == 0 || sc > 32 || !conv::is_power_of_two(sc) {
-                return Err(pipeline::RenderPipelineError::InvalidSampleCount(sc));
+        let (device_guard, mut token) = hub.devices.read(&mut token);
+        let error = loop {
+            let device = &device_guard[device_id];
+            if device.is_lost() {
+                break pipeline::CreateShaderModuleError::DeviceLost;
             }
-            sc as u8
-        };
-
-        let color_states =
-            unsafe { slice::from_raw_parts(desc.color_states, desc.color_states_length) };
-        let depth_stencil_state = unsafe { desc.depth_stencil_state.as_ref() };
-
-        let rasterization_state = unsafe { desc.rasterization_state.as_ref() }.cloned();
-        let rasterizer = conv::map_rasterization_state_descriptor(
-            &rasterization_state.clone().unwrap_or_default(),
-        );
+            let spv_owned;
+            let spv_flags = if cfg!(debug_assertions) {
+                naga::back::spv::WriterFlags::DEBUG
+            } else {
+                naga::back::spv::WriterFlags::empty()
+            };
 
-        let mut interface = validation::StageInterface::default();
-        let mut validated_stages = wgt::ShaderStage::empty();
+            let (spv, naga) = match source {
+                pipeline::ShaderModuleSource::SpirV(spv) => {
+                    let module = if device.private_features.shader_validation {
+                        // Parse the given shader code and store its representation.
+                        let spv_iter = spv.into_iter().cloned();
+                        naga::front::spv::Parser::new(spv_iter)
+                            .parse()
+                            .map_err(|err| {
+                                log::warn!("Failed to parse shader SPIR-V code: {:?}", err);
+                                log::warn!("Shader module will not be validated");
+                            })
+                            .ok()
+                    } else {
+                        None
+                    };
+                    (spv, module)
+                }
+                pipeline::ShaderModuleSource::Wgsl(code) => {
+                    let module = match naga::front::wgsl::parse_str(code) {
+                        Ok(module) => module,
+                        Err(err) => {
+                            break device.capture(
+                                context,
+                                pipeline::CreateShaderModuleError::Parsing(
+                                    pipeline::ShaderParseError::from_wgsl(code, &err),
+                                ),
+                            )
+                        }
+                    };
+                    spv_owned =
+                        naga::back::spv::Writer::new(&module.header, spv_flags).write(&module);
+                    (
+                        spv_owned.as_slice(),
+                        if device.private_features.shader_validation {
+                            Some(module)
+                        } else {
+                            None
+                        },
+                    )
+                }
+                pipeline::ShaderModuleSource::Naga(module) => {
+                    spv_owned =
+                        naga::back::spv::Writer::new(&module.header, spv_flags).write(&module);
+                    (
+                        spv_owned.as_slice(),
+                        if device.private_features.shader_validation {
+                            Some(module)
+                        } else {
+                            None
+                        },
+                    )
+                }
+            };
 
-        let desc_vbs = unsafe {
-            slice::from_raw_parts(
-                desc.vertex_state.vertex_buffers,
-                desc.vertex_state.vertex_buffers_length,
-            )
-        };
-        let mut vertex_strides = Vec::with_capacity(desc_vbs.len());
-        let mut vertex_buffers = Vec::with_capacity(desc_vbs.len());
-        let mut attributes = Vec::new();
-        for (i, vb_state) in desc_vbs.iter().enumerate() {
-            vertex_strides
-                .alloc()
-                .init((vb_state.array_stride, vb_state.step_mode));
-            if vb_state.attributes_length == 0 {
-                continue;
-            }
-            vertex_buffers.alloc().init(hal::pso::VertexBufferDesc {
-                binding: i as u32,
-                stride: vb_state.array_stride as u32,
-                rate: match vb_state.step_mode {
-                    InputStepMode::Vertex => hal::pso::VertexInputRate::Vertex,
-                    InputStepMode::Instance => hal::pso::VertexInputRate::Instance(1),
+            let raw = match unsafe { device.raw.create_shader_module(spv) } {
+                Ok(raw) => raw,
+                Err(hal::device::ShaderError::OutOfMemory(_)) => {
+                    break device
+                        .capture_oom(context, pipeline::CreateShaderModuleError::OutOfMemory)
+                }
+                Err(err) => {
+                    break device.capture(
+                        context,
+                        pipeline::CreateShaderModuleError::Compilation(format!("{:?}", err)),
+                    )
+                }
+            };
+            let shader = pipeline::ShaderModule {
+                raw,
+                device_id: Stored {
+                    value: device_id,
+                    ref_count: device.life_guard.add_ref(),
                 },
-            });
-            let desc_atts =
-                unsafe { slice::from_raw_parts(vb_state.attributes, vb_state.attributes_length) };
-            for attribute in desc_atts {
-                if attribute.offset >= 0x10000000 {
-                    return Err(
-                        pipeline::RenderPipelineError::InvalidVertexAttributeOffset {
-                            location: attribute.shader_location,
-                            offset: attribute.offset,
-                        },
-                    );
+                module: naga,
+            };
+
+            let id = hub
+                .shader_modules
+                .register_identity(id_in, shader, &mut token);
+            #[cfg(feature = "trace")]
+            match device.trace {
+                Some(ref trace) => {
+                    let mut trace = trace.lock();
+                    let data = trace.make_binary("spv", unsafe {
+                        slice::from_raw_parts(spv.as_ptr() as *const u8, spv.len() * 4)
+                    });
+                    trace.add(trace::Action::CreateShaderModule { id, data });
                 }
-                attributes.alloc().init(hal::pso::AttributeDesc {
-                    location: attribute.shader_location,
-                    binding: i as u32,
-                    element: hal::pso::Element {
-                        format: conv::map_vertex_format(attribute.format),
-                        offset: attribute.offset as u32,
-                    },
-                });
-                interface.insert(
-                    attribute.shader_location,
-                    validation::MaybeOwned::Owned(validation::map_vertex_format(attribute.format)),
-                );
-            }
-        }
+                None => {}
+            };
+            return (id, None);
+        };
+
+        let id = hub.shader_modules.register_error(id_in, &mut token);
+        (id, Some(error))
+    }
+
+    pub fn shader_module_destroy<B: GfxBackend>(&self, shader_module_id: id::ShaderModuleId) {
+        let hub = B::hub(self);
+        let mut token = Token::root();
+        let (device_guard, mut token) = hub.devices.read(&mut token);
+        let module = match hub
+            .shader_modules
+            .unregister(shader_module_id, &mut token)
+            .0
+        {
+            Some(module) => module,
+            None => return,
+        };
 
-        let input_assembler = hal::pso::InputAssemblerDesc {
-            primitive: conv::map_primitive_topology(desc.primitive_topology),
-            with_adjacency: false,
-            restart_index: None, //TODO
+        let device = &device_guard[module.device_id.value];
+        #[cfg(feature = "trace")]
+        match device.trace {
+            Some(ref trace) => trace
+                .lock()
+                .add(trace::Action::DestroyShaderModule(shader_module_id)),
+            None => (),
         };
+        unsafe {
+            device.raw.destroy_shader_module(module.raw);
+        }
+    }
 
-        let blender = hal::pso::BlendDesc {
-            logic_op: None, // TODO
-            targets: color_states
-                .iter()
-                .map(conv::map_color_state_descriptor)
-                .collect(),
+    /// Create a pipeline cache, seeded with the `data` of a previous
+    /// `pipeline_cache_get_data` call.
+    pub fn device_create_pipeline_cache<B: GfxBackend>(
+        &self,
+        device_id: id::DeviceId,
+        data: Option<&[u8]>,
+        id_in: Input<G, id::PipelineCacheId>,
+    ) -> (
+        id::PipelineCacheId,
+        Option<pipeline::CreatePipelineCacheError>,
+    ) {
+        let hub = B::hub(self);
+        let context = ErrorContext::new("device_create_pipeline_cache");
+        let mut token = Token::root();
+        let (device_guard, mut token) = hub.devices.read(&mut token);
+        let error = loop {
+            let device = &device_guard[device_id];
+            if device.is_lost() {
+                break pipeline::CreatePipelineCacheError::DeviceLost;
+            }
+            // The driver validates the header of the initial data,
+            // and ignores it if it was produced by a different device.
+            let raw = match unsafe { device.raw.create_pipeline_cache(data) } {
+                Ok(raw) => raw,
+                Err(_) => {
+                    break device
+                        .capture_oom(context, pipeline::CreatePipelineCacheError::OutOfMemory)
+                }
+            };
+            let cache = pipeline::PipelineCache {
+                raw,
+                device_id: Stored {
+                    value: device_id,
+                    ref_count: device.life_guard.add_ref(),
+                },
+            };
+
+            let id = hub
+                .pipeline_caches
+                .register_identity(id_in, cache, &mut token);
+            #[cfg(feature = "trace")]
+            match device.trace {
+                Some(ref trace) => {
+                    let mut trace = trace.lock();
+                    let data = data.map(|data| trace.make_binary("bin", data));
+                    trace.add(trace::Action::CreatePipelineCache { id, data });
+                }
+                None => {}
+            };
+            return (id, None);
         };
-        let depth_stencil = depth_stencil_state
-            .map(conv::map_depth_stencil_state_descriptor)
-            .unwrap_or_default();
 
-        let multisampling: Option<hal::pso::Multisampling> = if samples == 1 {
-            None
-        } else {
-            Some(hal::pso::Multisampling {
-                rasterization_samples: samples,
-                sample_shading: None,
-                sample_mask: desc.sample_mask as u64,
-                alpha_coverage: desc.alpha_to_coverage_enabled,
-                alpha_to_one: false,
-            })
+        let id = hub.pipeline_caches.register_error(id_in, &mut token);
+        (id, Some(error))
+    }
+
+    pub fn pipeline_cache_get_data<B: GfxBackend>(
+        &self,
+        pipeline_cache_id: id::PipelineCacheId,
+    ) -> Result<Vec<u8>, pipeline::PipelineCacheError> {
+        let hub = B::hub(self);
+        let mut token = Token::root();
+        let (device_guard, mut token) = hub.devices.read(&mut token);
+        let (cache_guard, _) = hub.pipeline_caches.read(&mut token);
+        let cache = cache_guard
+            .get(pipeline_cache_id)
+            .map_err(|_| pipeline::PipelineCacheError::Invalid(pipeline_cache_id))?;
+        let device = &device_guard[cache.device_id.value];
+        unsafe { device.raw.get_pipeline_cache_data(&cache.raw) }.map_err(|_| {
+            device.capture_oom(
+                ErrorContext::new("pipeline_cache_get_data"),
+                pipeline::PipelineCacheError::OutOfMemory,
+            )
+        })
+    }
+
+    pub fn pipeline_cache_destroy<B: GfxBackend>(&self, pipeline_cache_id: id::PipelineCacheId) {
+        let hub = B::hub(self);
+        let mut token = Token::root();
+        let (device_guard, mut token) = hub.devices.read(&mut token);
+        let cache = match hub
+            .pipeline_caches
+            .unregister(pipeline_cache_id, &mut token)
+            .0
+        {
+            Some(cache) => cache,
+            None => return,
         };
 
-        // TODO
-        let baked_states = hal::pso::BakedStates {
-            viewport: None,
-            scissor: None,
-            blend_color: None,
-            depth_bounds: None,
+        let device = &device_guard[cache.device_id.value];
+        #[cfg(feature = "trace")]
+        match device.trace {
+            Some(ref trace) => trace
+                .lock()
+                .add(trace::Action::DestroyPipelineCache(pipeline_cache_id)),
+            None => (),
         };
+        // Pipelines don't keep a reference to the cache they were created with,
+        // so it can be destroyed right away.
+        unsafe {
+            device.raw.destroy_pipeline_cache(cache.raw);
+        }
+    }
+
+    pub fn device_create_command_encoder<B: GfxBackend>(
+        &self,
+        device_id: id::DeviceId,
+        desc: &wgt::CommandEncoderDescriptor<Label>,
+        id_in: Input<G, id::CommandEncoderId>,
+    ) -> id::CommandEncoderId {
+        let hub = B::hub(self);
+        let mut token = Token::root();
 
         let (device_guard, mut token) = hub.devices.read(&mut token);
         let device = &device_guard[device_id];
-        let (raw_pipeline, layout_ref_count) = {
-            let (pipeline_layout_guard, mut token) = hub.pipeline_layouts.read(&mut token);
-            let (bgl_guard, mut token) = hub.bind_group_layouts.read(&mut token);
-            let layout = &pipeline_layout_guard[desc.layout];
-            let group_layouts = layout
-                .bind_group_layout_ids
-                .iter()
-                .map(|id| &bgl_guard[id.value].entries)
-                .collect::<ArrayVec<[&binding_model::BindEntryMap; MAX_BIND_GROUPS]>>();
-
-            let (shader_module_guard, _) = hub.shader_modules.read(&mut token);
-
-            let rp_key = RenderPassKey {
-                colors: color_states
-                    .iter()
-                    .map(|state| {
-                        let at = hal::pass::Attachment {
-                            format: Some(conv::map_texture_format(
-                                state.format,
-                                device.private_features,
-                            )),
-                            samples,
-                            ops: hal::pass::AttachmentOps::PRESERVE,
-                            stencil_ops: hal::pass::AttachmentOps::DONT_CARE,
-                            layouts: hal::image::Layout::General..hal::image::Layout::General,
-                        };
-                        (at, hal::image::Layout::ColorAttachmentOptimal)
-                    })
-                    .collect(),
-                // We can ignore the resolves as the vulkan specs says:
-                // As an additional special case, if two render passes have a single subpass,
-                // they are compatible even if they have different resolve attachment references
-                // or depth/stencil resolve modes but satisfy the other compatibility conditions.
-                resolves: ArrayVec::new(),
-                depth_stencil: depth_stencil_state.map(|state| {
-                    let at = hal::pass::Attachment {
-                        format: Some(conv::map_texture_format(
-                            state.format,
-                            device.private_features,
-                        )),
-                        samples,
-                        ops: hal::pass::AttachmentOps::PRESERVE,
-                        stencil_ops: hal::pass::AttachmentOps::PRESERVE,
-                        layouts: hal::image::Layout::General..hal::image::Layout::General,
-                    };
-                    (at, hal::image::Layout::DepthStencilAttachmentOptimal)
-                }),
-            };
 
-            let vertex = {
-                let entry_point_name =
-                    unsafe { ffi::CStr::from_ptr(desc.vertex_stage.entry_point) }
-                        .to_str()
-                        .to_owned()
-                        .unwrap();
+        let dev_stored = Stored {
+            value: device_id,
+            ref_count: device.life_guard.add_ref(),
+        };
 
-                let shader_module = &shader_module_guard[desc.vertex_stage.module];
+        let mut command_buffer = device.com_allocator.allocate(
+            dev_stored,
+            &device.raw,
+            device.limits.clone(),
+            device.private_features,
+            #[cfg(feature = "trace")]
+            device.trace.is_some(),
+        );
 
-                if let Some(ref module) = shader_module.module {
-                    let flag = wgt::ShaderStage::VERTEX;
-                    interface = validation::check_stage(
-                        module,
-                        &group_layouts,
-                        entry_point_name,
-                        ExecutionModel::Vertex,
-                        interface,
-                    )
-                    .map_err(|error| pipeline::RenderPipelineError::Stage { flag, error })?;
-                    validated_stages |= flag;
-                }
+        unsafe {
+            let raw_command_buffer = command_buffer.raw.last_mut().unwrap();
+            if !desc.label.is_null() {
+                let label = ffi::CStr::from_ptr(desc.label).to_string_lossy();
+                device
+                    .raw
+                    .set_command_buffer_name(raw_command_buffer, &label);
+            }
+            raw_command_buffer.begin_primary(hal::command::CommandBufferFlags::ONE_TIME_SUBMIT);
+        }
 
-                hal::pso::EntryPoint::<B> {
-                    entry: entry_point_name, // TODO
-                    module: &shader_module.raw,
-                    specialization: hal::pso::Specialization::EMPTY,
-                }
-            };
+        hub.command_buffers
+            .register_identity(id_in, command_buffer, &mut token)
+    }
 
-            let fragment = match unsafe { desc.fragment_stage.as_ref() } {
-                Some(stage) => {
-                    let entry_point_name = unsafe { ffi::CStr::from_ptr(stage.entry_point) }
-                        .to_str()
-                        .to_owned()
-                        .unwrap();
+    pub fn command_encoder_destroy<B: GfxBackend>(&self, command_encoder_id: id::CommandEncoderId) {
+        let hub = B::hub(self);
+        let mut token = Token::root();
 
-                    let shader_module = &shader_module_guard[stage.module];
+        let (mut device_guard, mut token) = hub.devices.write(&mut token);
+        let comb = {
+            let (mut command_buffer_guard, _) = hub.command_buffers.write(&mut token);
+            command_buffer_guard.remove(command_encoder_id).unwrap()
+        };
 
-                    if validated_stages == wgt::ShaderStage::VERTEX {
-                        if let Some(ref module) = shader_module.module {
-                            let flag = wgt::ShaderStage::FRAGMENT;
-                            interface = validation::check_stage(
-                                module,
-                                &group_layouts,
-                                entry_point_name,
-                                ExecutionModel::Fragment,
-                                interface,
-                            )
-                            .map_err(|error| {
-                                pipeline::RenderPipelineError::Stage { flag, error }
-                            })?;
-                            validated_stages |= flag;
-                        }
-                    }
+        let device = &mut device_guard[comb.device_id.value];
+        device.untrack::<G>(&hub, &comb.trackers, &mut token);
+        device.com_allocator.discard(comb);
+    }
 
-                    Some(hal::pso::EntryPoint::<B> {
-                        entry: entry_point_name,
-                        module: &shader_module.raw,
-                        specialization: hal::pso::Specialization::EMPTY,
-                    })
-                }
-                None => None,
-            };
+    pub fn command_buffer_destroy<B: GfxBackend>(&self, command_buffer_id: id::CommandBufferId) {
+        self.command_encoder_destroy::<B>(command_buffer_id)
+    }
 
-            if validated_stages.contains(wgt::ShaderStage::FRAGMENT) {
-                for (i, state) in color_states.iter().enumerate() {
-                    let output = &interface[&(i as wgt::ShaderLocation)];
-                    if !validation::check_texture_format(state.format, output) {
-                        log::warn!(
-                            "Incompatible fragment output[{}]. Shader: {:?}. Expected: {:?}",
-                            i,
-                            state.format,
-                            &**output
-                        );
-                        return Err(pipeline::RenderPipelineError::IncompatibleOutputFormat {
-                            index: i as u8,
-                        });
-                    }
-                }
-            }
+    pub fn device_create_render_bundle_encoder(
+        &self,
+        device_id: id::DeviceId,
+        desc: &wgt::RenderBundleEncoderDescriptor,
+    ) -> id::RenderBundleEncoderId {
+        let encoder = command::RenderBundleEncoder::new(desc, device_id);
+        Box::into_raw(Box::new(encoder))
+    }
 
-            let shaders = hal::pso::GraphicsShaderSet {
-                vertex,
-                hull: None,
-                domain: None,
-                geometry: None,
-                fragment,
-            };
+    pub fn render_bundle_encoder_destroy(
+        &self,
+        render_bundle_encoder: command::RenderBundleEncoder,
+    ) {
+        render_bundle_encoder.destroy();
+    }
 
-            // TODO
-            let flags = hal::pso::PipelineCreationFlags::empty();
+    pub fn render_bundle_destroy<B: GfxBackend>(&self, render_bundle_id: id::RenderBundleId) {
+        let hub = B::hub(self);
+        let mut token = Token::root();
 
-            let mut render_pass_cache = device.render_passes.lock();
-            let pipeline_desc = hal::pso::GraphicsPipelineDesc {
-                shaders,
-                rasterizer,
-                vertex_buffers,
-                attributes,
-                input_assembler,
-                blender,
-                depth_stencil,
-                multisampling,
-                baked_states,
-                layout: &layout.raw,
-                subpass: hal::pass::Subpass {
-                    index: 0,
-                    main_pass: match render_pass_cache.entry(rp_key) {
-                        Entry::Occupied(e) => e.into_mut(),
-                        Entry::Vacant(e) => {
-                            let pass = device.create_compatible_render_pass(e.key());
-                            e.insert(pass)
-                        }
-                    },
-                },
-                flags,
-                parent: hal::pso::BasePipeline::None,
-            };
-            // TODO: cache
-            let pipeline = unsafe {
-                device
-                    .raw
-                    .create_graphics_pipeline(&pipeline_desc, None)
-                    .unwrap()
+        let (device_guard, mut token) = hub.devices.read(&mut token);
+        let device_id = {
+            let (mut bundle_guard, _) = hub.render_bundles.write(&mut token);
+            let bundle = match bundle_guard.get_mut(render_bundle_id) {
+                Ok(bundle) => bundle,
+                Err(_) => {
+                    hub.render_bundles
+                        .unregister_locked(render_bundle_id, &mut *bundle_guard);
+                    return;
+                }
             };
-
-            (pipeline, layout.life_guard.add_ref())
+            bundle.life_guard.ref_count.take();
+            bundle.device_id.value
         };
 
-        let pass_context = RenderPassContext {
-            attachments: AttachmentData {
-                colors: color_states.iter().map(|state| state.format).collect(),
-                resolves: ArrayVec::new(),
-                depth_stencil: depth_stencil_state.map(|state| state.format),
-            },
-            sample_count: samples,
-        };
+        device_guard[device_id]
+            .lock_life(&mut token)
+            .suspected_resources
+            .render_bundles
+            .push(render_bundle_id);
+    }
 
-        let mut flags = pipeline::PipelineFlags::empty();
-        for state in color_states {
-            if state.color_blend.uses_color() | state.alpha_blend.uses_color() {
-                flags |= pipeline::PipelineFlags::BLEND_COLOR;
-            }
-        }
-        if let Some(ds) = depth_stencil_state {
-            if ds.needs_stencil_reference() {
-                flags |= pipeline::PipelineFlags::STENCIL_REFERENCE;
-            }
-            if ds.is_read_only() {
-                flags |= pipeline::PipelineFlags::DEPTH_STENCIL_READ_ONLY;
-            }
-        }
+    pub fn device_create_render_pipeline<B: GfxBackend>(
+        &self,
+        device_id: id::DeviceId,
+        desc: &pipeline::RenderPipelineDescriptor,
+        id_in: Input<G, id::RenderPipelineId>,
+    ) -> (id::RenderPipelineId, Option<pipeline::RenderPipelineError>) {
+        let hub = B::hub(self);
+        let context = ErrorContext::new("device_create_render_pipeline");
+        let mut token = Token::root();
 
-        let pipeline = pipeline::RenderPipeline {
-            raw: raw_pipeline,
-            layout_id: Stored {
-                value: desc.layout,
-                ref_count: layout_ref_count,
-            },
-            device_id: Stored {
-                value: device_id,
-                ref_count: device.life_guard.add_ref(),
-            },
-            pass_context,
-            flags,
-            index_format: desc.vertex_state.index_format,
-            vertex_strides,
-            life_guard: LifeGuard::new(),
-        };
+        let (device_guard, mut token) = hub.devices.read(&mut token);
+        let error = loop {
+            let device = &device_guard[device_id];
+            if device.is_lost() {
+                break pipeline::RenderPipelineError::DeviceLost;
+            }
+            let pipeline = match device.create_render_pipeline(device_id, desc, hub, &mut token) {
+                Ok(pipeline) => pipeline,
+                Err(e) => break device.capture(context, e),
+            };
 
-        let id = hub
-            .render_pipelines
-            .register_identity(id_in, pipeline, &mut token);
+            let id = hub
+                .render_pipelines
+                .register_identity(id_in, pipeline, &mut token);
 
-        #[cfg(feature = "trace")]
-        match device.trace {
-            Some(ref trace) => trace.lock().add(trace::Action::CreateRenderPipeline {
-                id,
-                desc: trace::RenderPipelineDescriptor {
-                    layout: desc.layout,
-                    vertex_stage: trace::ProgrammableStageDescriptor::new(&desc.vertex_stage),
-                    fragment_stage: unsafe { desc.fragment_stage.as_ref() }
-                        .map(trace::ProgrammableStageDescriptor::new),
-                    primitive_topology: desc.primitive_topology,
-                    rasterization_state,
-                    color_states: color_states.to_vec(),
-                    depth_stencil_state: depth_stencil_state.cloned(),
-                    vertex_state: trace::VertexStateDescriptor {
-                        index_format: desc.vertex_state.index_format,
-                        vertex_buffers: desc_vbs
-                            .iter()
-                            .map(|vbl| trace::VertexBufferLayoutDescriptor {
-                                array_stride: vbl.array_stride,
-                                step_mode: vbl.step_mode,
-                                attributes: unsafe {
-                                    slice::from_raw_parts(vbl.attributes, vbl.attributes_length)
-                                }
-                                .iter()
-                                .cloned()
-                                .collect(),
-                            })
-                            .collect(),
-                    },
-                    sample_count: desc.sample_count,
-                    sample_mask: desc.sample_mask,
-                    alpha_to_coverage_enabled: desc.alpha_to_coverage_enabled,
-                },
-            }),
-            None => (),
+            #[cfg(feature = "trace")]
+            match device.trace {
+                Some(ref trace) => trace.lock().add(trace::Action::CreateRenderPipeline {
+                    id,
+                    desc: trace::RenderPipelineDescriptor::new(desc),
+                }),
+                None => (),
+            };
+            return (id, None);
         };
-        Ok(id)
+
+        let id = hub.render_pipelines.register_error(id_in, &mut token);
+        (id, Some(error))
     }
 
     pub fn render_pipeline_destroy<B: GfxBackend>(&self, render_pipeline_id: id::RenderPipelineId) {
@@ -2373,7 +3796,14 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
 
         let (device_id, layout_id) = {
             let (mut pipeline_guard, _) = hub.render_pipelines.write(&mut token);
-            let pipeline = &mut pipeline_guard[render_pipeline_id];
+            let pipeline = match pipeline_guard.get_mut(render_pipeline_id) {
+                Ok(pipeline) => pipeline,
+                Err(_) => {
+                    hub.render_pipelines
+                        .unregister_locked(render_pipeline_id, &mut *pipeline_guard);
+                    return;
+                }
+            };
             pipeline.life_guard.ref_count.take();
             (pipeline.device_id.value, pipeline.layout_id.clone())
         };
@@ -2394,99 +3824,46 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         device_id: id::DeviceId,
         desc: &pipeline::ComputePipelineDescriptor,
         id_in: Input<G, id::ComputePipelineId>,
-    ) -> Result<id::ComputePipelineId, pipeline::ComputePipelineError> {
+    ) -> (
+        id::ComputePipelineId,
+        Option<pipeline::ComputePipelineError>,
+    ) {
         let hub = B::hub(self);
+        let context = ErrorContext::new("device_create_compute_pipeline");
         let mut token = Token::root();
 
         let (device_guard, mut token) = hub.devices.read(&mut token);
-        let device = &device_guard[device_id];
-        let (raw_pipeline, layout_ref_count) = {
-            let (pipeline_layout_guard, mut token) = hub.pipeline_layouts.read(&mut token);
-            let (bgl_guard, mut token) = hub.bind_group_layouts.read(&mut token);
-            let layout = &pipeline_layout_guard[desc.layout];
-            let group_layouts = layout
-                .bind_group_layout_ids
-                .iter()
-                .map(|id| &bgl_guard[id.value].entries)
-                .collect::<ArrayVec<[&binding_model::BindEntryMap; MAX_BIND_GROUPS]>>();
-
-            let interface = validation::StageInterface::default();
-            let pipeline_stage = &desc.compute_stage;
-            let (shader_module_guard, _) = hub.shader_modules.read(&mut token);
-
-            let entry_point_name = unsafe { ffi::CStr::from_ptr(pipeline_stage.entry_point) }
-                .to_str()
-                .to_owned()
-                .unwrap();
-
-            let shader_module = &shader_module_guard[pipeline_stage.module];
-
-            if let Some(ref module) = shader_module.module {
-                let _ = validation::check_stage(
-                    module,
-                    &group_layouts,
-                    entry_point_name,
-                    ExecutionModel::GLCompute,
-                    interface,
-                )
-                .map_err(pipeline::ComputePipelineError::Stage)?;
+        let error = loop {
+            let device = &device_guard[device_id];
+            if device.is_lost() {
+                break pipeline::ComputePipelineError::DeviceLost;
             }
-
-            let shader = hal::pso::EntryPoint::<B> {
-                entry: entry_point_name, // TODO
-                module: &shader_module.raw,
-                specialization: hal::pso::Specialization::EMPTY,
+            let pipeline = match device.create_compute_pipeline(device_id, desc, hub, &mut token) {
+                Ok(pipeline) => pipeline,
+                Err(e) => break device.capture(context, e),
             };
 
-            // TODO
-            let flags = hal::pso::PipelineCreationFlags::empty();
-            // TODO
-            let parent = hal::pso::BasePipeline::None;
-
-            let pipeline_desc = hal::pso::ComputePipelineDesc {
-                shader,
-                layout: &layout.raw,
-                flags,
-                parent,
-            };
+            let id = hub
+                .compute_pipelines
+                .register_identity(id_in, pipeline, &mut token);
 
-            let pipeline = unsafe {
-                device
-                    .raw
-                    .create_compute_pipeline(&pipeline_desc, None)
-                    .unwrap()
+            #[cfg(feature = "trace")]
+            match device.trace {
+                Some(ref trace) => trace.lock().add(trace::Action::CreateComputePipeline {
+                    id,
+                    desc: trace::ComputePipelineDescriptor {
+                        layout: desc.layout,
+                        compute_stage: trace::ProgrammableStageDescriptor::new(&desc.compute_stage),
+                        cache: desc.cache,
+                    },
+                }),
+                None => (),
             };
-            (pipeline, layout.life_guard.add_ref())
-        };
-
-        let pipeline = pipeline::ComputePipeline {
-            raw: raw_pipeline,
-            layout_id: Stored {
-                value: desc.layout,
-                ref_count: layout_ref_count,
-            },
-            device_id: Stored {
-                value: device_id,
-                ref_count: device.life_guard.add_ref(),
-            },
-            life_guard: LifeGuard::new(),
+            return (id, None);
         };
-        let id = hub
-            .compute_pipelines
-            .register_identity(id_in, pipeline, &mut token);
 
-        #[cfg(feature = "trace")]
-        match device.trace {
-            Some(ref trace) => trace.lock().add(trace::Action::CreateComputePipeline {
-                id,
-                desc: trace::ComputePipelineDescriptor {
-                    layout: desc.layout,
-                    compute_stage: trace::ProgrammableStageDescriptor::new(&desc.compute_stage),
-                },
-            }),
-            None => (),
-        };
-        Ok(id)
+        let id = hub.compute_pipelines.register_error(id_in, &mut token);
+        (id, Some(error))
     }
 
     pub fn compute_pipeline_destroy<B: GfxBackend>(
@@ -2499,7 +3876,14 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
 
         let (device_id, layout_id) = {
             let (mut pipeline_guard, _) = hub.compute_pipelines.write(&mut token);
-            let pipeline = &mut pipeline_guard[compute_pipeline_id];
+            let pipeline = match pipeline_guard.get_mut(compute_pipeline_id) {
+                Ok(pipeline) => pipeline,
+                Err(_) => {
+                    hub.compute_pipelines
+                        .unregister_locked(compute_pipeline_id, &mut *pipeline_guard);
+                    return;
+                }
+            };
             pipeline.life_guard.ref_count.take();
             (pipeline.device_id.value, pipeline.layout_id.clone())
         };
@@ -2520,7 +3904,7 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         device_id: id::DeviceId,
         surface_id: id::SurfaceId,
         desc: &wgt::SwapChainDescriptor,
-    ) -> id::SwapChainId {
+    ) -> Result<id::SwapChainId, swap_chain::CreateSwapChainError> {
         fn validate_swap_chain_descriptor(
             config: &mut hal::window::SwapchainConfig,
             caps: &hal::window::SurfaceCapabilities,
@@ -2551,6 +3935,7 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
 
         log::info!("creating swap chain {:?}", desc);
         let hub = B::hub(self);
+        let context = ErrorContext::new("device_create_swap_chain");
         let mut token = Token::root();
 
         let (mut surface_guard, mut token) = self.surfaces.write(&mut token);
@@ -2558,17 +3943,20 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         let (device_guard, mut token) = hub.devices.read(&mut token);
         let (mut swap_chain_guard, _) = hub.swap_chains.write(&mut token);
         let device = &device_guard[device_id];
+        if device.is_lost() {
+            return Err(swap_chain::CreateSwapChainError::DeviceLost);
+        }
         let surface = &mut surface_guard[surface_id];
 
         let (caps, formats) = {
             let suf = B::get_surface_mut(surface);
             let adapter = &adapter_guard[device.adapter_id.value];
-            assert!(
-                suf.supports_queue_family(&adapter.raw.queue_families[0]),
-                "Surface {:?} doesn't support queue family {:?}",
-                suf,
-                &adapter.raw.queue_families[0]
-            );
+            if !suf.supports_queue_family(&adapter.raw.queue_families[0]) {
+                return Err(device.capture(
+                    context,
+                    swap_chain::CreateSwapChainError::UnsupportedQueueFamily,
+                ));
+            }
             let formats = suf.supported_formats(&adapter.raw.physical_device);
             let caps = suf.capabilities(&adapter.raw.physical_device);
             (caps, formats)
@@ -2579,27 +3967,33 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         let mut config =
             swap_chain::swap_chain_descriptor_to_hal(&desc, num_frames, device.private_features);
         if let Some(formats) = formats {
-            assert!(
-                formats.contains(&config.format),
-                "Requested format {:?} is not in supported list: {:?}",
-                config.format,
-                formats
-            );
+            if !formats.contains(&config.format) {
+                return Err(device.capture(
+                    context,
+                    swap_chain::CreateSwapChainError::UnsupportedFormat {
+                        requested: config.format,
+                        available: formats,
+                    },
+                ));
+            }
         }
         validate_swap_chain_descriptor(&mut config, &caps);
 
+        let sc_id = surface_id.to_swap_chain_id(B::VARIANT);
+        if swap_chain_guard.contains(sc_id) && swap_chain_guard[sc_id].acquired_view_id.is_some() {
+            return Err(device.capture(
+                context,
+                swap_chain::CreateSwapChainError::SwapChainOutputExists,
+            ));
+        }
+
         unsafe {
             B::get_surface_mut(surface)
                 .configure_swapchain(&device.raw, config)
                 .unwrap();
         }
 
-        let sc_id = surface_id.to_swap_chain_id(B::VARIANT);
         if let Some(sc) = swap_chain_guard.remove(sc_id) {
-            assert!(
-                sc.acquired_view_id.is_none(),
-                "SwapChainOutput must be dropped before a new SwapChain is made."
-            );
             unsafe {
                 device.raw.destroy_semaphore(sc.semaphore);
             }
@@ -2627,7 +4021,7 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
             active_submission_index: 0,
         };
         swap_chain_guard.insert(sc_id, swap_chain);
-        sc_id
+        Ok(sc_id)
     }
 
     #[cfg(feature = "replay")]
@@ -2647,54 +4041,116 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         );
     }
 
-    pub fn device_poll<B: GfxBackend>(&self, device_id: id::DeviceId, force_wait: bool) {
+    pub fn device_poll<B: GfxBackend>(
+        &self,
+        device_id: id::DeviceId,
+        force_wait: bool,
+    ) -> Result<(), DeviceError> {
         let hub = B::hub(self);
         let mut token = Token::root();
-        let callbacks = {
+        let (closures, lost) = {
             let (device_guard, mut token) = hub.devices.read(&mut token);
-            device_guard[device_id].maintain(&hub, force_wait, &mut token)
+            let device = &device_guard[device_id];
+            if device.is_lost() {
+                return Err(DeviceError::Lost);
+            }
+            let closures = device.maintain(&hub, force_wait, &mut token);
+            (closures, device.is_lost())
         };
-        fire_map_callbacks(callbacks);
+        closures.fire();
+        if lost {
+            Err(DeviceError::Lost)
+        } else {
+            Ok(())
+        }
     }
 
-    fn poll_devices<B: GfxBackend>(
-        &self,
-        force_wait: bool,
-        callbacks: &mut Vec<BufferMapPendingCallback>,
-    ) {
+    fn poll_devices<B: GfxBackend>(&self, force_wait: bool, closures: &mut UserClosures) {
         let hub = B::hub(self);
         let mut token = Token::root();
         let (device_guard, mut token) = hub.devices.read(&mut token);
         for (_, device) in device_guard.iter(B::VARIANT) {
+            if device.is_lost() {
+                continue;
+            }
             let cbs = device.maintain(&hub, force_wait, &mut token);
-            callbacks.extend(cbs);
+            closures.extend(cbs);
         }
     }
 
     pub fn poll_all_devices(&self, force_wait: bool) {
         use crate::backend;
-        let mut callbacks = Vec::new();
+        let mut closures = UserClosures::default();
 
         #[cfg(any(
             not(any(target_os = "ios", target_os = "macos")),
             feature = "gfx-backend-vulkan"
         ))]
-        self.poll_devices::<backend::Vulkan>(force_wait, &mut callbacks);
+        self.poll_devices::<backend::Vulkan>(force_wait, &mut closures);
         #[cfg(windows)]
-        self.poll_devices::<backend::Dx11>(force_wait, &mut callbacks);
+        self.poll_devices::<backend::Dx11>(force_wait, &mut closures);
         #[cfg(windows)]
-        self.poll_devices::<backend::Dx12>(force_wait, &mut callbacks);
+        self.poll_devices::<backend::Dx12>(force_wait, &mut closures);
         #[cfg(any(target_os = "ios", target_os = "macos"))]
-        self.poll_devices::<backend::Metal>(force_wait, &mut callbacks);
+        self.poll_devices::<backend::Metal>(force_wait, &mut closures);
+
+        closures.fire();
+    }
 
-        fire_map_callbacks(callbacks);
+    /// Register a callback to be fired when the device is lost.
+    ///
+    /// If the device is already lost, the callback is fired right away.
+    pub fn device_set_device_lost_callback<B: GfxBackend>(
+        &self,
+        device_id: id::DeviceId,
+        callback: DeviceLostCallback,
+        user_data: *mut u8,
+    ) {
+        let hub = B::hub(self);
+        let mut token = Token::root();
+        let closure = DeviceLostClosure {
+            callback,
+            user_data,
+        };
+        let pending = {
+            let (device_guard, _) = hub.devices.read(&mut token);
+            let device = &device_guard[device_id];
+            // Hold the reason locked, so that the device can't be lost
+            // before the callback is registered.
+            let lost_reason = device.lost_reason.lock();
+            match *lost_reason {
+                Some(reason) => Some((closure, reason)),
+                None => {
+                    *device.lost_callback.lock() = Some(closure);
+                    None
+                }
+            }
+        };
+        if let Some((closure, reason)) = pending {
+            unsafe { (closure.callback)(reason, closure.user_data) }
+        }
     }
 
     pub fn device_destroy<B: GfxBackend>(&self, device_id: id::DeviceId) {
         let hub = B::hub(self);
         let mut token = Token::root();
+
+        // Let the user know about the destruction, and fail the pending mappings.
+        let closures = {
+            let (device_guard, mut token) = hub.devices.read(&mut token);
+            let device = &device_guard[device_id];
+            let mut closures = UserClosures::default();
+            closures
+                .device_lost
+                .extend(device.lose(DeviceLostReason::Destroyed));
+            closures.extend(device.maintain(&hub, true, &mut token));
+            closures
+        };
+        closures.fire();
+
         let device = {
-            let (mut device, _) = hub.devices.unregister(device_id, &mut token);
+            let (device, _) = hub.devices.unregister(device_id, &mut token);
+            let mut device = device.unwrap();
             device.prepare_to_die();
             device
         };
@@ -2713,8 +4169,9 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         buffer_id: id::BufferId,
         range: Range<BufferAddress>,
         op: resource::BufferMapOperation,
-    ) {
+    ) -> Result<(), resource::BufferAccessError> {
         let hub = B::hub(self);
+        let context = ErrorContext::new("buffer_map_async");
         let mut token = Token::root();
         let (device_guard, mut token) = hub.devices.read(&mut token);
         let (pub_usage, internal_use) = match op.host {
@@ -2724,21 +4181,35 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
 
         let (device_id, ref_count) = {
             let (mut buffer_guard, _) = hub.buffers.write(&mut token);
-            let buffer = &mut buffer_guard[buffer_id];
+            let buffer = match buffer_guard.get_mut(buffer_id) {
+                Ok(buffer) => buffer,
+                Err(_) => {
+                    op.call_error();
+                    return Err(resource::BufferAccessError::InvalidBuffer(buffer_id));
+                }
+            };
+            let device = &device_guard[buffer.device_id.value];
 
-            assert!(
-                buffer.usage.contains(pub_usage),
-                "Buffer usage {:?} must contain usage flag(s) {:?}",
-                buffer.usage,
-                pub_usage
-            );
+            if device.is_lost() {
+                op.call_lost();
+                return Err(resource::BufferAccessError::DeviceLost);
+            }
+            if !buffer.usage.contains(pub_usage) {
+                op.call_error();
+                return Err(device.capture(
+                    context,
+                    resource::BufferAccessError::MissingUsage {
+                        actual: buffer.usage,
+                        expected: pub_usage,
+                    },
+                ));
+            }
             buffer.map_state = match buffer.map_state {
-                resource::BufferMapState::Init { .. } | resource::BufferMapState::Active { .. } => {
-                    panic!("Buffer already mapped")
-                }
-                resource::BufferMapState::Waiting(_) => {
+                resource::BufferMapState::Init { .. }
+                | resource::BufferMapState::Active { .. }
+                | resource::BufferMapState::Waiting(_) => {
                     op.call_error();
-                    return;
+                    return Err(device.capture(context, resource::BufferAccessError::AlreadyMapped));
                 }
                 resource::BufferMapState::Idle => {
                     resource::BufferMapState::Waiting(resource::BufferPendingMapping {
@@ -2764,6 +4235,7 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
             .change_replace(buffer_id, &ref_count, (), internal_use);
 
         device.lock_life(&mut token).map(buffer_id, ref_count);
+        Ok(())
     }
 
     pub fn buffer_get_mapped_range<B: GfxBackend>(
@@ -2771,31 +4243,37 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         buffer_id: id::BufferId,
         offset: BufferAddress,
         _size: BufferSize,
-    ) -> *mut u8 {
+    ) -> Result<*mut u8, resource::BufferAccessError> {
         let hub = B::hub(self);
         let mut token = Token::root();
         let (buffer_guard, _) = hub.buffers.read(&mut token);
-        let buffer = &buffer_guard[buffer_id];
+        let buffer = buffer_guard
+            .get(buffer_id)
+            .map_err(|_| resource::BufferAccessError::InvalidBuffer(buffer_id))?;
 
         match buffer.map_state {
             resource::BufferMapState::Init { ptr, .. }
             | resource::BufferMapState::Active { ptr, .. } => unsafe {
-                ptr.as_ptr().offset(offset as isize)
+                Ok(ptr.as_ptr().offset(offset as isize))
             },
             resource::BufferMapState::Idle | resource::BufferMapState::Waiting(_) => {
-                log::error!("Buffer is not mapped");
-                ptr::null_mut()
+                Err(resource::BufferAccessError::NotMapped)
             }
         }
     }
 
-    pub fn buffer_unmap<B: GfxBackend>(&self, buffer_id: id::BufferId) {
+    pub fn buffer_unmap<B: GfxBackend>(
+        &self,
+        buffer_id: id::BufferId,
+    ) -> Result<(), resource::BufferAccessError> {
         let hub = B::hub(self);
         let mut token = Token::root();
 
         let (mut device_guard, mut token) = hub.devices.write(&mut token);
         let (mut buffer_guard, _) = hub.buffers.write(&mut token);
-        let buffer = &mut buffer_guard[buffer_id];
+        let buffer = buffer_guard
+            .get_mut(buffer_id)
+            .map_err(|_| resource::BufferAccessError::InvalidBuffer(buffer_id))?;
         let device = &mut device_guard[buffer.device_id.value];
 
         log::debug!("Buffer {:?} map state -> Idle", buffer_id);
@@ -2812,11 +4290,12 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                         let data = trace.make_binary("bin", unsafe {
                             slice::from_raw_parts(ptr.as_ptr(), buffer.size as usize)
                         });
+                        // The replay writes this into the mapping at creation.
                         trace.add(trace::Action::WriteBuffer {
                             id: buffer_id,
                             data,
                             range: 0..buffer.size,
-                            queued: true,
+                            queued: false,
                         });
                     }
                     None => (),
@@ -2857,7 +4336,7 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                     .consume_temp(stage_buffer, stage_memory);
             }
             resource::BufferMapState::Idle => {
-                log::error!("Buffer is not mapped");
+                return Err(resource::BufferAccessError::NotMapped);
             }
             resource::BufferMapState::Waiting(_) => {}
             resource::BufferMapState::Active {
@@ -2888,5 +4367,96 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                 unmap_buffer(&device.raw, buffer);
             }
         }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_error_scope_filters() {
+    let mut stack = ErrorScopeStack::default();
+    stack.push(ErrorFilter::OutOfMemory);
+    stack.push(ErrorFilter::Validation);
+    assert_eq!(stack.capture(GpuError::OutOfMemory), None);
+    assert_eq!(
+        stack.capture(GpuError::Validation("first".to_string())),
+        None
+    );
+    assert_eq!(
+        stack.capture(GpuError::Validation("second".to_string())),
+        None
+    );
+    assert_eq!(
+        stack.pop().unwrap(),
+        Some(GpuError::Validation("first".to_string()))
+    );
+    assert_eq!(stack.pop().unwrap(), Some(GpuError::OutOfMemory));
+}
+
+#[test]
+fn test_error_scope_nesting() {
+    let mut stack = ErrorScopeStack::default();
+    stack.push(ErrorFilter::Validation);
+    stack.push(ErrorFilter::Validation);
+    assert_eq!(
+        stack.capture(GpuError::Validation("inner".to_string())),
+        None
+    );
+    assert_eq!(
+        stack.pop().unwrap(),
+        Some(GpuError::Validation("inner".to_string()))
+    );
+    assert_eq!(stack.pop().unwrap(), None);
+    // nothing is left to capture the errors
+    assert_eq!(
+        stack.capture(GpuError::OutOfMemory),
+        Some(GpuError::OutOfMemory)
+    );
+}
+
+#[test]
+fn test_error_scope_empty_stack() {
+    let mut stack = ErrorScopeStack::default();
+    stack.push(ErrorFilter::Validation);
+    assert_eq!(
+        stack.capture(GpuError::OutOfMemory),
+        Some(GpuError::OutOfMemory)
+    );
+    assert_eq!(stack.pop().unwrap(), None);
+    match stack.pop() {
+        Err(PopErrorScopeError::EmptyStack) => {}
+        other => panic!("Unexpected pop result: {:?}", other),
     }
 }
+
+#[test]
+fn test_map_zeroes_uninitialized_ranges() {
+    let mut status = InitTracker::new(0..16);
+    // a queue write initializes a part of the buffer
+    status.drain(4..8);
+
+    let mut mapping = [0xFFu8; 12];
+    assert!(zero_uninitialized_mapping(&mut status, &mut mapping, 2));
+    assert_eq!(mapping[..2], [0; 2]);
+    assert_eq!(mapping[2..6], [0xFF; 4]);
+    assert_eq!(mapping[6..], [0; 6]);
+
+    // only the never-mapped edges are left to zero
+    let mut mapping = [0xFFu8; 16];
+    assert!(zero_uninitialized_mapping(&mut status, &mut mapping, 0));
+    assert_eq!(mapping[..2], [0; 2]);
+    assert_eq!(mapping[2..14], [0xFF; 12]);
+    assert_eq!(mapping[14..], [0; 2]);
+
+    let mut mapping = [0xFFu8; 16];
+    assert!(!zero_uninitialized_mapping(&mut status, &mut mapping, 0));
+    assert_eq!(mapping, [0xFF; 16]);
+}
+
+#[test]
+fn test_map_skips_zero_initialization() {
+    // buffers start initialized when `skip_zero_initialization` is set
+    let mut status = InitTracker::initialized();
+    let mut mapping = [0xFFu8; 16];
+    assert!(!zero_uninitialized_mapping(&mut status, &mut mapping, 0));
+    assert_eq!(mapping, [0xFF; 16]);
+}
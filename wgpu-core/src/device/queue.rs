@@ -5,11 +5,17 @@
 #[cfg(feature = "trace")]
 use crate::device::trace::Action;
 use crate::{
-    command::{CommandAllocator, CommandBuffer, TextureCopyView, BITS_PER_BYTE},
+    command::{
+        texture_block_info, validate_linear_texture_data, validate_texture_copy_range,
+        zero_clear_value, CommandAllocator, CommandBuffer, CopySide, TextureCopyView,
+        TransferError,
+    },
     conv,
-    hub::{GfxBackend, Global, GlobalIdentityHandlerFactory, Token},
+    device::ErrorContext,
+    hub::{GfxBackend, Global, GlobalIdentityHandlerFactory, Storage, Token},
     id,
-    resource::{BufferMapState, BufferUse, TextureUse},
+    resource::{Buffer, BufferMapState, BufferUse, Texture, TextureUse},
+    track::TrackerSet,
 };
 
 use gfx_memory::{Block, Heaps, MemoryBlock};
@@ -17,6 +23,58 @@ use hal::{command::CommandBuffer as _, device::Device as _, queue::CommandQueue
 use smallvec::SmallVec;
 use std::iter;
 
+#[derive(Clone, Debug)]
+pub enum QueueWriteError {
+    /// The destination buffer is invalid.
+    InvalidBuffer(id::BufferId),
+    /// The destination texture is invalid.
+    InvalidTexture(id::TextureId),
+    /// The destination usage doesn't contain `COPY_DST`.
+    MissingCopyDstUsage,
+    /// The texture data layout or copy range is invalid.
+    Transfer(TransferError),
+    /// The write size must be a multiple of `COPY_BUFFER_ALIGNMENT`.
+    UnalignedSize(wgt::BufferAddress),
+    /// The destination offset must be a multiple of `COPY_BUFFER_ALIGNMENT`.
+    UnalignedOffset(wgt::BufferAddress),
+    /// The write overruns the destination buffer.
+    BufferOverrun {
+        end: wgt::BufferAddress,
+        size: wgt::BufferAddress,
+    },
+    /// Not enough memory to allocate the staging buffer.
+    OutOfMemory,
+    /// Unable to map the staging buffer memory.
+    MapFailed,
+    /// The device is lost.
+    DeviceLost,
+}
+
+impl From<hal::device::MapError> for QueueWriteError {
+    fn from(error: hal::device::MapError) -> Self {
+        match error {
+            hal::device::MapError::OutOfMemory(_) => QueueWriteError::OutOfMemory,
+            _ => QueueWriteError::MapFailed,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum QueueSubmitError {
+    /// The command buffer hasn't been finished.
+    StillRecording(id::CommandBufferId),
+    /// The command buffer is invalid, or a command recorded into it failed.
+    InvalidCommandBuffer(id::CommandBufferId),
+    /// A buffer used by the command buffer is invalid.
+    InvalidBuffer(id::BufferId),
+    /// The swap chain output was dropped before the command buffer got submitted.
+    SwapChainOutputDropped(id::SwapChainId),
+    /// A buffer used by the command buffer has a mapping pending.
+    BufferStillMapped(id::BufferId),
+    /// The device is lost.
+    DeviceLost,
+}
+
 struct StagingData<B: hal::Backend> {
     buffer: B::Buffer,
     memory: MemoryBlock<B>,
@@ -76,32 +134,45 @@ impl<B: hal::Backend> super::Device<B> {
         self.pending_writes.command_buffer.as_mut().unwrap()
     }
 
-    fn prepare_stage(&mut self, size: wgt::BufferAddress) -> StagingData<B> {
+    fn create_staging_buffer(
+        &self,
+        size: wgt::BufferAddress,
+        name: &str,
+    ) -> Result<(B::Buffer, MemoryBlock<B>), QueueWriteError> {
         let mut buffer = unsafe {
             self.raw
                 .create_buffer(size, hal::buffer::Usage::TRANSFER_SRC)
-                .unwrap()
+                .map_err(|_| QueueWriteError::OutOfMemory)?
         };
         //TODO: do we need to transition into HOST_WRITE access first?
         let requirements = unsafe { self.raw.get_buffer_requirements(&buffer) };
 
-        let memory = self
-            .mem_allocator
-            .lock()
-            .allocate(
-                &self.raw,
-                &requirements,
-                gfx_memory::MemoryUsage::Staging { read_back: false },
-                gfx_memory::Kind::Linear,
-            )
-            .unwrap();
+        let memory = match self.mem_allocator.lock().allocate(
+            &self.raw,
+            &requirements,
+            gfx_memory::MemoryUsage::Staging { read_back: false },
+            gfx_memory::Kind::Linear,
+        ) {
+            Ok(memory) => memory,
+            Err(_) => {
+                unsafe { self.raw.destroy_buffer(buffer) };
+                return Err(QueueWriteError::OutOfMemory);
+            }
+        };
         unsafe {
-            self.raw.set_buffer_name(&mut buffer, "<write_buffer_temp>");
+            self.raw.set_buffer_name(&mut buffer, name);
             self.raw
                 .bind_buffer_memory(memory.memory(), memory.segment().offset, &mut buffer)
                 .unwrap();
         }
+        Ok((buffer, memory))
+    }
 
+    fn prepare_stage(
+        &mut self,
+        size: wgt::BufferAddress,
+    ) -> Result<StagingData<B>, QueueWriteError> {
+        let (buffer, memory) = self.create_staging_buffer(size, "<write_buffer_temp>")?;
         let comb = match self.pending_writes.command_buffer.take() {
             Some(comb) => comb,
             None => {
@@ -112,12 +183,218 @@ impl<B: hal::Backend> super::Device<B> {
                 comb
             }
         };
-        StagingData {
+        Ok(StagingData {
             buffer,
             memory,
             comb,
+        })
+    }
+
+    /// Report a staging error to the error scopes with the matching kind, and return it back.
+    fn capture_staging(&self, context: ErrorContext, error: QueueWriteError) -> QueueWriteError {
+        match error {
+            QueueWriteError::OutOfMemory => self.capture_oom(context, error),
+            _ => self.capture(context, error),
         }
     }
+
+    /// Zero-initialize a range of a compressed texture, which can't be cleared
+    /// with `clear_image`, by copying from a zeroed staging buffer.
+    ///
+    /// The range has to be in the `TransferDstOptimal` layout already. The returned
+    /// staging buffer has to be kept alive until the copy is done executing.
+    fn zero_compressed_texture(
+        &self,
+        raw: &mut B::CommandBuffer,
+        texture: &Texture<B>,
+        range: hal::image::SubresourceRange,
+    ) -> Result<(B::Buffer, MemoryBlock<B>), QueueWriteError> {
+        let (block_width, block_height, bytes_per_block) =
+            texture_block_info(texture.format, self.private_features);
+        let bytes_per_row_alignment = get_lowest_common_denom(
+            self.hal_limits.optimal_buffer_copy_pitch_alignment as u32,
+            bytes_per_block,
+        );
+        // The first level is the biggest one, the others reuse the start of the buffer.
+        let extent = texture.kind.level_extent(range.levels.start);
+        let bytes_per_row = align_to(
+            bytes_per_block * ((extent.width + block_width - 1) / block_width),
+            bytes_per_row_alignment,
+        );
+        let block_rows = (extent.height + block_height - 1) / block_height;
+        let size = bytes_per_row as wgt::BufferAddress
+            * block_rows as wgt::BufferAddress
+            * extent.depth as wgt::BufferAddress;
+
+        let (buffer, mut memory) = self.create_staging_buffer(size, "<zero_init_temp>")?;
+        if let Err(e) = write_staging(&self.raw, &mut memory, |slice| {
+            for byte in slice.iter_mut() {
+                *byte = 0;
+            }
+        }) {
+            self.mem_allocator.lock().free(&self.raw, memory);
+            unsafe { self.raw.destroy_buffer(buffer) };
+            return Err(e);
+        }
+
+        let regions = range.levels.clone().flat_map(|level| {
+            let extent = texture.kind.level_extent(level);
+            let aspects = range.aspects;
+            range
+                .layers
+                .clone()
+                .map(move |layer| hal::command::BufferImageCopy {
+                    buffer_offset: 0,
+                    buffer_width: bytes_per_row / bytes_per_block * block_width,
+                    buffer_height: block_rows * block_height,
+                    image_layers: hal::image::SubresourceLayers {
+                        aspects,
+                        level,
+                        layers: layer..layer + 1,
+                    },
+                    image_offset: hal::image::Offset { x: 0, y: 0, z: 0 },
+                    image_extent: extent,
+                })
+        });
+        unsafe {
+            raw.pipeline_barrier(
+                hal::pso::PipelineStage::HOST..hal::pso::PipelineStage::TRANSFER,
+                hal::memory::Dependencies::empty(),
+                iter::once(hal::memory::Barrier::Buffer {
+                    states: hal::buffer::Access::HOST_WRITE..hal::buffer::Access::TRANSFER_READ,
+                    target: &buffer,
+                    range: hal::buffer::SubRange::WHOLE,
+                    families: None,
+                }),
+            );
+            raw.copy_buffer_to_image(
+                &buffer,
+                &texture.raw,
+                hal::image::Layout::TransferDstOptimal,
+                regions,
+            );
+        }
+        Ok((buffer, memory))
+    }
+}
+
+/// Map the whole staging memory, and fill it with `fill`.
+fn write_staging<B: hal::Backend>(
+    device: &B::Device,
+    memory: &mut MemoryBlock<B>,
+    fill: impl FnOnce(&mut [u8]),
+) -> Result<(), QueueWriteError> {
+    let mut mapped = memory.map(device, hal::memory::Segment::ALL)?;
+    let mut mapping = unsafe { mapped.write(device, hal::memory::Segment::ALL) }?;
+    fill(&mut *mapping.slice);
+    Ok(())
+}
+
+/// Zero-initialize the parts of the given buffers and textures that haven't
+/// been initialized yet, by recording clears into `raw`.
+///
+/// Returns the staging buffers used for the compressed textures, which have
+/// to live until the submission is done.
+fn initialize_resources<B: hal::Backend>(
+    raw: &mut B::CommandBuffer,
+    device: &super::Device<B>,
+    trackers: &mut TrackerSet,
+    buffer_ids: impl Iterator<Item = id::BufferId>,
+    texture_ids: impl Iterator<Item = id::TextureId>,
+    buffer_guard: &mut Storage<Buffer<B>, id::BufferId>,
+    texture_guard: &mut Storage<Texture<B>, id::TextureId>,
+) -> Vec<(B::Buffer, MemoryBlock<B>)> {
+    let mut buffer_clears = Vec::new();
+    for id in buffer_ids {
+        let buffer = &mut buffer_guard[id];
+        let size = buffer.size;
+        buffer_clears.extend(
+            buffer
+                .initialization_status
+                .drain(0..size)
+                .into_iter()
+                .map(|range| (id, range)),
+        );
+    }
+    let mut texture_clears = Vec::new();
+    for id in texture_ids {
+        let texture = &mut texture_guard[id];
+        let aspects = texture.full_range.aspects;
+        let layer_end = texture.full_range.layers.end;
+        for (level, status) in texture.initialization_status.iter_mut().enumerate() {
+            let level = level as hal::image::Level;
+            texture_clears.extend(status.drain(0..layer_end).into_iter().map(|layers| {
+                let range = hal::image::SubresourceRange {
+                    aspects,
+                    levels: level..level + 1,
+                    layers,
+                };
+                (id, range)
+            }));
+        }
+    }
+    for (id, range) in buffer_clears {
+        log::trace!("Zero-initializing buffer {:?} range {:?}", id, range);
+        let (buffer, transition) = trackers
+            .buffers
+            .use_replace(&*buffer_guard, id, (), BufferUse::COPY_DST)
+            .unwrap();
+        unsafe {
+            raw.pipeline_barrier(
+                super::all_buffer_stages()..hal::pso::PipelineStage::TRANSFER,
+                hal::memory::Dependencies::empty(),
+                transition.map(|pending| pending.into_hal(buffer)),
+            );
+            raw.fill_buffer(
+                &buffer.raw,
+                hal::buffer::SubRange {
+                    offset: range.start,
+                    size: Some(range.end - range.start),
+                },
+                0,
+            );
+        }
+    }
+    let mut temp_buffers = Vec::new();
+    let mut failed_clears = Vec::new();
+    for (id, range) in texture_clears {
+        log::trace!("Zero-initializing texture {:?} range {:?}", id, range);
+        let (texture, transition) = trackers
+            .textures
+            .use_replace(&*texture_guard, id, range.clone(), TextureUse::COPY_DST)
+            .unwrap();
+        unsafe {
+            raw.pipeline_barrier(
+                super::all_image_stages()..hal::pso::PipelineStage::TRANSFER,
+                hal::memory::Dependencies::empty(),
+                transition.map(|pending| pending.into_hal(texture)),
+            );
+        }
+        if texture.format.is_compressed() {
+            match device.zero_compressed_texture(raw, texture, range.clone()) {
+                Ok(stage) => temp_buffers.push(stage),
+                Err(e) => {
+                    device.capture_staging(ErrorContext::new("queue_submit"), e);
+                    failed_clears.push((id, range));
+                }
+            }
+        } else {
+            unsafe {
+                raw.clear_image(
+                    &texture.raw,
+                    hal::image::Layout::TransferDstOptimal,
+                    zero_clear_value(range.aspects),
+                    iter::once(range),
+                );
+            }
+        }
+    }
+    // The subresources weren't cleared, so they have to be cleared again
+    // on the next use.
+    for (id, range) in failed_clears {
+        texture_guard[id].initialization_status[range.levels.start as usize].discard(range.layers);
+    }
+    temp_buffers
 }
 
 //TODO: move out common parts of write_xxx.
@@ -129,12 +406,16 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         buffer_id: id::BufferId,
         buffer_offset: wgt::BufferAddress,
         data: &[u8],
-    ) {
+    ) -> Result<(), QueueWriteError> {
         let hub = B::hub(self);
+        let context = ErrorContext::new("queue_write_buffer");
         let mut token = Token::root();
         let (mut device_guard, mut token) = hub.devices.write(&mut token);
         let device = &mut device_guard[queue_id];
-        let (buffer_guard, _) = hub.buffers.read(&mut token);
+        if device.is_lost() {
+            return Err(QueueWriteError::DeviceLost);
+        }
+        let (mut buffer_guard, _) = hub.buffers.write(&mut token);
 
         #[cfg(feature = "trace")]
         match device.trace {
@@ -154,57 +435,53 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         let data_size = data.len() as wgt::BufferAddress;
         if data_size == 0 {
             log::trace!("Ignoring write_buffer of size 0");
-            return;
+            return Ok(());
         }
 
-        let mut stage = device.prepare_stage(data_size);
-        {
-            let mut mapped = stage
-                .memory
-                .map(&device.raw, hal::memory::Segment::ALL)
-                .unwrap();
-            unsafe { mapped.write(&device.raw, hal::memory::Segment::ALL) }
-                .unwrap()
-                .slice[..data.len()]
-                .copy_from_slice(data);
+        let dst_buffer = match buffer_guard.get(buffer_id) {
+            Ok(buffer) => buffer,
+            Err(_) => {
+                return Err(device.capture(context, QueueWriteError::InvalidBuffer(buffer_id)))
+            }
+        };
+        if !dst_buffer.usage.contains(wgt::BufferUsage::COPY_DST) {
+            return Err(device.capture(context, QueueWriteError::MissingCopyDstUsage));
+        }
+        if data_size % wgt::COPY_BUFFER_ALIGNMENT != 0 {
+            return Err(device.capture(context, QueueWriteError::UnalignedSize(data_size)));
+        }
+        if buffer_offset % wgt::COPY_BUFFER_ALIGNMENT != 0 {
+            return Err(device.capture(context, QueueWriteError::UnalignedOffset(buffer_offset)));
+        }
+        let destination_end_offset = buffer_offset + data_size;
+        if destination_end_offset > dst_buffer.size {
+            return Err(device.capture(
+                context,
+                QueueWriteError::BufferOverrun {
+                    end: destination_end_offset,
+                    size: dst_buffer.size,
+                },
+            ));
+        }
+
+        let mut stage = device
+            .prepare_stage(data_size)
+            .map_err(|e| device.capture_staging(context, e))?;
+        if let Err(e) = write_staging(&device.raw, &mut stage.memory, |slice| {
+            slice[..data.len()].copy_from_slice(data)
+        }) {
+            // the staging buffer is freed along with the other pending writes
+            device.pending_writes.consume(stage);
+            return Err(device.capture_staging(context, e));
         }
 
         let mut trackers = device.trackers.lock();
-        let (dst, transition) =
-            trackers
-                .buffers
-                .use_replace(&*buffer_guard, buffer_id, (), BufferUse::COPY_DST);
-        assert!(
-            dst.usage.contains(wgt::BufferUsage::COPY_DST),
-            "Write buffer usage {:?} must contain flag COPY_DST",
-            dst.usage
-        );
+        let (dst, transition) = trackers
+            .buffers
+            .use_replace(&*buffer_guard, buffer_id, (), BufferUse::COPY_DST)
+            .unwrap();
         dst.life_guard.use_at(device.active_submission_index + 1);
 
-        assert_eq!(
-            data_size % wgt::COPY_BUFFER_ALIGNMENT,
-            0,
-            "Buffer write size {} must be a multiple of {}",
-            buffer_offset,
-            wgt::COPY_BUFFER_ALIGNMENT,
-        );
-        assert_eq!(
-            buffer_offset % wgt::COPY_BUFFER_ALIGNMENT,
-            0,
-            "Buffer offset {} must be a multiple of {}",
-            buffer_offset,
-            wgt::COPY_BUFFER_ALIGNMENT,
-        );
-        let destination_start_offset = buffer_offset;
-        let destination_end_offset = buffer_offset + data_size;
-        assert!(
-            destination_end_offset <= dst.size,
-            "Write buffer with indices {}..{} overruns destination buffer of size {}",
-            destination_start_offset,
-            destination_end_offset,
-            dst.size
-        );
-
         let region = hal::command::BufferCopy {
             src: 0,
             dst: buffer_offset,
@@ -228,6 +505,11 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         }
 
         device.pending_writes.consume(stage);
+        // the written range doesn't need to be zero-initialized any more
+        buffer_guard[buffer_id]
+            .initialization_status
+            .drain(buffer_offset..destination_end_offset);
+        Ok(())
     }
 
     pub fn queue_write_texture<B: GfxBackend>(
@@ -237,13 +519,25 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         data: &[u8],
         data_layout: &wgt::TextureDataLayout,
         size: &wgt::Extent3d,
-    ) {
+    ) -> Result<(), QueueWriteError> {
         let hub = B::hub(self);
+        let context = ErrorContext::new("queue_write_texture");
         let mut token = Token::root();
         let (mut device_guard, mut token) = hub.devices.write(&mut token);
         let device = &mut device_guard[queue_id];
-        let (texture_guard, _) = hub.textures.read(&mut token);
-        let (image_layers, image_range, image_offset) = destination.to_hal(&*texture_guard);
+        if device.is_lost() {
+            return Err(QueueWriteError::DeviceLost);
+        }
+        let (mut texture_guard, _) = hub.textures.write(&mut token);
+        let dst_texture = match texture_guard.get(destination.texture) {
+            Ok(texture) => texture,
+            Err(_) => {
+                return Err(device.capture(
+                    context,
+                    QueueWriteError::InvalidTexture(destination.texture),
+                ))
+            }
+        };
 
         #[cfg(feature = "trace")]
         match device.trace {
@@ -260,77 +554,125 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
             None => {}
         }
 
-        if size.width == 0 || size.height == 0 || size.width == 0 {
+        if size.width == 0 || size.height == 0 || size.depth == 0 {
             log::trace!("Ignoring write_texture of size 0");
-            return;
+            return Ok(());
+        }
+
+        if !dst_texture.usage.contains(wgt::TextureUsage::COPY_DST) {
+            return Err(device.capture(context, QueueWriteError::MissingCopyDstUsage));
         }
 
-        let texture_format = texture_guard[destination.texture].format;
-        let bytes_per_texel = conv::map_texture_format(texture_format, device.private_features)
-            .surface_desc()
-            .bits as u32
-            / BITS_PER_BYTE;
-        crate::command::validate_linear_texture_data(
+        let texture_format = dst_texture.format;
+        let (block_width, block_height, bytes_per_block) =
+            texture_block_info(texture_format, device.private_features);
+        validate_texture_copy_range(
+            destination,
+            dst_texture.kind,
+            CopySide::Destination,
+            (block_width, block_height),
+            size,
+        )
+        .map_err(|e| device.capture(context, QueueWriteError::Transfer(e)))?;
+        validate_linear_texture_data(
             data_layout,
             data.len() as wgt::BufferAddress,
-            bytes_per_texel as wgt::BufferAddress,
+            CopySide::Source,
+            bytes_per_block as wgt::BufferAddress,
+            (block_width, block_height),
             size,
-        );
+        )
+        .map_err(|e| device.capture(context, QueueWriteError::Transfer(e)))?;
+        let (image_layers, image_range, image_offset) = destination
+            .to_hal(&*texture_guard, size, CopySide::Destination)
+            .map_err(|e| device.capture(context, QueueWriteError::Transfer(e)))?;
+
+        // A write covering the whole mip level initializes the layers it touches,
+        // otherwise the rest of those layers has to be zeroed first.
+        let full_extent = {
+            let extent = dst_texture.kind.level_extent(destination.mip_level as _);
+            let (origin, depth) = match dst_texture.dimension {
+                wgt::TextureDimension::D3 => (destination.origin, extent.depth),
+                _ => (
+                    wgt::Origin3d {
+                        z: 0,
+                        ..destination.origin
+                    },
+                    size.depth,
+                ),
+            };
+            origin == wgt::Origin3d::ZERO
+                && size.width >= extent.width
+                && size.height >= extent.height
+                && size.depth >= depth
+        };
+        let is_compressed = (block_width, block_height) != (1, 1);
+        let uninitialized_layers = match texture_guard[destination.texture]
+            .initialization_status
+            .get_mut(destination.mip_level as usize)
+        {
+            Some(status) => status.drain(image_range.layers.clone()),
+            None => SmallVec::new(),
+        };
 
         let bytes_per_row_alignment = get_lowest_common_denom(
             device.hal_limits.optimal_buffer_copy_pitch_alignment as u32,
-            bytes_per_texel,
+            bytes_per_block,
+        );
+        let stage_bytes_per_row = align_to(
+            bytes_per_block * size.width / block_width,
+            bytes_per_row_alignment,
         );
-        let stage_bytes_per_row = align_to(bytes_per_texel * size.width, bytes_per_row_alignment);
+        // Rows of a compressed texture are rows of blocks.
+        let block_rows_per_image = data_layout.rows_per_image / block_height;
+        let block_rows = size.height / block_height;
         let stage_size = stage_bytes_per_row as u64
-            * ((size.depth - 1) * data_layout.rows_per_image + size.height) as u64;
-        let mut stage = device.prepare_stage(stage_size);
-        {
-            let mut mapped = stage
-                .memory
-                .map(&device.raw, hal::memory::Segment::ALL)
-                .unwrap();
-            let mapping = unsafe { mapped.write(&device.raw, hal::memory::Segment::ALL) }.unwrap();
+            * ((size.depth - 1) * block_rows_per_image + block_rows) as u64;
+        let mut stage = device
+            .prepare_stage(stage_size)
+            .map_err(|e| device.capture_staging(context, e))?;
+        if let Err(e) = write_staging(&device.raw, &mut stage.memory, |slice| {
             if stage_bytes_per_row == data_layout.bytes_per_row {
                 // Unlikely case of the data already being aligned optimally.
-                mapping.slice[..stage_size as usize].copy_from_slice(data);
+                slice[..stage_size as usize].copy_from_slice(data);
             } else {
                 // Copy row by row into the optimal alignment.
                 let copy_bytes_per_row =
                     stage_bytes_per_row.min(data_layout.bytes_per_row) as usize;
                 for layer in 0..size.depth {
-                    let rows_offset = layer * data_layout.rows_per_image;
-                    for row in 0..size.height {
+                    let rows_offset = layer * block_rows_per_image;
+                    for row in 0..block_rows {
                         let data_offset =
                             (rows_offset + row) as usize * data_layout.bytes_per_row as usize;
                         let stage_offset =
                             (rows_offset + row) as usize * stage_bytes_per_row as usize;
-                        mapping.slice[stage_offset..stage_offset + copy_bytes_per_row]
+                        slice[stage_offset..stage_offset + copy_bytes_per_row]
                             .copy_from_slice(&data[data_offset..data_offset + copy_bytes_per_row]);
                     }
                 }
             }
+        }) {
+            // the staging buffer is freed along with the other pending writes
+            device.pending_writes.consume(stage);
+            return Err(device.capture_staging(context, e));
         }
 
         let mut trackers = device.trackers.lock();
-        let (dst, transition) = trackers.textures.use_replace(
-            &*texture_guard,
-            destination.texture,
-            image_range,
-            TextureUse::COPY_DST,
-        );
-        assert!(
-            dst.usage.contains(wgt::TextureUsage::COPY_DST),
-            "Write texture usage {:?} must contain flag COPY_DST",
-            dst.usage
-        );
-        crate::command::validate_texture_copy_range(destination, dst.kind, size);
+        let (dst, transition) = trackers
+            .textures
+            .use_replace(
+                &*texture_guard,
+                destination.texture,
+                image_range.clone(),
+                TextureUse::COPY_DST,
+            )
+            .unwrap();
 
         dst.life_guard.use_at(device.active_submission_index + 1);
 
         let region = hal::command::BufferImageCopy {
             buffer_offset: 0,
-            buffer_width: stage_bytes_per_row / bytes_per_texel,
+            buffer_width: stage_bytes_per_row / bytes_per_block * block_width,
             buffer_height: data_layout.rows_per_image,
             image_layers,
             image_offset,
@@ -349,6 +691,51 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                 })
                 .chain(transition.map(|pending| pending.into_hal(dst))),
             );
+            if !full_extent && !uninitialized_layers.is_empty() {
+                if is_compressed {
+                    for layers in uninitialized_layers.iter() {
+                        let range = hal::image::SubresourceRange {
+                            layers: layers.clone(),
+                            ..image_range.clone()
+                        };
+                        match device.zero_compressed_texture(&mut stage.comb, dst, range) {
+                            Ok((buffer, memory)) => {
+                                device.pending_writes.consume_temp(buffer, memory)
+                            }
+                            Err(e) => {
+                                device.pending_writes.consume(stage);
+                                return Err(device.capture_staging(context, e));
+                            }
+                        }
+                    }
+                } else {
+                    stage.comb.clear_image(
+                        &dst.raw,
+                        hal::image::Layout::TransferDstOptimal,
+                        zero_clear_value(image_range.aspects),
+                        uninitialized_layers
+                            .iter()
+                            .map(|layers| hal::image::SubresourceRange {
+                                layers: layers.clone(),
+                                ..image_range.clone()
+                            }),
+                    );
+                }
+                let state = (
+                    hal::image::Access::TRANSFER_WRITE,
+                    hal::image::Layout::TransferDstOptimal,
+                );
+                stage.comb.pipeline_barrier(
+                    hal::pso::PipelineStage::TRANSFER..hal::pso::PipelineStage::TRANSFER,
+                    hal::memory::Dependencies::empty(),
+                    iter::once(hal::memory::Barrier::Image {
+                        states: state..state,
+                        target: &dst.raw,
+                        range: image_range.clone(),
+                        families: None,
+                    }),
+                );
+            }
             stage.comb.copy_buffer_to_image(
                 &stage.buffer,
                 &dst.raw,
@@ -358,19 +745,66 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         }
 
         device.pending_writes.consume(stage);
+        Ok(())
     }
 
     pub fn queue_submit<B: GfxBackend>(
         &self,
         queue_id: id::QueueId,
         command_buffer_ids: &[id::CommandBufferId],
-    ) {
+    ) -> Result<(), QueueSubmitError> {
         let hub = B::hub(self);
+        let context = ErrorContext::new("queue_submit");
 
         let callbacks = {
             let mut token = Token::root();
             let (mut device_guard, mut token) = hub.devices.write(&mut token);
             let device = &mut device_guard[queue_id];
+            if device.is_lost() {
+                return Err(QueueSubmitError::DeviceLost);
+            }
+
+            // validate the command buffers before touching any state
+            {
+                let (swap_chain_guard, mut token) = hub.swap_chains.read(&mut token);
+                let (command_buffer_guard, mut token) = hub.command_buffers.read(&mut token);
+                let (buffer_guard, _) = hub.buffers.read(&mut token);
+                for &cmb_id in command_buffer_ids {
+                    let comb = match command_buffer_guard.get(cmb_id) {
+                        Ok(comb) if comb.is_valid => comb,
+                        _ => {
+                            return Err(device
+                                .capture(context, QueueSubmitError::InvalidCommandBuffer(cmb_id)))
+                        }
+                    };
+                    if comb.is_recording {
+                        return Err(
+                            device.capture(context, QueueSubmitError::StillRecording(cmb_id))
+                        );
+                    }
+                    if let Some((ref sc_id, _)) = comb.used_swap_chain {
+                        if swap_chain_guard[sc_id.value].acquired_view_id.is_none() {
+                            return Err(device.capture(
+                                context,
+                                QueueSubmitError::SwapChainOutputDropped(sc_id.value),
+                            ));
+                        }
+                    }
+                    for id in comb.trackers.buffers.used() {
+                        let buffer = buffer_guard.get(id).map_err(|_| {
+                            device.capture(context, QueueSubmitError::InvalidBuffer(id))
+                        })?;
+                        if let BufferMapState::Waiting(_) = buffer.map_state {
+                            return Err(
+                                device.capture(context, QueueSubmitError::BufferStillMapped(id))
+                            );
+                        }
+                    }
+                }
+            }
+            // From here on, the command buffers and their resources are known to be valid,
+            // and they can't be destroyed while the device is locked.
+
             let pending_write_command_buffer =
                 device
                     .pending_writes
@@ -393,8 +827,9 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                     let (bind_group_guard, mut token) = hub.bind_groups.read(&mut token);
                     let (compute_pipe_guard, mut token) = hub.compute_pipelines.read(&mut token);
                     let (render_pipe_guard, mut token) = hub.render_pipelines.read(&mut token);
+                    let (query_set_guard, mut token) = hub.query_sets.read(&mut token);
                     let (mut buffer_guard, mut token) = hub.buffers.write(&mut token);
-                    let (texture_guard, mut token) = hub.textures.read(&mut token);
+                    let (mut texture_guard, mut token) = hub.textures.write(&mut token);
                     let (texture_view_guard, mut token) = hub.texture_views.read(&mut token);
                     let (sampler_guard, _) = hub.samplers.read(&mut token);
 
@@ -419,9 +854,6 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                         if let Some((sc_id, fbo)) = comb.used_swap_chain.take() {
                             let sc = &mut swap_chain_guard[sc_id.value];
                             sc.active_submission_index = submit_index;
-                            assert!(sc.acquired_view_id.is_some(),
-                                "SwapChainOutput for {:?} was dropped before the respective command buffer {:?} got submitted!",
-                                sc_id.value, cmb_id);
                             if sc.acquired_framebuffers.is_empty() {
                                 signal_swapchain_semaphores.push(sc_id.value);
                             }
@@ -433,9 +865,6 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
 
                         // update submission IDs
                         for id in comb.trackers.buffers.used() {
-                            if let BufferMapState::Waiting(_) = buffer_guard[id].map_state {
-                                panic!("Buffer has a pending mapping.");
-                            }
                             if !buffer_guard[id].life_guard.use_at(submit_index) {
                                 if let BufferMapState::Active { .. } = buffer_guard[id].map_state {
                                     log::warn!("Dropped buffer has a pending mapping.");
@@ -464,6 +893,11 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                                 device.temp_suspected.samplers.push(id);
                             }
                         }
+                        for id in comb.trackers.query_sets.used() {
+                            if !query_set_guard[id].life_guard.use_at(submit_index) {
+                                device.temp_suspected.query_sets.push(id);
+                            }
+                        }
                         for id in comb.trackers.compute_pipes.used() {
                             if !compute_pipe_guard[id].life_guard.use_at(submit_index) {
                                 device.temp_suspected.compute_pipelines.push(id);
@@ -484,6 +918,16 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                                 .begin_primary(hal::command::CommandBufferFlags::ONE_TIME_SUBMIT);
                         }
                         log::trace!("Stitching command buffer {:?} before submission", cmb_id);
+                        let temp_buffers = initialize_resources(
+                            &mut transit,
+                            device,
+                            &mut *trackers,
+                            comb.trackers.buffers.used(),
+                            comb.trackers.textures.used(),
+                            &mut *buffer_guard,
+                            &mut *texture_guard,
+                        );
+                        device.pending_writes.temp_buffers.extend(temp_buffers);
                         CommandBuffer::insert_barriers(
                             &mut transit,
                             &mut *trackers,
@@ -491,7 +935,22 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                             &*buffer_guard,
                             &*texture_guard,
                         );
+                        // surfaces discarded by the command buffer have to be cleared
+                        // again before their next use
+                        for surface in comb.discarded_surfaces.drain(..) {
+                            let texture = &mut texture_guard[surface.texture_id];
+                            for level in surface.range.levels.clone() {
+                                texture.initialization_status[level as usize]
+                                    .discard(surface.range.layers.clone());
+                            }
+                        }
                         unsafe {
+                            for (query_set_id, query_index) in comb.query_resets.drain(..) {
+                                transit.reset_query_pool(
+                                    &query_set_guard[query_set_id].raw,
+                                    query_index..query_index + 1,
+                                );
+                            }
                             transit.finish();
                         }
                         comb.raw.insert(0, transit);
@@ -537,13 +996,16 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
             // finally, return the command buffers to the allocator
             for &cmb_id in command_buffer_ids {
                 let (cmd_buf, _) = hub.command_buffers.unregister(cmb_id, &mut token);
-                device.com_allocator.after_submit(cmd_buf, submit_index);
+                device
+                    .com_allocator
+                    .after_submit(cmd_buf.unwrap(), submit_index);
             }
 
             callbacks
         };
 
-        super::fire_map_callbacks(callbacks);
+        callbacks.fire();
+        Ok(())
     }
 }
 
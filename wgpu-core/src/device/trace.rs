@@ -11,7 +11,32 @@ use std::ops::Range;
 
 type FileName = String;
 
+pub use super::TraceFormat;
+
 pub const FILE_NAME: &str = "trace.ron";
+pub const BINARY_FILE_NAME: &str = "trace.bin";
+/// Magic number at the start of binary traces, followed by the
+/// little-endian `BINARY_VERSION`. Each action is then stored as
+/// a little-endian `u32` size, followed by the bincode-encoded `Action`.
+pub const BINARY_MAGIC: [u8; 8] = *b"WGPUTRCE";
+pub const BINARY_VERSION: u32 = 1;
+
+impl TraceFormat {
+    pub fn file_name(self) -> &'static str {
+        match self {
+            TraceFormat::Ron => FILE_NAME,
+            TraceFormat::Binary => BINARY_FILE_NAME,
+        }
+    }
+
+    /// Find out which format the trace in the `dir` directory has been written in.
+    pub fn detect(dir: &std::path::Path) -> Option<Self> {
+        [TraceFormat::Binary, TraceFormat::Ron]
+            .iter()
+            .cloned()
+            .find(|format| dir.join(format.file_name()).is_file())
+    }
+}
 
 #[derive(Debug)]
 #[cfg_attr(feature = "trace", derive(serde::Serialize))]
@@ -53,6 +78,7 @@ impl ProgrammableStageDescriptor {
 pub struct ComputePipelineDescriptor {
     pub layout: id::PipelineLayoutId,
     pub compute_stage: ProgrammableStageDescriptor,
+    pub cache: Option<id::PipelineCacheId>,
 }
 
 #[derive(Debug)]
@@ -80,6 +106,7 @@ pub struct RenderPipelineDescriptor {
     pub vertex_stage: ProgrammableStageDescriptor,
     pub fragment_stage: Option<ProgrammableStageDescriptor>,
     pub primitive_topology: wgt::PrimitiveTopology,
+    pub primitive_restart: bool,
     pub rasterization_state: Option<wgt::RasterizationStateDescriptor>,
     pub color_states: Vec<wgt::ColorStateDescriptor>,
     pub depth_stencil_state: Option<wgt::DepthStencilStateDescriptor>,
@@ -87,6 +114,51 @@ pub struct RenderPipelineDescriptor {
     pub sample_count: u32,
     pub sample_mask: u32,
     pub alpha_to_coverage_enabled: bool,
+    pub cache: Option<id::PipelineCacheId>,
+}
+
+#[cfg(feature = "trace")]
+impl RenderPipelineDescriptor {
+    pub fn new(desc: &crate::pipeline::RenderPipelineDescriptor) -> Self {
+        let desc_vbs = unsafe {
+            std::slice::from_raw_parts(
+                desc.vertex_state.vertex_buffers,
+                desc.vertex_state.vertex_buffers_length,
+            )
+        };
+        RenderPipelineDescriptor {
+            layout: desc.layout,
+            vertex_stage: ProgrammableStageDescriptor::new(&desc.vertex_stage),
+            fragment_stage: unsafe { desc.fragment_stage.as_ref() }
+                .map(ProgrammableStageDescriptor::new),
+            primitive_topology: desc.primitive_topology,
+            primitive_restart: desc.primitive_restart,
+            rasterization_state: unsafe { desc.rasterization_state.as_ref() }.cloned(),
+            color_states: unsafe {
+                std::slice::from_raw_parts(desc.color_states, desc.color_states_length)
+            }
+            .to_vec(),
+            depth_stencil_state: unsafe { desc.depth_stencil_state.as_ref() }.cloned(),
+            vertex_state: VertexStateDescriptor {
+                index_format: desc.vertex_state.index_format,
+                vertex_buffers: desc_vbs
+                    .iter()
+                    .map(|vbl| VertexBufferLayoutDescriptor {
+                        array_stride: vbl.array_stride,
+                        step_mode: vbl.step_mode,
+                        attributes: unsafe {
+                            std::slice::from_raw_parts(vbl.attributes, vbl.attributes_length)
+                        }
+                        .to_vec(),
+                    })
+                    .collect(),
+            },
+            sample_count: desc.sample_count,
+            sample_mask: desc.sample_mask,
+            alpha_to_coverage_enabled: desc.alpha_to_coverage_enabled,
+            cache: desc.cache,
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -118,6 +190,7 @@ pub enum Action {
     Init {
         desc: wgt::DeviceDescriptor,
         backend: wgt::Backend,
+        pipeline_cache_data: Option<FileName>,
     },
     CreateBuffer {
         id: id::BufferId,
@@ -140,6 +213,11 @@ pub enum Action {
         desc: wgt::SamplerDescriptor<String>,
     },
     DestroySampler(id::SamplerId),
+    CreateQuerySet {
+        id: id::QuerySetId,
+        desc: wgt::QuerySetDescriptor<String>,
+    },
+    DestroyQuerySet(id::QuerySetId),
     CreateSwapChain {
         id: id::SwapChainId,
         desc: wgt::SwapChainDescriptor,
@@ -152,12 +230,13 @@ pub enum Action {
     CreateBindGroupLayout {
         id: id::BindGroupLayoutId,
         label: String,
-        entries: Vec<wgt::BindGroupLayoutEntry>,
+        entries: Vec<crate::binding_model::BindGroupLayoutEntry>,
     },
     DestroyBindGroupLayout(id::BindGroupLayoutId),
     CreatePipelineLayout {
         id: id::PipelineLayoutId,
         bind_group_layouts: Vec<id::BindGroupLayoutId>,
+        push_constant_ranges: Vec<wgt::PushConstantRange>,
     },
     DestroyPipelineLayout(id::PipelineLayoutId),
     CreateBindGroup {
@@ -172,6 +251,11 @@ pub enum Action {
         data: FileName,
     },
     DestroyShaderModule(id::ShaderModuleId),
+    CreatePipelineCache {
+        id: id::PipelineCacheId,
+        data: Option<FileName>,
+    },
+    DestroyPipelineCache(id::PipelineCacheId),
     CreateComputePipeline {
         id: id::ComputePipelineId,
         desc: ComputePipelineDescriptor,
@@ -187,12 +271,15 @@ pub enum Action {
         desc: RenderBundleDescriptor,
         commands: Vec<crate::command::RenderCommand>,
         dynamic_offsets: Vec<wgt::DynamicOffset>,
+        push_constant_data: Vec<u32>,
     },
     DestroyRenderBundle(id::RenderBundleId),
     WriteBuffer {
         id: id::BufferId,
         data: FileName,
         range: Range<wgt::BufferAddress>,
+        /// Whether the data was written with `queue_write_buffer`,
+        /// as opposed to through a mapping of the buffer.
         queued: bool,
     },
     WriteTexture {
@@ -230,37 +317,670 @@ pub enum Command {
         dst: crate::command::TextureCopyView,
         size: wgt::Extent3d,
     },
+    ClearBuffer {
+        dst: id::BufferId,
+        offset: wgt::BufferAddress,
+        size: wgt::BufferSize,
+    },
+    ClearTexture {
+        dst: id::TextureId,
+        subresource_range: wgt::ImageSubresourceRange,
+    },
+    WriteTimestamp {
+        query_set_id: id::QuerySetId,
+        query_index: u32,
+    },
+    ResolveQuerySet {
+        query_set_id: id::QuerySetId,
+        start_query: u32,
+        query_count: u32,
+        destination: id::BufferId,
+        destination_offset: wgt::BufferAddress,
+    },
     RunComputePass {
         commands: Vec<crate::command::ComputeCommand>,
         dynamic_offsets: Vec<wgt::DynamicOffset>,
+        push_constant_data: Vec<u32>,
     },
     RunRenderPass {
         target_colors: Vec<crate::command::RenderPassColorAttachmentDescriptor>,
         target_depth_stencil: Option<crate::command::RenderPassDepthStencilAttachmentDescriptor>,
+        occlusion_query_set: Option<id::QuerySetId>,
         commands: Vec<crate::command::RenderCommand>,
         dynamic_offsets: Vec<wgt::DynamicOffset>,
+        push_constant_data: Vec<u32>,
     },
 }
 
+/// Writes actions into a trace file of the given format.
 #[cfg(feature = "trace")]
 #[derive(Debug)]
-pub struct Trace {
-    path: std::path::PathBuf,
+pub struct Writer {
     file: std::fs::File,
+    format: TraceFormat,
     config: ron::ser::PrettyConfig,
+}
+
+#[cfg(feature = "trace")]
+impl Writer {
+    /// Create the trace file for `format` in the `dir` directory.
+    pub fn new(dir: &std::path::Path, format: TraceFormat) -> Result<Self, std::io::Error> {
+        let mut file = std::fs::File::create(dir.join(format.file_name()))?;
+        match format {
+            TraceFormat::Ron => file.write_all(b"[\n")?,
+            TraceFormat::Binary => {
+                file.write_all(&BINARY_MAGIC)?;
+                file.write_all(&BINARY_VERSION.to_le_bytes())?;
+            }
+        }
+        Ok(Writer {
+            file,
+            format,
+            config: ron::ser::PrettyConfig::default(),
+        })
+    }
+
+    pub fn write(&mut self, action: &Action) -> Result<(), WriteError> {
+        match self.format {
+            TraceFormat::Ron => {
+                let string = ron::ser::to_string_pretty(action, self.config.clone())
+                    .map_err(WriteError::Ron)?;
+                writeln!(self.file, "{},", string).map_err(WriteError::Io)
+            }
+            TraceFormat::Binary => {
+                let size = bincode::serialized_size(action).map_err(WriteError::Binary)?;
+                // Write the whole record at once, so that a crash doesn't leave
+                // a size without the data behind.
+                let mut record = Vec::with_capacity(4 + size as usize);
+                record.extend_from_slice(&(size as u32).to_le_bytes());
+                bincode::serialize_into(&mut record, action).map_err(WriteError::Binary)?;
+                self.file.write_all(&record).map_err(WriteError::Io)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "trace")]
+impl Drop for Writer {
+    fn drop(&mut self) {
+        if self.format == TraceFormat::Ron {
+            let _ = self.file.write_all(b"]");
+        }
+    }
+}
+
+#[cfg(feature = "trace")]
+#[derive(Debug)]
+pub enum WriteError {
+    Io(std::io::Error),
+    Ron(ron::ser::Error),
+    Binary(bincode::Error),
+}
+
+/// Reads the actions of a trace one by one.
+///
+/// A trace of a crashed application is missing the closing bracket,
+/// and its last action may be cut in the middle. The reader stops
+/// at the last complete action in this case.
+#[cfg(feature = "replay")]
+#[derive(Debug)]
+pub struct Reader {
+    file: std::io::BufReader<std::fs::File>,
+    format: TraceFormat,
+    backend: Option<wgt::Backend>,
+    done: bool,
+}
+
+#[cfg(feature = "replay")]
+impl Reader {
+    /// Open the trace in the `dir` directory,
+    /// picking the binary trace file if both are present.
+    pub fn open(dir: &std::path::Path) -> Result<Self, ReadError> {
+        use std::io::{BufRead as _, Read as _};
+
+        let format = TraceFormat::detect(dir).ok_or(ReadError::NotFound)?;
+        let file = std::fs::File::open(dir.join(format.file_name())).map_err(ReadError::Io)?;
+        let mut file = std::io::BufReader::new(file);
+        match format {
+            TraceFormat::Ron => {
+                let mut prefix = Vec::new();
+                file.read_until(b'[', &mut prefix).map_err(ReadError::Io)?;
+                if prefix.last() != Some(&b'[')
+                    || !prefix[..prefix.len() - 1]
+                        .iter()
+                        .all(u8::is_ascii_whitespace)
+                {
+                    return Err(ReadError::InvalidHeader);
+                }
+            }
+            TraceFormat::Binary => {
+                let mut header = [0u8; 12];
+                file.read_exact(&mut header).map_err(|e| match e.kind() {
+                    std::io::ErrorKind::UnexpectedEof => ReadError::InvalidHeader,
+                    _ => ReadError::Io(e),
+                })?;
+                if header[..8] != BINARY_MAGIC {
+                    return Err(ReadError::InvalidHeader);
+                }
+                let version = u32::from_le_bytes([header[8], header[9], header[10], header[11]]);
+                if version != BINARY_VERSION {
+                    return Err(ReadError::UnsupportedVersion(version));
+                }
+            }
+        }
+        Ok(Reader {
+            file,
+            format,
+            backend: None,
+            done: false,
+        })
+    }
+
+    pub fn format(&self) -> TraceFormat {
+        self.format
+    }
+
+    /// Replace the backend the trace was recorded on, both in `Action::Init`
+    /// and in all the IDs of the following actions.
+    pub fn override_backend(&mut self, backend: wgt::Backend) {
+        self.backend = Some(backend);
+    }
+
+    fn next_ron(&mut self) -> Result<Option<Action>, ReadError> {
+        use std::io::BufRead as _;
+
+        // Split the list into entries by looking for the top-level commas,
+        // so that each action can be parsed on its own.
+        let mut entry = Vec::new();
+        let mut depth = 0usize;
+        let mut in_string = false;
+        let mut escaped = false;
+        let mut unterminated = false;
+        let closed = loop {
+            let buf = self.file.fill_buf().map_err(ReadError::Io)?;
+            if buf.is_empty() {
+                if entry.iter().all(u8::is_ascii_whitespace) {
+                    log::warn!("Trace is not terminated, the application may have crashed");
+                    return Ok(None);
+                }
+                if depth != 0 || in_string {
+                    log::warn!("Trace is truncated, skipping the incomplete last action");
+                    return Ok(None);
+                }
+                unterminated = true;
+                break true;
+            }
+            let mut end = None;
+            for (i, &byte) in buf.iter().enumerate() {
+                if in_string {
+                    if escaped {
+                        escaped = false;
+                    } else if byte == b'\\' {
+                        escaped = true;
+                    } else if byte == b'"' {
+                        in_string = false;
+                    }
+                    continue;
+                }
+                match byte {
+                    b'"' => in_string = true,
+                    b'(' | b'[' | b'{' => depth += 1,
+                    b')' | b']' | b'}' if depth != 0 => depth -= 1,
+                    b']' => {
+                        end = Some((i, true));
+                        break;
+                    }
+                    b',' if depth == 0 => {
+                        end = Some((i, false));
+                        break;
+                    }
+                    _ => {}
+                }
+            }
+            match end {
+                Some((i, closed)) => {
+                    entry.extend_from_slice(&buf[..i]);
+                    self.file.consume(i + 1);
+                    break closed;
+                }
+                None => {
+                    let len = buf.len();
+                    entry.extend_from_slice(buf);
+                    self.file.consume(len);
+                }
+            }
+        };
+
+        if closed {
+            self.done = true;
+            if entry.iter().all(u8::is_ascii_whitespace) {
+                return Ok(None);
+            }
+        }
+        let text = String::from_utf8_lossy(&entry);
+        match ron::de::from_str(&text) {
+            Ok(action) => Ok(Some(action)),
+            // The brackets may happen to be balanced where the trace was cut.
+            Err(_) if unterminated => {
+                log::warn!("Trace is truncated, skipping the incomplete last action");
+                Ok(None)
+            }
+            Err(e) => Err(ReadError::Ron(e)),
+        }
+    }
+
+    fn next_binary(&mut self) -> Result<Option<Action>, ReadError> {
+        use std::io::Read as _;
+
+        let mut size = [0u8; 4];
+        match read_until_eof(&mut self.file, &mut size).map_err(ReadError::Io)? {
+            0 => return Ok(None),
+            4 => {}
+            _ => {
+                log::warn!("Trace is truncated, skipping the incomplete last action");
+                return Ok(None);
+            }
+        }
+        // The size of a truncated record can be garbage, so the buffer
+        // only grows as far as the data in the file goes.
+        let size = u32::from_le_bytes(size) as usize;
+        let mut data = Vec::new();
+        (&mut self.file)
+            .take(size as u64)
+            .read_to_end(&mut data)
+            .map_err(ReadError::Io)?;
+        if data.len() != size {
+            log::warn!("Trace is truncated, skipping the incomplete last action");
+            return Ok(None);
+        }
+        bincode::deserialize(&data)
+            .map(Some)
+            .map_err(ReadError::Binary)
+    }
+}
+
+/// Fill `buf` from `reader`, returning how many bytes were read
+/// before reaching the end of the file.
+#[cfg(feature = "replay")]
+fn read_until_eof(reader: &mut impl std::io::Read, buf: &mut [u8]) -> std::io::Result<usize> {
+    let mut total = 0;
+    while total < buf.len() {
+        match reader.read(&mut buf[total..]) {
+            Ok(0) => break,
+            Ok(count) => total += count,
+            Err(ref e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(total)
+}
+
+#[cfg(feature = "replay")]
+impl Iterator for Reader {
+    type Item = Result<Action, ReadError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let result = match self.format {
+            TraceFormat::Ron => self.next_ron(),
+            TraceFormat::Binary => self.next_binary(),
+        };
+        match result {
+            Ok(Some(mut action)) => {
+                if let Some(backend) = self.backend {
+                    action.set_backend(backend);
+                }
+                Some(Ok(action))
+            }
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+#[cfg(feature = "replay")]
+fn set_id_backend<T>(id: &mut id::Id<T>, backend: wgt::Backend) {
+    use crate::id::TypedId as _;
+    let (index, epoch, _) = id.unzip();
+    *id = id::Id::zip(index, epoch, backend);
+}
+
+#[cfg(feature = "replay")]
+fn set_copy_view_backend(view: &mut crate::command::TextureCopyView, backend: wgt::Backend) {
+    set_id_backend(&mut view.texture, backend);
+}
+
+#[cfg(feature = "replay")]
+fn set_render_command_backend(command: &mut crate::command::RenderCommand, backend: wgt::Backend) {
+    use crate::command::RenderCommand as C;
+    match *command {
+        C::SetBindGroup {
+            ref mut bind_group_id,
+            ..
+        } => set_id_backend(bind_group_id, backend),
+        C::SetPipeline(ref mut id) => set_id_backend(id, backend),
+        C::SetIndexBuffer {
+            ref mut buffer_id, ..
+        }
+        | C::SetVertexBuffer {
+            ref mut buffer_id, ..
+        }
+        | C::DrawIndirect {
+            ref mut buffer_id, ..
+        }
+        | C::DrawIndexedIndirect {
+            ref mut buffer_id, ..
+        }
+        | C::MultiDrawIndirect {
+            ref mut buffer_id, ..
+        } => set_id_backend(buffer_id, backend),
+        C::MultiDrawIndirectCount {
+            ref mut buffer_id,
+            ref mut count_buffer_id,
+            ..
+        } => {
+            set_id_backend(buffer_id, backend);
+            set_id_backend(count_buffer_id, backend);
+        }
+        C::ExecuteBundle(ref mut id) => set_id_backend(id, backend),
+        C::WriteTimestamp {
+            ref mut query_set_id,
+            ..
+        } => set_id_backend(query_set_id, backend),
+        C::SetBlendColor(_)
+        | C::SetStencilReference(_)
+        | C::SetViewport { .. }
+        | C::SetScissor(_)
+        | C::SetPushConstant { .. }
+        | C::Draw { .. }
+        | C::DrawIndexed { .. }
+        | C::PushDebugGroup { .. }
+        | C::PopDebugGroup
+        | C::InsertDebugMarker { .. }
+        | C::BeginOcclusionQuery { .. }
+        | C::EndOcclusionQuery
+        | C::End => {}
+    }
+}
+
+#[cfg(feature = "replay")]
+fn set_compute_command_backend(
+    command: &mut crate::command::ComputeCommand,
+    backend: wgt::Backend,
+) {
+    use crate::command::ComputeCommand as C;
+    match *command {
+        C::SetBindGroup {
+            ref mut bind_group_id,
+            ..
+        } => set_id_backend(bind_group_id, backend),
+        C::SetPipeline(ref mut id) => set_id_backend(id, backend),
+        C::DispatchIndirect {
+            ref mut buffer_id, ..
+        } => set_id_backend(buffer_id, backend),
+        C::WriteTimestamp {
+            ref mut query_set_id,
+            ..
+        } => set_id_backend(query_set_id, backend),
+        C::SetPushConstant { .. }
+        | C::Dispatch(_)
+        | C::PushDebugGroup { .. }
+        | C::PopDebugGroup
+        | C::InsertDebugMarker { .. }
+        | C::End => {}
+    }
+}
+
+#[cfg(feature = "replay")]
+impl Command {
+    fn set_backend(&mut self, backend: wgt::Backend) {
+        match *self {
+            Command::CopyBufferToBuffer {
+                ref mut src,
+                ref mut dst,
+                ..
+            } => {
+                set_id_backend(src, backend);
+                set_id_backend(dst, backend);
+            }
+            Command::CopyBufferToTexture {
+                ref mut src,
+                ref mut dst,
+                ..
+            } => {
+                set_id_backend(&mut src.buffer, backend);
+                set_copy_view_backend(dst, backend);
+            }
+            Command::CopyTextureToBuffer {
+                ref mut src,
+                ref mut dst,
+                ..
+            } => {
+                set_copy_view_backend(src, backend);
+                set_id_backend(&mut dst.buffer, backend);
+            }
+            Command::CopyTextureToTexture {
+                ref mut src,
+                ref mut dst,
+                ..
+            } => {
+                set_copy_view_backend(src, backend);
+                set_copy_view_backend(dst, backend);
+            }
+            Command::ClearBuffer { ref mut dst, .. } => set_id_backend(dst, backend),
+            Command::ClearTexture { ref mut dst, .. } => set_id_backend(dst, backend),
+            Command::WriteTimestamp {
+                ref mut query_set_id,
+                ..
+            } => set_id_backend(query_set_id, backend),
+            Command::ResolveQuerySet {
+                ref mut query_set_id,
+                ref mut destination,
+                ..
+            } => {
+                set_id_backend(query_set_id, backend);
+                set_id_backend(destination, backend);
+            }
+            Command::RunComputePass {
+                ref mut commands, ..
+            } => {
+                for command in commands.iter_mut() {
+                    set_compute_command_backend(command, backend);
+                }
+            }
+            Command::RunRenderPass {
+                ref mut target_colors,
+                ref mut target_depth_stencil,
+                ref mut occlusion_query_set,
+                ref mut commands,
+                ..
+            } => {
+                for at in target_colors.iter_mut() {
+                    set_id_backend(&mut at.attachment, backend);
+                    if let Some(ref mut id) = at.resolve_target {
+                        set_id_backend(id, backend);
+                    }
+                }
+                if let Some(ref mut at) = *target_depth_stencil {
+                    set_id_backend(&mut at.attachment, backend);
+                }
+                if let Some(ref mut id) = *occlusion_query_set {
+                    set_id_backend(id, backend);
+                }
+                for command in commands.iter_mut() {
+                    set_render_command_backend(command, backend);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(feature = "replay")]
+impl Action {
+    /// Move the action, including all the IDs it refers to, to another backend.
+    fn set_backend(&mut self, backend: wgt::Backend) {
+        match *self {
+            Action::Init {
+                backend: ref mut init_backend,
+                ..
+            } => *init_backend = backend,
+            Action::CreateBuffer { ref mut id, .. }
+            | Action::DestroyBuffer(ref mut id)
+            | Action::WriteBuffer { ref mut id, .. } => set_id_backend(id, backend),
+            Action::CreateTexture { ref mut id, .. } | Action::DestroyTexture(ref mut id) => {
+                set_id_backend(id, backend)
+            }
+            Action::CreateTextureView {
+                ref mut id,
+                ref mut parent_id,
+                ..
+            } => {
+                set_id_backend(id, backend);
+                set_id_backend(parent_id, backend);
+            }
+            Action::DestroyTextureView(ref mut id) => set_id_backend(id, backend),
+            Action::CreateSampler { ref mut id, .. } | Action::DestroySampler(ref mut id) => {
+                set_id_backend(id, backend)
+            }
+            Action::CreateQuerySet { ref mut id, .. } | Action::DestroyQuerySet(ref mut id) => {
+                set_id_backend(id, backend)
+            }
+            Action::CreateSwapChain { ref mut id, .. } | Action::PresentSwapChain(ref mut id) => {
+                set_id_backend(id, backend)
+            }
+            Action::GetSwapChainTexture {
+                ref mut id,
+                ref mut parent_id,
+            } => {
+                if let Some(ref mut id) = *id {
+                    set_id_backend(id, backend);
+                }
+                set_id_backend(parent_id, backend);
+            }
+            Action::CreateBindGroupLayout { ref mut id, .. }
+            | Action::DestroyBindGroupLayout(ref mut id) => set_id_backend(id, backend),
+            Action::CreatePipelineLayout {
+                ref mut id,
+                ref mut bind_group_layouts,
+                ..
+            } => {
+                set_id_backend(id, backend);
+                for layout in bind_group_layouts.iter_mut() {
+                    set_id_backend(layout, backend);
+                }
+            }
+            Action::DestroyPipelineLayout(ref mut id) => set_id_backend(id, backend),
+            Action::CreateBindGroup {
+                ref mut id,
+                ref mut layout_id,
+                ref mut entries,
+                ..
+            } => {
+                set_id_backend(id, backend);
+                set_id_backend(layout_id, backend);
+                for resource in entries.values_mut() {
+                    match *resource {
+                        BindingResource::Buffer { ref mut id, .. } => set_id_backend(id, backend),
+                        BindingResource::Sampler(ref mut id) => set_id_backend(id, backend),
+                        BindingResource::TextureView(ref mut id) => set_id_backend(id, backend),
+                        BindingResource::TextureViewArray(ref mut ids) => {
+                            for id in ids.iter_mut() {
+                                set_id_backend(id, backend);
+                            }
+                        }
+                    }
+                }
+            }
+            Action::DestroyBindGroup(ref mut id) => set_id_backend(id, backend),
+            Action::CreateShaderModule { ref mut id, .. }
+            | Action::DestroyShaderModule(ref mut id) => set_id_backend(id, backend),
+            Action::CreatePipelineCache { ref mut id, .. }
+            | Action::DestroyPipelineCache(ref mut id) => set_id_backend(id, backend),
+            Action::CreateComputePipeline {
+                ref mut id,
+                ref mut desc,
+            } => {
+                set_id_backend(id, backend);
+                set_id_backend(&mut desc.layout, backend);
+                set_id_backend(&mut desc.compute_stage.module, backend);
+                if let Some(ref mut cache) = desc.cache {
+                    set_id_backend(cache, backend);
+                }
+            }
+            Action::DestroyComputePipeline(ref mut id) => set_id_backend(id, backend),
+            Action::CreateRenderPipeline {
+                ref mut id,
+                ref mut desc,
+            } => {
+                set_id_backend(id, backend);
+                set_id_backend(&mut desc.layout, backend);
+                set_id_backend(&mut desc.vertex_stage.module, backend);
+                if let Some(ref mut stage) = desc.fragment_stage {
+                    set_id_backend(&mut stage.module, backend);
+                }
+                if let Some(ref mut cache) = desc.cache {
+                    set_id_backend(cache, backend);
+                }
+            }
+            Action::DestroyRenderPipeline(ref mut id) => set_id_backend(id, backend),
+            Action::CreateRenderBundle {
+                ref mut id,
+                ref mut commands,
+                ..
+            } => {
+                set_id_backend(id, backend);
+                for command in commands.iter_mut() {
+                    set_render_command_backend(command, backend);
+                }
+            }
+            Action::DestroyRenderBundle(ref mut id) => set_id_backend(id, backend),
+            Action::WriteTexture { ref mut to, .. } => set_copy_view_backend(to, backend),
+            Action::Submit(_, ref mut commands) => {
+                for command in commands.iter_mut() {
+                    command.set_backend(backend);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(feature = "replay")]
+#[derive(Debug)]
+pub enum ReadError {
+    /// The directory doesn't contain a trace file.
+    NotFound,
+    Io(std::io::Error),
+    Ron(ron::de::Error),
+    Binary(bincode::Error),
+    /// The trace doesn't start with the expected header.
+    InvalidHeader,
+    /// The binary trace was written by an incompatible version.
+    UnsupportedVersion(u32),
+}
+
+#[cfg(feature = "trace")]
+#[derive(Debug)]
+pub struct Trace {
+    path: std::path::PathBuf,
+    writer: Writer,
     binary_id: usize,
 }
 
 #[cfg(feature = "trace")]
 impl Trace {
-    pub fn new(path: &std::path::Path) -> Result<Self, std::io::Error> {
-        log::info!("Tracing into '{:?}'", path);
-        let mut file = std::fs::File::create(path.join(FILE_NAME))?;
-        file.write_all(b"[\n")?;
+    pub fn new(path: &std::path::Path, format: TraceFormat) -> Result<Self, std::io::Error> {
+        log::info!("Tracing into '{:?}' as {:?}", path, format);
         Ok(Trace {
             path: path.to_path_buf(),
-            file,
-            config: ron::ser::PrettyConfig::default(),
+            writer: Writer::new(path, format)?,
             binary_id: 0,
         })
     }
@@ -273,20 +993,325 @@ impl Trace {
     }
 
     pub(crate) fn add(&mut self, action: Action) {
-        match ron::ser::to_string_pretty(&action, self.config.clone()) {
-            Ok(string) => {
-                let _ = writeln!(self.file, "{},", string);
+        if let Err(e) = self.writer.write(&action) {
+            log::warn!("Trace serialization failure: {:?}", e);
+        }
+    }
+}
+
+#[cfg(all(test, feature = "replay"))]
+mod tests {
+    use super::*;
+
+    /// Create an empty directory for the trace of `test`.
+    fn trace_dir(test: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("wgpu-trace-{}-{}", test, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Write `contents` as the trace file of a fresh directory, and open it.
+    fn open<R>(
+        test: &str,
+        format: TraceFormat,
+        contents: &[u8],
+        fun: impl FnOnce(Result<Reader, ReadError>) -> R,
+    ) -> R {
+        let dir = trace_dir(test);
+        std::fs::write(dir.join(format.file_name()), contents).unwrap();
+        let result = fun(Reader::open(&dir));
+        let _ = std::fs::remove_dir_all(&dir);
+        result
+    }
+
+    fn read(test: &str, format: TraceFormat, contents: &[u8]) -> Vec<Action> {
+        open(test, format, contents, |reader| {
+            reader.unwrap().collect::<Result<_, _>>().unwrap()
+        })
+    }
+
+    fn binary_trace(records: &[u8]) -> Vec<u8> {
+        let mut contents = BINARY_MAGIC.to_vec();
+        contents.extend_from_slice(&BINARY_VERSION.to_le_bytes());
+        contents.extend_from_slice(records);
+        contents
+    }
+
+    #[test]
+    fn ron_terminated() {
+        let actions = read(
+            "ron_terminated",
+            TraceFormat::Ron,
+            b"[\n    DestroyBuffer(Id(1, 1, Empty)),\n    DestroyTexture(Id(2, 1, Empty)),\n]",
+        );
+        match actions[..] {
+            [Action::DestroyBuffer(_), Action::DestroyTexture(_)] => {}
+            ref other => panic!("Unexpected actions {:?}", other),
+        }
+    }
+
+    #[test]
+    fn ron_unterminated() {
+        let actions = read(
+            "ron_unterminated",
+            TraceFormat::Ron,
+            b"[\n    DestroyBuffer(Id(1, 1, Empty)),\n    DestroyTexture(Id(2, 1, Empty)),\n",
+        );
+        match actions[..] {
+            [Action::DestroyBuffer(_), Action::DestroyTexture(_)] => {}
+            ref other => panic!("Unexpected actions {:?}", other),
+        }
+    }
+
+    #[test]
+    fn ron_cut_mid_action() {
+        let actions = read(
+            "ron_cut_mid_action",
+            TraceFormat::Ron,
+            b"[\n    DestroyBuffer(Id(1, 1, Empty)),\n    DestroyTexture(Id(2, ",
+        );
+        match actions[..] {
+            [Action::DestroyBuffer(_)] => {}
+            ref other => panic!("Unexpected actions {:?}", other),
+        }
+    }
+
+    #[test]
+    fn ron_cut_balanced() {
+        let actions = read(
+            "ron_cut_balanced",
+            TraceFormat::Ron,
+            b"[\n    DestroyBuffer(Id(1, 1, Empty)),\n    DestroyTex",
+        );
+        match actions[..] {
+            [Action::DestroyBuffer(_)] => {}
+            ref other => panic!("Unexpected actions {:?}", other),
+        }
+    }
+
+    #[test]
+    fn ron_string_with_delimiters() {
+        let actions = read(
+            "ron_string_with_delimiters",
+            TraceFormat::Ron,
+            br#"[
+    CreateBuffer(
+        id: Id(1, 1, Empty),
+        desc: (label: "a, b] (\"c\"", size: 4, usage: (bits: 8), mapped_at_creation: false),
+    ),
+    DestroyBuffer(Id(1, 1, Empty)),
+]"#,
+        );
+        match actions[..] {
+            [Action::CreateBuffer { ref desc, .. }, Action::DestroyBuffer(_)] => {
+                assert_eq!(desc.label, "a, b] (\"c\"");
             }
-            Err(e) => {
-                log::warn!("RON serialization failure: {:?}", e);
+            ref other => panic!("Unexpected actions {:?}", other),
+        }
+    }
+
+    #[test]
+    fn backend_override() {
+        let contents = br#"[
+    CreatePipelineLayout(
+        id: Id(1, 1, Vulkan),
+        bind_group_layouts: [Id(2, 1, Vulkan)],
+        push_constant_ranges: [],
+    ),
+]"#;
+        let actions = open("backend_override", TraceFormat::Ron, contents, |reader| {
+            let mut reader = reader.unwrap();
+            reader.override_backend(wgt::Backend::Metal);
+            reader.collect::<Result<Vec<_>, _>>().unwrap()
+        });
+        match actions[..] {
+            [Action::CreatePipelineLayout {
+                id,
+                ref bind_group_layouts,
+                ..
+            }] => {
+                assert_eq!(id.backend(), wgt::Backend::Metal);
+                assert_eq!(bind_group_layouts[0].backend(), wgt::Backend::Metal);
             }
+            ref other => panic!("Unexpected actions {:?}", other),
         }
     }
-}
 
-#[cfg(feature = "trace")]
-impl Drop for Trace {
-    fn drop(&mut self) {
-        let _ = self.file.write_all(b"]");
+    #[test]
+    fn ron_invalid_header() {
+        open(
+            "ron_invalid_header",
+            TraceFormat::Ron,
+            b"()[]",
+            |reader| match reader {
+                Err(ReadError::InvalidHeader) => {}
+                other => panic!("Unexpected result {:?}", other),
+            },
+        );
+    }
+
+    #[test]
+    fn binary_empty() {
+        assert!(read("binary_empty", TraceFormat::Binary, &binary_trace(&[])).is_empty());
+    }
+
+    #[test]
+    fn binary_truncated_size() {
+        let contents = binary_trace(&[4, 0]);
+        assert!(read("binary_truncated_size", TraceFormat::Binary, &contents).is_empty());
+    }
+
+    #[test]
+    fn binary_truncated_payload() {
+        let contents = binary_trace(&[16, 0, 0, 0, 1, 2, 3]);
+        assert!(read("binary_truncated_payload", TraceFormat::Binary, &contents).is_empty());
+    }
+
+    #[test]
+    fn binary_garbage_size() {
+        // Must not try to allocate the 4 GiB claimed by the size.
+        let contents = binary_trace(&[0xFF, 0xFF, 0xFF, 0xFF, 1, 2, 3]);
+        assert!(read("binary_garbage_size", TraceFormat::Binary, &contents).is_empty());
+    }
+
+    #[test]
+    fn binary_invalid_header() {
+        let mut contents = binary_trace(&[]);
+        contents[0] = b'X';
+        let check = |reader: Result<Reader, ReadError>| match reader {
+            Err(ReadError::InvalidHeader) => {}
+            other => panic!("Unexpected result {:?}", other),
+        };
+        open(
+            "binary_invalid_header",
+            TraceFormat::Binary,
+            &contents,
+            check,
+        );
+        open(
+            "binary_short_header",
+            TraceFormat::Binary,
+            &BINARY_MAGIC,
+            check,
+        );
+    }
+
+    #[test]
+    fn binary_unsupported_version() {
+        let mut contents = BINARY_MAGIC.to_vec();
+        contents.extend_from_slice(&(BINARY_VERSION + 1).to_le_bytes());
+        open(
+            "binary_unsupported_version",
+            TraceFormat::Binary,
+            &contents,
+            |reader| match reader {
+                Err(ReadError::UnsupportedVersion(version)) => {
+                    assert_eq!(version, BINARY_VERSION + 1)
+                }
+                other => panic!("Unexpected result {:?}", other),
+            },
+        );
+    }
+
+    #[cfg(feature = "trace")]
+    fn sample_actions() -> Vec<Action> {
+        use crate::id::TypedId as _;
+        let buffer_id = id::BufferId::zip(3, 2, wgt::Backend::Vulkan);
+        vec![
+            Action::Init {
+                desc: wgt::DeviceDescriptor::default(),
+                backend: wgt::Backend::Vulkan,
+                pipeline_cache_data: None,
+            },
+            Action::CreateBuffer {
+                id: buffer_id,
+                desc: wgt::BufferDescriptor {
+                    label: "a, b] (\"c\"".to_string(),
+                    size: 16,
+                    usage: wgt::BufferUsage::COPY_DST,
+                    mapped_at_creation: false,
+                },
+            },
+            Action::DestroyBuffer(buffer_id),
+        ]
+    }
+
+    /// Compare the actions through their debug output,
+    /// which shows the IDs with their backend.
+    #[cfg(feature = "trace")]
+    fn assert_same(actions: &[Action], expected: &[Action]) {
+        assert_eq!(format!("{:?}", actions), format!("{:?}", expected));
+    }
+
+    #[cfg(feature = "trace")]
+    fn write(dir: &std::path::Path, format: TraceFormat, actions: &[Action]) {
+        let mut writer = Writer::new(dir, format).unwrap();
+        for action in actions {
+            writer.write(action).unwrap();
+        }
+    }
+
+    #[cfg(feature = "trace")]
+    fn read_dir(dir: &std::path::Path, format: TraceFormat) -> Vec<Action> {
+        let reader = Reader::open(dir).unwrap();
+        assert_eq!(reader.format(), format);
+        reader.collect::<Result<_, _>>().unwrap()
+    }
+
+    #[cfg(feature = "trace")]
+    #[test]
+    fn ron_round_trip() {
+        let dir = trace_dir("ron_round_trip");
+        write(&dir, TraceFormat::Ron, &sample_actions());
+        assert_same(&read_dir(&dir, TraceFormat::Ron), &sample_actions());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(feature = "trace")]
+    #[test]
+    fn binary_round_trip() {
+        let dir = trace_dir("binary_round_trip");
+        let actions = sample_actions();
+        write(&dir, TraceFormat::Binary, &actions);
+
+        // Check the layout of the file against the format description.
+        let contents = std::fs::read(dir.join(BINARY_FILE_NAME)).unwrap();
+        assert_eq!(contents[..8], BINARY_MAGIC);
+        assert_eq!(contents[8..12], BINARY_VERSION.to_le_bytes());
+        let mut records = &contents[12..];
+        for action in actions.iter() {
+            let size = u32::from_le_bytes([records[0], records[1], records[2], records[3]]);
+            assert_eq!(size as u64, bincode::serialized_size(action).unwrap());
+            let end = 4 + size as usize;
+            let decoded: Action = bincode::deserialize(&records[4..end]).unwrap();
+            assert_same(&[decoded], std::slice::from_ref(action));
+            records = &records[end..];
+        }
+        assert!(records.is_empty());
+
+        assert_same(&read_dir(&dir, TraceFormat::Binary), &actions);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(feature = "trace")]
+    #[test]
+    fn format_conversion() {
+        let ron_dir = trace_dir("format_conversion_ron");
+        write(&ron_dir, TraceFormat::Ron, &sample_actions());
+
+        // RON to binary, written next to the RON trace.
+        let actions = read_dir(&ron_dir, TraceFormat::Ron);
+        write(&ron_dir, TraceFormat::Binary, &actions);
+        let actions = read_dir(&ron_dir, TraceFormat::Binary);
+        assert_same(&actions, &sample_actions());
+
+        // And back to RON, in a directory of its own.
+        let binary_dir = trace_dir("format_conversion_binary");
+        write(&binary_dir, TraceFormat::Ron, &actions);
+        assert_same(&read_dir(&binary_dir, TraceFormat::Ron), &sample_actions());
+
+        let _ = std::fs::remove_dir_all(&ron_dir);
+        let _ = std::fs::remove_dir_all(&binary_dir);
     }
 }
@@ -9,12 +9,12 @@ use crate::{
     device::Device,
     id::{
         AdapterId, BindGroupId, BindGroupLayoutId, BufferId, CommandBufferId, ComputePipelineId,
-        DeviceId, PipelineLayoutId, RenderBundleId, RenderPipelineId, SamplerId, ShaderModuleId,
-        SurfaceId, SwapChainId, TextureId, TextureViewId, TypedId,
+        DeviceId, PipelineCacheId, PipelineLayoutId, QuerySetId, RenderBundleId, RenderPipelineId,
+        SamplerId, ShaderModuleId, SurfaceId, SwapChainId, TextureId, TextureViewId, TypedId,
     },
     instance::{Adapter, Instance, Surface},
-    pipeline::{ComputePipeline, RenderPipeline, ShaderModule},
-    resource::{Buffer, Sampler, Texture, TextureView},
+    pipeline::{ComputePipeline, PipelineCache, RenderPipeline, ShaderModule},
+    resource::{Buffer, QuerySet, Sampler, Texture, TextureView},
     swap_chain::SwapChain,
     Epoch, Index,
 };
@@ -76,76 +76,154 @@ impl IdentityManager {
     }
 }
 
+/// An entry in a `Storage`.
+#[derive(Debug)]
+enum Element<T> {
+    /// The object is alive.
+    Occupied(T, Epoch),
+    /// The object failed to be created. The ID stays registered so that
+    /// any use of it can be reported as an error.
+    Error(Epoch),
+}
+
+/// The ID refers to an object that failed to be created.
+#[derive(Clone, Debug)]
+pub struct InvalidId;
+
 #[derive(Debug)]
 pub struct Storage<T, I: TypedId> {
     //TODO: consider concurrent hashmap?
-    map: VecMap<(T, Epoch)>,
+    map: VecMap<Element<T>>,
     kind: &'static str,
     _phantom: PhantomData<I>,
 }
 
-impl<T, I: TypedId> ops::Index<I> for Storage<T, I> {
+impl<T, I: TypedId + Copy> ops::Index<I> for Storage<T, I> {
     type Output = T;
     fn index(&self, id: I) -> &T {
+        let (index, _, _) = id.unzip();
+        match self.get(id) {
+            Ok(value) => value,
+            Err(InvalidId) => panic!("{}[{}] is invalid", self.kind, index),
+        }
+    }
+}
+
+impl<T, I: TypedId + Copy> ops::IndexMut<I> for Storage<T, I> {
+    fn index_mut(&mut self, id: I) -> &mut T {
+        let (index, _, _) = id.unzip();
+        let kind = self.kind;
+        match self.get_mut(id) {
+            Ok(value) => value,
+            Err(InvalidId) => panic!("{}[{}] is invalid", kind, index),
+        }
+    }
+}
+
+impl<T, I: TypedId> Storage<T, I> {
+    pub fn contains(&self, id: I) -> bool {
+        let (index, epoch, _) = id.unzip();
+        match self.map.get(index as usize) {
+            Some(&Element::Occupied(_, storage_epoch)) | Some(&Element::Error(storage_epoch)) => {
+                epoch == storage_epoch
+            }
+            None => false,
+        }
+    }
+
+    /// Get a reference to an item behind a potentially invalid ID.
+    /// Panics if there is an epoch mismatch, or the entry is empty.
+    pub fn get(&self, id: I) -> Result<&T, InvalidId> {
         let (index, epoch, _) = id.unzip();
-        let (ref value, storage_epoch) = match self.map.get(index as usize) {
-            Some(v) => v,
+        let (result, storage_epoch) = match self.map.get(index as usize) {
+            Some(&Element::Occupied(ref v, epoch)) => (Ok(v), epoch),
+            Some(&Element::Error(epoch)) => (Err(InvalidId), epoch),
             None => panic!("{}[{}] does not exist", self.kind, index),
         };
         assert_eq!(
-            epoch, *storage_epoch,
+            epoch, storage_epoch,
             "{}[{}] is no longer alive",
             self.kind, index
         );
-        value
+        result
     }
-}
 
-impl<T, I: TypedId> ops::IndexMut<I> for Storage<T, I> {
-    fn index_mut(&mut self, id: I) -> &mut T {
+    /// Get a mutable reference to an item behind a potentially invalid ID.
+    /// Panics if there is an epoch mismatch, or the entry is empty.
+    pub fn get_mut(&mut self, id: I) -> Result<&mut T, InvalidId> {
         let (index, epoch, _) = id.unzip();
-        let (ref mut value, storage_epoch) = match self.map.get_mut(index as usize) {
-            Some(v) => v,
+        let (result, storage_epoch) = match self.map.get_mut(index as usize) {
+            Some(&mut Element::Occupied(ref mut v, epoch)) => (Ok(v), epoch),
+            Some(&mut Element::Error(epoch)) => (Err(InvalidId), epoch),
             None => panic!("{}[{}] does not exist", self.kind, index),
         };
         assert_eq!(
-            epoch, *storage_epoch,
+            epoch, storage_epoch,
             "{}[{}] is no longer alive",
             self.kind, index
         );
-        value
+        result
     }
-}
 
-impl<T, I: TypedId> Storage<T, I> {
-    pub fn contains(&self, id: I) -> bool {
+    pub fn insert(&mut self, id: I, value: T) {
         let (index, epoch, _) = id.unzip();
-        match self.map.get(index as usize) {
-            Some(&(_, storage_epoch)) => epoch == storage_epoch,
-            None => false,
-        }
+        let old = self
+            .map
+            .insert(index as usize, Element::Occupied(value, epoch));
+        assert!(
+            old.is_none(),
+            "{}[{}] is already occupied",
+            self.kind,
+            index
+        );
     }
 
-    pub fn insert(&mut self, id: I, value: T) -> Option<T> {
+    pub fn insert_error(&mut self, id: I) {
         let (index, epoch, _) = id.unzip();
-        let old = self.map.insert(index as usize, (value, epoch));
-        old.map(|(v, _storage_epoch)| v)
+        let old = self.map.insert(index as usize, Element::Error(epoch));
+        assert!(
+            old.is_none(),
+            "{}[{}] is already occupied",
+            self.kind,
+            index
+        );
     }
 
+    /// Remove the entry behind an ID, returning the object if it was valid.
     pub fn remove(&mut self, id: I) -> Option<T> {
         let (index, epoch, _) = id.unzip();
-        self.map
-            .remove(index as usize)
-            .map(|(value, storage_epoch)| {
+        match self.map.remove(index as usize) {
+            Some(Element::Occupied(value, storage_epoch)) => {
                 assert_eq!(epoch, storage_epoch);
-                value
-            })
+                Some(value)
+            }
+            Some(Element::Error(storage_epoch)) => {
+                assert_eq!(epoch, storage_epoch);
+                None
+            }
+            None => None,
+        }
     }
 
     pub fn iter(&self, backend: Backend) -> impl Iterator<Item = (I, &T)> {
-        self.map.iter().map(move |(index, (value, storage_epoch))| {
-            (I::zip(index as Index, *storage_epoch, backend), value)
-        })
+        self.map
+            .iter()
+            .filter_map(move |(index, element)| match *element {
+                Element::Occupied(ref value, storage_epoch) => {
+                    Some((I::zip(index as Index, storage_epoch, backend), value))
+                }
+                Element::Error(_) => None,
+            })
+    }
+
+    /// Drain all the valid objects, dropping the invalid entries.
+    fn drain(&mut self) -> impl Iterator<Item = (Index, T, Epoch)> + '_ {
+        self.map
+            .drain()
+            .filter_map(|(index, element)| match element {
+                Element::Occupied(value, epoch) => Some((index as Index, value, epoch)),
+                Element::Error(_) => None,
+            })
     }
 }
 
@@ -193,8 +271,16 @@ impl<B: hal::Backend> Access<ComputePipeline<B>> for BindGroup<B> {}
 impl<B: hal::Backend> Access<RenderPipeline<B>> for Device<B> {}
 impl<B: hal::Backend> Access<RenderPipeline<B>> for BindGroup<B> {}
 impl<B: hal::Backend> Access<RenderPipeline<B>> for ComputePipeline<B> {}
+impl<B: hal::Backend> Access<QuerySet<B>> for Root {}
+impl<B: hal::Backend> Access<QuerySet<B>> for Device<B> {}
+impl<B: hal::Backend> Access<QuerySet<B>> for CommandBuffer<B> {}
+impl<B: hal::Backend> Access<QuerySet<B>> for ComputePipeline<B> {}
+impl<B: hal::Backend> Access<QuerySet<B>> for RenderPipeline<B> {}
 impl<B: hal::Backend> Access<ShaderModule<B>> for Device<B> {}
 impl<B: hal::Backend> Access<ShaderModule<B>> for BindGroupLayout<B> {}
+impl<B: hal::Backend> Access<PipelineCache<B>> for Root {}
+impl<B: hal::Backend> Access<PipelineCache<B>> for Device<B> {}
+impl<B: hal::Backend> Access<PipelineCache<B>> for ShaderModule<B> {}
 impl<B: hal::Backend> Access<Buffer<B>> for Root {}
 impl<B: hal::Backend> Access<Buffer<B>> for Device<B> {}
 impl<B: hal::Backend> Access<Buffer<B>> for BindGroupLayout<B> {}
@@ -202,6 +288,7 @@ impl<B: hal::Backend> Access<Buffer<B>> for BindGroup<B> {}
 impl<B: hal::Backend> Access<Buffer<B>> for CommandBuffer<B> {}
 impl<B: hal::Backend> Access<Buffer<B>> for ComputePipeline<B> {}
 impl<B: hal::Backend> Access<Buffer<B>> for RenderPipeline<B> {}
+impl<B: hal::Backend> Access<Buffer<B>> for QuerySet<B> {}
 impl<B: hal::Backend> Access<Texture<B>> for Root {}
 impl<B: hal::Backend> Access<Texture<B>> for Device<B> {}
 impl<B: hal::Backend> Access<Texture<B>> for Buffer<B> {}
@@ -303,10 +390,12 @@ pub trait GlobalIdentityHandlerFactory:
     + IdentityHandlerFactory<RenderBundleId>
     + IdentityHandlerFactory<RenderPipelineId>
     + IdentityHandlerFactory<ComputePipelineId>
+    + IdentityHandlerFactory<PipelineCacheId>
     + IdentityHandlerFactory<BufferId>
     + IdentityHandlerFactory<TextureId>
     + IdentityHandlerFactory<TextureViewId>
     + IdentityHandlerFactory<SamplerId>
+    + IdentityHandlerFactory<QuerySetId>
     + IdentityHandlerFactory<SurfaceId>
 {
 }
@@ -351,8 +440,7 @@ impl<T, I: TypedId, F: IdentityHandlerFactory<I>> Registry<T, I, F> {
 impl<T, I: TypedId + Copy, F: IdentityHandlerFactory<I>> Registry<T, I, F> {
     pub fn register<A: Access<T>>(&self, id: I, value: T, _token: &mut Token<A>) {
         debug_assert_eq!(id.unzip().2, self.backend);
-        let old = self.data.write().insert(id, value);
-        assert!(old.is_none());
+        self.data.write().insert(id, value);
     }
 
     pub fn read<'a, A: Access<T>>(
@@ -382,17 +470,38 @@ impl<T, I: TypedId + Copy, F: IdentityHandlerFactory<I>> Registry<T, I, F> {
         id
     }
 
+    /// Register an error-marked entry under the requested ID, so that
+    /// any later use of it produces an error instead of a panic.
+    pub fn register_error<A: Access<T>>(
+        &self,
+        id_in: <F::Filter as IdentityHandler<I>>::Input,
+        _token: &mut Token<A>,
+    ) -> I {
+        let id = self.identity.process(id_in, self.backend);
+        debug_assert_eq!(id.unzip().2, self.backend);
+        self.data.write().insert_error(id);
+        id
+    }
+
     pub fn unregister<'a, A: Access<T>>(
         &self,
         id: I,
         _token: &'a mut Token<A>,
-    ) -> (T, Token<'a, T>) {
-        let value = self.data.write().remove(id).unwrap();
+    ) -> (Option<T>, Token<'a, T>) {
+        let value = self.data.write().remove(id);
         //Note: careful about the order here!
         self.identity.free(id);
         (value, Token::new())
     }
 
+    /// Same as `unregister`, but for the case where the storage is already locked.
+    pub fn unregister_locked(&self, id: I, guard: &mut Storage<T, I>) -> Option<T> {
+        let value = guard.remove(id);
+        //Note: careful about the order here!
+        self.identity.free(id);
+        value
+    }
+
     pub fn free_id(&self, id: I) {
         self.identity.free(id)
     }
@@ -411,10 +520,12 @@ pub struct Hub<B: hal::Backend, F: GlobalIdentityHandlerFactory> {
     pub render_bundles: Registry<RenderBundle, RenderBundleId, F>,
     pub render_pipelines: Registry<RenderPipeline<B>, RenderPipelineId, F>,
     pub compute_pipelines: Registry<ComputePipeline<B>, ComputePipelineId, F>,
+    pub pipeline_caches: Registry<PipelineCache<B>, PipelineCacheId, F>,
     pub buffers: Registry<Buffer<B>, BufferId, F>,
     pub textures: Registry<Texture<B>, TextureId, F>,
     pub texture_views: Registry<TextureView<B>, TextureViewId, F>,
     pub samplers: Registry<Sampler<B>, SamplerId, F>,
+    pub query_sets: Registry<QuerySet<B>, QuerySetId, F>,
 }
 
 impl<B: GfxBackend, F: GlobalIdentityHandlerFactory> Hub<B, F> {
@@ -431,10 +542,12 @@ impl<B: GfxBackend, F: GlobalIdentityHandlerFactory> Hub<B, F> {
             render_bundles: Registry::new(B::VARIANT, factory, "RenderBundle"),
             render_pipelines: Registry::new(B::VARIANT, factory, "RenderPipeline"),
             compute_pipelines: Registry::new(B::VARIANT, factory, "ComputePipeline"),
+            pipeline_caches: Registry::new(B::VARIANT, factory, "PipelineCache"),
             buffers: Registry::new(B::VARIANT, factory, "Buffer"),
             textures: Registry::new(B::VARIANT, factory, "Texture"),
             texture_views: Registry::new(B::VARIANT, factory, "TextureView"),
             samplers: Registry::new(B::VARIANT, factory, "Sampler"),
+            query_sets: Registry::new(B::VARIANT, factory, "QuerySet"),
         }
     }
 }
@@ -445,20 +558,29 @@ impl<B: GfxBackend, F: GlobalIdentityHandlerFactory> Hub<B, F> {
         use hal::{device::Device as _, window::PresentationSurface as _};
 
         let mut devices = self.devices.data.write();
-        for (device, _) in devices.map.values_mut() {
-            device.prepare_to_die();
+        for element in devices.map.values_mut() {
+            if let Element::Occupied(ref mut device, _) = *element {
+                device.prepare_to_die();
+            }
         }
 
-        for (_, (sampler, _)) in self.samplers.data.write().map.drain() {
+        for (_, sampler, _) in self.samplers.data.write().drain() {
             unsafe {
                 devices[sampler.device_id.value]
                     .raw
                     .destroy_sampler(sampler.raw);
             }
         }
+        for (_, query_set, _) in self.query_sets.data.write().drain() {
+            unsafe {
+                devices[query_set.device_id.value]
+                    .raw
+                    .destroy_query_pool(query_set.raw);
+            }
+        }
         {
             let textures = self.textures.data.read();
-            for (_, (texture_view, _)) in self.texture_views.data.write().map.drain() {
+            for (_, texture_view, _) in self.texture_views.data.write().drain() {
                 match texture_view.inner {
                     TextureViewInner::Native { raw, source_id } => {
                         let device = &devices[textures[source_id.value].device_id.value];
@@ -471,57 +593,63 @@ impl<B: GfxBackend, F: GlobalIdentityHandlerFactory> Hub<B, F> {
             }
         }
 
-        for (_, (texture, _)) in self.textures.data.write().map.drain() {
+        for (_, texture, _) in self.textures.data.write().drain() {
             devices[texture.device_id.value].destroy_texture(texture);
         }
-        for (_, (buffer, _)) in self.buffers.data.write().map.drain() {
+        for (_, buffer, _) in self.buffers.data.write().drain() {
             //TODO: unmap if needed
             devices[buffer.device_id.value].destroy_buffer(buffer);
         }
-        for (_, (command_buffer, _)) in self.command_buffers.data.write().map.drain() {
+        for (_, command_buffer, _) in self.command_buffers.data.write().drain() {
             devices[command_buffer.device_id.value]
                 .com_allocator
                 .after_submit(command_buffer, 0);
         }
-        for (_, (bind_group, _)) in self.bind_groups.data.write().map.drain() {
+        for (_, bind_group, _) in self.bind_groups.data.write().drain() {
             let device = &devices[bind_group.device_id.value];
             device.destroy_bind_group(bind_group);
         }
 
-        for (_, (module, _)) in self.shader_modules.data.write().map.drain() {
+        for (_, module, _) in self.shader_modules.data.write().drain() {
             let device = &devices[module.device_id.value];
             unsafe {
                 device.raw.destroy_shader_module(module.raw);
             }
         }
-        for (_, (bgl, _)) in self.bind_group_layouts.data.write().map.drain() {
+        for (_, bgl, _) in self.bind_group_layouts.data.write().drain() {
             let device = &devices[bgl.device_id.value];
             unsafe {
                 device.raw.destroy_descriptor_set_layout(bgl.raw);
             }
         }
-        for (_, (pipeline_layout, _)) in self.pipeline_layouts.data.write().map.drain() {
+        for (_, pipeline_layout, _) in self.pipeline_layouts.data.write().drain() {
             let device = &devices[pipeline_layout.device_id.value];
             unsafe {
                 device.raw.destroy_pipeline_layout(pipeline_layout.raw);
             }
         }
-        for (_, (pipeline, _)) in self.compute_pipelines.data.write().map.drain() {
+        for (_, pipeline, _) in self.compute_pipelines.data.write().drain() {
             let device = &devices[pipeline.device_id.value];
             unsafe {
                 device.raw.destroy_compute_pipeline(pipeline.raw);
             }
         }
-        for (_, (pipeline, _)) in self.render_pipelines.data.write().map.drain() {
+        for (_, pipeline, _) in self.render_pipelines.data.write().drain() {
             let device = &devices[pipeline.device_id.value];
             unsafe {
                 device.raw.destroy_graphics_pipeline(pipeline.raw);
             }
         }
+        for (_, cache, _) in self.pipeline_caches.data.write().drain() {
+            let device = &devices[cache.device_id.value];
+            unsafe {
+                device.raw.destroy_pipeline_cache(cache.raw);
+            }
+        }
 
-        for (index, (swap_chain, epoch)) in self.swap_chains.data.write().map.drain() {
+        for (index, swap_chain, epoch) in self.swap_chains.data.write().drain() {
             let device = &devices[swap_chain.device_id.value];
-            let surface = &mut surface_guard[TypedId::zip(index as Index, epoch, B::VARIANT)];
+            let surface = &mut surface_guard[TypedId::zip(index, epoch, B::VARIANT)];
             let suf = B::get_surface_mut(surface);
             unsafe {
                 device.raw.destroy_semaphore(swap_chain.semaphore);
@@ -529,7 +657,7 @@ impl<B: GfxBackend, F: GlobalIdentityHandlerFactory> Hub<B, F> {
             }
         }
 
-        for (_, (device, _)) in devices.map.drain() {
+        for (_, device, _) in devices.drain() {
             device.dispose();
         }
     }
@@ -603,7 +731,7 @@ impl<G: GlobalIdentityHandlerFactory> Drop for Global<G> {
             #[cfg(windows)]
             self.hubs.dx11.clear(&mut *surface_guard);
             // destroy surfaces
-            for (_, (surface, _)) in surface_guard.map.drain() {
+            for (_, surface, _) in surface_guard.drain() {
                 self.instance.destroy_surface(surface);
             }
         }
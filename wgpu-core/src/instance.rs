@@ -4,7 +4,7 @@
 
 use crate::{
     backend,
-    device::Device,
+    device::{Device, TraceFormat},
     hub::{GfxBackend, Global, GlobalIdentityHandlerFactory, Input, Token},
     id::{AdapterId, DeviceId, SurfaceId},
     power, LifeGuard, PrivateFeatures, Stored, MAX_BIND_GROUPS,
@@ -142,6 +142,8 @@ pub struct Adapter<B: hal::Backend> {
     limits: wgt::Limits,
     capabilities: wgt::Capabilities,
     unsafe_extensions: wgt::UnsafeExtensions,
+    /// Number of nanoseconds it takes for a timestamp query value to be incremented by 1.
+    timestamp_period: f32,
     life_guard: LifeGuard,
 }
 
@@ -161,15 +163,57 @@ impl<B: hal::Backend> Adapter<B> {
                     | hal::Features::UNSIZED_DESCRIPTOR_ARRAY,
             ),
         );
+        extensions.set(
+            wgt::Extensions::TEXTURE_COMPRESSION_BC,
+            adapter_features.contains(hal::Features::FORMAT_BC),
+        );
+        extensions.set(
+            wgt::Extensions::TEXTURE_COMPRESSION_ETC2,
+            adapter_features.contains(hal::Features::FORMAT_ETC2),
+        );
+        extensions.set(
+            wgt::Extensions::TEXTURE_COMPRESSION_ASTC_LDR,
+            adapter_features.contains(hal::Features::FORMAT_ASTC_LDR),
+        );
+        extensions.set(
+            wgt::Extensions::DEPTH_CLAMPING,
+            adapter_features.contains(hal::Features::DEPTH_CLAMP),
+        );
+        extensions.set(
+            wgt::Extensions::LOGIC_OP,
+            adapter_features.contains(hal::Features::LOGIC_OP),
+        );
+        extensions.set(
+            wgt::Extensions::NON_FILL_POLYGON_MODE,
+            adapter_features.contains(hal::Features::NON_FILL_POLYGON_MODE),
+        );
+        extensions.set(
+            wgt::Extensions::MULTI_DRAW_INDIRECT,
+            adapter_features.contains(hal::Features::MULTI_DRAW_INDIRECT),
+        );
+        extensions.set(
+            wgt::Extensions::MULTI_DRAW_INDIRECT_COUNT,
+            adapter_features.contains(hal::Features::DRAW_INDIRECT_COUNT),
+        );
+        //TODO: expose conservative rasterization once gfx-hal reports support for it
         if unsafe_extensions.allowed() {
             // Unsafe extensions go here
         }
 
         let adapter_limits = raw.physical_device.limits();
+        extensions.set(
+            wgt::Extensions::PUSH_CONSTANTS,
+            adapter_limits.max_push_constants_size != 0,
+        );
+        extensions.set(
+            wgt::Extensions::TIMESTAMP_QUERY,
+            adapter_limits.timestamp_compute_and_graphics,
+        );
 
         let limits = wgt::Limits {
             max_bind_groups: (adapter_limits.max_bound_descriptor_sets as u32)
                 .min(MAX_BIND_GROUPS as u32),
+            max_push_constant_size: adapter_limits.max_push_constants_size as u32,
             _non_exhaustive: unsafe { wgt::NonExhaustive::new() },
         };
 
@@ -198,6 +242,7 @@ impl<B: hal::Backend> Adapter<B> {
             limits,
             capabilities,
             unsafe_extensions,
+            timestamp_period: adapter_limits.timestamp_period,
             life_guard: LifeGuard::new(),
         }
     }
@@ -652,6 +697,15 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         adapter.capabilities
     }
 
+    pub fn adapter_get_timestamp_period<B: GfxBackend>(&self, adapter_id: AdapterId) -> f32 {
+        let hub = B::hub(self);
+        let mut token = Token::root();
+        let (adapter_guard, _) = hub.adapters.read(&mut token);
+        let adapter = &adapter_guard[adapter_id];
+
+        adapter.timestamp_period
+    }
+
     pub fn adapter_destroy<B: GfxBackend>(&self, adapter_id: AdapterId) {
         let hub = B::hub(self);
         let mut token = Token::root();
@@ -672,11 +726,17 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
 }
 
 impl<G: GlobalIdentityHandlerFactory> Global<G> {
+    /// Create a device on the adapter.
+    ///
+    /// The device pipeline cache, used by the pipelines without an explicit cache,
+    /// is seeded with `pipeline_cache_data`, as returned by
+    /// `device_get_pipeline_cache_data` on a previous run.
     pub fn adapter_request_device<B: GfxBackend>(
         &self,
         adapter_id: AdapterId,
         desc: &DeviceDescriptor,
-        trace_path: Option<&std::path::Path>,
+        pipeline_cache_data: Option<&[u8]>,
+        trace_path: Option<(&std::path::Path, TraceFormat)>,
         id_in: Input<G, DeviceId>,
     ) -> DeviceId {
         let hub = B::hub(self);
@@ -714,7 +774,8 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
             // Check features that are always needed
             let wishful_features = hal::Features::VERTEX_STORES_AND_ATOMICS
                 | hal::Features::FRAGMENT_STORES_AND_ATOMICS
-                | hal::Features::NDC_Y_UP;
+                | hal::Features::NDC_Y_UP
+                | hal::Features::IMAGE_CUBE_ARRAY;
             let mut enabled_features = available_features & wishful_features;
             if enabled_features != wishful_features {
                 log::warn!(
@@ -729,6 +790,44 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                 desc.extensions
                     .contains(wgt::Extensions::ANISOTROPIC_FILTERING),
             );
+            enabled_features.set(
+                hal::Features::FORMAT_BC,
+                desc.extensions
+                    .contains(wgt::Extensions::TEXTURE_COMPRESSION_BC),
+            );
+            enabled_features.set(
+                hal::Features::FORMAT_ETC2,
+                desc.extensions
+                    .contains(wgt::Extensions::TEXTURE_COMPRESSION_ETC2),
+            );
+            enabled_features.set(
+                hal::Features::FORMAT_ASTC_LDR,
+                desc.extensions
+                    .contains(wgt::Extensions::TEXTURE_COMPRESSION_ASTC_LDR),
+            );
+            enabled_features.set(
+                hal::Features::DEPTH_CLAMP,
+                desc.extensions.contains(wgt::Extensions::DEPTH_CLAMPING),
+            );
+            enabled_features.set(
+                hal::Features::LOGIC_OP,
+                desc.extensions.contains(wgt::Extensions::LOGIC_OP),
+            );
+            enabled_features.set(
+                hal::Features::NON_FILL_POLYGON_MODE,
+                desc.extensions
+                    .contains(wgt::Extensions::NON_FILL_POLYGON_MODE),
+            );
+            enabled_features.set(
+                hal::Features::MULTI_DRAW_INDIRECT,
+                desc.extensions
+                    .contains(wgt::Extensions::MULTI_DRAW_INDIRECT),
+            );
+            enabled_features.set(
+                hal::Features::DRAW_INDIRECT_COUNT,
+                desc.extensions
+                    .contains(wgt::Extensions::MULTI_DRAW_INDIRECT_COUNT),
+            );
 
             let mut enabled_capabilities = adapter.capabilities & wgt::Capabilities::ALL_BUILT_IN;
 
@@ -791,13 +890,27 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                     "Adapter does not support the requested max_bind_groups"
                 );
             }
+            if desc.limits.max_push_constant_size != 0 {
+                assert!(
+                    desc.extensions.contains(wgt::Extensions::PUSH_CONSTANTS),
+                    "Push constants require the PUSH_CONSTANTS extension"
+                );
+                assert!(
+                    limits.max_push_constants_size as u32 >= desc.limits.max_push_constant_size,
+                    "Adapter does not support the requested max_push_constant_size"
+                );
+            }
 
             let mem_props = phd.memory_properties();
             if !desc.shader_validation {
                 log::warn!("Shader validation is disabled");
             }
+            if desc.skip_zero_initialization {
+                log::warn!("Zero-initialization of resources is disabled");
+            }
             let private_features = PrivateFeatures {
                 shader_validation: desc.shader_validation,
+                zero_initialize: !desc.skip_zero_initialization,
                 texture_d24_s8: phd
                     .format_properties(Some(hal::format::Format::D24UnormS8Uint))
                     .optimal_tiling
@@ -816,6 +929,7 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                 private_features,
                 desc,
                 enabled_capabilities,
+                pipeline_cache_data,
                 trace_path,
             )
         };
@@ -2,6 +2,10 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
+#![allow(
+    // We use loops for getting early-out of scope without closures.
+    clippy::never_loop
+)]
 #![warn(
     trivial_casts,
     trivial_numeric_casts,
@@ -166,6 +170,7 @@ struct Stored<T> {
 #[derive(Clone, Copy, Debug)]
 struct PrivateFeatures {
     shader_validation: bool,
+    zero_initialize: bool,
     texture_d24_s8: bool,
 }
 
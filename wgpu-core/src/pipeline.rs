@@ -4,7 +4,7 @@
 
 use crate::{
     device::RenderPassContext,
-    id::{DeviceId, PipelineLayoutId, ShaderModuleId},
+    id::{DeviceId, PipelineCacheId, PipelineLayoutId, ShaderModuleId},
     validation::StageError,
     LifeGuard, RawString, RefCount, Stored,
 };
@@ -39,6 +39,46 @@ pub enum ShaderModuleSource<'a> {
     Naga(naga::Module),
 }
 
+/// Error parsing shader source, located by line and column.
+#[derive(Clone, Debug)]
+pub struct ShaderParseError {
+    /// The parse error reported by naga.
+    pub error: String,
+    /// Line of the error, starting from 1.
+    pub line: usize,
+    /// Column of the error within the line.
+    pub column: usize,
+    /// The source line containing the error.
+    pub snippet: String,
+}
+
+impl ShaderParseError {
+    pub(crate) fn from_wgsl(source: &str, err: &naga::front::wgsl::ParseError) -> Self {
+        let (line, column) = err.pos;
+        ShaderParseError {
+            error: format!("{:?}", err.error),
+            line,
+            column,
+            snippet: source
+                .lines()
+                .nth(line.saturating_sub(1))
+                .unwrap_or_default()
+                .to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum CreateShaderModuleError {
+    /// Failed to parse the WGSL source.
+    Parsing(ShaderParseError),
+    /// The backend failed to compile the shader.
+    Compilation(String),
+    OutOfMemory,
+    /// The device is lost.
+    DeviceLost,
+}
+
 #[derive(Debug)]
 pub struct ShaderModule<B: hal::Backend> {
     pub(crate) raw: B::ShaderModule,
@@ -46,6 +86,30 @@ pub struct ShaderModule<B: hal::Backend> {
     pub(crate) module: Option<naga::Module>,
 }
 
+#[derive(Clone, Debug)]
+pub enum CreatePipelineCacheError {
+    OutOfMemory,
+    /// The device is lost.
+    DeviceLost,
+}
+
+#[derive(Clone, Debug)]
+pub enum PipelineCacheError {
+    /// The pipeline cache is invalid.
+    Invalid(PipelineCacheId),
+    OutOfMemory,
+}
+
+/// Cache of compiled pipeline state, shared between pipeline creations.
+///
+/// Its contents can be retrieved with `pipeline_cache_get_data` and used
+/// to seed a new cache on the next run.
+#[derive(Debug)]
+pub struct PipelineCache<B: hal::Backend> {
+    pub(crate) raw: B::PipelineCache,
+    pub(crate) device_id: Stored<DeviceId>,
+}
+
 #[repr(C)]
 #[derive(Debug)]
 pub struct ProgrammableStageDescriptor {
@@ -58,11 +122,21 @@ pub struct ProgrammableStageDescriptor {
 pub struct ComputePipelineDescriptor {
     pub layout: PipelineLayoutId,
     pub compute_stage: ProgrammableStageDescriptor,
+    /// Pipeline cache to look up and store the compiled pipeline in.
+    pub cache: Option<PipelineCacheId>,
 }
 
 #[derive(Clone, Debug)]
 pub enum ComputePipelineError {
+    /// The pipeline layout is invalid.
+    InvalidLayout(PipelineLayoutId),
+    /// The shader module is invalid.
+    InvalidShaderModule(ShaderModuleId),
+    /// The pipeline cache is invalid.
+    InvalidPipelineCache(PipelineCacheId),
     Stage(StageError),
+    /// The device is lost.
+    DeviceLost,
 }
 
 #[derive(Debug)]
@@ -86,6 +160,8 @@ pub struct RenderPipelineDescriptor {
     pub vertex_stage: ProgrammableStageDescriptor,
     pub fragment_stage: *const ProgrammableStageDescriptor,
     pub primitive_topology: PrimitiveTopology,
+    /// Restart strips whenever the maximum value of the index format is encountered.
+    pub primitive_restart: bool,
     pub rasterization_state: *const RasterizationStateDescriptor,
     pub color_states: *const ColorStateDescriptor,
     pub color_states_length: usize,
@@ -94,10 +170,18 @@ pub struct RenderPipelineDescriptor {
     pub sample_count: u32,
     pub sample_mask: u32,
     pub alpha_to_coverage_enabled: bool,
+    /// Pipeline cache to look up and store the compiled pipeline in.
+    pub cache: Option<PipelineCacheId>,
 }
 
 #[derive(Clone, Debug)]
 pub enum RenderPipelineError {
+    /// The pipeline layout is invalid.
+    InvalidLayout(PipelineLayoutId),
+    /// One of the shader modules is invalid.
+    InvalidShaderModule(ShaderModuleId),
+    /// The pipeline cache is invalid.
+    InvalidPipelineCache(PipelineCacheId),
     InvalidVertexAttributeOffset {
         location: wgt::ShaderLocation,
         offset: BufferAddress,
@@ -110,6 +194,20 @@ pub enum RenderPipelineError {
         index: u8,
     },
     InvalidSampleCount(u32),
+    /// The pipeline requires an extension which isn't enabled.
+    MissingExtension(wgt::Extensions),
+    /// Primitive restart is only supported for strip topologies.
+    PrimitiveRestartWithListTopology(PrimitiveTopology),
+    /// All color states need to use the same logic operation.
+    InconsistentLogicOp {
+        index: u8,
+    },
+    /// The color state combines a logic operation with blending.
+    LogicOpWithBlending {
+        index: u8,
+    },
+    /// The device is lost.
+    DeviceLost,
 }
 
 bitflags::bitflags! {
@@ -3,8 +3,8 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use crate::{
-    id::{DeviceId, SwapChainId, TextureId},
-    track::DUMMY_SELECTOR,
+    id::{BufferId, DeviceId, SwapChainId, TextureId},
+    track::{InitTracker, DUMMY_SELECTOR},
     LifeGuard, RefCount, Stored,
 };
 
@@ -27,16 +27,19 @@ bitflags::bitflags! {
         const STORAGE_LOAD = 128;
         const STORAGE_STORE = 256;
         const INDIRECT = 512;
+        const QUERY_RESOLVE = 1024;
         /// The combination of all read-only usages.
         const READ_ALL = Self::MAP_READ.bits | Self::COPY_SRC.bits |
             Self::INDEX.bits | Self::VERTEX.bits | Self::UNIFORM.bits |
             Self::STORAGE_LOAD.bits | Self::INDIRECT.bits;
         /// The combination of all write-only and read-write usages.
-        const WRITE_ALL = Self::MAP_WRITE.bits | Self::COPY_DST.bits | Self::STORAGE_STORE.bits;
+        const WRITE_ALL = Self::MAP_WRITE.bits | Self::COPY_DST.bits |
+            Self::STORAGE_STORE.bits | Self::QUERY_RESOLVE.bits;
         /// The combination of all usages that the are guaranteed to be be ordered by the hardware.
         /// If a usage is not ordered, then even if it doesn't change between draw calls, there
         /// still need to be pipeline barriers inserted for synchronization.
-        const ORDERED = Self::READ_ALL.bits | Self::MAP_WRITE.bits | Self::COPY_DST.bits;
+        const ORDERED = Self::READ_ALL.bits | Self::MAP_WRITE.bits | Self::COPY_DST.bits |
+            Self::QUERY_RESOLVE.bits;
     }
 }
 
@@ -110,11 +113,18 @@ unsafe impl Sync for BufferMapOperation {}
 
 impl BufferMapOperation {
     pub(crate) fn call_error(self) {
-        log::error!("wgpu_buffer_map_async failed: buffer mapping is pending");
+        log::error!("wgpu_buffer_map_async failed");
         unsafe {
             (self.callback)(BufferMapAsyncStatus::Error, self.user_data);
         }
     }
+
+    pub(crate) fn call_lost(self) {
+        log::error!("wgpu_buffer_map_async failed: device is lost");
+        unsafe {
+            (self.callback)(BufferMapAsyncStatus::ContextLost, self.user_data);
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -125,6 +135,38 @@ pub struct BufferPendingMapping {
     pub parent_ref_count: RefCount,
 }
 
+#[derive(Clone, Debug)]
+pub enum CreateBufferError {
+    /// `MAP_READ` and `MAP_WRITE` usages can only be combined with the opposite
+    /// `COPY_*` usage, unless `Extensions::MAPPABLE_PRIMARY_BUFFERS` is enabled.
+    UsageMismatch(BufferUsage),
+    /// Not enough memory to allocate the buffer.
+    OutOfMemory,
+    /// Unable to map the buffer memory at creation.
+    MapFailed,
+    /// The device is lost.
+    DeviceLost,
+}
+
+#[derive(Clone, Debug)]
+pub enum BufferAccessError {
+    /// The buffer is invalid.
+    InvalidBuffer(BufferId),
+    /// The buffer usage doesn't contain the flag required for this access.
+    MissingUsage {
+        actual: BufferUsage,
+        expected: BufferUsage,
+    },
+    /// The buffer is already mapped, or has a mapping pending.
+    AlreadyMapped,
+    /// Unable to map the buffer memory.
+    MapFailed,
+    /// The buffer is not mapped.
+    NotMapped,
+    /// The device of the buffer is lost.
+    DeviceLost,
+}
+
 #[derive(Debug)]
 pub struct Buffer<B: hal::Backend> {
     pub(crate) raw: B::Buffer,
@@ -133,6 +175,8 @@ pub struct Buffer<B: hal::Backend> {
     pub(crate) memory: MemoryBlock<B>,
     pub(crate) size: BufferAddress,
     pub(crate) full_range: (),
+    /// Byte ranges that haven't been zero-initialized yet.
+    pub(crate) initialization_status: InitTracker<BufferAddress>,
     pub(crate) sync_mapped_writes: Option<hal::memory::Segment>,
     pub(crate) life_guard: LifeGuard,
     pub(crate) map_state: BufferMapState<B>,
@@ -150,6 +194,36 @@ impl<B: hal::Backend> Borrow<()> for Buffer<B> {
     }
 }
 
+#[derive(Clone, Debug)]
+pub enum TextureDimensionError {
+    /// 1D textures must have a height of 1.
+    InvalidHeight,
+    InvalidSampleCount(u32),
+    /// The number of array layers doesn't fit in the backend limits.
+    LayerCount(u32),
+}
+
+#[derive(Clone, Debug)]
+pub enum CreateTextureError {
+    /// `Depth24Plus` textures can't be used for copies.
+    CannotCopyD24Plus,
+    /// The texture format requires an extension which isn't enabled.
+    MissingExtension(wgt::Extensions),
+    /// Compressed textures must be 2D.
+    InvalidCompressedDimension(wgt::TextureDimension),
+    /// The size of a compressed texture must be a multiple of the texel block dimensions.
+    UnalignedCompressedSize(wgt::Extent3d),
+    /// Compressed textures can only be sampled or copied.
+    InvalidCompressedUsage(TextureUsage),
+    InvalidDimension(TextureDimensionError),
+    /// The mip level count must be less than `MAX_MIP_LEVELS`.
+    InvalidMipLevelCount(u32),
+    /// Not enough memory to allocate the texture.
+    OutOfMemory,
+    /// The device is lost.
+    DeviceLost,
+}
+
 #[derive(Debug)]
 pub struct Texture<B: hal::Backend> {
     pub(crate) raw: B::Image,
@@ -159,6 +233,8 @@ pub struct Texture<B: hal::Backend> {
     pub(crate) kind: hal::image::Kind,
     pub(crate) format: TextureFormat,
     pub(crate) full_range: hal::image::SubresourceRange,
+    /// Array layers that haven't been zero-initialized yet, for each mip level.
+    pub(crate) initialization_status: Vec<InitTracker<hal::image::Layer>>,
     pub(crate) memory: MemoryBlock<B>,
     pub(crate) life_guard: LifeGuard,
 }
@@ -187,11 +263,49 @@ pub(crate) enum TextureViewInner<B: hal::Backend> {
     },
 }
 
+#[derive(Clone, Debug)]
+pub enum CreateTextureViewError {
+    /// The parent texture is invalid.
+    InvalidTexture(TextureId),
+    /// The mip level range doesn't fit in the texture.
+    InvalidMipLevelRange {
+        base: u32,
+        count: u32,
+        total: u32,
+    },
+    /// The array layer range doesn't fit in the texture.
+    InvalidArrayLayerRange {
+        base: u32,
+        count: u32,
+        total: u32,
+    },
+    /// The view dimension is not compatible with the texture dimension.
+    InvalidTextureViewDimension {
+        view: wgt::TextureViewDimension,
+        texture: wgt::TextureDimension,
+    },
+    /// The number of array layers doesn't match the view dimension: it must be
+    /// 1 for `D1`, `D2` and `D3`, 6 for `Cube`, and a multiple of 6 for `CubeArray`.
+    InvalidArrayLayerCount {
+        dimension: wgt::TextureViewDimension,
+        count: u32,
+    },
+    /// Cube views require the texture width and height to be equal.
+    InvalidCubeExtent {
+        width: u32,
+        height: u32,
+    },
+    OutOfMemory,
+    /// The device is lost.
+    DeviceLost,
+}
+
 #[derive(Debug)]
 pub struct TextureView<B: hal::Backend> {
     pub(crate) inner: TextureViewInner<B>,
     //TODO: store device_id for quick access?
     pub(crate) format: TextureFormat,
+    pub(crate) dimension: wgt::TextureViewDimension,
     pub(crate) extent: hal::image::Extent,
     pub(crate) samples: hal::image::NumSamples,
     pub(crate) range: hal::image::SubresourceRange,
@@ -210,6 +324,16 @@ impl<B: hal::Backend> Borrow<()> for TextureView<B> {
     }
 }
 
+#[derive(Clone, Debug)]
+pub enum CreateSamplerError {
+    MissingExtension(wgt::Extensions),
+    /// Anisotropy clamp must be one of 1, 2, 4, 8 or 16.
+    InvalidAnisotropyClamp(u8),
+    OutOfMemory,
+    /// The device is lost.
+    DeviceLost,
+}
+
 #[derive(Debug)]
 pub struct Sampler<B: hal::Backend> {
     pub(crate) raw: B::Sampler,
@@ -230,3 +354,34 @@ impl<B: hal::Backend> Borrow<()> for Sampler<B> {
         &DUMMY_SELECTOR
     }
 }
+
+#[derive(Clone, Debug)]
+pub enum CreateQuerySetError {
+    MissingExtension(wgt::Extensions),
+    /// The number of queries is zero or exceeds `QUERY_SET_MAX_QUERIES`.
+    InvalidCount(u32),
+    OutOfMemory,
+    /// The device is lost.
+    DeviceLost,
+}
+
+#[derive(Debug)]
+pub struct QuerySet<B: hal::Backend> {
+    pub(crate) raw: B::QueryPool,
+    pub(crate) device_id: Stored<DeviceId>,
+    pub(crate) life_guard: LifeGuard,
+    pub(crate) ty: wgt::QueryType,
+    pub(crate) count: u32,
+}
+
+impl<B: hal::Backend> Borrow<RefCount> for QuerySet<B> {
+    fn borrow(&self) -> &RefCount {
+        self.life_guard.ref_count.as_ref().unwrap()
+    }
+}
+
+impl<B: hal::Backend> Borrow<()> for QuerySet<B> {
+    fn borrow(&self) -> &() {
+        &DUMMY_SELECTOR
+    }
+}
@@ -36,6 +36,7 @@
 use crate::device::trace::Action;
 use crate::{
     conv,
+    device::{DeviceLostReason, UserClosures},
     hub::{GfxBackend, Global, GlobalIdentityHandlerFactory, Input, Token},
     id::{DeviceId, SwapChainId, TextureViewId},
     resource, LifeGuard, PrivateFeatures, Stored, SubmissionIndex,
@@ -59,6 +60,21 @@ pub struct SwapChain<B: hal::Backend> {
     pub(crate) active_submission_index: SubmissionIndex,
 }
 
+#[derive(Clone, Debug)]
+pub enum CreateSwapChainError {
+    /// The surface can't be presented from the device queue family.
+    UnsupportedQueueFamily,
+    /// The requested format is not supported by the surface.
+    UnsupportedFormat {
+        requested: hal::format::Format,
+        available: Vec<hal::format::Format>,
+    },
+    /// The previous swap chain still has its output acquired.
+    SwapChainOutputExists,
+    /// The device is lost.
+    DeviceLost,
+}
+
 pub(crate) fn swap_chain_descriptor_to_hal(
     desc: &SwapChainDescriptor,
     num_frames: u32,
@@ -102,9 +118,15 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         let (device_guard, mut token) = hub.devices.read(&mut token);
         let (mut swap_chain_guard, mut token) = hub.swap_chains.write(&mut token);
         let sc = &mut swap_chain_guard[swap_chain_id];
-        #[cfg_attr(not(feature = "trace"), allow(unused_variables))]
         let device = &device_guard[sc.device_id.value];
+        if device.is_lost() {
+            return SwapChainOutput {
+                status: SwapChainStatus::Lost,
+                view_id: None,
+            };
+        }
 
+        let mut closures = UserClosures::default();
         let suf = B::get_surface_mut(surface);
         let (image, status) = match unsafe { suf.acquire_image(FRAME_TIMEOUT_MS * 1_000_000) } {
             Ok((surface_image, None)) => (Some(surface_image), SwapChainStatus::Good),
@@ -117,7 +139,12 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                     hal::window::AcquireError::Timeout => SwapChainStatus::Timeout,
                     hal::window::AcquireError::OutOfDate => SwapChainStatus::Outdated,
                     hal::window::AcquireError::SurfaceLost(_) => SwapChainStatus::Lost,
-                    hal::window::AcquireError::DeviceLost(_) => SwapChainStatus::Lost,
+                    hal::window::AcquireError::DeviceLost(_) => {
+                        closures
+                            .device_lost
+                            .extend(device.lose(DeviceLostReason::Unknown));
+                        SwapChainStatus::Lost
+                    }
                 },
             ),
         };
@@ -132,6 +159,7 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                     },
                 },
                 format: sc.desc.format,
+                dimension: wgt::TextureViewDimension::D2,
                 extent: hal::image::Extent {
                     width: sc.desc.width,
                     height: sc.desc.height,
@@ -173,6 +201,11 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
             None => (),
         };
 
+        drop(swap_chain_guard);
+        drop(device_guard);
+        drop(surface_guard);
+        closures.fire();
+
         SwapChainOutput { status, view_id }
     }
 
@@ -198,7 +231,7 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
             .take()
             .expect("Swap chain image is not acquired");
         let (view, _) = hub.texture_views.unregister(view_id.value, &mut token);
-        let image = match view.inner {
+        let image = match view.unwrap().inner {
             resource::TextureViewInner::Native { .. } => unreachable!(),
             resource::TextureViewInner::SwapChain { image, .. } => image,
         };
@@ -212,8 +245,15 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
             let queue = &mut device.queue_group.queues[0];
             unsafe { queue.present_surface(B::get_surface_mut(surface), image, sem) }
         };
-        if let Err(e) = err {
-            log::warn!("present failed: {:?}", e);
+        let mut closures = UserClosures::default();
+        match err {
+            Ok(_) => (),
+            Err(hal::window::PresentError::DeviceLost(_)) => {
+                closures
+                    .device_lost
+                    .extend(device.lose(DeviceLostReason::Unknown));
+            }
+            Err(e) => log::warn!("present failed: {:?}", e),
         }
 
         for fbo in sc.acquired_framebuffers.drain(..) {
@@ -221,5 +261,10 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                 device.raw.destroy_framebuffer(fbo);
             }
         }
+
+        drop(swap_chain_guard);
+        drop(device_guard);
+        drop(surface_guard);
+        closures.fire();
     }
 }
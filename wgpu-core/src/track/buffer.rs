@@ -210,6 +210,37 @@ mod test {
         );
     }
 
+    #[test]
+    fn change_query_resolve() {
+        let mut bs = Unit {
+            first: None,
+            last: BufferUse::QUERY_RESOLVE,
+        };
+        let id = Id::default();
+        assert_eq!(
+            bs.change(id, (), BufferUse::COPY_SRC, None),
+            Err(PendingTransition {
+                id,
+                selector: (),
+                usage: BufferUse::QUERY_RESOLVE..BufferUse::COPY_SRC,
+            }),
+        );
+        let mut list = Vec::new();
+        bs.change(id, (), BufferUse::QUERY_RESOLVE, Some(&mut list))
+            .unwrap();
+        assert!(list.is_empty());
+        bs.change(id, (), BufferUse::COPY_SRC, Some(&mut list))
+            .unwrap();
+        assert_eq!(
+            &list,
+            &[PendingTransition {
+                id,
+                selector: (),
+                usage: BufferUse::QUERY_RESOLVE..BufferUse::COPY_SRC,
+            }],
+        );
+    }
+
     #[test]
     fn prepend() {
         let mut bs = Unit {
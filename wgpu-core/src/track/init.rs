@@ -0,0 +1,152 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use smallvec::SmallVec;
+
+use std::{cmp, ops::Range};
+
+/// Structure that keeps track of the parts of a resource
+/// that haven't been initialized yet.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct InitTracker<I> {
+    /// List of uninitialized ranges.
+    /// Ranges have to be non-empty, non-intersecting and ordered.
+    uninitialized: SmallVec<[Range<I>; 1]>,
+}
+
+impl<I: Copy + Ord> InitTracker<I> {
+    /// Create a tracker with the whole `range` uninitialized.
+    pub fn new(range: Range<I>) -> Self {
+        let mut uninitialized = SmallVec::new();
+        if range.start < range.end {
+            uninitialized.push(range);
+        }
+        InitTracker { uninitialized }
+    }
+
+    /// Create a tracker with everything initialized.
+    pub fn initialized() -> Self {
+        InitTracker {
+            uninitialized: SmallVec::new(),
+        }
+    }
+
+    /// Mark `range` as initialized.
+    ///
+    /// Returns the parts of `range` that were not initialized before,
+    /// in ascending order.
+    pub fn drain(&mut self, range: Range<I>) -> SmallVec<[Range<I>; 1]> {
+        let mut drained = SmallVec::new();
+        let mut pos = match self.uninitialized.iter().position(|r| r.end > range.start) {
+            Some(pos) => pos,
+            None => return drained,
+        };
+        while pos < self.uninitialized.len() {
+            let current = self.uninitialized[pos].clone();
+            if current.start >= range.end {
+                break;
+            }
+            let start = cmp::max(current.start, range.start);
+            let end = cmp::min(current.end, range.end);
+            drained.push(start..end);
+            match (current.start < start, end < current.end) {
+                (true, true) => {
+                    self.uninitialized[pos].end = start;
+                    self.uninitialized.insert(pos + 1, end..current.end);
+                    break;
+                }
+                (true, false) => {
+                    self.uninitialized[pos].end = start;
+                    pos += 1;
+                }
+                (false, true) => {
+                    self.uninitialized[pos].start = end;
+                    break;
+                }
+                (false, false) => {
+                    self.uninitialized.remove(pos);
+                }
+            }
+        }
+        drained
+    }
+
+    /// Mark `range` as uninitialized again.
+    pub fn discard(&mut self, range: Range<I>) {
+        if range.start >= range.end {
+            return;
+        }
+        self.drain(range.clone());
+        let pos = self
+            .uninitialized
+            .iter()
+            .position(|r| r.start > range.start)
+            .unwrap_or_else(|| self.uninitialized.len());
+        self.uninitialized.insert(pos, range);
+        // merge with the neighbors
+        if pos + 1 < self.uninitialized.len()
+            && self.uninitialized[pos].end == self.uninitialized[pos + 1].start
+        {
+            self.uninitialized[pos].end = self.uninitialized[pos + 1].end;
+            self.uninitialized.remove(pos + 1);
+        }
+        if pos > 0 && self.uninitialized[pos - 1].end == self.uninitialized[pos].start {
+            self.uninitialized[pos - 1].end = self.uninitialized[pos].end;
+            self.uninitialized.remove(pos);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::InitTracker;
+    use std::ops::Range;
+
+    fn drain(tracker: &mut InitTracker<u32>, range: Range<u32>) -> Vec<Range<u32>> {
+        tracker.drain(range).into_iter().collect()
+    }
+
+    #[test]
+    fn drain_empty() {
+        let mut tracker = InitTracker::new(0..0);
+        assert_eq!(drain(&mut tracker, 0..10), &[]);
+    }
+
+    #[test]
+    fn drain_whole() {
+        let mut tracker = InitTracker::new(0..10);
+        assert_eq!(drain(&mut tracker, 0..10), &[0..10]);
+        assert_eq!(tracker, InitTracker::initialized());
+    }
+
+    #[test]
+    fn drain_middle() {
+        let mut tracker = InitTracker::new(0..10);
+        assert_eq!(drain(&mut tracker, 4..6), &[4..6]);
+        assert_eq!(drain(&mut tracker, 2..8), &[2..4, 6..8]);
+        assert_eq!(drain(&mut tracker, 0..10), &[0..2, 8..10]);
+        assert_eq!(drain(&mut tracker, 0..10), &[]);
+    }
+
+    #[test]
+    fn drain_edges() {
+        let mut tracker = InitTracker::new(0..10);
+        assert_eq!(drain(&mut tracker, 0..3), &[0..3]);
+        assert_eq!(drain(&mut tracker, 7..12), &[7..10]);
+        assert_eq!(drain(&mut tracker, 1..9), &[3..7]);
+        assert_eq!(tracker, InitTracker::initialized());
+    }
+
+    #[test]
+    fn discard() {
+        let mut tracker = InitTracker::new(0..10);
+        tracker.drain(0..10);
+        tracker.discard(2..4);
+        tracker.discard(6..8);
+        tracker.discard(4..6);
+        assert_eq!(drain(&mut tracker, 0..10), &[2..8]);
+        tracker.discard(0..10);
+        assert_eq!(tracker, InitTracker::new(0..10));
+    }
+}
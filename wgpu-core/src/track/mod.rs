@@ -3,12 +3,13 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 mod buffer;
+mod init;
 mod range;
 mod texture;
 
 use crate::{
     conv,
-    hub::Storage,
+    hub::{InvalidId, Storage},
     id::{self, TypedId},
     resource, Epoch, FastHashMap, Index, RefCount,
 };
@@ -18,6 +19,7 @@ use std::{
 };
 
 pub(crate) use buffer::BufferState;
+pub(crate) use init::InitTracker;
 pub(crate) use texture::TextureState;
 
 /// A single unit of state tracking. It keeps an initial
@@ -43,6 +45,15 @@ impl<U: Copy> Unit<U> {
     }
 }
 
+/// The reason for a failed `use_extend` call.
+#[derive(Clone, Debug, PartialEq)]
+pub enum UseExtendError<U> {
+    /// The resource ID refers to an invalid object.
+    InvalidResource,
+    /// The resource is already used in a conflicting way.
+    Conflict(U),
+}
+
 /// The main trait that abstracts away the tracking logic of
 /// a particular resource type, like a buffer or a texture.
 pub trait ResourceState: Clone + Default {
@@ -395,27 +406,31 @@ impl<S: ResourceState> ResourceTracker<S> {
         id: S::Id,
         selector: S::Selector,
         usage: S::Usage,
-    ) -> Result<&'a T, S::Usage> {
-        let item = &storage[id];
+    ) -> Result<&'a T, UseExtendError<S::Usage>> {
+        let item = storage
+            .get(id)
+            .map_err(|_| UseExtendError::InvalidResource)?;
         self.change_extend(id, item.borrow(), selector, usage)
             .map(|()| item)
-            .map_err(|pending| pending.usage.start)
+            .map_err(|pending| UseExtendError::Conflict(pending.usage.start))
     }
 
     /// Use a given resource provided by an `Id` with the specified usage.
     /// Combines storage access by 'Id' with the transition that replaces
     /// the last usage with a new one, returning an iterator over these
     /// transitions.
+    ///
+    /// Returns `InvalidId` if the resource is not valid.
     pub fn use_replace<'a, T: 'a + Borrow<RefCount>>(
         &mut self,
         storage: &'a Storage<T, S::Id>,
         id: S::Id,
         selector: S::Selector,
         usage: S::Usage,
-    ) -> (&'a T, Drain<PendingTransition<S>>) {
-        let item = &storage[id];
+    ) -> Result<(&'a T, Drain<PendingTransition<S>>), InvalidId> {
+        let item = storage.get(id)?;
         let drain = self.change_replace(id, item.borrow(), selector, usage);
-        (item, drain)
+        Ok((item, drain))
     }
 }
 
@@ -469,6 +484,7 @@ pub(crate) struct TrackerSet {
     pub views: ResourceTracker<PhantomData<id::TextureViewId>>,
     pub bind_groups: ResourceTracker<PhantomData<id::BindGroupId>>,
     pub samplers: ResourceTracker<PhantomData<id::SamplerId>>,
+    pub query_sets: ResourceTracker<PhantomData<id::QuerySetId>>,
     pub compute_pipes: ResourceTracker<PhantomData<id::ComputePipelineId>>,
     pub render_pipes: ResourceTracker<PhantomData<id::RenderPipelineId>>,
     pub bundles: ResourceTracker<PhantomData<id::RenderBundleId>>,
@@ -483,6 +499,7 @@ impl TrackerSet {
             views: ResourceTracker::new(backend),
             bind_groups: ResourceTracker::new(backend),
             samplers: ResourceTracker::new(backend),
+            query_sets: ResourceTracker::new(backend),
             compute_pipes: ResourceTracker::new(backend),
             render_pipes: ResourceTracker::new(backend),
             bundles: ResourceTracker::new(backend),
@@ -496,6 +513,7 @@ impl TrackerSet {
         self.views.clear();
         self.bind_groups.clear();
         self.samplers.clear();
+        self.query_sets.clear();
         self.compute_pipes.clear();
         self.render_pipes.clear();
         self.bundles.clear();
@@ -508,6 +526,7 @@ impl TrackerSet {
         self.views.optimize();
         self.bind_groups.optimize();
         self.samplers.optimize();
+        self.query_sets.optimize();
         self.compute_pipes.optimize();
         self.render_pipes.optimize();
         self.bundles.optimize();
@@ -521,6 +540,7 @@ impl TrackerSet {
         self.views.merge_extend(&other.views).unwrap();
         self.bind_groups.merge_extend(&other.bind_groups).unwrap();
         self.samplers.merge_extend(&other.samplers).unwrap();
+        self.query_sets.merge_extend(&other.query_sets).unwrap();
         self.compute_pipes
             .merge_extend(&other.compute_pipes)
             .unwrap();
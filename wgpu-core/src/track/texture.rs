@@ -269,7 +269,6 @@ impl ResourceState for TextureState {
 
 #[cfg(test)]
 mod test {
-    //TODO: change() tests
     use super::*;
     use crate::id::Id;
     use hal::{format::Aspects, image::SubresourceRange};
@@ -322,6 +321,57 @@ mod test {
         );
     }
 
+    #[test]
+    fn change_layers() {
+        let id = Id::default();
+        let mut ts = TextureState::default();
+        ts.mips.push(PlaneStates::from_slice(&[(
+            0..6,
+            Unit::new(TextureUse::SAMPLED),
+        )]));
+
+        let mut list = Vec::new();
+        ts.change(
+            id,
+            SubresourceRange {
+                aspects: Aspects::COLOR,
+                levels: 0..1,
+                layers: 2..4,
+            },
+            TextureUse::COPY_DST,
+            Some(&mut list),
+        )
+        .unwrap();
+        assert_eq!(
+            &list,
+            &[PendingTransition {
+                id,
+                selector: SubresourceRange {
+                    aspects: Aspects::empty(),
+                    levels: 0..1,
+                    layers: 2..4,
+                },
+                usage: TextureUse::SAMPLED..TextureUse::COPY_DST,
+            }],
+            "only the changed layers need a transition"
+        );
+        assert_eq!(
+            ts.mips[0],
+            PlaneStates::from_slice(&[
+                (0..2, Unit::new(TextureUse::SAMPLED)),
+                (
+                    2..4,
+                    Unit {
+                        first: Some(TextureUse::SAMPLED),
+                        last: TextureUse::COPY_DST,
+                    },
+                ),
+                (4..6, Unit::new(TextureUse::SAMPLED)),
+            ]),
+            "the other layers are left untouched"
+        );
+    }
+
     #[test]
     fn merge() {
         let id = Id::default();
@@ -2,9 +2,13 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
-use crate::{binding_model::BindEntryMap, FastHashMap};
+use crate::{
+    binding_model::{BindEntryMap, BindGroupLayoutEntry},
+    FastHashMap,
+};
 use spirv_headers as spirv;
-use wgt::{BindGroupLayoutEntry, BindingType};
+use std::ops::Range;
+use wgt::BindingType;
 
 #[derive(Clone, Debug)]
 pub enum BindingError {
@@ -55,6 +59,9 @@ pub enum StageError {
         location: wgt::ShaderLocation,
         error: InputError,
     },
+    /// The push constant block of the shader is not contained in the
+    /// push constant range visible from the stage.
+    PushConstantOutOfRange { block: Range<wgt::BufferAddress> },
 }
 
 fn get_aligned_type_size(
@@ -107,6 +114,20 @@ fn get_aligned_type_size(
     }
 }
 
+/// Check if a push constant range visible from the stage contains the whole
+/// push constant block of the shader.
+fn is_push_constant_block_covered(
+    ranges: &[wgt::PushConstantRange],
+    stage_bit: wgt::ShaderStage,
+    block: &Range<wgt::BufferAddress>,
+) -> bool {
+    ranges.iter().any(|range| {
+        range.stages.contains(stage_bit)
+            && range.range.start as wgt::BufferAddress <= block.start
+            && range.range.end as wgt::BufferAddress >= block.end
+    })
+}
+
 fn check_binding(
     module: &naga::Module,
     var: &naga::GlobalVariable,
@@ -601,6 +622,69 @@ fn map_texture_format(format: wgt::TextureFormat) -> naga::TypeInner {
         Tf::Depth32Float | Tf::Depth24Plus | Tf::Depth24PlusStencil8 => {
             panic!("Unexpected depth format")
         }
+        Tf::Bc4RUnorm | Tf::Bc4RSnorm | Tf::EacRUnorm | Tf::EacRSnorm => Ti::Scalar {
+            kind: Sk::Float,
+            width: 8,
+        },
+        Tf::Bc5RgUnorm | Tf::Bc5RgSnorm | Tf::EacRgUnorm | Tf::EacRgSnorm => Ti::Vector {
+            size: Vs::Bi,
+            kind: Sk::Float,
+            width: 8,
+        },
+        Tf::Bc6hRgbUfloat | Tf::Bc6hRgbSfloat => Ti::Vector {
+            size: Vs::Tri,
+            kind: Sk::Float,
+            width: 16,
+        },
+        Tf::Etc2RgbUnorm | Tf::Etc2RgbUnormSrgb => Ti::Vector {
+            size: Vs::Tri,
+            kind: Sk::Float,
+            width: 8,
+        },
+        Tf::Bc1RgbaUnorm
+        | Tf::Bc1RgbaUnormSrgb
+        | Tf::Bc2RgbaUnorm
+        | Tf::Bc2RgbaUnormSrgb
+        | Tf::Bc3RgbaUnorm
+        | Tf::Bc3RgbaUnormSrgb
+        | Tf::Bc7RgbaUnorm
+        | Tf::Bc7RgbaUnormSrgb
+        | Tf::Etc2RgbA1Unorm
+        | Tf::Etc2RgbA1UnormSrgb
+        | Tf::Etc2RgbA8Unorm
+        | Tf::Etc2RgbA8UnormSrgb
+        | Tf::Astc4x4RgbaUnorm
+        | Tf::Astc4x4RgbaUnormSrgb
+        | Tf::Astc5x4RgbaUnorm
+        | Tf::Astc5x4RgbaUnormSrgb
+        | Tf::Astc5x5RgbaUnorm
+        | Tf::Astc5x5RgbaUnormSrgb
+        | Tf::Astc6x5RgbaUnorm
+        | Tf::Astc6x5RgbaUnormSrgb
+        | Tf::Astc6x6RgbaUnorm
+        | Tf::Astc6x6RgbaUnormSrgb
+        | Tf::Astc8x5RgbaUnorm
+        | Tf::Astc8x5RgbaUnormSrgb
+        | Tf::Astc8x6RgbaUnorm
+        | Tf::Astc8x6RgbaUnormSrgb
+        | Tf::Astc8x8RgbaUnorm
+        | Tf::Astc8x8RgbaUnormSrgb
+        | Tf::Astc10x5RgbaUnorm
+        | Tf::Astc10x5RgbaUnormSrgb
+        | Tf::Astc10x6RgbaUnorm
+        | Tf::Astc10x6RgbaUnormSrgb
+        | Tf::Astc10x8RgbaUnorm
+        | Tf::Astc10x8RgbaUnormSrgb
+        | Tf::Astc10x10RgbaUnorm
+        | Tf::Astc10x10RgbaUnormSrgb
+        | Tf::Astc12x10RgbaUnorm
+        | Tf::Astc12x10RgbaUnormSrgb
+        | Tf::Astc12x12RgbaUnorm
+        | Tf::Astc12x12RgbaUnormSrgb => Ti::Vector {
+            size: Vs::Quad,
+            kind: Sk::Float,
+            width: 8,
+        },
     }
 }
 
@@ -615,6 +699,7 @@ pub type StageInterface<'a> = FastHashMap<wgt::ShaderLocation, MaybeOwned<'a, na
 pub fn check_stage<'a>(
     module: &'a naga::Module,
     group_layouts: &[&BindEntryMap],
+    push_constant_ranges: &[wgt::PushConstantRange],
     entry_point_name: &str,
     execution_model: spirv::ExecutionModel,
     inputs: StageInterface<'a>,
@@ -689,8 +774,57 @@ pub fn check_stage<'a>(
                     }
                 }
             }
+            None if var.class == spirv::StorageClass::PushConstant => {
+                let mut ty = var.ty;
+                //TODO: change naga's IR to avoid a pointer here
+                if let naga::TypeInner::Pointer { base, class: _ } = module.types[ty].inner {
+                    ty = base;
+                }
+                // The block doesn't have to start at zero, if the stage shares
+                // the push constants with other stages.
+                let start = match module.types[ty].inner {
+                    naga::TypeInner::Struct { ref members } => members
+                        .first()
+                        .map_or(0, |member| member.offset as wgt::BufferAddress),
+                    _ => 0,
+                };
+                let block = start..get_aligned_type_size(module, ty, false);
+                if !is_push_constant_block_covered(push_constant_ranges, stage_bit, &block) {
+                    return Err(StageError::PushConstantOutOfRange { block });
+                }
+            }
             _ => {}
         }
     }
     Ok(outputs)
 }
+
+#[test]
+fn test_push_constant_block_coverage() {
+    let ranges = [
+        wgt::PushConstantRange {
+            stages: wgt::ShaderStage::VERTEX,
+            range: 0..16,
+        },
+        wgt::PushConstantRange {
+            stages: wgt::ShaderStage::FRAGMENT,
+            range: 16..32,
+        },
+    ];
+    let vs = wgt::ShaderStage::VERTEX;
+    let fs = wgt::ShaderStage::FRAGMENT;
+    assert!(is_push_constant_block_covered(&ranges, vs, &(0..16)));
+    assert!(is_push_constant_block_covered(&ranges, fs, &(16..32)));
+    assert!(is_push_constant_block_covered(&ranges, fs, &(20..24)));
+    // the block begins before the range of the stage
+    assert!(!is_push_constant_block_covered(&ranges, fs, &(0..32)));
+    assert!(!is_push_constant_block_covered(&ranges, fs, &(8..24)));
+    // the block ends after the range of the stage
+    assert!(!is_push_constant_block_covered(&ranges, vs, &(0..20)));
+    // the range isn't visible from the stage
+    assert!(!is_push_constant_block_covered(
+        &ranges,
+        wgt::ShaderStage::COMPUTE,
+        &(0..4)
+    ));
+}
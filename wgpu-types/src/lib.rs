@@ -23,6 +23,20 @@ pub const COPY_BYTES_PER_ROW_ALIGNMENT: u32 = 256;
 pub const BIND_BUFFER_ALIGNMENT: BufferAddress = 256;
 /// Buffer to buffer copy offsets and sizes must be aligned to this number.
 pub const COPY_BUFFER_ALIGNMENT: BufferAddress = 4;
+/// Push constant ranges, offsets, and sizes must be aligned to this number.
+pub const PUSH_CONSTANT_ALIGNMENT: u32 = 4;
+/// Size of a single query result when resolved into a buffer, in bytes.
+pub const QUERY_SIZE: u32 = 8;
+/// Query set resolve destination offsets must be aligned to this number.
+pub const QUERY_RESOLVE_BUFFER_ALIGNMENT: BufferAddress = 256;
+/// Maximum number of queries in a query set.
+pub const QUERY_SET_MAX_QUERIES: u32 = 8192;
+/// Size of the arguments of a single indirect draw, in bytes.
+pub const DRAW_INDIRECT_SIZE: BufferAddress = 16;
+/// Size of the arguments of a single indexed indirect draw, in bytes.
+pub const DRAW_INDEXED_INDIRECT_SIZE: BufferAddress = 20;
+/// Indirect draw argument and count buffer offsets must be aligned to this number.
+pub const INDIRECT_BUFFER_OFFSET_ALIGNMENT: BufferAddress = 4;
 
 /// Integral newtype for buffer sizes.
 #[repr(transparent)]
@@ -212,6 +226,162 @@ bitflags::bitflags! {
         /// [`SAMPLED_TEXTURE_ARRAY_NON_UNIFORM_INDEXING`]: struct.Capabilities.html#associatedconstant.SAMPLED_TEXTURE_ARRAY_NON_UNIFORM_INDEXING
         /// [`UNSIZED_BINDING_ARRAY`]: struct.Capabilities.html#associatedconstant.UNSIZED_BINDING_ARRAY
         const BINDING_INDEXING = 0x0000_0000_0004_0000;
+        /// Allows the user to set a small amount of data directly in the command stream,
+        /// which is visible to the shaders as a `push_constant` uniform block.
+        ///
+        /// The amount of available space is given by [`Limits::max_push_constant_size`].
+        ///
+        /// Supported platforms:
+        /// - DX12
+        /// - Vulkan
+        /// - Metal
+        ///
+        /// Provided Capabilities:
+        /// - None
+        ///
+        /// This is a native only extension.
+        ///
+        /// [`Limits::max_push_constant_size`]: struct.Limits.html#structfield.max_push_constant_size
+        const PUSH_CONSTANTS = 0x0000_0000_0008_0000;
+        /// Allows the creation of query sets with [`QueryType::Timestamp`], and writing
+        /// timestamps into them from command encoders and passes.
+        ///
+        /// The values written are in ticks, which can be converted to nanoseconds
+        /// by multiplying with the timestamp period of the adapter.
+        ///
+        /// Supported platforms:
+        /// - DX12
+        /// - Vulkan
+        /// - Metal
+        ///
+        /// Provided Capabilities:
+        /// - None
+        ///
+        /// This is a native only extension.
+        ///
+        /// [`QueryType::Timestamp`]: enum.QueryType.html#variant.Timestamp
+        const TIMESTAMP_QUERY = 0x0000_0000_0010_0000;
+        /// Allows the creation of textures with the BC (S3TC/RGTC/BPTC) compressed formats,
+        /// like [`TextureFormat::Bc7RgbaUnorm`].
+        ///
+        /// Compressed textures are always 2D, and can only be sampled or copied.
+        ///
+        /// Supported platforms:
+        /// - DX12
+        /// - Vulkan (desktop)
+        /// - Metal (macOS)
+        ///
+        /// Provided Capabilities:
+        /// - None
+        ///
+        /// This is a native only extension.
+        ///
+        /// [`TextureFormat::Bc7RgbaUnorm`]: enum.TextureFormat.html#variant.Bc7RgbaUnorm
+        const TEXTURE_COMPRESSION_BC = 0x0000_0000_0020_0000;
+        /// Allows the creation of textures with the ETC2/EAC compressed formats,
+        /// like [`TextureFormat::Etc2RgbUnorm`].
+        ///
+        /// Compressed textures are always 2D, and can only be sampled or copied.
+        ///
+        /// Supported platforms:
+        /// - Vulkan (mobile)
+        /// - Metal (iOS)
+        ///
+        /// Provided Capabilities:
+        /// - None
+        ///
+        /// This is a native only extension.
+        ///
+        /// [`TextureFormat::Etc2RgbUnorm`]: enum.TextureFormat.html#variant.Etc2RgbUnorm
+        const TEXTURE_COMPRESSION_ETC2 = 0x0000_0000_0040_0000;
+        /// Allows the creation of textures with the ASTC LDR compressed formats,
+        /// like [`TextureFormat::Astc4x4RgbaUnorm`].
+        ///
+        /// Compressed textures are always 2D, and can only be sampled or copied.
+        ///
+        /// Supported platforms:
+        /// - Vulkan (mobile)
+        /// - Metal (iOS)
+        ///
+        /// Provided Capabilities:
+        /// - None
+        ///
+        /// This is a native only extension.
+        ///
+        /// [`TextureFormat::Astc4x4RgbaUnorm`]: enum.TextureFormat.html#variant.Astc4x4RgbaUnorm
+        const TEXTURE_COMPRESSION_ASTC_LDR = 0x0000_0000_0080_0000;
+        /// Allows depth values outside of the viewport depth range to be clamped instead
+        /// of clipped, using [`RasterizationStateDescriptor::clamp_depth`].
+        ///
+        /// Supported platforms:
+        /// - DX12
+        /// - Vulkan
+        /// - Metal (macOS)
+        ///
+        /// Provided Capabilities:
+        /// - None
+        ///
+        /// This is a native only extension.
+        ///
+        /// [`RasterizationStateDescriptor::clamp_depth`]: struct.RasterizationStateDescriptor.html#structfield.clamp_depth
+        const DEPTH_CLAMPING = 0x0000_0000_0100_0000;
+        /// Allows render pipelines to combine fragment outputs with the color attachments
+        /// through a bitwise [`LogicOp`] instead of blending.
+        ///
+        /// Supported platforms:
+        /// - DX12
+        /// - Vulkan (desktop)
+        ///
+        /// Provided Capabilities:
+        /// - None
+        ///
+        /// This is a native only extension.
+        ///
+        /// [`LogicOp`]: enum.LogicOp.html
+        const LOGIC_OP = 0x0000_0000_0200_0000;
+        /// Allows render pipelines to rasterize polygons as lines or points, using
+        /// [`RasterizationStateDescriptor::polygon_mode`].
+        ///
+        /// Supported platforms:
+        /// - DX12 (lines only)
+        /// - Vulkan
+        /// - Metal (lines only)
+        ///
+        /// Provided Capabilities:
+        /// - None
+        ///
+        /// This is a native only extension.
+        ///
+        /// [`RasterizationStateDescriptor::polygon_mode`]: struct.RasterizationStateDescriptor.html#structfield.polygon_mode
+        const NON_FILL_POLYGON_MODE = 0x0000_0000_0400_0000;
+        /// Allows render passes and bundles to issue several indirect draws with a single
+        /// `multi_draw_indirect` or `multi_draw_indexed_indirect` call.
+        ///
+        /// The draw arguments are tightly packed in the indirect buffer.
+        ///
+        /// Supported platforms:
+        /// - DX12
+        /// - Vulkan
+        /// - Metal (emulated)
+        ///
+        /// Provided Capabilities:
+        /// - None
+        ///
+        /// This is a native only extension.
+        const MULTI_DRAW_INDIRECT = 0x0000_0000_0800_0000;
+        /// Allows render passes and bundles to issue indirect draws whose number is read
+        /// from a count buffer, with `multi_draw_indirect_count` or
+        /// `multi_draw_indexed_indirect_count`.
+        ///
+        /// Supported platforms:
+        /// - DX12
+        /// - Vulkan (with VK_KHR_draw_indirect_count)
+        ///
+        /// Provided Capabilities:
+        /// - None
+        ///
+        /// This is a native only extension.
+        const MULTI_DRAW_INDIRECT_COUNT = 0x0000_0000_1000_0000;
         /// Extensions which are part of the upstream webgpu standard
         const ALL_WEBGPU = 0x0000_0000_0000_FFFF;
         /// Extensions that require activating the unsafe extension flag
@@ -273,6 +443,11 @@ impl UnsafeExtensions {
 pub struct Limits {
     /// Amount of bind groups that can be attached to a pipeline at the same time. Defaults to 4. Higher is "better".
     pub max_bind_groups: u32,
+    /// Amount of storage available for push constants, in bytes. Defaults to 0. Higher is "better".
+    /// Requesting more than 0 requires [`Extensions::PUSH_CONSTANTS`] to be enabled.
+    ///
+    /// [`Extensions::PUSH_CONSTANTS`]: struct.Extensions.html#associatedconstant.PUSH_CONSTANTS
+    pub max_push_constant_size: u32,
     /// This struct must be partially constructed from its default.
     pub _non_exhaustive: NonExhaustive,
 }
@@ -281,6 +456,7 @@ impl Default for Limits {
     fn default() -> Self {
         Limits {
             max_bind_groups: 4,
+            max_push_constant_size: 0,
             _non_exhaustive: unsafe { NonExhaustive::new() },
         }
     }
@@ -384,6 +560,10 @@ pub struct DeviceDescriptor {
     /// Switch shader validation on/off. This is a temporary field
     /// that will be removed once our validation logic is complete.
     pub shader_validation: bool,
+    /// Skip the zero-initialization of buffers and textures before their first use.
+    /// Reading a resource that wasn't written to then returns unspecified contents,
+    /// so this should only be set by trusted native applications.
+    pub skip_zero_initialization: bool,
 }
 
 bitflags::bitflags! {
@@ -393,6 +573,7 @@ bitflags::bitflags! {
     ///
     /// `ShaderStage::VERTEX | ShaderStage::FRAGMENT`
     #[repr(transparent)]
+    #[cfg_attr(feature = "peek-poke", derive(PeekPoke))]
     #[cfg_attr(feature = "trace", derive(Serialize))]
     #[cfg_attr(feature = "replay", derive(Deserialize))]
     pub struct ShaderStage: u32 {
@@ -407,6 +588,25 @@ bitflags::bitflags! {
     }
 }
 
+/// A range of push constant memory to pass to a shader stage.
+///
+/// Requires [`Extensions::PUSH_CONSTANTS`].
+///
+/// [`Extensions::PUSH_CONSTANTS`]: struct.Extensions.html#associatedconstant.PUSH_CONSTANTS
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "trace", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+pub struct PushConstantRange {
+    /// Stages the push constant range is visible from. Each stage can only be served by at most
+    /// one range in a pipeline layout.
+    pub stages: ShaderStage,
+    /// Range in push constant memory, in bytes. Both ends must be multiples of
+    /// [`PUSH_CONSTANT_ALIGNMENT`], and the end must not exceed `Limits::max_push_constant_size`.
+    ///
+    /// [`PUSH_CONSTANT_ALIGNMENT`]: constant.PUSH_CONSTANT_ALIGNMENT.html
+    pub range: std::ops::Range<u32>,
+}
+
 /// Dimensions of a particular texture view.
 #[repr(C)]
 #[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
@@ -523,6 +723,51 @@ pub struct ColorStateDescriptor {
     pub color_blend: BlendDescriptor,
     /// Mask which enables/disables writes to different color/alpha channel.
     pub write_mask: ColorWrite,
+    /// The bitwise operation applied instead of blending. Requires [`Extensions::LOGIC_OP`],
+    /// and has to be the same for all color states of a pipeline.
+    ///
+    /// [`Extensions::LOGIC_OP`]: struct.Extensions.html#associatedconstant.LOGIC_OP
+    pub logic_op: Option<LogicOp>,
+}
+
+/// Bitwise operation combining the fragment output `s` with the attachment value `d`.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+#[cfg_attr(feature = "trace", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+pub enum LogicOp {
+    /// 0
+    Clear = 0,
+    /// s & d
+    And = 1,
+    /// s & !d
+    AndReverse = 2,
+    /// s
+    Copy = 3,
+    /// !s & d
+    AndInverted = 4,
+    /// d
+    NoOp = 5,
+    /// s ^ d
+    Xor = 6,
+    /// s | d
+    Or = 7,
+    /// !(s | d)
+    Nor = 8,
+    /// !(s ^ d)
+    Equivalent = 9,
+    /// !d
+    Invert = 10,
+    /// s | !d
+    OrReverse = 11,
+    /// !s
+    CopyInverted = 12,
+    /// !s | d
+    OrInverted = 13,
+    /// !(s & d)
+    Nand = 14,
+    /// !0
+    Set = 15,
 }
 
 /// Primitive type the input mesh is composed of.
@@ -593,6 +838,26 @@ impl Default for CullMode {
     }
 }
 
+/// Type of drawing mode for polygons.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+#[cfg_attr(feature = "trace", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+pub enum PolygonMode {
+    /// Polygons are filled
+    Fill = 0,
+    /// Polygons are drawn as line segments
+    Line = 1,
+    /// Polygons are drawn as points
+    Point = 2,
+}
+
+impl Default for PolygonMode {
+    fn default() -> Self {
+        PolygonMode::Fill
+    }
+}
+
 /// Describes the state of the rasterizer in a render pipeline.
 #[repr(C)]
 #[derive(Clone, Debug, Default, PartialEq)]
@@ -601,9 +866,20 @@ impl Default for CullMode {
 pub struct RasterizationStateDescriptor {
     pub front_face: FrontFace,
     pub cull_mode: CullMode,
+    /// Controls the way each polygon is rasterized. Anything other than [`PolygonMode::Fill`]
+    /// requires [`Extensions::NON_FILL_POLYGON_MODE`].
+    ///
+    /// [`PolygonMode::Fill`]: enum.PolygonMode.html#variant.Fill
+    /// [`Extensions::NON_FILL_POLYGON_MODE`]: struct.Extensions.html#associatedconstant.NON_FILL_POLYGON_MODE
+    pub polygon_mode: PolygonMode,
     pub depth_bias: i32,
     pub depth_bias_slope_scale: f32,
     pub depth_bias_clamp: f32,
+    /// Clamp the fragment depth to the viewport depth range instead of clipping
+    /// the primitives. Requires [`Extensions::DEPTH_CLAMPING`].
+    ///
+    /// [`Extensions::DEPTH_CLAMPING`]: struct.Extensions.html#associatedconstant.DEPTH_CLAMPING
+    pub clamp_depth: bool,
 }
 
 /// Underlying texture data format.
@@ -704,6 +980,264 @@ pub enum TextureFormat {
     Depth24Plus = 36,
     /// Special depth/stencil format with at least 24 bit integer depth and 8 bits integer stencil.
     Depth24PlusStencil8 = 37,
+
+    // Compressed formats, which are always 2D and sampled as float.
+    // BC formats require `Extensions::TEXTURE_COMPRESSION_BC`.
+    /// 4x4 block compressed texture. 8 bytes per block (4 bit/px). 4 color + alpha pallet. 5 bit R + 6 bit G + 5 bit B + 1 bit alpha.
+    /// [0, 63] ([0, 1] for alpha) converted to/from float [0, 1] in shader.
+    ///
+    /// Also known as DXT1.
+    Bc1RgbaUnorm = 38,
+    /// 4x4 block compressed texture. 8 bytes per block (4 bit/px). 4 color + alpha pallet. 5 bit R + 6 bit G + 5 bit B + 1 bit alpha.
+    /// Srgb-color [0, 63] ([0, 1] for alpha) converted to/from linear-color float [0, 1] in shader.
+    ///
+    /// Also known as DXT1.
+    Bc1RgbaUnormSrgb = 39,
+    /// 4x4 block compressed texture. 16 bytes per block (8 bit/px). 4 color pallet. 5 bit R + 6 bit G + 5 bit B + 4 bit alpha.
+    /// [0, 63] ([0, 15] for alpha) converted to/from float [0, 1] in shader.
+    ///
+    /// Also known as DXT3.
+    Bc2RgbaUnorm = 40,
+    /// 4x4 block compressed texture. 16 bytes per block (8 bit/px). 4 color pallet. 5 bit R + 6 bit G + 5 bit B + 4 bit alpha.
+    /// Srgb-color [0, 63] ([0, 15] for alpha) converted to/from linear-color float [0, 1] in shader.
+    ///
+    /// Also known as DXT3.
+    Bc2RgbaUnormSrgb = 41,
+    /// 4x4 block compressed texture. 16 bytes per block (8 bit/px). 4 color pallet + 8 alpha pallet. 5 bit R + 6 bit G + 5 bit B + 8 bit alpha.
+    /// [0, 63] ([0, 255] for alpha) converted to/from float [0, 1] in shader.
+    ///
+    /// Also known as DXT5.
+    Bc3RgbaUnorm = 42,
+    /// 4x4 block compressed texture. 16 bytes per block (8 bit/px). 4 color pallet + 8 alpha pallet. 5 bit R + 6 bit G + 5 bit B + 8 bit alpha.
+    /// Srgb-color [0, 63] ([0, 255] for alpha) converted to/from linear-color float [0, 1] in shader.
+    ///
+    /// Also known as DXT5.
+    Bc3RgbaUnormSrgb = 43,
+    /// 4x4 block compressed texture. 8 bytes per block (4 bit/px). 8 color pallet. 8 bit R.
+    /// [0, 255] converted to/from float [0, 1] in shader.
+    ///
+    /// Also known as RGTC1.
+    Bc4RUnorm = 44,
+    /// 4x4 block compressed texture. 8 bytes per block (4 bit/px). 8 color pallet. 8 bit R.
+    /// [-127, 127] converted to/from float [-1, 1] in shader.
+    ///
+    /// Also known as RGTC1.
+    Bc4RSnorm = 45,
+    /// 4x4 block compressed texture. 16 bytes per block (8 bit/px). 8 color red pallet + 8 color green pallet. 8 bit RG.
+    /// [0, 255] converted to/from float [0, 1] in shader.
+    ///
+    /// Also known as RGTC2.
+    Bc5RgUnorm = 46,
+    /// 4x4 block compressed texture. 16 bytes per block (8 bit/px). 8 color red pallet + 8 color green pallet. 8 bit RG.
+    /// [-127, 127] converted to/from float [-1, 1] in shader.
+    ///
+    /// Also known as RGTC2.
+    Bc5RgSnorm = 47,
+    /// 4x4 block compressed texture. 16 bytes per block (8 bit/px). Variable sized pallet. 16 bit unsigned float RGB. Float in shader.
+    ///
+    /// Also known as BPTC (float).
+    Bc6hRgbUfloat = 48,
+    /// 4x4 block compressed texture. 16 bytes per block (8 bit/px). Variable sized pallet. 16 bit signed float RGB. Float in shader.
+    ///
+    /// Also known as BPTC (float).
+    Bc6hRgbSfloat = 49,
+    /// 4x4 block compressed texture. 16 bytes per block (8 bit/px). Variable sized pallet. 8 bit integer RGBA.
+    /// [0, 255] converted to/from float [0, 1] in shader.
+    ///
+    /// Also known as BPTC (unorm).
+    Bc7RgbaUnorm = 50,
+    /// 4x4 block compressed texture. 16 bytes per block (8 bit/px). Variable sized pallet. 8 bit integer RGBA.
+    /// Srgb-color [0, 255] converted to/from linear-color float [0, 1] in shader.
+    ///
+    /// Also known as BPTC (unorm).
+    Bc7RgbaUnormSrgb = 51,
+
+    // ETC2/EAC formats require `Extensions::TEXTURE_COMPRESSION_ETC2`.
+    /// 4x4 block compressed texture. 8 bytes per block (4 bit/px). Complex pallet. 8 bit integer RGB.
+    /// [0, 255] converted to/from float [0, 1] in shader.
+    Etc2RgbUnorm = 52,
+    /// 4x4 block compressed texture. 8 bytes per block (4 bit/px). Complex pallet. 8 bit integer RGB.
+    /// Srgb-color [0, 255] converted to/from linear-color float [0, 1] in shader.
+    Etc2RgbUnormSrgb = 53,
+    /// 4x4 block compressed texture. 8 bytes per block (4 bit/px). Complex pallet. 8 bit integer RGB + 1 bit alpha.
+    /// [0, 255] ([0, 1] for alpha) converted to/from float [0, 1] in shader.
+    Etc2RgbA1Unorm = 54,
+    /// 4x4 block compressed texture. 8 bytes per block (4 bit/px). Complex pallet. 8 bit integer RGB + 1 bit alpha.
+    /// Srgb-color [0, 255] ([0, 1] for alpha) converted to/from linear-color float [0, 1] in shader.
+    Etc2RgbA1UnormSrgb = 55,
+    /// 4x4 block compressed texture. 16 bytes per block (8 bit/px). Complex pallet. 8 bit integer RGB + 8 bit alpha.
+    /// [0, 255] converted to/from float [0, 1] in shader.
+    Etc2RgbA8Unorm = 56,
+    /// 4x4 block compressed texture. 16 bytes per block (8 bit/px). Complex pallet. 8 bit integer RGB + 8 bit alpha.
+    /// Srgb-color [0, 255] converted to/from linear-color float [0, 1] in shader.
+    Etc2RgbA8UnormSrgb = 57,
+    /// 4x4 block compressed texture. 8 bytes per block (4 bit/px). Complex pallet. 11 bit integer R.
+    /// [0, 255] converted to/from float [0, 1] in shader.
+    EacRUnorm = 58,
+    /// 4x4 block compressed texture. 8 bytes per block (4 bit/px). Complex pallet. 11 bit integer R.
+    /// [-127, 127] converted to/from float [-1, 1] in shader.
+    EacRSnorm = 59,
+    /// 4x4 block compressed texture. 16 bytes per block (8 bit/px). Complex pallet. 11 bit integer R + 11 bit integer G.
+    /// [0, 255] converted to/from float [0, 1] in shader.
+    EacRgUnorm = 60,
+    /// 4x4 block compressed texture. 16 bytes per block (8 bit/px). Complex pallet. 11 bit integer R + 11 bit integer G.
+    /// [-127, 127] converted to/from float [-1, 1] in shader.
+    EacRgSnorm = 61,
+
+    // ASTC formats require `Extensions::TEXTURE_COMPRESSION_ASTC_LDR`.
+    /// 4x4 block compressed texture. 16 bytes per block (8 bit/px). Complex pallet. 8 bit integer RGBA.
+    /// [0, 255] converted to/from float [0, 1] in shader.
+    Astc4x4RgbaUnorm = 62,
+    /// 4x4 block compressed texture. 16 bytes per block (8 bit/px). Complex pallet. 8 bit integer RGBA.
+    /// Srgb-color [0, 255] converted to/from linear-color float [0, 1] in shader.
+    Astc4x4RgbaUnormSrgb = 63,
+    /// 5x4 block compressed texture. 16 bytes per block (6.4 bit/px). Complex pallet. 8 bit integer RGBA.
+    /// [0, 255] converted to/from float [0, 1] in shader.
+    Astc5x4RgbaUnorm = 64,
+    /// 5x4 block compressed texture. 16 bytes per block (6.4 bit/px). Complex pallet. 8 bit integer RGBA.
+    /// Srgb-color [0, 255] converted to/from linear-color float [0, 1] in shader.
+    Astc5x4RgbaUnormSrgb = 65,
+    /// 5x5 block compressed texture. 16 bytes per block (5.12 bit/px). Complex pallet. 8 bit integer RGBA.
+    /// [0, 255] converted to/from float [0, 1] in shader.
+    Astc5x5RgbaUnorm = 66,
+    /// 5x5 block compressed texture. 16 bytes per block (5.12 bit/px). Complex pallet. 8 bit integer RGBA.
+    /// Srgb-color [0, 255] converted to/from linear-color float [0, 1] in shader.
+    Astc5x5RgbaUnormSrgb = 67,
+    /// 6x5 block compressed texture. 16 bytes per block (4.27 bit/px). Complex pallet. 8 bit integer RGBA.
+    /// [0, 255] converted to/from float [0, 1] in shader.
+    Astc6x5RgbaUnorm = 68,
+    /// 6x5 block compressed texture. 16 bytes per block (4.27 bit/px). Complex pallet. 8 bit integer RGBA.
+    /// Srgb-color [0, 255] converted to/from linear-color float [0, 1] in shader.
+    Astc6x5RgbaUnormSrgb = 69,
+    /// 6x6 block compressed texture. 16 bytes per block (3.56 bit/px). Complex pallet. 8 bit integer RGBA.
+    /// [0, 255] converted to/from float [0, 1] in shader.
+    Astc6x6RgbaUnorm = 70,
+    /// 6x6 block compressed texture. 16 bytes per block (3.56 bit/px). Complex pallet. 8 bit integer RGBA.
+    /// Srgb-color [0, 255] converted to/from linear-color float [0, 1] in shader.
+    Astc6x6RgbaUnormSrgb = 71,
+    /// 8x5 block compressed texture. 16 bytes per block (3.2 bit/px). Complex pallet. 8 bit integer RGBA.
+    /// [0, 255] converted to/from float [0, 1] in shader.
+    Astc8x5RgbaUnorm = 72,
+    /// 8x5 block compressed texture. 16 bytes per block (3.2 bit/px). Complex pallet. 8 bit integer RGBA.
+    /// Srgb-color [0, 255] converted to/from linear-color float [0, 1] in shader.
+    Astc8x5RgbaUnormSrgb = 73,
+    /// 8x6 block compressed texture. 16 bytes per block (2.67 bit/px). Complex pallet. 8 bit integer RGBA.
+    /// [0, 255] converted to/from float [0, 1] in shader.
+    Astc8x6RgbaUnorm = 74,
+    /// 8x6 block compressed texture. 16 bytes per block (2.67 bit/px). Complex pallet. 8 bit integer RGBA.
+    /// Srgb-color [0, 255] converted to/from linear-color float [0, 1] in shader.
+    Astc8x6RgbaUnormSrgb = 75,
+    /// 8x8 block compressed texture. 16 bytes per block (2 bit/px). Complex pallet. 8 bit integer RGBA.
+    /// [0, 255] converted to/from float [0, 1] in shader.
+    Astc8x8RgbaUnorm = 76,
+    /// 8x8 block compressed texture. 16 bytes per block (2 bit/px). Complex pallet. 8 bit integer RGBA.
+    /// Srgb-color [0, 255] converted to/from linear-color float [0, 1] in shader.
+    Astc8x8RgbaUnormSrgb = 77,
+    /// 10x5 block compressed texture. 16 bytes per block (2.56 bit/px). Complex pallet. 8 bit integer RGBA.
+    /// [0, 255] converted to/from float [0, 1] in shader.
+    Astc10x5RgbaUnorm = 78,
+    /// 10x5 block compressed texture. 16 bytes per block (2.56 bit/px). Complex pallet. 8 bit integer RGBA.
+    /// Srgb-color [0, 255] converted to/from linear-color float [0, 1] in shader.
+    Astc10x5RgbaUnormSrgb = 79,
+    /// 10x6 block compressed texture. 16 bytes per block (2.13 bit/px). Complex pallet. 8 bit integer RGBA.
+    /// [0, 255] converted to/from float [0, 1] in shader.
+    Astc10x6RgbaUnorm = 80,
+    /// 10x6 block compressed texture. 16 bytes per block (2.13 bit/px). Complex pallet. 8 bit integer RGBA.
+    /// Srgb-color [0, 255] converted to/from linear-color float [0, 1] in shader.
+    Astc10x6RgbaUnormSrgb = 81,
+    /// 10x8 block compressed texture. 16 bytes per block (1.6 bit/px). Complex pallet. 8 bit integer RGBA.
+    /// [0, 255] converted to/from float [0, 1] in shader.
+    Astc10x8RgbaUnorm = 82,
+    /// 10x8 block compressed texture. 16 bytes per block (1.6 bit/px). Complex pallet. 8 bit integer RGBA.
+    /// Srgb-color [0, 255] converted to/from linear-color float [0, 1] in shader.
+    Astc10x8RgbaUnormSrgb = 83,
+    /// 10x10 block compressed texture. 16 bytes per block (1.28 bit/px). Complex pallet. 8 bit integer RGBA.
+    /// [0, 255] converted to/from float [0, 1] in shader.
+    Astc10x10RgbaUnorm = 84,
+    /// 10x10 block compressed texture. 16 bytes per block (1.28 bit/px). Complex pallet. 8 bit integer RGBA.
+    /// Srgb-color [0, 255] converted to/from linear-color float [0, 1] in shader.
+    Astc10x10RgbaUnormSrgb = 85,
+    /// 12x10 block compressed texture. 16 bytes per block (1.07 bit/px). Complex pallet. 8 bit integer RGBA.
+    /// [0, 255] converted to/from float [0, 1] in shader.
+    Astc12x10RgbaUnorm = 86,
+    /// 12x10 block compressed texture. 16 bytes per block (1.07 bit/px). Complex pallet. 8 bit integer RGBA.
+    /// Srgb-color [0, 255] converted to/from linear-color float [0, 1] in shader.
+    Astc12x10RgbaUnormSrgb = 87,
+    /// 12x12 block compressed texture. 16 bytes per block (0.89 bit/px). Complex pallet. 8 bit integer RGBA.
+    /// [0, 255] converted to/from float [0, 1] in shader.
+    Astc12x12RgbaUnorm = 88,
+    /// 12x12 block compressed texture. 16 bytes per block (0.89 bit/px). Complex pallet. 8 bit integer RGBA.
+    /// Srgb-color [0, 255] converted to/from linear-color float [0, 1] in shader.
+    Astc12x12RgbaUnormSrgb = 89,
+}
+
+impl TextureFormat {
+    /// Returns the extension required to create textures of this format.
+    pub fn required_extensions(self) -> Extensions {
+        match self {
+            TextureFormat::Bc1RgbaUnorm
+            | TextureFormat::Bc1RgbaUnormSrgb
+            | TextureFormat::Bc2RgbaUnorm
+            | TextureFormat::Bc2RgbaUnormSrgb
+            | TextureFormat::Bc3RgbaUnorm
+            | TextureFormat::Bc3RgbaUnormSrgb
+            | TextureFormat::Bc4RUnorm
+            | TextureFormat::Bc4RSnorm
+            | TextureFormat::Bc5RgUnorm
+            | TextureFormat::Bc5RgSnorm
+            | TextureFormat::Bc6hRgbUfloat
+            | TextureFormat::Bc6hRgbSfloat
+            | TextureFormat::Bc7RgbaUnorm
+            | TextureFormat::Bc7RgbaUnormSrgb => Extensions::TEXTURE_COMPRESSION_BC,
+
+            TextureFormat::Etc2RgbUnorm
+            | TextureFormat::Etc2RgbUnormSrgb
+            | TextureFormat::Etc2RgbA1Unorm
+            | TextureFormat::Etc2RgbA1UnormSrgb
+            | TextureFormat::Etc2RgbA8Unorm
+            | TextureFormat::Etc2RgbA8UnormSrgb
+            | TextureFormat::EacRUnorm
+            | TextureFormat::EacRSnorm
+            | TextureFormat::EacRgUnorm
+            | TextureFormat::EacRgSnorm => Extensions::TEXTURE_COMPRESSION_ETC2,
+
+            TextureFormat::Astc4x4RgbaUnorm
+            | TextureFormat::Astc4x4RgbaUnormSrgb
+            | TextureFormat::Astc5x4RgbaUnorm
+            | TextureFormat::Astc5x4RgbaUnormSrgb
+            | TextureFormat::Astc5x5RgbaUnorm
+            | TextureFormat::Astc5x5RgbaUnormSrgb
+            | TextureFormat::Astc6x5RgbaUnorm
+            | TextureFormat::Astc6x5RgbaUnormSrgb
+            | TextureFormat::Astc6x6RgbaUnorm
+            | TextureFormat::Astc6x6RgbaUnormSrgb
+            | TextureFormat::Astc8x5RgbaUnorm
+            | TextureFormat::Astc8x5RgbaUnormSrgb
+            | TextureFormat::Astc8x6RgbaUnorm
+            | TextureFormat::Astc8x6RgbaUnormSrgb
+            | TextureFormat::Astc8x8RgbaUnorm
+            | TextureFormat::Astc8x8RgbaUnormSrgb
+            | TextureFormat::Astc10x5RgbaUnorm
+            | TextureFormat::Astc10x5RgbaUnormSrgb
+            | TextureFormat::Astc10x6RgbaUnorm
+            | TextureFormat::Astc10x6RgbaUnormSrgb
+            | TextureFormat::Astc10x8RgbaUnorm
+            | TextureFormat::Astc10x8RgbaUnormSrgb
+            | TextureFormat::Astc10x10RgbaUnorm
+            | TextureFormat::Astc10x10RgbaUnormSrgb
+            | TextureFormat::Astc12x10RgbaUnorm
+            | TextureFormat::Astc12x10RgbaUnormSrgb
+            | TextureFormat::Astc12x12RgbaUnorm
+            | TextureFormat::Astc12x12RgbaUnormSrgb => Extensions::TEXTURE_COMPRESSION_ASTC_LDR,
+
+            _ => Extensions::empty(),
+        }
+    }
+
+    /// Returns true for block compressed formats.
+    pub fn is_compressed(self) -> bool {
+        !self.required_extensions().is_empty()
+    }
 }
 
 bitflags::bitflags! {
@@ -1017,6 +1551,8 @@ bitflags::bitflags! {
         const STORAGE = 128;
         /// Allow a buffer to be the indirect buffer in an indirect draw call.
         const INDIRECT = 256;
+        /// Allow a buffer to be the destination of a [`CommandEncoder::resolve_query_set`] operation.
+        const QUERY_RESOLVE = 512;
     }
 }
 
@@ -1422,6 +1958,24 @@ impl<L> TextureViewDescriptor<L> {
     }
 }
 
+/// Subresource range of a texture, used by `clear_texture`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Hash, Eq, PartialEq)]
+#[cfg_attr(feature = "trace", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+pub struct ImageSubresourceRange {
+    /// Aspect of the texture. Color textures must be [`TextureAspect::All`].
+    pub aspect: TextureAspect,
+    /// Base mip level.
+    pub base_mip_level: u32,
+    /// Mip level count. Zero means all the remaining levels starting at `base_mip_level`.
+    pub level_count: u32,
+    /// Base array layer.
+    pub base_array_layer: u32,
+    /// Layer count. Zero means all the remaining layers starting at `base_array_layer`.
+    pub array_layer_count: u32,
+}
+
 /// How edges should be handled in texture addressing.
 #[repr(C)]
 #[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
@@ -1526,6 +2080,47 @@ impl<L> SamplerDescriptor<L> {
     }
 }
 
+/// Type of the queries in a [`QuerySet`].
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+#[cfg_attr(feature = "trace", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+pub enum QueryType {
+    /// Each query contains a GPU timestamp, in ticks.
+    ///
+    /// Requires [`Extensions::TIMESTAMP_QUERY`].
+    ///
+    /// [`Extensions::TIMESTAMP_QUERY`]: struct.Extensions.html#associatedconstant.TIMESTAMP_QUERY
+    Timestamp = 0,
+    /// Each query contains the number of samples that passed the depth and stencil tests
+    /// between `RenderPass::begin_occlusion_query` and `RenderPass::end_occlusion_query`.
+    Occlusion = 1,
+}
+
+/// Describes a [`QuerySet`].
+#[repr(C)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "trace", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+pub struct QuerySetDescriptor<L> {
+    /// Debug label of the query set. This will show up in graphics debuggers for easy identification.
+    pub label: L,
+    /// Type of the queries in the set.
+    pub ty: QueryType,
+    /// Amount of queries in the set. Can't be more than [`QUERY_SET_MAX_QUERIES`].
+    pub count: u32,
+}
+
+impl<L> QuerySetDescriptor<L> {
+    pub fn map_label<K>(&self, fun: impl FnOnce(&L) -> K) -> QuerySetDescriptor<K> {
+        QuerySetDescriptor {
+            label: fun(&self.label),
+            ty: self.ty,
+            count: self.count,
+        }
+    }
+}
+
 /// Describes a [`CommandBuffer`].
 #[repr(C)]
 #[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
@@ -1628,7 +2223,59 @@ impl From<TextureFormat> for TextureComponentType {
             | TextureFormat::Rgb10a2Unorm
             | TextureFormat::Depth32Float
             | TextureFormat::Depth24Plus
-            | TextureFormat::Depth24PlusStencil8 => Self::Float,
+            | TextureFormat::Depth24PlusStencil8
+            | TextureFormat::Bc1RgbaUnorm
+            | TextureFormat::Bc1RgbaUnormSrgb
+            | TextureFormat::Bc2RgbaUnorm
+            | TextureFormat::Bc2RgbaUnormSrgb
+            | TextureFormat::Bc3RgbaUnorm
+            | TextureFormat::Bc3RgbaUnormSrgb
+            | TextureFormat::Bc4RUnorm
+            | TextureFormat::Bc4RSnorm
+            | TextureFormat::Bc5RgUnorm
+            | TextureFormat::Bc5RgSnorm
+            | TextureFormat::Bc6hRgbUfloat
+            | TextureFormat::Bc6hRgbSfloat
+            | TextureFormat::Bc7RgbaUnorm
+            | TextureFormat::Bc7RgbaUnormSrgb
+            | TextureFormat::Etc2RgbUnorm
+            | TextureFormat::Etc2RgbUnormSrgb
+            | TextureFormat::Etc2RgbA1Unorm
+            | TextureFormat::Etc2RgbA1UnormSrgb
+            | TextureFormat::Etc2RgbA8Unorm
+            | TextureFormat::Etc2RgbA8UnormSrgb
+            | TextureFormat::EacRUnorm
+            | TextureFormat::EacRSnorm
+            | TextureFormat::EacRgUnorm
+            | TextureFormat::EacRgSnorm
+            | TextureFormat::Astc4x4RgbaUnorm
+            | TextureFormat::Astc4x4RgbaUnormSrgb
+            | TextureFormat::Astc5x4RgbaUnorm
+            | TextureFormat::Astc5x4RgbaUnormSrgb
+            | TextureFormat::Astc5x5RgbaUnorm
+            | TextureFormat::Astc5x5RgbaUnormSrgb
+            | TextureFormat::Astc6x5RgbaUnorm
+            | TextureFormat::Astc6x5RgbaUnormSrgb
+            | TextureFormat::Astc6x6RgbaUnorm
+            | TextureFormat::Astc6x6RgbaUnormSrgb
+            | TextureFormat::Astc8x5RgbaUnorm
+            | TextureFormat::Astc8x5RgbaUnormSrgb
+            | TextureFormat::Astc8x6RgbaUnorm
+            | TextureFormat::Astc8x6RgbaUnormSrgb
+            | TextureFormat::Astc8x8RgbaUnorm
+            | TextureFormat::Astc8x8RgbaUnormSrgb
+            | TextureFormat::Astc10x5RgbaUnorm
+            | TextureFormat::Astc10x5RgbaUnormSrgb
+            | TextureFormat::Astc10x6RgbaUnorm
+            | TextureFormat::Astc10x6RgbaUnormSrgb
+            | TextureFormat::Astc10x8RgbaUnorm
+            | TextureFormat::Astc10x8RgbaUnormSrgb
+            | TextureFormat::Astc10x10RgbaUnorm
+            | TextureFormat::Astc10x10RgbaUnormSrgb
+            | TextureFormat::Astc12x10RgbaUnorm
+            | TextureFormat::Astc12x10RgbaUnormSrgb
+            | TextureFormat::Astc12x12RgbaUnorm
+            | TextureFormat::Astc12x12RgbaUnormSrgb => Self::Float,
         }
     }
 }
@@ -1769,7 +2416,7 @@ pub enum BindingType {
 #[derive(Clone, Debug, PartialEq, Eq)]
 #[cfg_attr(feature = "trace", derive(Serialize))]
 #[cfg_attr(feature = "replay", derive(Deserialize))]
-pub struct BindGroupLayoutEntry {
+pub struct BindGroupLayoutEntryBase<S> {
     /// Binding index. Must match shader index and be unique inside a BindGroupLayout. A binding
     /// of index 1, would be described as `layout(set = 0, binding = 1) uniform` in shaders.
     pub binding: u32,
@@ -1783,18 +2430,25 @@ pub struct BindGroupLayoutEntry {
     ///
     /// If this value is Some and `ty` is any other variant, bind group creation will fail.
     pub count: Option<u32>,
+    /// Samplers embedded into the layout, one per array element. Only valid if `ty` is
+    /// `BindingType::Sampler`.
+    ///
+    /// If this is not empty, bind groups created with this layout don't provide a resource
+    /// for this binding, and the samplers are kept alive as long as the layout.
+    pub immutable_samplers: Vec<S>,
     /// This struct should be partially initalized using the default method, but binding, visibility,
     /// and ty should be set.
     pub _non_exhaustive: NonExhaustive,
 }
 
-impl BindGroupLayoutEntry {
+impl<S> BindGroupLayoutEntryBase<S> {
     pub fn new(binding: u32, visibility: ShaderStage, ty: BindingType) -> Self {
         Self {
             binding,
             visibility,
             ty,
             count: None,
+            immutable_samplers: Vec::new(),
             _non_exhaustive: unsafe { NonExhaustive::new() },
         }
     }
@@ -1810,12 +2464,12 @@ impl BindGroupLayoutEntry {
 
 /// Describes a [`BindGroupLayout`].
 #[derive(Clone, Debug)]
-pub struct BindGroupLayoutDescriptor<'a> {
+pub struct BindGroupLayoutDescriptorBase<'a, S> {
     /// Debug label of the bind group layout. This will show up in graphics debuggers for easy identification.
     pub label: Option<&'a str>,
 
     /// Array of bindings in this BindGroupLayout
-    pub bindings: &'a [BindGroupLayoutEntry],
+    pub bindings: &'a [BindGroupLayoutEntryBase<S>],
 }
 
 /// This type allows us to make the serialized representation of a BufferSize more human-readable